@@ -0,0 +1,555 @@
+/*!
+
+An arbitrary precision signed integer. The magnitude is a little-endian vector of 32-bit limbs with no leading zero
+limbs, so zero is the empty vector, and the sign is kept separately. Zero is never negative, so equal numbers have equal
+representations and the derived `Eq` and `Hash` are correct.
+
+Only what the rest of the crate needs is provided: the ring operations, truncating division (`quo` and `rem` in Maude's
+terms), `gcd`, comparison, and conversion to and from decimal strings and machine integers. The algorithms are the
+schoolbook ones. Division is done a bit at a time, which is slow for huge numbers but has no corner cases.
+
+*/
+
+use std::{
+  cmp::Ordering,
+  fmt::{Debug, Display, Formatter},
+  ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Rem, Sub, SubAssign},
+  str::FromStr,
+};
+
+/// The largest power of ten that fits in a limb, used for decimal conversion.
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInteger {
+  negative: bool,
+  limbs:    Vec<u32>,
+}
+
+impl BigInteger {
+  #[inline(always)]
+  pub fn zero() -> Self {
+    BigInteger::default()
+  }
+
+  #[inline(always)]
+  pub fn one() -> Self {
+    BigInteger::from(1u32)
+  }
+
+  #[inline(always)]
+  pub fn is_zero(&self) -> bool {
+    self.limbs.is_empty()
+  }
+
+  #[inline(always)]
+  pub fn is_one(&self) -> bool {
+    !self.negative && self.limbs == [1]
+  }
+
+  #[inline(always)]
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  #[inline(always)]
+  pub fn is_positive(&self) -> bool {
+    !self.negative && !self.is_zero()
+  }
+
+  pub fn abs(&self) -> BigInteger {
+    BigInteger {
+      negative: false,
+      limbs:    self.limbs.clone(),
+    }
+  }
+
+  /// The result of truncating division, rounding toward zero, and the remainder, which has the sign of `self`. Returns
+  /// `None` if `divisor` is zero.
+  pub fn div_rem(&self, divisor: &BigInteger) -> Option<(BigInteger, BigInteger)> {
+    if divisor.is_zero() {
+      return None;
+    }
+    let (quotient, remainder) = div_rem_magnitudes(&self.limbs, &divisor.limbs);
+
+    Some((
+      BigInteger::from_parts(self.negative != divisor.negative, quotient),
+      BigInteger::from_parts(self.negative, remainder),
+    ))
+  }
+
+  /// The greatest common divisor, which is never negative. The gcd of zero and zero is zero.
+  pub fn gcd(&self, other: &BigInteger) -> BigInteger {
+    let mut a = self.abs();
+    let mut b = other.abs();
+    while !b.is_zero() {
+      let (_, remainder) = div_rem_magnitudes(&a.limbs, &b.limbs);
+      a = b;
+      b = BigInteger::from_parts(false, remainder);
+    }
+    a
+  }
+
+  /// The value as a `usize`, if it fits.
+  pub fn to_usize(&self) -> Option<usize> {
+    if self.negative {
+      return None;
+    }
+    let mut value: usize = 0;
+    for &limb in self.limbs.iter().rev() {
+      value = value.checked_mul(1 << 32)?.checked_add(limb as usize)?;
+    }
+    Some(value)
+  }
+
+  /// The value as an `i64`, if it fits.
+  pub fn to_i64(&self) -> Option<i64> {
+    let mut magnitude: u64 = 0;
+    for &limb in self.limbs.iter().rev() {
+      magnitude = magnitude.checked_mul(1 << 32)?.checked_add(limb as u64)?;
+    }
+    if self.negative {
+      0i64.checked_sub_unsigned(magnitude)
+    } else {
+      i64::try_from(magnitude).ok()
+    }
+  }
+
+  /// A hash of the value that fits in the `u32` used for semantic hashes.
+  pub fn hash_value(&self) -> u32 {
+    let hash = self
+      .limbs
+      .iter()
+      .fold(0u32, |hash, &limb| hash.rotate_left(5) ^ limb);
+    if self.negative {
+      !hash
+    } else {
+      hash
+    }
+  }
+
+  /// Makes a number from a sign and a magnitude that may have leading zero limbs.
+  fn from_parts(negative: bool, mut limbs: Vec<u32>) -> BigInteger {
+    while limbs.last() == Some(&0) {
+      limbs.pop();
+    }
+    BigInteger {
+      negative: negative && !limbs.is_empty(),
+      limbs,
+    }
+  }
+}
+
+// region Magnitude arithmetic
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+  a.len()
+    .cmp(&b.len())
+    .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+  let mut result = Vec::with_capacity(long.len() + 1);
+  let mut carry = 0u64;
+
+  for (i, &limb) in long.iter().enumerate() {
+    let sum = limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+    result.push(sum as u32);
+    carry = sum >> 32;
+  }
+  if carry > 0 {
+    result.push(carry as u32);
+  }
+  result
+}
+
+/// Computes `a - b`, where `a ≥ b`.
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let mut result = Vec::with_capacity(a.len());
+  let mut borrow = 0i64;
+
+  for (i, &limb) in a.iter().enumerate() {
+    let mut difference = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+    borrow = 0;
+    if difference < 0 {
+      difference += 1 << 32;
+      borrow = 1;
+    }
+    result.push(difference as u32);
+  }
+  assert_eq!(borrow, 0, "magnitude subtraction underflowed. This is a bug.");
+  result
+}
+
+fn multiply_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+  if a.is_empty() || b.is_empty() {
+    return vec![];
+  }
+  let mut result = vec![0u32; a.len() + b.len()];
+
+  for (i, &x) in a.iter().enumerate() {
+    let mut carry = 0u64;
+    for (j, &y) in b.iter().enumerate() {
+      let product = x as u64 * y as u64 + result[i + j] as u64 + carry;
+      result[i + j] = product as u32;
+      carry = product >> 32;
+    }
+    result[i + b.len()] = carry as u32;
+  }
+  result
+}
+
+/// Divides `a` by the single limb `divisor`, returning the quotient and the remainder.
+fn div_rem_limb(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+  let mut quotient = vec![0u32; a.len()];
+  let mut remainder = 0u64;
+
+  for i in (0..a.len()).rev() {
+    let current = (remainder << 32) | a[i] as u64;
+    quotient[i] = (current / divisor as u64) as u32;
+    remainder = current % divisor as u64;
+  }
+  (quotient, remainder as u32)
+}
+
+/// Shift-and-subtract long division of magnitudes. `b` must be nonzero.
+fn div_rem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+  if compare_magnitudes(a, b) == Ordering::Less {
+    return (vec![], a.to_vec());
+  }
+  if b.len() == 1 {
+    let (quotient, remainder) = div_rem_limb(a, b[0]);
+    return (quotient, vec![remainder]);
+  }
+
+  let mut quotient = vec![0u32; a.len()];
+  let mut remainder: Vec<u32> = Vec::with_capacity(b.len() + 1);
+
+  for bit in (0..a.len() * 32).rev() {
+    // remainder = remainder * 2 + next bit of a
+    let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+    for limb in remainder.iter_mut() {
+      let next_carry = *limb >> 31;
+      *limb = (*limb << 1) | carry;
+      carry = next_carry;
+    }
+    if carry != 0 {
+      remainder.push(carry);
+    }
+
+    if compare_magnitudes(&remainder, b) != Ordering::Less {
+      remainder = subtract_magnitudes(&remainder, b);
+      while remainder.last() == Some(&0) {
+        remainder.pop();
+      }
+      quotient[bit / 32] |= 1 << (bit % 32);
+    }
+  }
+  (quotient, remainder)
+}
+
+// endregion
+
+// region Operators
+
+impl Ord for BigInteger {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, true) => Ordering::Greater,
+      (true, false) => Ordering::Less,
+      (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+      (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+    }
+  }
+}
+
+impl PartialOrd for BigInteger {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Neg for &BigInteger {
+  type Output = BigInteger;
+
+  fn neg(self) -> BigInteger {
+    BigInteger::from_parts(!self.negative, self.limbs.clone())
+  }
+}
+
+impl Neg for BigInteger {
+  type Output = BigInteger;
+
+  fn neg(self) -> BigInteger {
+    BigInteger::from_parts(!self.negative, self.limbs)
+  }
+}
+
+impl Add for &BigInteger {
+  type Output = BigInteger;
+
+  fn add(self, other: &BigInteger) -> BigInteger {
+    if self.negative == other.negative {
+      return BigInteger::from_parts(self.negative, add_magnitudes(&self.limbs, &other.limbs));
+    }
+    // The signs differ, so we subtract the smaller magnitude from the larger and take the sign of the larger.
+    match compare_magnitudes(&self.limbs, &other.limbs) {
+      Ordering::Less => BigInteger::from_parts(other.negative, subtract_magnitudes(&other.limbs, &self.limbs)),
+      _ => BigInteger::from_parts(self.negative, subtract_magnitudes(&self.limbs, &other.limbs)),
+    }
+  }
+}
+
+impl Sub for &BigInteger {
+  type Output = BigInteger;
+
+  fn sub(self, other: &BigInteger) -> BigInteger {
+    self + &(-other)
+  }
+}
+
+impl Mul for &BigInteger {
+  type Output = BigInteger;
+
+  fn mul(self, other: &BigInteger) -> BigInteger {
+    BigInteger::from_parts(
+      self.negative != other.negative,
+      multiply_magnitudes(&self.limbs, &other.limbs),
+    )
+  }
+}
+
+/// Truncating division. Panics on division by zero, like the primitive integers.
+impl Div for &BigInteger {
+  type Output = BigInteger;
+
+  fn div(self, other: &BigInteger) -> BigInteger {
+    match self.div_rem(other) {
+      Some((quotient, _)) => quotient,
+      None => panic!("attempt to divide by zero"),
+    }
+  }
+}
+
+/// The remainder of truncating division. Panics on division by zero, like the primitive integers.
+impl Rem for &BigInteger {
+  type Output = BigInteger;
+
+  fn rem(self, other: &BigInteger) -> BigInteger {
+    match self.div_rem(other) {
+      Some((_, remainder)) => remainder,
+      None => panic!("attempt to calculate the remainder with a divisor of zero"),
+    }
+  }
+}
+
+/// Implements the owned versions of a binary operator in terms of the version on references.
+macro_rules! forward_binary_operator {
+  ($trait_name:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+    impl $trait_name for BigInteger {
+      type Output = BigInteger;
+
+      #[inline(always)]
+      fn $method(self, other: BigInteger) -> BigInteger {
+        (&self).$method(&other)
+      }
+    }
+
+    impl $trait_name<&BigInteger> for BigInteger {
+      type Output = BigInteger;
+
+      #[inline(always)]
+      fn $method(self, other: &BigInteger) -> BigInteger {
+        (&self).$method(other)
+      }
+    }
+
+    impl $assign_trait<&BigInteger> for BigInteger {
+      #[inline(always)]
+      fn $assign_method(&mut self, other: &BigInteger) {
+        *self = (&*self).$method(other);
+      }
+    }
+
+    impl $assign_trait for BigInteger {
+      #[inline(always)]
+      fn $assign_method(&mut self, other: BigInteger) {
+        *self = (&*self).$method(&other);
+      }
+    }
+  };
+}
+
+forward_binary_operator!(Add, add, AddAssign, add_assign);
+forward_binary_operator!(Sub, sub, SubAssign, sub_assign);
+forward_binary_operator!(Mul, mul, MulAssign, mul_assign);
+
+impl Div for BigInteger {
+  type Output = BigInteger;
+
+  #[inline(always)]
+  fn div(self, other: BigInteger) -> BigInteger {
+    &self / &other
+  }
+}
+
+impl Rem for BigInteger {
+  type Output = BigInteger;
+
+  #[inline(always)]
+  fn rem(self, other: BigInteger) -> BigInteger {
+    &self % &other
+  }
+}
+
+// endregion
+
+// region Conversions
+
+macro_rules! from_unsigned {
+  ($($t:ty),*) => {
+    $(
+      impl From<$t> for BigInteger {
+        fn from(value: $t) -> Self {
+          let mut value = value as u128;
+          let mut limbs = Vec::new();
+          while value > 0 {
+            limbs.push(value as u32);
+            value >>= 32;
+          }
+          BigInteger { negative: false, limbs }
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! from_signed {
+  ($($t:ty),*) => {
+    $(
+      impl From<$t> for BigInteger {
+        fn from(value: $t) -> Self {
+          let magnitude = BigInteger::from((value as i128).unsigned_abs());
+          BigInteger::from_parts(value < 0, magnitude.limbs)
+        }
+      }
+    )*
+  };
+}
+
+from_unsigned!(u8, u16, u32, u64, u128, usize);
+from_signed!(i8, i16, i32, i64, i128, isize);
+
+impl FromStr for BigInteger {
+  type Err = String;
+
+  /// Parses an optionally signed decimal integer.
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let (negative, digits) = match text.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+      return Err(format!("invalid integer literal: {}", text));
+    }
+
+    let ten_to_the_digits = BigInteger::from(DECIMAL_BASE);
+    let mut value = BigInteger::zero();
+    // The leading chunk may be short, so that the rest have exactly `DECIMAL_DIGITS` digits.
+    let first_chunk = match digits.len() % DECIMAL_DIGITS {
+      0 => DECIMAL_DIGITS,
+      length => length,
+    };
+    let mut start = 0;
+    let mut end = first_chunk;
+    while start < digits.len() {
+      let chunk: u32 = digits[start..end].parse().map_err(|_| format!("invalid integer literal: {}", text))?;
+      value = &(&value * &ten_to_the_digits) + &BigInteger::from(chunk);
+      start = end;
+      end += DECIMAL_DIGITS;
+    }
+
+    Ok(BigInteger::from_parts(negative, value.limbs))
+  }
+}
+
+impl Display for BigInteger {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    if self.is_zero() {
+      return write!(f, "0");
+    }
+
+    // Peel off chunks of decimal digits, least significant first.
+    let mut chunks: Vec<u32> = Vec::new();
+    let mut magnitude = self.limbs.clone();
+    while !magnitude.is_empty() {
+      let (quotient, remainder) = div_rem_limb(&magnitude, DECIMAL_BASE);
+      chunks.push(remainder);
+      magnitude = quotient;
+      while magnitude.last() == Some(&0) {
+        magnitude.pop();
+      }
+    }
+
+    if self.negative {
+      write!(f, "-")?;
+    }
+    write!(f, "{}", chunks.last().unwrap())?;
+    for chunk in chunks.iter().rev().skip(1) {
+      write!(f, "{:09}", chunk)?;
+    }
+    Ok(())
+  }
+}
+
+impl Debug for BigInteger {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+// endregion
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn big(text: &str) -> BigInteger {
+    text.parse().unwrap()
+  }
+
+  #[test]
+  fn arithmetic_test() {
+    let a = big("123456789012345678901234567890");
+    let b = big("-987654321098765432109876543210");
+
+    assert_eq!((&a + &b).to_string(), "-864197532086419753208641975320");
+    assert_eq!((&a - &b).to_string(), "1111111110111111111011111111100");
+    assert_eq!(
+      (&a * &b).to_string(),
+      "-121932631137021795226185032733622923332237463801111263526900"
+    );
+    assert_eq!(&(&a * &b) / &a, b);
+    assert!((&(&a * &b) % &a).is_zero());
+
+    // Truncating division rounds toward zero, and the remainder has the sign of the dividend.
+    let (quotient, remainder) = BigInteger::from(-7).div_rem(&BigInteger::from(2)).unwrap();
+    assert_eq!((quotient, remainder), (BigInteger::from(-3), BigInteger::from(-1)));
+    assert!(BigInteger::from(1).div_rem(&BigInteger::zero()).is_none());
+
+    assert_eq!(BigInteger::from(-12).gcd(&BigInteger::from(18)), BigInteger::from(6));
+    assert!(b < a && BigInteger::from(-1) < BigInteger::zero());
+  }
+
+  #[test]
+  fn conversion_test() {
+    assert_eq!(BigInteger::from(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!(big("-0"), BigInteger::zero());
+    assert_eq!(big("4294967296").to_usize(), Some(1 << 32));
+    assert_eq!(big("1000000000000000000").to_string(), "1000000000000000000");
+    assert!("12a".parse::<BigInteger>().is_err());
+  }
+}
@@ -0,0 +1,282 @@
+/*!
+
+A solver for the bounded systems of linear Diophantine equations that arise when distributing the arguments of an ACU
+subject among the variables of a pattern. The system is a matrix of unknowns `x[i][j]` over the natural numbers, one
+row per subject argument and one column per variable, subject to
+
+  * a row constraint `Σ_j coefficient[j]·x[i][j] = row_value[i]` for every row `i`, and
+  * a column constraint `lower[j] ≤ Σ_i x[i][j] ≤ upper[j]` for every column `j`.
+
+Here `row_value[i]` is the multiplicity of the `i`th subject argument, `coefficient[j]` is the multiplicity of the
+`j`th variable, and `x[i][j]` is the multiplicity with which the `i`th subject argument occurs in the value assigned to
+the `j`th variable.
+
+Solutions are enumerated lazily by a depth first search over the cells in row major order. The last cell of each row is
+forced by the row constraint, and at the end of every row we check that the column lower bounds can still be met by
+what remains, so we never hold more than the current solution in memory.
+
+*/
+
+#[derive(Copy, Clone, Debug)]
+struct Column {
+  coefficient: u32,
+  lower:       u32,
+  upper:       u32,
+}
+
+#[derive(Default)]
+pub struct DiophantineSystem {
+  rows:    Vec<u32>,
+  columns: Vec<Column>,
+
+  // Search state
+  started:        bool,
+  exhausted:      bool,
+  /// The current assignment, in row major order.
+  solution:       Vec<u32>,
+  /// The part of each row value not yet accounted for by the assigned cells of that row.
+  row_remainders: Vec<u32>,
+  column_sums:    Vec<u32>,
+  /// The sum of the values of the rows after the current one, used for pruning.
+  suffix_sums:    Vec<u32>,
+}
+
+impl DiophantineSystem {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a row whose weighted sum must equal `value`.
+  pub fn insert_row(&mut self, value: u32) {
+    assert!(!self.started, "cannot modify a system after solving has started");
+    self.rows.push(value);
+  }
+
+  /// Adds a column with the given coefficient whose sum must lie in `lower..=upper`.
+  pub fn insert_column(&mut self, coefficient: u32, lower: u32, upper: u32) {
+    assert!(!self.started, "cannot modify a system after solving has started");
+    assert!(coefficient > 0, "column coefficient must be positive");
+    self.columns.push(Column {
+      coefficient,
+      lower,
+      upper,
+    });
+  }
+
+  #[inline(always)]
+  pub fn row_count(&self) -> usize {
+    self.rows.len()
+  }
+
+  #[inline(always)]
+  pub fn column_count(&self) -> usize {
+    self.columns.len()
+  }
+
+  /// The value of `x[row][column]` in the current solution. Only meaningful after `solve()` has returned `true`.
+  #[inline(always)]
+  pub fn solution(&self, row: usize, column: usize) -> u32 {
+    self.solution[row * self.columns.len() + column]
+  }
+
+  /// Restarts the enumeration, so that the next call to `solve()` finds the first solution again.
+  pub fn reset(&mut self) {
+    self.started = false;
+    self.exhausted = false;
+  }
+
+  /// Advances to the next solution, returning `false` once they are exhausted. The first call finds the first solution.
+  pub fn solve(&mut self) -> bool {
+    if self.exhausted {
+      return false;
+    }
+
+    let cell_count = self.rows.len() * self.columns.len();
+    // Either the cell we are about to fill, or, when `forward` is false, the cell whose value we must increase.
+    let mut position: usize;
+    let mut forward: bool;
+
+    if !self.started {
+      self.started = true;
+      if !self.initialize() {
+        self.exhausted = true;
+        return false;
+      }
+      if cell_count == 0 {
+        // A system without unknowns has exactly one solution if its constraints are trivially met.
+        let trivial = self.rows.iter().all(|&value| value == 0) && self.columns.iter().all(|column| column.lower == 0);
+        if !trivial {
+          self.exhausted = true;
+        }
+        return trivial;
+      }
+      position = 0;
+      forward = true;
+    } else {
+      if cell_count == 0 {
+        self.exhausted = true;
+        return false;
+      }
+      position = cell_count - 1;
+      forward = false;
+    }
+
+    loop {
+      let assigned = if forward {
+        self.assign(position, 0)
+      } else {
+        let previous = self.unassign(position);
+        self.assign(position, previous + 1)
+      };
+
+      if assigned {
+        position += 1;
+        if position == cell_count {
+          return true;
+        }
+        forward = true;
+      } else {
+        if position == 0 {
+          self.exhausted = true;
+          return false;
+        }
+        position -= 1;
+        forward = false;
+      }
+    }
+  }
+
+  /// Sets up the search state. Returns `false` if the system obviously has no solutions.
+  fn initialize(&mut self) -> bool {
+    let column_count = self.columns.len();
+    self.solution = vec![0; self.rows.len() * column_count];
+    self.row_remainders = self.rows.clone();
+    self.column_sums = vec![0; column_count];
+
+    self.suffix_sums = vec![0; self.rows.len()];
+    let mut sum = 0;
+    for i in (0..self.rows.len()).rev() {
+      self.suffix_sums[i] = sum;
+      sum += self.rows[i];
+    }
+
+    if self.columns.iter().any(|column| column.lower > column.upper) {
+      return false;
+    }
+    // Every unit of a column's lower bound costs `coefficient` units of the row values.
+    let required: u64 = self
+      .columns
+      .iter()
+      .map(|column| column.lower as u64 * column.coefficient as u64)
+      .sum();
+    required <= sum as u64
+  }
+
+  /// Gives the cell at `position` the smallest admissible value that is at least `start`. Returns `false` if there
+  /// is no such value, in which case the cell is left unassigned.
+  fn assign(&mut self, position: usize, start: u32) -> bool {
+    let column_count = self.columns.len();
+    let (row, column) = (position / column_count, position % column_count);
+    let Column { coefficient, upper, .. } = self.columns[column];
+    let remainder = self.row_remainders[row];
+    let room = upper - self.column_sums[column];
+
+    let value = if column + 1 == column_count {
+      // The last cell of the row is forced.
+      if remainder % coefficient != 0 {
+        return false;
+      }
+      let value = remainder / coefficient;
+      if value < start || value > room {
+        return false;
+      }
+      value
+    } else {
+      if start > remainder / coefficient || start > room {
+        return false;
+      }
+      start
+    };
+
+    self.solution[position] = value;
+    self.row_remainders[row] -= value * coefficient;
+    self.column_sums[column] += value;
+
+    if column + 1 == column_count && !self.lower_bounds_reachable(row) {
+      self.unassign(position);
+      return false;
+    }
+    true
+  }
+
+  /// Clears the cell at `position`, returning its old value.
+  fn unassign(&mut self, position: usize) -> u32 {
+    let column_count = self.columns.len();
+    let (row, column) = (position / column_count, position % column_count);
+    let value = self.solution[position];
+
+    self.solution[position] = 0;
+    self.row_remainders[row] += value * self.columns[column].coefficient;
+    self.column_sums[column] -= value;
+    value
+  }
+
+  /// Having completed `row`, checks whether the rows after it can still bring every column up to its lower bound.
+  fn lower_bounds_reachable(&self, row: usize) -> bool {
+    let required: u64 = self
+      .columns
+      .iter()
+      .zip(self.column_sums.iter())
+      .map(|(column, &sum)| column.lower.saturating_sub(sum) as u64 * column.coefficient as u64)
+      .sum();
+    required <= self.suffix_sums[row] as u64
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Collects every solution as a vector of rows.
+  fn all_solutions(system: &mut DiophantineSystem) -> Vec<Vec<Vec<u32>>> {
+    let mut solutions = vec![];
+    while system.solve() {
+      solutions.push(
+        (0..system.row_count())
+          .map(|i| (0..system.column_count()).map(|j| system.solution(i, j)).collect())
+          .collect(),
+      );
+    }
+    solutions
+  }
+
+  #[test]
+  fn bounded_system_test() {
+    // f(X, X, Y) against f(a, a, a, a, b): X gets multiplicity 2, Y gets 1, and neither may be empty.
+    let mut system = DiophantineSystem::new();
+    system.insert_row(4);
+    system.insert_row(1);
+    system.insert_column(2, 1, u32::MAX);
+    system.insert_column(1, 1, u32::MAX);
+
+    let solutions = all_solutions(&mut system);
+    // X=a, Y=a a b  or  X=a a, Y=b.
+    assert_eq!(solutions, vec![vec![vec![1, 2], vec![0, 1]], vec![vec![2, 0], vec![0, 1]]]);
+    assert!(!system.solve());
+
+    // Upper bounds are respected.
+    let mut system = DiophantineSystem::new();
+    system.insert_row(3);
+    system.insert_column(1, 0, 1);
+    system.insert_column(1, 0, 2);
+    assert_eq!(all_solutions(&mut system), vec![vec![vec![1, 2]]]);
+
+    // Lower bounds that cannot be met.
+    let mut system = DiophantineSystem::new();
+    system.insert_row(1);
+    system.insert_column(1, 1, u32::MAX);
+    system.insert_column(1, 1, u32::MAX);
+    assert!(all_solutions(&mut system).is_empty());
+  }
+}
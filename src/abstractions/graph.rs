@@ -29,6 +29,8 @@ impl Graph {
 
   pub(crate) fn color(&self, coloring: &mut Vec<i32>) -> i32 {
     let node_count = self.adj_sets.len();
+    // Every node starts out uncolored, whatever `coloring` held before.
+    coloring.clear();
     coloring.resize(node_count, UNDEFINED);
     let mut max_color = UNDEFINED;
     for i in 0..node_count {
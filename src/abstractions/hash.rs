@@ -72,32 +72,33 @@ impl Hasher for FastHasher {
       v1 = v1.wrapping_add(*byte as u64);
       v2 = v2.shl(7i32).wrapping_add(*byte as u64);
     }
-    self.value = hash3(self.value, v1, v2);
+    // Folding in `v1` first leaves it unchanged when nothing has been written yet.
+    self.value = hash2(hash2(self.value, v1), v2);
   }
 
   #[inline(always)]
   fn write_u32(&mut self, v: u32) {
-    self.value = hash3(self.value, self.value, v as u64)
+    self.value = hash2(self.value, v as u64)
   }
 
   #[inline(always)]
   fn write_u64(&mut self, v: u64) {
-    self.value = hash3(self.value, self.value, v)
+    self.value = hash2(self.value, v)
   }
 
   #[inline(always)]
   fn write_usize(&mut self, v: usize) {
-    self.value = hash3(self.value, self.value, v as u64)
+    self.value = hash2(self.value, v as u64)
   }
 
   #[inline(always)]
   fn write_i32(&mut self, v: i32) {
-    self.value = hash3(self.value, self.value, v as u64)
+    self.value = hash2(self.value, v as u64)
   }
 
   #[inline(always)]
   fn write_i64(&mut self, v: i64) {
-    self.value = hash3(self.value, self.value, v as u64)
+    self.value = hash2(self.value, v as u64)
   }
 }
 
@@ -157,27 +158,27 @@ mod tests {
       .wrapping_add(3u64)
       .shl(7u32)
       .wrapping_add(4u64);
-    let hash_result = hash3(v0, v1, v2);
+    let hash_result = hash2(hash2(v0, v1), v2);
     assert_eq!(hasher.finish(), hash_result);
 
     hasher.write_u32(1u32);
-    let hash_result = hash3(hash_result, hash_result, 1u64);
+    let hash_result = hash2(hash_result, 1u64);
     assert_eq!(hasher.finish(), hash_result);
 
     hasher.write_u64(2u64);
-    let hash_result = hash3(hash_result, hash_result, 2u64);
+    let hash_result = hash2(hash_result, 2u64);
     assert_eq!(hasher.finish(), hash_result);
 
     hasher.write_usize(3usize);
-    let hash_result = hash3(hash_result, hash_result, 3u64);
+    let hash_result = hash2(hash_result, 3u64);
     assert_eq!(hasher.finish(), hash_result);
 
     hasher.write_i32(4i32);
-    let hash_result = hash3(hash_result, hash_result, 4u64);
+    let hash_result = hash2(hash_result, 4u64);
     assert_eq!(hasher.finish(), hash_result);
 
     hasher.write_i64(5i64);
-    let hash_result = hash3(hash_result, hash_result, 5u64);
+    let hash_result = hash2(hash_result, 5u64);
     assert_eq!(hasher.finish(), hash_result);
   }
 
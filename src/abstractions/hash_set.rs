@@ -55,25 +55,33 @@ where
     }
   }
 
-  /// Inserts the value into the set, returning true if the value was not already present.
-  pub fn insert_replace(&mut self, value: T) -> Option<T> {
-    // TODO: Same questions as in `contains`.
+  /// Fetches the value from the set, returning `None` if it is not present.
+  #[inline(always)]
+  pub fn find_for_hash(&self, hash: HashValueType) -> Option<T> {
+    self.inner.get(&hash).cloned()
+  }
+}
+
+// In this impl, `T = Rc<U>`. Items are keyed by the hash of their contents, as are the values they are looked up with,
+// rather than by the pointer that `RcCell` hashes.
+impl<U: ?Sized + Hash> HashSet<RcCell<U>> {
+  #[inline(always)]
+  fn key(&self, value: &RcCell<U>) -> HashValueType {
     let mut fast_hasher = self.inner.hasher().build_hasher();
-    value.hash(&mut fast_hasher);
+    value.borrow().hash(&mut fast_hasher);
+    fast_hasher.finish()
+  }
 
-    let key = fast_hasher.finish();
+  /// Inserts the value into the set, returning the value it replaced, if any.
+  pub fn insert_replace(&mut self, value: RcCell<U>) -> Option<RcCell<U>> {
+    let key = self.key(&value);
     self.inner.insert(key, value)
   }
 
   /// Inserts the value into the set if it is not already present, returning `(found_value, not_present)`, where
   /// `not_present` is true if the value was not already present.
-  pub fn insert_no_replace(&mut self, value: T) -> (T, bool) {
-    // TODO: Same questions as in `contains`.
-    let mut fast_hasher = self.inner.hasher().build_hasher();
-    value.hash(&mut fast_hasher);
-
-    let key = fast_hasher.finish();
-
+  pub fn insert_no_replace(&mut self, value: RcCell<U>) -> (RcCell<U>, bool) {
+    let key = self.key(&value);
     match self.inner.entry(key) {
       Entry::Occupied(entry) => (entry.get().clone(), false),
       Entry::Vacant(entry) => {
@@ -82,12 +90,6 @@ where
       }
     }
   }
-
-  /// Fetches the value from the set, returning `None` if it is not present.
-  #[inline(always)]
-  pub fn find_for_hash(&self, hash: HashValueType) -> Option<T> {
-    self.inner.get(&hash).cloned()
-  }
 }
 
 // In this impl, `T = Rc<U>`
@@ -7,6 +7,8 @@ access to its contents and supports weak references. A number of external crates
 module redirects to whatever chosen implementation we want.
 
 */
+mod big_integer;
+mod diophantine;
 mod graph;
 mod hash;
 mod hash_set;
@@ -16,6 +18,10 @@ mod rccell;
 
 use std::{collections::HashSet as StdHashSet, iter::once};
 
+// Arbitrary precision integers
+pub use big_integer::BigInteger;
+// Bounded systems of linear Diophantine equations
+pub use diophantine::DiophantineSystem;
 pub use graph::Graph;
 // Fast and simple hash functions
 pub use hash::{hash2, hash3, FastHasher, FastHasherBuilder};
@@ -32,9 +38,6 @@ pub use string_cache::DefaultAtom as IString;
 pub use tiny_logger::{log, set_verbosity, Channel};
 pub use yansi::{Color, Paint, Style};
 
-/// Arbitrary precision integers
-pub type BigInteger = isize; // ToDo: An `isize` is not "arbitrary precision."
-
 /// A `ThingSet` is a hash set of `*const dyn Things`. They are useful if you need to test membership but never need
 /// to access the original `Thing`.
 pub type Set<T> = StdHashSet<*const T>; // This replaces Maude's `PointerSet` in most situations.
@@ -0,0 +1,128 @@
+/*!
+
+The built-in rational division `_/_`. A rational number is `n/d` with an integer numerator `n ≠ 0` and a natural
+denominator `d > 1` such that `gcd(n, d) = 1`. Once the arguments are reduced, a quotient of numbers is put into this
+form natively: common factors are cancelled, and if the denominator becomes `1` the subject is replaced by the
+numerator. Division by zero is left to the user's equations.
+
+*/
+
+use std::any::Any;
+
+use super::NumberSystem;
+use crate::{
+  abstractions::{BigInteger, IString},
+  core::rewrite_context::RewritingContext,
+  theory::{
+    free_theory::FreeDagNode,
+    make_rc_dag_node,
+    BasicSymbolTypes,
+    DagNode,
+    RcDagNode,
+    Symbol,
+    SymbolMembers,
+    SymbolType,
+  },
+};
+
+
+pub struct DivisionSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  symbol_type: SymbolType,
+  numbers:     NumberSystem,
+}
+
+impl DivisionSymbol {
+  pub fn new(name: IString, numbers: NumberSystem) -> DivisionSymbol {
+    let mut symbol_type = SymbolType::default();
+    symbol_type.set_basic_type(BasicSymbolTypes::DivisionSymbol);
+
+    DivisionSymbol {
+      symbol_members: SymbolMembers::new(name, 2, false),
+      symbol_type,
+      numbers,
+    }
+  }
+
+  #[inline(always)]
+  pub fn symbol_type(&self) -> SymbolType {
+    self.symbol_type
+  }
+
+  /// If `dag_node` is a rational number in normal form (which includes the integers), returns its numerator and
+  /// denominator.
+  pub fn get_rational(&self, dag_node: &dyn DagNode) -> Option<(BigInteger, BigInteger)> {
+    if dag_node.symbol().as_ref().eq(self) {
+      let mut args = dag_node.iter_args();
+      let numerator = self.numbers.get_integer(&**args.next()?.borrow())?;
+      let denominator = self.numbers.get_nat(&**args.next()?.borrow())?;
+      return Some((numerator, denominator));
+    }
+    self
+      .numbers
+      .get_integer(dag_node)
+      .map(|numerator| (numerator, BigInteger::one()))
+  }
+
+  /// The normal form of the quotient `subject`, if it is a quotient of numbers that is not already in normal form.
+  fn evaluate(&self, subject: &dyn DagNode) -> Option<RcDagNode> {
+    let mut args = subject.iter_args();
+    let numerator = self.numbers.get_integer(&**args.next()?.borrow())?;
+    let denominator = self.numbers.get_nat(&**args.next()?.borrow())?;
+    if denominator.is_zero() {
+      return None;
+    }
+
+    let gcd = numerator.gcd(&denominator);
+    if gcd.is_one() && !denominator.is_one() {
+      // Already in normal form.
+      return None;
+    }
+    let numerator = &numerator / &gcd;
+    let denominator = &denominator / &gcd;
+    if denominator.is_one() {
+      return self.numbers.make_integer_dag(&numerator);
+    }
+
+    let mut quotient = FreeDagNode::new(subject.symbol());
+    quotient.members.args.push(self.numbers.make_integer_dag(&numerator)?);
+    quotient.members.args.push(self.numbers.make_nat_dag(&denominator));
+    Some(make_rc_dag_node(quotient))
+  }
+}
+
+impl Symbol for DivisionSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
+    }
+
+    let result = self.evaluate(&**subject.borrow());
+    if let Some(result) = result {
+      context.builtin_replace(subject, result);
+      return true;
+    }
+
+    self.symbol_members.apply_replace(subject, context)
+  }
+}
@@ -0,0 +1,80 @@
+/*!
+
+The LHS and RHS automata for literals. A literal pattern matches exactly the literals with its symbol and value, and a
+literal in a right-hand side builds a fresh node with its value.
+
+*/
+
+use super::{Literal, LiteralDagNode};
+use crate::{
+  core::{substitution::Substitution, VariableInfo},
+  theory::{make_rc_dag_node, DagNode, LHSAutomaton, MaybeDagNode, MaybeSubproblem, RHSAutomaton, RcDagNode, RcSymbol},
+};
+
+
+pub struct LiteralLHSAutomaton {
+  top_symbol: RcSymbol,
+  value:      Literal,
+}
+
+impl LiteralLHSAutomaton {
+  pub fn new(top_symbol: RcSymbol, value: Literal) -> Self {
+    LiteralLHSAutomaton { top_symbol, value }
+  }
+}
+
+impl LHSAutomaton for LiteralLHSAutomaton {
+  fn match_(&mut self, subject: RcDagNode, _solution: &mut Substitution) -> (bool, MaybeSubproblem) {
+    let subject_ref = subject.borrow();
+    if subject_ref.symbol().as_ref() != self.top_symbol.as_ref() {
+      return (false, None);
+    }
+    match subject_ref.as_any().downcast_ref::<LiteralDagNode>() {
+      Some(literal) => (literal.value() == &self.value, None),
+      None => unreachable!("Could not downcast to LiteralDagNode. This is a bug."),
+    }
+  }
+}
+
+
+pub struct LiteralRHSAutomaton {
+  top_symbol:  RcSymbol,
+  value:       Literal,
+  destination: i32,
+}
+
+impl LiteralRHSAutomaton {
+  pub fn new(top_symbol: RcSymbol, value: Literal, destination: i32) -> Self {
+    LiteralRHSAutomaton {
+      top_symbol,
+      value,
+      destination,
+    }
+  }
+}
+
+impl RHSAutomaton for LiteralRHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let new_dag_node: RcDagNode = make_rc_dag_node(LiteralDagNode::new(self.top_symbol.clone(), self.value.clone()));
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, _matcher: &mut Substitution) {
+    let mut new_dag_node = LiteralDagNode::new(self.top_symbol.clone(), self.value.clone());
+    new_dag_node.overwrite_with_clone(old);
+  }
+}
@@ -0,0 +1,128 @@
+/*!
+
+DAG nodes of float and string literals. A literal has no arguments, so most of the copying operations are trivial.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{Literal, LiteralTerm};
+use crate::{
+  abstractions::{hash2, RcCell},
+  core::{hash_cons_set::HashConsSet, sort::SpecialSort, RedexPosition},
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, DagNodeFlag, DagNodeFlags, DagNodeMembers, NodeList, RcDagNode, RcSymbol, RcTerm},
+};
+
+
+pub struct LiteralDagNode {
+  pub(crate) members: DagNodeMembers,
+  pub(crate) value:   Literal,
+}
+
+impl LiteralDagNode {
+  pub fn new(symbol: RcSymbol, value: Literal) -> Self {
+    LiteralDagNode {
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args:       NodeList::new(),
+        flags:      DagNodeFlags::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc:  None,
+        hash:       0,
+      },
+      value,
+    }
+  }
+
+  #[inline(always)]
+  pub fn value(&self) -> &Literal {
+    &self.value
+  }
+}
+
+impl DagNode for LiteralDagNode {
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
+  }
+
+  fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<LiteralDagNode>() {
+      Some(other) => self.value.compare(&other.value),
+      None => unreachable!("Could not downcast a DagNode to a LiteralDagNode. This is a bug."),
+    }
+  }
+
+  fn structural_hash(&self) -> u32 {
+    hash2(self.symbol().semantic_hash(), self.value.hash_value())
+  }
+
+  /// Every literal of a kind has the range sort of its symbol.
+  fn compute_base_sort(&mut self) -> i32 {
+    let index = self.symbol().sort_table().get_range_sort().borrow().sort_index;
+    self.set_sort_index(index);
+    index
+  }
+
+  fn termify(&self) -> RcTerm {
+    rc_cell!(LiteralTerm::new(self.symbol(), self.value.clone()))
+  }
+
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = LiteralDagNode::new(self.symbol(), self.value.clone());
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacements(&self, _stack: &[RedexPosition], _first_idx: usize, _last_idx: usize) -> RcDagNode {
+    unreachable!("A literal has no arguments to replace. This is a bug.")
+  }
+
+  fn copy_with_replacement(&self, _replacement: RcDagNode, _arg_index: usize) -> RcDagNode {
+    unreachable!("A literal has no arguments to replace. This is a bug.")
+  }
+
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    make_rc_dag_node(LiteralDagNode::new(self.symbol(), self.value.clone()))
+  }
+
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    make_rc_dag_node(LiteralDagNode::new(self.symbol(), self.value.clone()))
+  }
+
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = LiteralDagNode::new(self.symbol(), self.value.clone());
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() | DagNodeFlag::Reduced | DagNodeFlag::Unrewritable | DagNodeFlag::Ground);
+
+    *old.borrow_mut() = Box::new(new_node);
+  }
+
+  /// A literal has no arguments, so it is its own canonical version.
+  fn make_canonical(&self, node: RcDagNode, _hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    node
+  }
+}
@@ -0,0 +1,153 @@
+/*!
+
+Floating point and string literals. These are constants whose value is not determined by their symbol, so every
+literal term and DAG node carries its value, and two literals are equal when they have the same symbol and the same
+value. This is Maude's non-algebraic (NA) theory.
+
+There is one symbol for all floats and one for all strings, distinguished by their basic symbol types
+`BasicSymbolTypes::Float` and `BasicSymbolTypes::String`. Integer literals are not handled here: they are numbers in a
+`NumberSystem`.
+
+*/
+
+mod automaton;
+mod dag_node;
+mod symbol;
+mod term;
+
+use std::{
+  cmp::Ordering,
+  fmt::{Display, Formatter},
+};
+
+pub use automaton::{LiteralLHSAutomaton, LiteralRHSAutomaton};
+pub use dag_node::LiteralDagNode;
+pub use symbol::LiteralSymbol;
+pub use term::LiteralTerm;
+
+use crate::abstractions::IString;
+
+
+/// The value of a literal.
+#[derive(Clone, Debug)]
+pub enum Literal {
+  Float(f64),
+  String(IString),
+}
+
+impl Literal {
+  /// A hash of the value, consistent with `compare`.
+  pub fn hash_value(&self) -> u32 {
+    match self {
+      // `compare` identifies `0.0` with `-0.0`.
+      Literal::Float(value) if *value == 0.0 => 0,
+      Literal::Float(value) => {
+        let bits = value.to_bits();
+        (bits ^ (bits >> 32)) as u32
+      }
+      Literal::String(value) => IString::get_hash(value),
+    }
+  }
+
+  /// Floats are ordered numerically, with all NaNs equal and greater than every number, and strings lexicographically.
+  /// Floats come before strings, but literals of different kinds only meet if they somehow share a symbol.
+  pub fn compare(&self, other: &Literal) -> Ordering {
+    match (self, other) {
+      (Literal::Float(a), Literal::Float(b)) => {
+        match (a.is_nan(), b.is_nan()) {
+          (false, false) => a.partial_cmp(b).unwrap(),
+          (a_is_nan, b_is_nan) => a_is_nan.cmp(&b_is_nan),
+        }
+      }
+      (Literal::String(a), Literal::String(b)) => a.as_ref().cmp(b.as_ref()),
+      (Literal::Float(_), Literal::String(_)) => Ordering::Less,
+      (Literal::String(_), Literal::Float(_)) => Ordering::Greater,
+    }
+  }
+}
+
+impl PartialEq for Literal {
+  fn eq(&self, other: &Self) -> bool {
+    self.compare(other).is_eq()
+  }
+}
+
+impl Eq for Literal {}
+
+impl Display for Literal {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      // Always show a decimal point so that floats cannot be mistaken for integers.
+      Literal::Float(value) if value.is_finite() && value.fract() == 0.0 => write!(f, "{:.1}", value),
+      Literal::Float(value) => write!(f, "{}", value),
+      Literal::String(value) => write!(f, "{:?}", value.as_ref()),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::{NatSet, RcCell},
+    builtin::BuiltinSorts,
+    core::{substitution::Substitution, VariableInfo},
+    rc_cell,
+    theory::{BasicSymbolTypes, DagNode, RcSymbol, RcTerm, Term},
+  };
+
+  fn make_literal(symbol: &RcSymbol, value: Literal) -> RcTerm {
+    rc_cell!(LiteralTerm::new(symbol.clone(), value))
+  }
+
+  #[test]
+  fn literal_equality_test() {
+    let sorts = BuiltinSorts::new();
+    let floats: RcSymbol = Rc::new(LiteralSymbol::new(BasicSymbolTypes::Float, &sorts));
+    let strings: RcSymbol = Rc::new(LiteralSymbol::new(BasicSymbolTypes::String, &sorts));
+
+    let a = make_literal(&floats, Literal::Float(2.5));
+    let b = make_literal(&floats, Literal::Float(2.5));
+    let c = make_literal(&floats, Literal::Float(-0.0));
+    let d = make_literal(&floats, Literal::Float(0.0));
+    let e = make_literal(&strings, Literal::String(IString::from("abc")));
+
+    assert!(a.borrow().compare(&*b.borrow()).is_eq());
+    assert_eq!(a.borrow().semantic_hash(), b.borrow().semantic_hash());
+    assert!(c.borrow().compare(&*d.borrow()).is_eq());
+    assert_eq!(c.borrow().semantic_hash(), d.borrow().semantic_hash());
+    assert!(a.borrow().compare(&*d.borrow()).is_gt());
+    assert!(a.borrow().compare(&*e.borrow()).is_ne());
+
+    assert_eq!(a.borrow().to_string(), "2.5");
+    assert_eq!(d.borrow().to_string(), "0.0");
+    assert_eq!(e.borrow().to_string(), "\"abc\"");
+
+    // DAG nodes compare like their terms.
+    let a_dag = a.borrow().make_dag();
+    let e_dag = e.borrow().make_dag();
+    assert!(a.borrow().compare_dag_node(&**a_dag.borrow()).is_eq());
+    assert!(a_dag.borrow().compare(&**e_dag.borrow()).is_ne());
+    assert!(e_dag.borrow().termify().borrow().compare(&*e.borrow()).is_eq());
+  }
+
+  #[test]
+  fn literal_match_test() {
+    let sorts = BuiltinSorts::new();
+    let strings: RcSymbol = Rc::new(LiteralSymbol::new(BasicSymbolTypes::String, &sorts));
+    let pattern = make_literal(&strings, Literal::String(IString::from("abc")));
+    let (automaton, _) = pattern
+      .borrow()
+      .compile_lhs(true, &VariableInfo::default(), &mut NatSet::default());
+
+    let mut solution = Substitution::with_capacity(0);
+    let same = make_literal(&strings, Literal::String(IString::from("abc"))).borrow().make_dag();
+    let different = make_literal(&strings, Literal::String(IString::from("abd"))).borrow().make_dag();
+
+    assert!(automaton.borrow_mut().match_(same, &mut solution).0);
+    assert!(!automaton.borrow_mut().match_(different, &mut solution).0);
+  }
+}
@@ -0,0 +1,72 @@
+/*!
+
+The symbol of float or string literals. The value lives in the term or DAG node, so there is a single symbol for each
+kind of literal.
+
+*/
+
+use std::any::Any;
+
+use crate::{
+  abstractions::IString,
+  builtin::{declare_builtin_op, BuiltinSorts},
+  core::rewrite_context::RewritingContext,
+  theory::{BasicSymbolTypes, RcDagNode, Symbol, SymbolMembers, SymbolType},
+};
+
+
+pub struct LiteralSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  symbol_type: SymbolType,
+}
+
+impl LiteralSymbol {
+  /// Makes the symbol for literals of the given basic type, which must be `Float` or `String`. The symbol gets Maude's
+  /// name for it, `<Floats>` or `<Strings>`, and is declared with the built-in sort `Float` or `String`.
+  pub fn new(basic_type: BasicSymbolTypes, sorts: &BuiltinSorts) -> LiteralSymbol {
+    let (name, sort) = match basic_type {
+      BasicSymbolTypes::Float => ("<Floats>", sorts.float()),
+      BasicSymbolTypes::String => ("<Strings>", sorts.string()),
+      _ => unreachable!("{:?} is not a literal type. This is a bug.", basic_type),
+    };
+    let mut symbol_type = SymbolType::default();
+    symbol_type.set_basic_type(basic_type);
+
+    let mut symbol = LiteralSymbol {
+      symbol_members: SymbolMembers::new(IString::from(name), 0, false),
+      symbol_type,
+    };
+    declare_builtin_op(&mut symbol, vec![sort.clone()]);
+    symbol
+  }
+
+  #[inline(always)]
+  pub fn symbol_type(&self) -> SymbolType {
+    self.symbol_type
+  }
+}
+
+impl Symbol for LiteralSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+    // A literal has no arguments to evaluate, but the user may still have equations for particular values.
+    self.symbol_members.apply_replace(subject, context)
+  }
+}
@@ -0,0 +1,161 @@
+/*!
+
+Terms of float and string literals. A literal is ground and has no arguments, so the compiler-related methods are
+short: the LHS automaton compares values, and the RHS automaton makes a new node with the value.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{Literal, LiteralDagNode, LiteralLHSAutomaton, LiteralRHSAutomaton};
+use crate::{
+  abstractions::{hash2 as term_hash, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    BindingLHSAutomaton,
+    TermBag,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, NodeCache, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm, Term, TermMembers},
+  NONE,
+};
+
+
+pub struct LiteralTerm {
+  pub(crate) term_members: TermMembers,
+  pub(crate) value:        Literal,
+}
+
+impl LiteralTerm {
+  pub fn new(symbol: RcSymbol, value: Literal) -> LiteralTerm {
+    LiteralTerm {
+      term_members: TermMembers::new(symbol),
+      value,
+    }
+  }
+
+  #[inline(always)]
+  pub fn value(&self) -> &Literal {
+    &self.value
+  }
+}
+
+
+impl Term for LiteralTerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  fn semantic_hash(&self) -> u32 {
+    term_hash(self.symbol().semantic_hash(), self.value.hash_value())
+  }
+
+  fn normalize(&mut self, _full: bool) -> (u32, bool) {
+    (self.semantic_hash(), false)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(std::iter::empty::<RcTerm>())
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    match other.as_any().downcast_ref::<LiteralTerm>() {
+      Some(other) => self.value.compare(&other.value),
+      None => unreachable!("Could not downcast Term to LiteralTerm. This is a bug."),
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<LiteralDagNode>() {
+      Some(other) => self.value.compare(other.value()),
+      None => unreachable!("Could not downcast DagNode to LiteralDagNode. This is a bug."),
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, _sub_dags: &mut NodeCache, _set_sort_info: bool) -> RcDagNode {
+    make_rc_dag_node(LiteralDagNode::new(self.symbol(), self.value.clone()))
+  }
+
+  // region Compiler-related
+  fn compile_lhs(
+    &self,
+    _match_at_top: bool,
+    _variable_info: &VariableInfo,
+    _bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    let mut automaton: RcLHSAutomaton = rc_cell!(LiteralLHSAutomaton::new(self.symbol(), self.value.clone()));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    (automaton, false)
+  }
+
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    _available_terms: &mut TermBag,
+    _eager_context: bool,
+  ) -> i32 {
+    let index = variable_info.make_construction_index();
+    rhs_builder.add_rhs_automaton(Box::new(LiteralRHSAutomaton::new(
+      self.symbol(),
+      self.value.clone(),
+      index,
+    )));
+    index
+  }
+
+  fn analyse_constraint_propagation(&mut self, _bound_uniquely: &mut NatSet) {
+    // A literal binds no variables.
+  }
+
+  fn find_available_terms_aux(&self, _available_terms: &mut TermBag, _eager_context: bool, _at_top: bool) {
+    // A literal is ground, so there is nothing to find.
+  }
+
+  // endregion
+}
+
+
+impl Formattable for LiteralTerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    match style {
+      FormatStyle::Debug => format!("lit<{}>", self.value),
+      _ => self.value.to_string(),
+    }
+  }
+}
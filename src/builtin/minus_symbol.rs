@@ -0,0 +1,93 @@
+/*!
+
+The built-in unary minus `-_`. A term `-(s^n(0))` with `n > 0` is a negative integer and is in normal form. Minus
+applied to zero or to a negative integer is evaluated natively: `-(0)` is `0` and `-(-(s^n(0)))` is `s^n(0)`. Anything
+else is left to the user's equations.
+
+*/
+
+use std::any::Any;
+
+use super::NumberSystem;
+use crate::{
+  abstractions::IString,
+  core::rewrite_context::RewritingContext,
+  theory::{BasicSymbolTypes, DagNode, RcDagNode, Symbol, SymbolMembers, SymbolType},
+};
+
+
+pub struct MinusSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  symbol_type: SymbolType,
+  /// The natural numbers we negate. Its `minus_symbol` is not used.
+  numbers:     NumberSystem,
+}
+
+impl MinusSymbol {
+  pub fn new(name: IString, numbers: NumberSystem) -> MinusSymbol {
+    let mut symbol_type = SymbolType::default();
+    symbol_type.set_basic_type(BasicSymbolTypes::MinusSymbol);
+
+    MinusSymbol {
+      symbol_members: SymbolMembers::new(name, 1, false),
+      symbol_type,
+      numbers,
+    }
+  }
+
+  #[inline(always)]
+  pub fn symbol_type(&self) -> SymbolType {
+    self.symbol_type
+  }
+
+  /// The node that `-(arg)` evaluates to, if it is not already a negative number in normal form.
+  fn evaluate(&self, arg: RcDagNode) -> Option<RcDagNode> {
+    let arg_ref = arg.borrow();
+    if self.numbers.is_zero(&**arg_ref) {
+      return Some(arg.clone());
+    }
+    if arg_ref.symbol().as_ref().eq(self) {
+      let inner = arg_ref.iter_args().next()?;
+      if self.numbers.get_nat(&**inner.borrow()).is_some() {
+        return Some(inner);
+      }
+    }
+    None
+  }
+}
+
+impl Symbol for MinusSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    let arg = match subject.borrow().iter_args().next() {
+      Some(arg) => arg,
+      None => unreachable!("MinusSymbol node without an argument. This is a bug."),
+    };
+    context.reduce_dag_node(arg.clone());
+
+    if let Some(result) = self.evaluate(arg) {
+      context.builtin_replace(subject, result);
+      return true;
+    }
+
+    self.symbol_members.apply_replace(subject, context)
+  }
+}
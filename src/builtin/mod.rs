@@ -0,0 +1,159 @@
+/*!
+
+Built-in symbols whose operations are evaluated natively rather than by equations. Numbers are terms over a successor
+symbol in the S theory, so a natural number of any size is a single node `s^n(0)`, and the built-in operations compute
+on the `BigInteger` exponents.
+
+  * `NumberSystem` - the successor, zero, and minus symbols, and conversions between numbers and DAGs.
+  * `NumberOpSymbol` - a free binary operation `+ - * quo rem gcd` on integers.
+  * `MinusSymbol` - unary minus, which also represents the negative integers.
+  * `DivisionSymbol` - rational division, kept in lowest terms.
+  * `literal` - float and string literals, whose values are stored in their terms and DAG nodes.
+  * `BuiltinSorts` - the sorts `Float`, `String`, and `NzNat < Nat < Int` that built-in symbols are declared with.
+
+AC operations on an ACU symbol are attached to the `ACUSymbol` itself with `ACUSymbol::attach_number_op`, because the
+ACU theory needs its symbols to be `ACUSymbol`s.
+
+*/
+
+mod division_symbol;
+pub mod literal;
+mod minus_symbol;
+mod number_op;
+mod number_op_symbol;
+mod number_system;
+mod sorts;
+
+pub use division_symbol::DivisionSymbol;
+pub use minus_symbol::MinusSymbol;
+pub use number_op::NumberOp;
+pub use number_op_symbol::NumberOpSymbol;
+pub use number_system::NumberSystem;
+pub use sorts::{declare_builtin_op, BuiltinSorts};
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::{BigInteger, IString, RcCell},
+    core::{module::Module, Strategy},
+    rc_cell,
+    theory::{
+      acu_theory::{ACUSymbol, ACUTerm},
+      free_theory::{FreeSymbol, FreeTerm},
+      s_theory::SSymbol,
+      test_fixtures::{add_constant, add_s_symbol, add_symbol, make_context, make_sorts},
+      RcSymbol,
+      RcTerm,
+      SymbolType,
+    },
+  };
+
+  fn make_numbers() -> NumberSystem {
+    let succ_symbol: RcSymbol = Rc::new(SSymbol::new(IString::from("s_"), Strategy::default(), false));
+    let zero_symbol: RcSymbol = Rc::new(FreeSymbol::new(IString::from("0"), 0, false, Strategy::default()));
+    let numbers = NumberSystem::new(succ_symbol, zero_symbol);
+    let minus_symbol: RcSymbol = Rc::new(MinusSymbol::new(IString::from("-_"), numbers.clone()));
+
+    numbers.with_minus(minus_symbol)
+  }
+
+  #[test]
+  fn number_dag_test() {
+    let numbers = make_numbers();
+
+    for text in ["0", "1", "-1", "18446744073709551617", "-340282366920938463463374607431768211457"] {
+      let value: BigInteger = text.parse().unwrap();
+      let dag = numbers.make_integer_dag(&value).unwrap();
+      assert_eq!(numbers.get_integer(&**dag.borrow()), Some(value.clone()));
+      assert_eq!(numbers.get_nat(&**dag.borrow()).is_some(), !value.is_negative());
+    }
+
+    // Without a minus symbol there are no negative numbers.
+    let naturals = NumberSystem::new(numbers.succ_symbol.clone(), numbers.zero_symbol.clone());
+    assert!(naturals.make_integer_dag(&BigInteger::from(-3)).is_none());
+  }
+
+  #[test]
+  fn number_op_test() {
+    let seven = BigInteger::from(7);
+    let minus_two = BigInteger::from(-2);
+
+    assert_eq!(NumberOp::from_name("_quo_"), Some(NumberOp::Quo));
+    assert_eq!(NumberOp::Quo.evaluate(&seven, &minus_two), Some(BigInteger::from(-3)));
+    assert_eq!(NumberOp::Rem.evaluate(&seven, &minus_two), Some(BigInteger::from(1)));
+    assert_eq!(NumberOp::Rem.evaluate(&seven, &BigInteger::zero()), None);
+    assert_eq!(NumberOp::Minus.evaluate(&minus_two, &seven), Some(BigInteger::from(-9)));
+
+    // 3 + 3 + 4, 2 * 2 * 2 * 5, and gcd(12, 12, 18).
+    let values = [(BigInteger::from(3), 2), (BigInteger::from(4), 1)];
+    assert_eq!(NumberOp::Plus.fold(&values), Some(BigInteger::from(10)));
+    let values = [(BigInteger::from(2), 3), (BigInteger::from(5), 1)];
+    assert_eq!(NumberOp::Times.fold(&values), Some(BigInteger::from(40)));
+    let values = [(BigInteger::from(12), 2), (BigInteger::from(18), 1)];
+    assert_eq!(NumberOp::Gcd.fold(&values), Some(BigInteger::from(6)));
+  }
+
+  #[test]
+  fn number_op_reduce_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let succ_symbol = add_s_symbol(&module, &nat);
+    let zero_symbol = add_constant(&module, "0", &nat).borrow().symbol();
+    let a = add_constant(&module, "a", &nat);
+    let numbers = NumberSystem::new(succ_symbol, zero_symbol);
+
+    let mut plus = ACUSymbol::new(IString::from("_+_"), SymbolType::default(), Strategy::default(), false, None);
+    plus.attach_number_op(NumberOp::Plus, numbers.clone());
+    let plus = add_symbol(&module, plus, &nat);
+    let quo = NumberOpSymbol::new(IString::from("_quo_"), NumberOp::Quo, numbers.clone());
+    let quo = add_symbol(&module, quo, &nat);
+    let gcd = NumberOpSymbol::new(IString::from("gcd"), NumberOp::Gcd, numbers.clone());
+    let gcd = add_symbol(&module, gcd, &nat);
+    Module::close_theory(&module);
+
+    let number = |value: i32| numbers.make_integer_term(&BigInteger::from(value)).unwrap();
+    let apply = |symbol: &RcSymbol, args: Vec<RcTerm>| -> RcTerm {
+      if symbol.as_ref() == plus.as_ref() {
+        rc_cell!(ACUTerm::new(symbol.clone(), args))
+      } else {
+        rc_cell!(FreeTerm::with_args(symbol.clone(), args))
+      }
+    };
+    // Reduces `term` in place, returning the number it reduced to, if any, and the count of equational rewrites.
+    let reduce = |term: RcTerm| {
+      let subject = term.borrow().make_dag();
+      let mut context = make_context(&module, subject.clone());
+      context.reduce();
+      assert!(subject.borrow().is_reduced());
+      let value = numbers.get_integer(&**subject.borrow());
+      (value, context.eq_count())
+    };
+
+    // 3 + 4 + 3 folds to the S node s^10(0), and quo and gcd replace their free nodes by their results.
+    assert_eq!(reduce(apply(&plus, vec![number(3), number(4), number(3)])), (Some(BigInteger::from(10)), 1));
+    assert_eq!(reduce(apply(&quo, vec![number(7), number(2)])), (Some(BigInteger::from(3)), 1));
+    assert_eq!(reduce(apply(&gcd, vec![number(12), number(18)])), (Some(BigInteger::from(6)), 1));
+
+    // A zero result is the constant 0, without the arguments of the node it replaces.
+    for term in [
+      apply(&plus, vec![number(0), number(0)]),
+      apply(&quo, vec![number(2), number(5)]),
+      apply(&gcd, vec![number(0), number(0)]),
+    ] {
+      assert_eq!(reduce(term), (Some(BigInteger::zero()), 1));
+    }
+
+    // Arguments are reduced first, and numbers are folded past other arguments.
+    let nested = apply(&quo, vec![apply(&plus, vec![number(5), number(4)]), apply(&gcd, vec![number(6), number(4)])]);
+    assert_eq!(reduce(nested), (Some(BigInteger::from(4)), 3));
+    let subject = apply(&plus, vec![a.clone(), number(3), number(4)]).borrow().make_dag();
+    let mut context = make_context(&module, subject.clone());
+    context.reduce();
+    let expected = apply(&plus, vec![a.clone(), number(7)]).borrow().make_dag();
+    assert!(subject.borrow().compare(&**expected.borrow()).is_eq());
+  }
+}
@@ -0,0 +1,73 @@
+/*!
+
+The built-in operations on integers. Division follows Maude: `quo` truncates toward zero, `rem` takes the sign of the
+dividend, and both are undefined (the term does not evaluate) for a zero divisor.
+
+*/
+
+use crate::abstractions::BigInteger;
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum NumberOp {
+  Plus,
+  Minus,
+  Times,
+  Quo,
+  Rem,
+  Gcd,
+}
+
+impl NumberOp {
+  /// The operation a built-in symbol with the given name stands for.
+  pub fn from_name(name: &str) -> Option<NumberOp> {
+    match name {
+      "+" | "_+_" => Some(NumberOp::Plus),
+      "-" | "_-_" => Some(NumberOp::Minus),
+      "*" | "_*_" => Some(NumberOp::Times),
+      "quo" | "_quo_" => Some(NumberOp::Quo),
+      "rem" | "_rem_" => Some(NumberOp::Rem),
+      "gcd" => Some(NumberOp::Gcd),
+      _ => None,
+    }
+  }
+
+  /// Whether the operation is associative and commutative, so that it can be attached to an ACU symbol.
+  #[inline(always)]
+  pub fn is_ac(&self) -> bool {
+    matches!(self, NumberOp::Plus | NumberOp::Times | NumberOp::Gcd)
+  }
+
+  /// Applies the operation. Returns `None` if it is undefined for these arguments.
+  pub fn evaluate(&self, a: &BigInteger, b: &BigInteger) -> Option<BigInteger> {
+    match self {
+      NumberOp::Plus => Some(a + b),
+      NumberOp::Minus => Some(a - b),
+      NumberOp::Times => Some(a * b),
+      NumberOp::Quo => a.div_rem(b).map(|(quotient, _)| quotient),
+      NumberOp::Rem => a.div_rem(b).map(|(_, remainder)| remainder),
+      NumberOp::Gcd => Some(a.gcd(b)),
+    }
+  }
+
+  /// Folds an AC operation over `multiplicity` copies of each value, for the arguments of an ACU node.
+  pub fn fold(&self, values: &[(BigInteger, u32)]) -> Option<BigInteger> {
+    assert!(self.is_ac(), "only AC operations can be folded");
+    let mut accumulator: Option<BigInteger> = None;
+
+    for (value, multiplicity) in values {
+      // Repeated addition is a multiplication, and the gcd is idempotent.
+      let combined = match self {
+        NumberOp::Plus => value * &BigInteger::from(*multiplicity),
+        NumberOp::Times => (1..*multiplicity).fold(value.clone(), |product, _| &product * value),
+        _ => value.clone(),
+      };
+      accumulator = Some(match accumulator {
+        Some(accumulator) => self.evaluate(&accumulator, &combined)?,
+        None => combined,
+      });
+    }
+
+    accumulator
+  }
+}
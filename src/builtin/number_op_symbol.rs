@@ -0,0 +1,98 @@
+/*!
+
+A binary built-in operation on integers, such as `_+_` or `gcd`, in the free theory. Once its arguments are reduced,
+if both are numbers the operation is evaluated natively and the subject is replaced by the result. Otherwise, and when
+the operation is undefined (a zero divisor, or a negative result with no minus symbol), the user's equations are tried
+as for any free symbol.
+
+AC operations declared on an ACU symbol are handled by `ACUSymbol` itself; see `ACUSymbol::attach_number_op`.
+
+*/
+
+use std::any::Any;
+
+use super::{NumberOp, NumberSystem};
+use crate::{
+  abstractions::IString,
+  core::rewrite_context::RewritingContext,
+  theory::{BasicSymbolTypes, DagNode, RcDagNode, Symbol, SymbolMembers, SymbolType},
+};
+
+
+pub struct NumberOpSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  symbol_type: SymbolType,
+  op:          NumberOp,
+  numbers:     NumberSystem,
+}
+
+impl NumberOpSymbol {
+  pub fn new(name: IString, op: NumberOp, numbers: NumberSystem) -> NumberOpSymbol {
+    let mut symbol_type = SymbolType::default();
+    symbol_type.set_basic_type(BasicSymbolTypes::NumberOpSymbol);
+
+    NumberOpSymbol {
+      symbol_members: SymbolMembers::new(name, 2, false),
+      symbol_type,
+      op,
+      numbers,
+    }
+  }
+
+  #[inline(always)]
+  pub fn op(&self) -> NumberOp {
+    self.op
+  }
+
+  #[inline(always)]
+  pub fn symbol_type(&self) -> SymbolType {
+    self.symbol_type
+  }
+
+  /// The result of applying our operation to the arguments of `subject`, if they are numbers and it is defined.
+  fn evaluate(&self, subject: &dyn DagNode) -> Option<RcDagNode> {
+    let mut args = subject.iter_args();
+    let a = self.numbers.get_integer(&**args.next()?.borrow())?;
+    let b = self.numbers.get_integer(&**args.next()?.borrow())?;
+
+    self.numbers.make_integer_dag(&self.op.evaluate(&a, &b)?)
+  }
+}
+
+impl Symbol for NumberOpSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    // Built-in operations only support the standard strategy.
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
+    }
+
+    let result = self.evaluate(&**subject.borrow());
+    if let Some(result) = result {
+      context.builtin_replace(subject, result);
+      return true;
+    }
+
+    self.symbol_members.apply_replace(subject, context)
+  }
+}
+
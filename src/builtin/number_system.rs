@@ -0,0 +1,128 @@
+/*!
+
+The representation of numbers as terms. A natural number `n > 0` is a single S node `s^n(0)` over the successor
+symbol, and zero is the constant `0`. If there is a minus symbol, a negative integer `-n` is `-(s^n(0))`. This is
+Maude's representation, in which the exponent of the S node carries the arbitrary precision value.
+
+A `NumberSystem` just holds the symbols involved. It is cheap to clone, and each built-in symbol keeps its own copy.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{BigInteger, RcCell},
+  rc_cell,
+  theory::{
+    free_theory::{FreeDagNode, FreeTerm},
+    make_rc_dag_node,
+    s_theory::{SDagNode, STerm},
+    DagNode,
+    RcDagNode,
+    RcSymbol,
+    RcTerm,
+  },
+};
+
+
+#[derive(Clone)]
+pub struct NumberSystem {
+  /// The S theory symbol `s_`.
+  pub(crate) succ_symbol:  RcSymbol,
+  /// The constant `0`.
+  pub(crate) zero_symbol:  RcSymbol,
+  /// The unary `-_` used for negative integers, if we have integers at all.
+  pub(crate) minus_symbol: Option<RcSymbol>,
+}
+
+impl NumberSystem {
+  pub fn new(succ_symbol: RcSymbol, zero_symbol: RcSymbol) -> Self {
+    NumberSystem {
+      succ_symbol,
+      zero_symbol,
+      minus_symbol: None,
+    }
+  }
+
+  /// A copy of the number system that also has negative integers.
+  pub fn with_minus(&self, minus_symbol: RcSymbol) -> Self {
+    NumberSystem {
+      minus_symbol: Some(minus_symbol),
+      ..self.clone()
+    }
+  }
+
+  #[inline(always)]
+  pub fn is_zero(&self, dag_node: &dyn DagNode) -> bool {
+    dag_node.symbol().as_ref() == self.zero_symbol.as_ref()
+  }
+
+  /// If `dag_node` is a natural number, returns its value.
+  pub fn get_nat(&self, dag_node: &dyn DagNode) -> Option<BigInteger> {
+    if self.is_zero(dag_node) {
+      return Some(BigInteger::zero());
+    }
+    if dag_node.symbol().as_ref() != self.succ_symbol.as_ref() {
+      return None;
+    }
+    match dag_node.as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) if self.is_zero(&**s_dag_node.arg().borrow()) => Some(s_dag_node.number().clone()),
+      Some(_) => None,
+      None => unreachable!("Could not downcast to SDagNode. This is a bug."),
+    }
+  }
+
+  /// If `dag_node` is an integer, returns its value.
+  pub fn get_integer(&self, dag_node: &dyn DagNode) -> Option<BigInteger> {
+    if let Some(minus_symbol) = &self.minus_symbol {
+      if dag_node.symbol().as_ref() == minus_symbol.as_ref() {
+        // Only `-(s^n(0))` is a negative number; `-(0)` is not in normal form.
+        return match self.get_nat(&**dag_node.iter_args().next()?.borrow()) {
+          Some(value) if value.is_positive() => Some(-value),
+          _ => None,
+        };
+      }
+    }
+    self.get_nat(dag_node)
+  }
+
+  /// Makes the DAG representing the natural number `value`, which must not be negative.
+  pub fn make_nat_dag(&self, value: &BigInteger) -> RcDagNode {
+    assert!(!value.is_negative(), "negative natural number");
+    let zero: RcDagNode = make_rc_dag_node(FreeDagNode::new(self.zero_symbol.clone()));
+    if value.is_zero() {
+      return zero;
+    }
+    make_rc_dag_node(SDagNode::new(self.succ_symbol.clone(), value.clone(), zero))
+  }
+
+  /// Makes the DAG representing the integer `value`. Returns `None` for a negative value if we have no minus symbol.
+  pub fn make_integer_dag(&self, value: &BigInteger) -> Option<RcDagNode> {
+    if !value.is_negative() {
+      return Some(self.make_nat_dag(value));
+    }
+    let minus_symbol = self.minus_symbol.clone()?;
+    let mut negative = FreeDagNode::new(minus_symbol);
+    negative.members.args.push(self.make_nat_dag(&-value));
+    let negative: RcDagNode = make_rc_dag_node(negative);
+    Some(negative)
+  }
+
+  /// Makes the term representing the integer `value`, as the parser does for integer literals. Returns `None` for a
+  /// negative value if we have no minus symbol.
+  pub fn make_integer_term(&self, value: &BigInteger) -> Option<RcTerm> {
+    let zero: RcTerm = rc_cell!(FreeTerm::new(self.zero_symbol.clone()));
+    let nat: RcTerm = if value.is_zero() {
+      zero
+    } else {
+      rc_cell!(STerm::new(self.succ_symbol.clone(), value.abs(), zero))
+    };
+    if !value.is_negative() {
+      return Some(nat);
+    }
+
+    let minus_symbol = self.minus_symbol.clone()?;
+    let negative: RcTerm = rc_cell!(FreeTerm::with_args(minus_symbol, vec![nat]));
+    Some(negative)
+  }
+}
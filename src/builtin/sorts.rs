@@ -0,0 +1,76 @@
+/*!
+
+The sorts of built-in values: `Float`, `String`, and the numbers `NzNat < Nat < Int`. Each is in a connected component
+of its own kind.
+
+*/
+
+use crate::{
+  core::sort::{make_component, RcSort},
+  theory::Symbol,
+};
+
+
+/// The built-in sorts. Components only hold weak references to their sorts, so whoever declares ops with these sorts
+/// keeps a `BuiltinSorts` alive alongside the symbols.
+pub struct BuiltinSorts {
+  float_sorts:  Vec<RcSort>,
+  string_sorts: Vec<RcSort>,
+  number_sorts: Vec<RcSort>,
+}
+
+impl BuiltinSorts {
+  pub fn new() -> BuiltinSorts {
+    BuiltinSorts {
+      float_sorts:  make_component(&[("Float", &[1])]),
+      string_sorts: make_component(&[("String", &[1])]),
+      // NzNat < Nat < Int
+      number_sorts: make_component(&[("Int", &[1, 2, 3]), ("Nat", &[2, 3]), ("NzNat", &[3])]),
+    }
+  }
+
+  #[inline(always)]
+  pub fn float(&self) -> &RcSort {
+    &self.float_sorts[1]
+  }
+
+  #[inline(always)]
+  pub fn string(&self) -> &RcSort {
+    &self.string_sorts[1]
+  }
+
+  #[inline(always)]
+  pub fn int(&self) -> &RcSort {
+    &self.number_sorts[1]
+  }
+
+  #[inline(always)]
+  pub fn nat(&self) -> &RcSort {
+    &self.number_sorts[2]
+  }
+
+  #[inline(always)]
+  pub fn nz_nat(&self) -> &RcSort {
+    &self.number_sorts[3]
+  }
+}
+
+impl Default for BuiltinSorts {
+  fn default() -> Self {
+    BuiltinSorts::new()
+  }
+}
+
+
+/// Gives `symbol` the constructor op declaration `domain_and_range` and compiles its op declarations. The built-in
+/// declarations are fixed, so a problem with them is a bug.
+pub fn declare_builtin_op(symbol: &mut dyn Symbol, domain_and_range: Vec<RcSort>) {
+  let symbol_members = symbol.symbol_members_mut();
+  symbol_members.sort_table.add_op_declaration(domain_and_range, true);
+  let warnings = symbol_members.compile_op_declarations();
+  assert!(
+    warnings.is_empty(),
+    "Bad op declaration for built-in symbol {}. This is a bug.",
+    symbol_members.name
+  );
+}
@@ -67,25 +67,29 @@ impl RHSAutomaton for CopyRHSAutomaton {
         format!("CopyRhsAutomaton::construct {}", orig_dag_node.borrow()).as_str(),
       );
 
-      let mut new_dag_node = orig_dag_node.borrow_mut().copy_eager_upto_reduced();
+      // A node that is already reduced is used as it is.
+      let new_dag_node = orig_dag_node
+        .borrow_mut()
+        .copy_eager_upto_reduced()
+        .unwrap_or_else(|| orig_dag_node.clone());
       orig_dag_node.borrow_mut().clear_copied_rc();
-      matcher.bind(self.copy_index as i32, new_dag_node.clone());
-      new_dag_node
+      matcher.bind(self.copy_index as i32, Some(new_dag_node.clone()));
+      Some(new_dag_node)
     } else {
       unreachable!("No DagNode for original index. This is a bug.");
     }
   }
 
-  fn replace(&mut self, old: RcDagNode, matcher: &mut Substitution) {
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
     let orig = matcher.value(self.original_index as usize);
 
     if let Some(orig_dag_node) = orig {
-      let mut new_dag_node = orig_dag_node.borrow_mut().copy_eager_upto_reduced();
+      let new_dag_node = orig_dag_node
+        .borrow_mut()
+        .copy_eager_upto_reduced()
+        .unwrap_or_else(|| orig_dag_node.clone());
       orig_dag_node.borrow_mut().clear_copied_rc();
-
-      if let Some(new_dag_node) = new_dag_node {
-        new_dag_node.borrow_mut().overwrite_with_clone(old);
-      }
+      new_dag_node.borrow_mut().overwrite_with_clone(old);
     } else {
       unreachable!("No DagNode for original index. This is a bug.");
     }
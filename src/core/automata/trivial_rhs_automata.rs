@@ -43,7 +43,7 @@ impl RHSAutomaton for TrivialRHSAutomaton {
     return matcher.value(self.index as usize);
   }
 
-  fn replace(&mut self, old: RcDagNode, matcher: &mut Substitution) {
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
     matcher
       .value(self.index as usize)
       .unwrap()
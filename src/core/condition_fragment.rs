@@ -69,7 +69,7 @@ pub enum ConditionFragment {
 use ConditionFragment::*;
 
 use crate::{
-  core::rewrite_context::{make_subcontext, Purpose, RcRewritingContext},
+  core::rewrite_context::{make_subcontext, Purpose},
   theory::{find_available_terms, index_variables, term_compiler::compile_rhs},
 };
 
@@ -283,7 +283,7 @@ impl ConditionFragment {
     }
   }
 
-  pub fn solve(&mut self, find_first: bool, solution: RcRewritingContext, state: &mut Vec<ConditionState>) -> bool {
+  pub fn solve(&self, find_first: bool, solution: &mut RewritingContext, state: &mut Vec<ConditionState>) -> bool {
     match self {
       Assignment { .. } => false,
 
@@ -297,18 +297,18 @@ impl ConditionFragment {
           return false;
         }
 
-        builder.safe_construct(&mut solution.borrow_mut().substitution);
-        let lhs_root = solution.borrow().substitution.get(*lhs_index);
-        let lhs_context = make_subcontext(solution.clone(), lhs_root, Purpose::ConditionEval);
-        let rhs_root = solution.borrow().substitution.get(*rhs_index);
-        let rhs_context = make_subcontext(solution.clone(), rhs_root, Purpose::ConditionEval);
+        builder.safe_construct(&mut solution.substitution);
+        let lhs_root = solution.substitution.get(*lhs_index);
+        let mut lhs_context = make_subcontext(solution, lhs_root, Purpose::ConditionEval);
+        let rhs_root = solution.substitution.get(*rhs_index);
+        let mut rhs_context = make_subcontext(solution, rhs_root, Purpose::ConditionEval);
 
         lhs_context.reduce();
-        solution.borrow_mut().add_counts_from(&lhs_context);
+        solution.add_counts_from(&lhs_context);
         rhs_context.reduce();
-        solution.borrow_mut().add_counts_from(&rhs_context);
+        solution.add_counts_from(&rhs_context);
 
-        *lhs_context.root.unwrap().borrow_mut() == *rhs_context.root.unwrap().borrow_mut()
+        **lhs_context.root.unwrap().borrow() == **rhs_context.root.unwrap().borrow()
       }
 
       Rewrite { .. } => false,
@@ -1,69 +1,233 @@
 /*!
 
-This wraps a `HashSet<RcDagNode>` so that operations specific to canonicity are built in.
+A table of canonical DAG nodes for hash consing. Every node in the table is _canonical_: its arguments are canonical,
+and no other node in the table is structurally equal to it. Routing every node that is built through the table means
+that structurally equal subterms share a single node, so that equality of canonical nodes is pointer equality.
+
+As in Maude, each canonical node gets an index, which is stable for the life of the table. Nodes are looked up by
+`DagNode::structural_hash` and then compared with `DagNode::compare`.
+
+A node that has been found to be equal to one in the table may know a sort the canonical node does not, so on lookup
+the canonical node's sort is upgraded (see `DagNode::upgrade_sort_index`).
 
 */
 
+use std::collections::HashMap;
+
 use crate::{
-  abstractions::{HashSet, HashValueType, RcCell},
+  abstractions::{FastHasherBuilder, RcCell},
   theory::{DagNode, RcDagNode},
 };
 
+#[derive(Default)]
 pub struct HashConsSet {
-  inner: HashSet<RcDagNode>,
+  /// The canonical nodes in order of insertion. A node's position is its index.
+  nodes:   Vec<RcDagNode>,
+  /// Maps a structural hash to the indices of the canonical nodes with that hash.
+  buckets: HashMap<u32, Vec<usize>, FastHasherBuilder>,
 }
 
-impl Default for HashConsSet {
-  fn default() -> Self {
-    HashConsSet {
-      inner: HashSet::default(),
+impl HashConsSet {
+  #[inline(always)]
+  pub fn new() -> Self {
+    HashConsSet::default()
+  }
+
+  #[inline(always)]
+  pub fn len(&self) -> usize {
+    self.nodes.len()
+  }
+
+  #[inline(always)]
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+
+  /// The canonical node with the given index.
+  #[inline(always)]
+  pub fn get_canonical(&self, index: usize) -> RcDagNode {
+    self.nodes[index].clone()
+  }
+
+  /// The index of the canonical node structurally equal to `node`, if there is one.
+  pub fn find(&self, node: &dyn DagNode) -> Option<usize> {
+    let bucket = self.buckets.get(&node.structural_hash())?;
+    bucket
+      .iter()
+      .copied()
+      .find(|&index| self.nodes[index].borrow().compare(node).is_eq())
+  }
+
+  /// Returns the canonical node structurally equal to `node` and its index. If there is none yet, a canonical version
+  /// of `node` is inserted, which is `node` itself if its arguments are already canonical.
+  pub fn insert(&mut self, node: RcDagNode) -> (RcDagNode, usize) {
+    if let Some(index) = self.find_and_upgrade(&node) {
+      return (self.nodes[index].clone(), index);
+    }
+
+    let canonical = node.borrow().make_canonical(node.clone(), self);
+    let index = self.insert_new(canonical.clone());
+    (canonical, index)
+  }
+
+  /// Like `insert`, except that the original is never put in the table: if there is no existing copy, a canonical
+  /// copy is made even if `node` has canonical arguments. This is for nodes that may later be reduced in place and so
+  /// are not safe to share.
+  ///
+  /// We assume that any sort `node` has is either unknown or unimportant.
+  pub fn insert_copy(&mut self, node: RcDagNode) -> (RcDagNode, usize) {
+    if let Some(index) = self.find_and_upgrade(&node) {
+      return (self.nodes[index].clone(), index);
+    }
+
+    let mut canonical = node.borrow().make_canonical(node.clone(), self);
+    if RcCell::ptr_eq(&canonical, &node) {
+      canonical = node.borrow().shallow_copy();
     }
+    let index = self.insert_new(canonical.clone());
+    (canonical, index)
+  }
+
+  /// Finds the canonical version of `node`, upgrading its sort with that of `node`.
+  fn find_and_upgrade(&mut self, node: &RcDagNode) -> Option<usize> {
+    let index = self.find(&**node.borrow())?;
+    let canonical = &self.nodes[index];
+    if !RcCell::ptr_eq(canonical, node) {
+      canonical.borrow_mut().upgrade_sort_index(&**node.borrow());
+    }
+    Some(index)
+  }
+
+  /// Adds a node known not to be in the table, caching its hash value in the node.
+  fn insert_new(&mut self, canonical: RcDagNode) -> usize {
+    let hash = {
+      let mut canonical_mut = canonical.borrow_mut();
+      let hash = canonical_mut.structural_hash();
+      canonical_mut.set_hash_value(hash);
+      hash
+    };
+    let index = self.nodes.len();
+
+    self.nodes.push(canonical);
+    self.buckets.entry(hash).or_default().push(index);
+    index
   }
 }
 
-impl HashConsSet {
-  /// If a version of the node is already in the bag, upgrade its sort.
-  /// Otherwise, insert a _canonical copy_ of the node into the bag.
-  pub fn insert(&mut self, node: RcDagNode) -> (RcDagNode, HashValueType) {
-    match self.inner.find(&node) {
-      Some((existing_node, existing_node_hash)) => {
-        // Found an existing node.
-        existing_node
-          .borrow_mut()
-          .upgrade_sort_index(node.borrow().sort_index());
-
-        // Return the existing node and its hash value.
-        (existing_node.clone(), existing_node_hash)
-      }
-      None => {
-        // Node does not exist, insert it after making a canonical copy.
-        let canonical_node = node.borrow().make_canonical(node.clone(), self);
-        let hash = self.hash_value(&canonical_node);
-
-        // Insert the canonical node into the set.
-        self.inner.insert(canonical_node.clone());
-
-        // Return the new node and its hash value.
-        (canonical_node, hash)
-      }
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::IString,
+    core::{module::Module, pre_equation::rule, sort::SpecialSort},
+    rc_cell,
+    theory::{
+      c_theory::CTerm,
+      test_fixtures::{
+        add_constant,
+        add_rule,
+        add_s_symbol,
+        make_c_symbol,
+        make_constant,
+        make_context,
+        make_s_term,
+        make_sorts,
+        make_variable,
+      },
+      RcSymbol,
+      RcTerm,
+      Term,
+    },
+  };
+
+  /// Makes the DAG for the C term `f(first, second)`, giving both arguments the sort `Elt`.
+  fn make_c_dag(f: &RcSymbol, first: &RcTerm, second: &RcTerm) -> RcDagNode {
+    let mut term = CTerm::new(f.clone(), first.clone(), second.clone());
+    term.normalize(true);
+
+    let dag_node = term.make_dag();
+    for arg in dag_node.borrow().iter_args() {
+      arg.borrow_mut().set_sort_index(1);
     }
+    dag_node
   }
 
-  ///  The only difference form the above function is we never insert the original; if there is no existing copy and
-  ///  we're not forced to make a copy because of non-canonical arguments, we make a copy anyway. This is useful where
-  ///  the original may be reduced in place and is therefore not safe to put in a hash cons table.
-  ///
-  ///  We make an assumption here that that any sort in d is either unknown or unimportant.
-  pub fn insert_copy(&mut self, node: RcDagNode) -> (RcDagNode, HashValueType) {
-    // Always create a canonical copy of the node, regardless of whether an equivalent node exists.
-    let (canonical_node, hash) = self.make_canonical(node);
-    // let hash = self.hash_value(&canonical_node);
-
-    // Insert the canonical node into the set. Unlike `insert`, do this regardless of whether
-    // a node with the same value already exists, as we always want a copy in this case.
-    self.inner.insert(canonical_node.clone());
-
-    // Return the new node and its hash value.
-    (canonical_node, hash)
+  #[test]
+  fn hash_cons_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_c_symbol(&elt);
+    let a = make_constant("a", &elt);
+    let b = make_constant("b", &elt);
+    let mut hash_cons_set = HashConsSet::new();
+
+    // f(a, b) and f(b, a) are built separately, but they are equal, so both have the first one as canonical node.
+    let ab = make_c_dag(&f, &a, &b);
+    let ba = make_c_dag(&f, &b, &a);
+    ab.borrow_mut().set_sort_index(SpecialSort::Unknown as i32);
+    ba.borrow_mut().set_sort_index(1);
+
+    let (first, first_index) = hash_cons_set.insert(ab.clone());
+    let (second, second_index) = hash_cons_set.insert(ba.clone());
+    assert!(RcCell::ptr_eq(&first, &ab) && RcCell::ptr_eq(&second, &ab));
+    assert_eq!(first_index, second_index);
+    assert_eq!(hash_cons_set.len(), 3);
+    // The canonical node learns the sort its copy had.
+    assert_eq!(ab.borrow().get_sort_index(), 1);
+
+    // A new DAG built into the table shares its subterms with the DAGs already there.
+    let mut aa = CTerm::new(f.clone(), a.clone(), a.clone());
+    aa.normalize(true);
+    let aa = aa.make_dag_hash_consed(&mut hash_cons_set);
+    let canonical_a = hash_cons_set.get_canonical(hash_cons_set.find(&**a.borrow().make_dag().borrow()).unwrap());
+    for arg in aa.borrow().iter_args() {
+      assert!(RcCell::ptr_eq(&arg, &canonical_a));
+    }
+    assert_eq!(hash_cons_set.len(), 4);
+  }
+
+  #[test]
+  fn module_hash_cons_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_c_symbol(&elt);
+    let a = make_constant("a", &elt);
+    let b = make_constant("b", &elt);
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let s = add_s_symbol(&module, &elt);
+    let zero = add_constant(&module, "0", &elt);
+    let x = make_variable("X", 0, &elt);
+    add_rule(&module, make_s_term(&s, 3, &x), make_s_term(&s, 1, &x));
+    Module::close_theory(&module);
+
+    let reduce = |dag_node: RcDagNode| {
+      make_context(&module, dag_node.clone()).reduce();
+      dag_node
+    };
+    let ab = reduce(make_c_dag(&f, &a, &b));
+    let ba = reduce(make_c_dag(&f, &b, &a));
+
+    // Without hash consing, canonicalizing does nothing. With it, equal reduced DAGs have the same canonical version,
+    // which is never one of the DAGs passed in, since those may still be rewritten.
+    assert!(RcCell::ptr_eq(&module.borrow().canonicalize(ab.clone()), &ab));
+    module.borrow_mut().enable_hash_consing();
+    let canonical_ab = module.borrow().canonicalize(ab.clone());
+    assert!(!RcCell::ptr_eq(&canonical_ab, &ab));
+    assert!(RcCell::ptr_eq(&canonical_ab, &module.borrow().canonicalize(ba.clone())));
+    assert!(!RcCell::ptr_eq(&canonical_ab, &ba));
+
+    // The unreduced result of a rule rewrite is not canonicalized: s^3(X) => s(X) rewrites s^3(0) to a fresh s(0),
+    // whose canonical version is that of any other reduced s(0).
+    let rl = module.borrow().rules[0].clone();
+    let subject = make_s_term(&s, 3, &zero).borrow().make_dag();
+    let mut context = make_context(&module, subject.clone());
+    let result = rule::apply(&rl, subject, &mut context).unwrap();
+    let hash_cons_set = module.borrow().hash_cons_set().unwrap();
+    assert!(hash_cons_set.borrow().find(&**result.borrow()).is_none());
+
+    let s_zero = module.borrow().canonicalize(reduce(make_s_term(&s, 1, &zero).borrow().make_dag()));
+    let canonical_result = module.borrow().canonicalize(reduce(result.clone()));
+    assert!(!RcCell::ptr_eq(&canonical_result, &result));
+    assert!(RcCell::ptr_eq(&canonical_result, &s_zero));
   }
 }
@@ -0,0 +1,33 @@
+/*!
+
+A `CacheableState` is the suspended state of a bounded command, kept by the interpreter so that `continue n` can resume
+it. In Maude, `CacheableState` is a superclass of the classes that hold the state of a command. Here it is an enum with
+a variant for each command that can be continued.
+
+The states are shared with whoever issued the command, so that the results of a continuation can be read from the same
+context or search that the original command was given.
+
+*/
+
+use crate::{
+  abstractions::RcCell,
+  core::{
+    narrowing_sequence_search::NarrowingSequenceSearch,
+    rewrite_context::RcRewritingContext,
+    rewrite_sequence_search::RewriteSequenceSearch,
+  },
+};
+
+pub type RcRewriteSequenceSearch = RcCell<RewriteSequenceSearch>;
+pub type RcNarrowingSequenceSearch = RcCell<NarrowingSequenceSearch>;
+
+pub enum CacheableState {
+  /// A `rewrite [limit]` command.
+  Rewrite(RcRewritingContext),
+  /// A `frewrite [limit, gas]` command.
+  FairRewrite { context: RcRewritingContext, gas: u64 },
+  /// A `search [max_solutions]` command.
+  Search(RcRewriteSequenceSearch),
+  /// A `narrow [max_solutions]` command.
+  Narrow(RcNarrowingSequenceSearch),
+}
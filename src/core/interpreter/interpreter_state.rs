@@ -3,10 +3,28 @@ use std::{
   rc::{Rc, Weak},
 };
 
-use super::{ContinueFuncPtr, InterpreterAttribute, InterpreterAttributes, PrintFlags};
+use tiny_logger::{log, Channel};
+
+use super::{
+  ContinueFuncPtr,
+  InterpreterAttribute,
+  InterpreterAttributes,
+  PrintFlags,
+  RcNarrowingSequenceSearch,
+  RcRewriteSequenceSearch,
+};
 use crate::{
-  abstractions::IString,
-  core::{CacheableState, SyntacticPreModule, SyntacticView, Token, VisibleModule},
+  abstractions::{IString, RcCell},
+  core::{
+    module::Module,
+    rewrite_context::RcRewritingContext,
+    rewrite_sequence_search::RewriteSequenceSearch,
+    CacheableState,
+    PathStep,
+    SyntacticPreModule,
+    SyntacticView,
+    Token,
+  },
 };
 
 
@@ -24,7 +42,7 @@ pub enum SearchKind {
   FVUNarrow,
 }
 
-#[derive()]
+#[derive(Default)]
 pub struct Interpreter {
   // ToDo: We won't be implementing MaudeML, but what kind of logging do we want?
   // xml_log   : Option<File>, // Unused?
@@ -37,7 +55,7 @@ pub struct Interpreter {
   // Continuation information
   saved_state:          Option<CacheableState>,
   saved_solution_count: u64, // ToDo: As far as I know, this is nonnegative, so changed i64->u64.
-  saved_module:         Option<VisibleModule>, // ToDo: Why is this a different type from `current_module`?
+  saved_module:         Option<RcCell<Module>>,
   continue_func:        Option<ContinueFuncPtr>,
   saved_loop_subject:   Vec<Token>, // ToDo: Why is the loop subject a syntactic structure?
 
@@ -61,4 +79,288 @@ impl Interpreter {
   pub fn excluded_module(&self, name: &IString) -> bool {
     self.excluded_modules.contains(name)
   }
+
+  /// Prepares `module` for a new rewriting command. With `AutoClearMemo` set, every command starts with an empty memo
+  /// map.
+  pub fn begin_rewriting(&self, module: &mut Module) {
+    if self.attribute(InterpreterAttribute::AutoClearMemo) {
+      module.clear_memo();
+    }
+  }
+
+  // region Commands
+
+  /// The `rewrite [limit]` command. Rewrites the root of `context` with the rules of `module`, performing at most
+  /// `limit` rule rewrites. The result and the rewrite counts are left in `context`. A bounded rewrite can be resumed
+  /// with `cont`.
+  pub fn rewrite(&mut self, module: &RcCell<Module>, context: RcRewritingContext, limit: Option<u64>) {
+    self.clear_continue_info();
+    self.begin_rewriting(&mut module.borrow_mut());
+    let limit_reached = context.borrow_mut().rule_rewrite(limit);
+    if limit_reached {
+      self.save_continue_info(CacheableState::Rewrite(context), module.clone(), Interpreter::rewrite_cont);
+    }
+  }
+
+  fn rewrite_cont(&mut self, limit: usize, _debug: bool) -> Vec<usize> {
+    let (state, module) = self.take_continue_info();
+    let context = match state {
+      CacheableState::Rewrite(context) => context,
+      _ => unreachable!("Continuing a rewrite without a saved rewrite. This is a bug."),
+    };
+    // The counts of a continuation are reported on their own.
+    context.borrow_mut().clear_counts();
+    let limit_reached = context.borrow_mut().rule_rewrite(Some(limit as u64));
+    if limit_reached {
+      self.save_continue_info(CacheableState::Rewrite(context), module, Interpreter::rewrite_cont);
+    }
+    Vec::new()
+  }
+
+  /// The `frewrite [limit, gas]` command. Like `rewrite`, except that positions are rewritten fairly, each getting `gas`
+  /// rule rewrites per traversal.
+  pub fn frewrite(&mut self, module: &RcCell<Module>, context: RcRewritingContext, limit: Option<u64>, gas: u64) {
+    self.clear_continue_info();
+    self.begin_rewriting(&mut module.borrow_mut());
+    let limit_reached = context.borrow_mut().fair_rewrite(limit, gas);
+    if limit_reached {
+      let state = CacheableState::FairRewrite { context, gas };
+      self.save_continue_info(state, module.clone(), Interpreter::frewrite_cont);
+    }
+  }
+
+  fn frewrite_cont(&mut self, limit: usize, _debug: bool) -> Vec<usize> {
+    let (state, module) = self.take_continue_info();
+    let (context, gas) = match state {
+      CacheableState::FairRewrite { context, gas } => (context, gas),
+      _ => unreachable!("Continuing a fair rewrite without a saved fair rewrite. This is a bug."),
+    };
+    context.borrow_mut().clear_counts();
+    let limit_reached = context.borrow_mut().fair_rewrite(Some(limit as u64), gas);
+    if limit_reached {
+      self.save_continue_info(CacheableState::FairRewrite { context, gas }, module, Interpreter::frewrite_cont);
+    }
+    Vec::new()
+  }
+
+  /// The `search [max_solutions]` command. Finds at most `max_solutions` more solutions of `search`, returning their
+  /// state numbers. If the search stops because it found `max_solutions` solutions, it can be resumed with `cont`.
+  pub fn search(
+    &mut self,
+    module: &RcCell<Module>,
+    search: RcRewriteSequenceSearch,
+    max_solutions: Option<usize>,
+  ) -> Vec<usize> {
+    self.clear_continue_info();
+    self.begin_rewriting(&mut module.borrow_mut());
+    self.do_searching(module.clone(), search, max_solutions)
+  }
+
+  fn search_cont(&mut self, limit: usize, _debug: bool) -> Vec<usize> {
+    let (state, module) = self.take_continue_info();
+    let search = match state {
+      CacheableState::Search(search) => search,
+      _ => unreachable!("Continuing a search without a saved search. This is a bug."),
+    };
+    self.do_searching(module, search, Some(limit))
+  }
+
+  fn do_searching(
+    &mut self,
+    module: RcCell<Module>,
+    search: RcRewriteSequenceSearch,
+    max_solutions: Option<usize>,
+  ) -> Vec<usize> {
+    let mut solutions = Vec::new();
+    while max_solutions.map_or(true, |max_solutions| solutions.len() < max_solutions) {
+      match search.borrow_mut().find_next_match() {
+        Some(state_nr) => solutions.push(state_nr),
+        None => return solutions,
+      }
+    }
+
+    // We stopped because of the bound, so there may be more solutions.
+    self.saved_solution_count = search.borrow().solution_count() as u64;
+    self.save_continue_info(CacheableState::Search(search), module, Interpreter::search_cont);
+    solutions
+  }
+
+  /// The `narrow [max_solutions]` command, a search of kind `SearchKind::Narrow`. Finds at most `max_solutions` more
+  /// solutions of `search`, returning their state numbers. Like `search`, it can be resumed with `cont` if it stops
+  /// because it found `max_solutions` solutions.
+  pub fn narrow(
+    &mut self,
+    module: &RcCell<Module>,
+    search: RcNarrowingSequenceSearch,
+    max_solutions: Option<usize>,
+  ) -> Vec<usize> {
+    self.clear_continue_info();
+    self.begin_rewriting(&mut module.borrow_mut());
+    self.do_narrowing(module.clone(), search, max_solutions)
+  }
+
+  fn narrow_cont(&mut self, limit: usize, _debug: bool) -> Vec<usize> {
+    let (state, module) = self.take_continue_info();
+    let search = match state {
+      CacheableState::Narrow(search) => search,
+      _ => unreachable!("Continuing a narrowing search without a saved narrowing search. This is a bug."),
+    };
+    self.do_narrowing(module, search, Some(limit))
+  }
+
+  fn do_narrowing(
+    &mut self,
+    module: RcCell<Module>,
+    search: RcNarrowingSequenceSearch,
+    max_solutions: Option<usize>,
+  ) -> Vec<usize> {
+    let mut solutions = Vec::new();
+    while max_solutions.map_or(true, |max_solutions| solutions.len() < max_solutions) {
+      match search.borrow_mut().find_next_solution() {
+        Some(state_nr) => solutions.push(state_nr),
+        None => return solutions,
+      }
+    }
+
+    self.saved_solution_count = search.borrow().solution_count() as u64;
+    self.save_continue_info(CacheableState::Narrow(search), module, Interpreter::narrow_cont);
+    solutions
+  }
+
+  /// The `continue [limit]` command. Resumes the most recent bounded command, asking for at most `limit` more rule
+  /// rewrites or solutions. Returns the state numbers of the solutions found by a resumed search, in the order they
+  /// were found.
+  pub fn cont(&mut self, limit: usize, debug: bool) -> Vec<usize> {
+    if limit == 0 {
+      return Vec::new();
+    }
+    match self.continue_func {
+      Some(continue_func) => continue_func(self, limit, debug),
+      None => {
+        log(Channel::Warning, 1, "can't continue.");
+        Vec::new()
+      }
+    }
+  }
+
+  // endregion
+
+  // region Continuation
+
+  /// Whether there is a suspended command that `cont` can resume.
+  #[inline(always)]
+  pub fn can_continue(&self) -> bool {
+    self.continue_func.is_some()
+  }
+
+  /// The number of solutions the suspended search had found when it was suspended.
+  #[inline(always)]
+  pub fn saved_solution_count(&self) -> u64 {
+    self.saved_solution_count
+  }
+
+  pub fn clear_continue_info(&mut self) {
+    self.saved_state = None;
+    self.saved_solution_count = 0;
+    self.saved_module = None;
+    self.continue_func = None;
+  }
+
+  fn save_continue_info(&mut self, state: CacheableState, module: RcCell<Module>, continue_func: ContinueFuncPtr) {
+    self.saved_state = Some(state);
+    self.saved_module = Some(module);
+    self.continue_func = Some(continue_func);
+  }
+
+  /// Takes the suspended command and its module, leaving nothing to continue.
+  fn take_continue_info(&mut self) -> (CacheableState, RcCell<Module>) {
+    let state = self.saved_state.take();
+    let module = self.saved_module.take();
+    self.continue_func = None;
+    match (state, module) {
+      (Some(state), Some(module)) => (state, module),
+      _ => unreachable!("Continuing without a saved state. This is a bug."),
+    }
+  }
+
+  // endregion
+
+  /// The `show path` command. Returns the rewrites leading from the initial state of `search` to the given state.
+  pub fn show_path(&self, search: &RewriteSequenceSearch, state_nr: usize) -> Vec<PathStep> {
+    search.get_path(state_nr)
+  }
+
+  /// The `show path labels` command. Returns the labels of the rules of the rewrites leading from the initial state of
+  /// `search` to the given state, with `None` for unlabeled rules.
+  pub fn show_path_labels(&self, search: &RewriteSequenceSearch, state_nr: usize) -> Vec<Option<IString>> {
+    search
+      .get_path(state_nr)
+      .iter()
+      .map(|step| step.rule.borrow().name.clone())
+      .collect()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+
+  use super::*;
+  use crate::{
+    abstractions::BigInteger,
+    core::rewrite_sequence_search::SearchType,
+    rc_cell,
+    theory::{
+      test_fixtures::{arg_exponents, make_context, make_search, make_sorts, make_two_step_subject, make_variable},
+      Term,
+    },
+  };
+
+  #[test]
+  fn continue_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let subject = make_two_step_subject(&module, &nat);
+    let mut interpreter = Interpreter::default();
+    assert!(!interpreter.can_continue());
+
+    // A bounded rewrite is suspended, and each continuation reports only its own rewrites.
+    let context = rc_cell!(make_context(&module, subject.make_dag()));
+    interpreter.rewrite(&module, context.clone(), Some(1));
+    assert!(interpreter.can_continue());
+    let root = context.borrow().root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(3), BigInteger::from(4)]);
+
+    assert!(interpreter.cont(2, false).is_empty());
+    assert_eq!(context.borrow().rl_count(), 2);
+    let root = context.borrow().root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(2), BigInteger::from(3)]);
+
+    // The last continuation runs out of rewrites before its limit, so there is nothing left to continue.
+    interpreter.cont(5, false);
+    assert_eq!(context.borrow().rl_count(), 1);
+    let root = context.borrow().root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(2), BigInteger::from(2)]);
+    assert!(!interpreter.can_continue());
+
+    // A search that stops at its bound resumes where it left off, each continuation yielding exactly the next
+    // solutions an unbounded search finds, and one that runs out of solutions can't continue.
+    let x = make_variable("X", 0, &nat);
+    let unbounded = rc_cell!(make_search(&module, &subject, &x, SearchType::AnySteps, None));
+    let all_solutions = Interpreter::default().search(&module, unbounded, None);
+    assert_eq!(all_solutions.len(), 9);
+
+    let search = rc_cell!(make_search(&module, &subject, &x, SearchType::AnySteps, None));
+    assert_eq!(interpreter.search(&module, search.clone(), Some(2)), all_solutions[..2]);
+    assert!(interpreter.can_continue());
+    assert_eq!(interpreter.saved_solution_count(), 2);
+
+    assert_eq!(interpreter.cont(3, false), all_solutions[2..5]);
+    assert_eq!(search.borrow().solution_count(), 5);
+    assert_eq!(interpreter.cont(10, false), all_solutions[5..]);
+    assert_eq!(search.borrow().solution_count(), 9);
+    assert!(!interpreter.can_continue());
+    assert!(interpreter.cont(1, false).is_empty());
+  }
 }
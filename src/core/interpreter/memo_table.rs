@@ -1,84 +1,78 @@
 /*!
 
-In the current implementation this is just a shell. The actually dag nodes are stored in the module.
-Thus `MemoTable` needs to be derived from `ModuleItem` in order to find the module.
+Memoization for symbols with the `memo` attribute. In Maude, `MemoTable` is a superclass of `Symbol`. Here it is a
+trait implemented by `SymbolMembers`. The DAG nodes themselves are stored in the memo map of the symbol's module, so a
+`MemoTable` needs to be able to find its module, just like a `ModuleItem`.
 
-*/
-
-use crate::core::module::{ModuleItem, WeakModule};
-use crate::core::rewrite_context::{ContextAttribute, RcRewritingContext, RewriteType};
-use crate::theory::RcDagNode;
-
-pub type SourceSet = Vec<i32>;
+A memoized symbol reduces a subject as follows. Before trying equations, the subject is looked up in the memo map. If
+it has been seen before, it is overwritten with its recorded normal form. Otherwise, its index is added to a source set,
+and the subject is rewritten to normal form. Every subject that is tried along the way is recorded as a source, and at
+the end every source in the set is mapped to the normal form.
 
-pub struct MemoTable{
-  is_memoized: bool,
+*/
 
-  // `ModuleItem`
-  index_within_parent_module: i32,
-  pub(crate) parent_module  : WeakModule,
-}
+use crate::{
+  abstractions::RcCell,
+  core::{
+    interpreter::SourceSet,
+    module::WeakModule,
+    rewrite_context::{RewriteType, RewritingContext},
+  },
+  theory::{RcDagNode, SymbolMembers},
+};
 
-impl ModuleItem for MemoTable {
-  fn get_index_within_module(&self) -> i32 {
-    self.index_within_parent_module
-  }
+pub(crate) trait MemoTable {
+  /// The module whose memo map holds the memoized DAGs.
+  fn memo_module(&self) -> WeakModule;
 
-  fn set_module_information(&mut self, module: WeakModule, index_within_module: i32) {
-    self.parent_module = module;
-    self.index_within_parent_module = index_within_module;
-  }
+  /// Looks `subject` up in the module's memo map. If it has a recorded normal form, `subject` is overwritten with it
+  /// and we return `true`. Otherwise, `subject` is added to `source_set` and we return `false`.
+  fn memo_rewrite(&self, source_set: &mut SourceSet, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    // A symbol that doesn't belong to a module has nowhere to store its memo map.
+    let module = match self.memo_module().upgrade() {
+      Some(module) => module,
+      None => return false,
+    };
 
-  fn get_module(&self) -> WeakModule {
-    self.parent_module.clone()
-  }
-}
+    let (subject_index, to_dag) = {
+      let mut module = module.borrow_mut();
+      let subject_index = module.memo_map.get_from_index(subject.clone());
+      (subject_index, module.memo_map.get_to_dag(subject_index))
+    };
 
-
-impl MemoTable {
-  fn memo_rewrite(&mut self, source_set: &mut SourceSet, subject: RcDagNode, context: RcRewritingContext) -> bool {
-    // #if 0
-    // DebugAdvisory("memoRewrite()  subject " << subject <<
-    //       " at " << ((void*) subject) <<
-    //       " has sort index " << subject->getSortIndex());
-    // #endif
-    let memo_map = self.get_module().get_memo_map(); // Assuming appropriate methods for `get_module` and `get_memo_map`.
-    let subject_index = memo_map.get_from_index(subject.clone());
-
-    if let Some(to_dag) = memo_map.get_to_dag(subject_index) {
-      // #if 0
-      // DebugAdvisory("memoRewrite()  toDag " << subject <<
-      //     " at " << ((void*) toDag) <<
-      //     " has sort index " << subject->getSortIndex());
-      // #endif
-      let mut context = context.borrow_mut();
-      let trace = context.attribute(ContextAttribute::Trace);
-      if trace {
-        context.trace_pre_eq_application(Some(subject.clone()), None, RewriteType::Memoized);
-        if context.trace_abort() {
-          return false;
-        }
+    match to_dag {
+      Some(to_dag) if !RcCell::ptr_eq(&to_dag, &subject) => {
+        context.replace_at_top(subject, to_dag, RewriteType::Memoized);
+        true
       }
-      to_dag.overwrite_with_clone(subject.clone()); // Assuming appropriate methods for `overwrite_with_clone`.
-      context.eq_count += 1;
-      if trace {
-        context.trace_post_eq_rewrite(subject.clone());
+
+      _ => {
+        source_set.push(subject_index);
+        false
       }
-      return true;
     }
-    source_set.append(subject_index);
-    false
   }
 
-  fn memo_enter(&mut self, source_set: &SourceSet, destination: RcDagNode) {
-    // #if 0
-    // DebugAdvisory("memoEnter()  destination " << destination <<
-    //       " at " << ((void*) destination) <<
-    //       " has sort index " << destination->getSortIndex());
-    // #endif
-    let memo_map = self.get_module().upgrade().unwrap().get_mut().memo_map.as_mut();
+  /// Records `destination` as the normal form of every DAG in `source_set`.
+  fn memo_enter(&self, source_set: &SourceSet, destination: RcDagNode) {
+    if source_set.is_empty() {
+      return;
+    }
+    let module = match self.memo_module().upgrade() {
+      Some(module) => module,
+      None => return,
+    };
+
+    let mut module = module.borrow_mut();
     for index in source_set.iter() {
-      memo_map.assign_to_dag(*index, destination.clone()); // Assuming appropriate method for `assign_to_dag`.
+      module.memo_map.assign_to_dag(*index, destination.clone());
     }
   }
 }
+
+impl MemoTable for SymbolMembers {
+  #[inline(always)]
+  fn memo_module(&self) -> WeakModule {
+    self.parent_module.clone()
+  }
+}
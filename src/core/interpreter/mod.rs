@@ -5,18 +5,140 @@ This module houses things related to global state, the execution environment, fo
 */
 
 mod attributes;
+mod cacheable_state;
 pub mod format;
 mod interpreter_state;
+pub(crate) mod memo_table;
 pub mod module;
 mod print_flags;
 pub mod rewrite_context;
 mod tui;
-// pub mod memo_table;
 
 pub use attributes::{InterpreterAttribute, InterpreterAttributes};
+pub use cacheable_state::{CacheableState, RcNarrowingSequenceSearch, RcRewriteSequenceSearch};
 pub use interpreter_state::{Interpreter, SearchKind, WeakInterpreter};
 pub use print_flags::{PrintFlag, PrintFlags};
 
 
-pub type ContinueFuncPtr = fn(&mut Interpreter, limit: usize, debug: bool);
-pub type SourceSet = Vec<i32>;
+/// Resumes a suspended command, returning the state numbers of the solutions it finds, which are none for rewriting.
+pub type ContinueFuncPtr = fn(&mut Interpreter, limit: usize, debug: bool) -> Vec<usize>;
+/// The memo map indices of the DAGs rewritten on the way to a normal form.
+pub type SourceSet = Vec<usize>;
+
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::{memo_table::MemoTable, module::Module, rewrite_context::RewritingContext, SourceSet};
+  use crate::{
+    abstractions::{BigInteger, IString, RcCell},
+    builtin::{NumberOp, NumberSystem},
+    core::Strategy,
+    rc_cell,
+    theory::{
+      acu_theory::{ACUSymbol, ACUTerm},
+      free_theory::{FreeSymbol, FreeTerm},
+      test_fixtures::{
+        add_constant,
+        add_equation,
+        add_s_symbol,
+        add_symbol,
+        make_context,
+        make_s_term,
+        make_sorts,
+        make_variable,
+      },
+      DagNode,
+      RcSymbol,
+      RcTerm,
+      Symbol,
+      SymbolType,
+      Term,
+    },
+  };
+
+  #[test]
+  fn memo_test() {
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let mut f = FreeSymbol::new(IString::from("f"), 1, true, Strategy::default());
+    f.symbol_members_mut().parent_module = module.downgrade();
+    let f: RcSymbol = Rc::new(f);
+    let a_symbol: RcSymbol = Rc::new(FreeSymbol::new(IString::from("a"), 0, false, Strategy::default()));
+    let a: RcTerm = rc_cell!(FreeTerm::new(a_symbol));
+    let fa = FreeTerm::with_args(f.clone(), vec![a.clone()]);
+
+    let subject = fa.make_dag();
+    let mut context = RewritingContext::new(Some(subject.clone()), Weak::new());
+
+    // The first time f(a) is seen, it is recorded as a source, which we say has normal form a.
+    let mut from = SourceSet::new();
+    assert!(!f.symbol_members().memo_rewrite(&mut from, subject.clone(), &mut context));
+    assert_eq!(from.len(), 1);
+    f.symbol_members().memo_enter(&from, a.borrow().make_dag());
+
+    // A copy of f(a) built later is rewritten to the recorded normal form without becoming a source.
+    let copy = fa.make_dag();
+    let mut from = SourceSet::new();
+    assert!(f.symbol_members().memo_rewrite(&mut from, copy.clone(), &mut context));
+    assert!(from.is_empty());
+    assert!(copy.borrow().compare(&**a.borrow().make_dag().borrow()).is_eq());
+    assert_eq!(context.eq_count, 1);
+
+    // Clearing the memo map forgets every normal form.
+    module.borrow_mut().clear_memo();
+    let mut from = SourceSet::new();
+    assert!(!f.symbol_members().memo_rewrite(&mut from, fa.make_dag(), &mut context));
+    assert_eq!(from.len(), 1);
+  }
+  #[test]
+  fn memo_reduce_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let s = add_s_symbol(&module, &nat);
+    let zero_symbol = add_constant(&module, "0", &nat).borrow().symbol();
+    let numbers = NumberSystem::new(s.clone(), zero_symbol.clone());
+    let mut plus = ACUSymbol::new(IString::from("_+_"), SymbolType::default(), Strategy::default(), false, None);
+    plus.attach_number_op(NumberOp::Plus, numbers.clone());
+    let plus = add_symbol(&module, plus, &nat);
+    let fib = add_symbol(&module, FreeSymbol::new(IString::from("fib"), 1, true, Strategy::default()), &nat);
+
+    // Compiling a statement annotates its terms, so each position gets a term of its own.
+    let zero = || -> RcTerm { rc_cell!(FreeTerm::new(zero_symbol.clone())) };
+    let n = || make_variable("N", 0, &nat);
+    let apply_fib = |arg: RcTerm| -> RcTerm { rc_cell!(FreeTerm::with_args(fib.clone(), vec![arg])) };
+
+    // fib(0) = 0, fib(s 0) = s 0, and fib(s s N) = fib(s N) + fib(N).
+    add_equation(&module, apply_fib(zero()), zero());
+    add_equation(&module, apply_fib(make_s_term(&s, 1, &zero())), make_s_term(&s, 1, &zero()));
+    let sum: RcTerm = rc_cell!(ACUTerm::new(plus.clone(), vec![apply_fib(make_s_term(&s, 1, &n())), apply_fib(n())]));
+    add_equation(&module, apply_fib(make_s_term(&s, 2, &n())), sum);
+    Module::close_theory(&module);
+
+    // Reduces fib(k) from scratch, returning its value and the count of equational rewrites.
+    let reduce = |k: i32| {
+      let number = numbers.make_integer_term(&BigInteger::from(k)).unwrap();
+      let subject = apply_fib(number).borrow().make_dag();
+      let mut context = make_context(&module, subject.clone());
+      context.reduce();
+      let value = numbers.get_integer(&**subject.borrow()).unwrap();
+      (value, context.eq_count())
+    };
+
+    // Every fib(k) is computed once, so the count is linear in k rather than exponential.
+    let (value, eq_count) = reduce(20);
+    assert_eq!(value, BigInteger::from(6765));
+    assert!(eq_count <= 4 * 20);
+
+    // A second reduce of fib(20) is a single memoized rewrite, and so is fib(12), whose DAG was an intermediate source
+    // of the first reduce.
+    assert_eq!(reduce(20), (BigInteger::from(6765), 1));
+    assert_eq!(reduce(12), (BigInteger::from(144), 1));
+
+    // fib(21) applies one equation, finds fib(20) and fib(19) in the memo map, and adds them.
+    assert_eq!(reduce(21), (BigInteger::from(10946), 4));
+  }
+}
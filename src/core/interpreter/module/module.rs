@@ -1,13 +1,18 @@
+use std::{cell::RefCell, rc::Rc};
+
 use string_cache::DefaultAtom as IString;
 use tiny_logger::{Channel::Debug, log};
 
 use crate::{
+  abstractions::RcCell,
   core::{
-    module::{StatementProfile, SymbolProfile},
+    hash_cons_set::HashConsSet,
+    module::{memo_map::MemoMap, StatementProfile, SymbolProfile},
     pre_equation::RcPreEquation,
-    sort::{RcConnectedComponent, SortSet},
+    sort::{check_signature, RcConnectedComponent, SignatureWarning, SortSet},
   },
-  theory::RcSymbol,
+  rc_cell,
+  theory::{RcDagNode, RcSymbol, Symbol, Term},
 };
 
 #[derive(Copy, Clone, Default)]
@@ -42,17 +47,21 @@ pub struct Module {
   // sortBdds: RcSortBdds ,
   pub(crate) minimum_substitution_size: i32,
 
-  // pub memo_map: BxMemoMap ,  // Memoization map for all symbols in module
+  /// Memoization map for all symbols in module
+  pub(crate) memo_map: MemoMap,
+
+  /// The module-wide hash consing table, present only when hash consing is turned on for the module.
+  hash_cons_set: Option<RcCell<HashConsSet>>,
 
   // NamedEntity members
   /// An ID, a name given by the user.
   pub name: IString,
 
   // ProfileModule members
-  symbol_info: Vec<SymbolProfile>,
-  mb_info    : Vec<StatementProfile>, // Membership
-  eq_info    : Vec<StatementProfile>, // Equation
-  rl_info    : Vec<StatementProfile>, // Rule
+  pub(crate) symbol_info: Vec<SymbolProfile>,
+  pub(crate) mb_info    : Vec<StatementProfile>, // Membership
+  pub(crate) eq_info    : Vec<StatementProfile>, // Equation
+  pub(crate) rl_info    : Vec<StatementProfile>, // Rule
   // sd_info    : Vec<StatementProfile>, // Strategy Definition
 }
 
@@ -84,4 +93,75 @@ impl Module {
     }
   }
 
+  /// Forgets every memoized normal form.
+  #[inline(always)]
+  pub fn clear_memo(&mut self) {
+    self.memo_map = MemoMap::default();
+  }
+
+  /// Turns on hash consing for this module. Reduced DAGs passed to `canonicalize` then share structurally equal
+  /// subterms.
+  pub fn enable_hash_consing(&mut self) {
+    if self.hash_cons_set.is_none() {
+      self.hash_cons_set = Some(rc_cell!(HashConsSet::new()));
+    }
+  }
+
+  /// The module-wide hash consing table, if hash consing is turned on.
+  #[inline(always)]
+  pub fn hash_cons_set(&self) -> Option<RcCell<HashConsSet>> {
+    self.hash_cons_set.clone()
+  }
+
+  /// Returns the canonical version of the reduced DAG `dag_node` in the module's table, or `dag_node` itself if hash
+  /// consing is turned off. Reduction rewrites in place, so only reduced DAGs may be canonicalized. The canonical
+  /// version is shared and must not be rewritten, but `dag_node` itself is never put in the table.
+  pub fn canonicalize(&self, dag_node: RcDagNode) -> RcDagNode {
+    match &self.hash_cons_set {
+      Some(hash_cons_set) => hash_cons_set.borrow_mut().insert_copy(dag_node).0,
+      None => dag_node,
+    }
+  }
+
+  /// Checks that the op declarations of the module's symbols form a preregular signature with consistent constructor
+  /// status, returning the problems found.
+  pub fn check_signature(&self) -> Vec<SignatureWarning> {
+    check_signature(&self.symbols)
+  }
+
+  /// Closes the theory of the module: the equations and rules are checked, every statement is offered to every
+  /// symbol, and each symbol keeps and compiles the ones that might apply to it, ordering its sort constraints.
+  /// Compiling a statement notifies its module of the substitution size it needs, so the caller must not hold a borrow
+  /// of the module.
+  pub fn close_theory(this: &RcCell<Module>) {
+    let (symbols, sort_constraints, equations, rules) = {
+      let module = this.borrow();
+      (
+        module.symbols.clone(),
+        module.sort_constraints.clone(),
+        module.equations.clone(),
+        module.rules.clone(),
+      )
+    };
+    for statement in equations.iter().chain(rules.iter()) {
+      statement.borrow_mut().check();
+    }
+    for symbol in symbols.iter() {
+      let symbol_members = symbol.symbol_members();
+      for sort_constraint in sort_constraints.iter() {
+        symbol_members.offer_sort_constraint(sort_constraint.clone());
+      }
+      for equation in equations.iter() {
+        symbol_members.offer_equation(equation.clone());
+      }
+      for rule in rules.iter() {
+        symbol_members.offer_rule(rule.clone());
+      }
+      symbol_members.order_sort_constraints();
+      symbol_members.compile_sort_constraints();
+      symbol_members.compile_equations();
+      symbol_members.compile_rules();
+    }
+    this.borrow_mut().status = Status::TheoryClosed;
+  }
 }
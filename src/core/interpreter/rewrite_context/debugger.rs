@@ -74,7 +74,7 @@ impl RewritingContext {
         broken = true;
         broken_symbol = Some(symbol);
       } else if let Some(pe) = pre_equation {
-        if interpreter.break_names.contains(&pe.name.unwrap()) {
+        if pe.name.as_ref().map_or(false, |name| interpreter.break_names.contains(name)) {
           broken = true;
         }
       }
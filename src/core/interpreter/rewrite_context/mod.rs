@@ -36,7 +36,8 @@ use crate::{
     NarrowingVariableInfo,
     RedexPosition,
   },
-  theory::RcDagNode,
+  theory::{DagNode, DagNodeFlag, RcDagNode},
+  NONE,
   ROOT_OK,
   UNDEFINED,
 };
@@ -90,10 +91,10 @@ pub(crate) struct RewritingContext {
   /// Statistics, records how many rewrites were done.
   pub(crate) mb_count: u64, // Membership
   pub(crate) eq_count: u64, // Equation
-  rl_count:            u64, // Rule
+  pub(crate) rl_count: u64, // Rule
 
-  narrowing_count:         u64,
-  variant_narrowing_count: u64,
+  pub(crate) narrowing_count:         u64,
+  pub(crate) variant_narrowing_count: u64,
 
   //	For rule rewriting
   redex_stack:   Vec<RedexPosition>,
@@ -101,9 +102,10 @@ pub(crate) struct RewritingContext {
   lazy_marker:   i32, // NONE = -1, an index when >= 0
   current_index: i32,
 
-  // rewrite_limit: u64, // NONE = -1, Only used for object system
-  // gas_per_node : u64, // Only used for object system
-  // current_gas  : u64, // Only used for object system
+  //	For fair rewriting
+  rewrite_limit: Option<u64>, // The number of rule rewrites left, if limited
+  gas_per_node:  u64,         // The most rule rewrites at a position in one traversal
+  current_gas:   u64,         // The rule rewrites left at the current position
 
   // "User Level" members
   parent:                  Option<WeakRewritingContext>,
@@ -131,6 +133,9 @@ impl RewritingContext {
       stale_marker: 0,
       lazy_marker: 0,
       current_index: 0,
+      rewrite_limit: None,
+      gas_per_node: 0,
+      current_gas: 0,
       parent: None,
       interpreter,
       substitution: Substitution::default(),
@@ -160,6 +165,9 @@ impl RewritingContext {
       stale_marker: 0,
       lazy_marker: 0,
       current_index: 0,
+      rewrite_limit: None,
+      gas_per_node: 0,
+      current_gas: 0,
       parent,
       interpreter,
       substitution: Default::default(),
@@ -207,7 +215,7 @@ impl RewritingContext {
 
   // region Statistics
   #[inline(always)]
-  fn clear_counts(&mut self) {
+  pub(crate) fn clear_counts(&mut self) {
     self.mb_count = 0;
     self.eq_count = 0;
     self.rl_count = 0;
@@ -224,6 +232,26 @@ impl RewritingContext {
     self.variant_narrowing_count += other.variant_narrowing_count;
   }
 
+  #[inline(always)]
+  pub fn eq_count(&self) -> u64 {
+    self.eq_count
+  }
+
+  #[inline(always)]
+  pub fn mb_count(&self) -> u64 {
+    self.mb_count
+  }
+
+  #[inline(always)]
+  pub fn rl_count(&self) -> u64 {
+    self.rl_count
+  }
+
+  #[inline(always)]
+  pub fn total_count(&self) -> u64 {
+    self.mb_count + self.eq_count + self.rl_count + self.narrowing_count + self.variant_narrowing_count
+  }
+
   #[inline(always)]
   fn transfer_counts_from(&mut self, other: &mut RewritingContext) {
     self.add_counts_from(other);
@@ -289,11 +317,229 @@ impl RewritingContext {
   // endregion
 
 
+  // region Rule Rewriting
+
+  /// The `rewrite [limit]` command: rewrites the root with rules, normalizing with equations before the first rule
+  /// rewrite and after each one. Each rule rewrite is at the leftmost-outermost position where a rule applies. We stop
+  /// after `limit` rule rewrites, or when no rule applies anywhere. Returns `true` if we stopped because the limit was
+  /// reached, in which case further rewrites may be possible.
+  pub fn rule_rewrite(&mut self, limit: Option<u64>) -> bool {
+    self.reduce();
+    let mut rewrite_count: u64 = 0;
+    while limit.map_or(true, |limit| rewrite_count < limit) {
+      if !self.rewrite_leftmost_outermost() {
+        return false;
+      }
+      rewrite_count += 1;
+      self.reduce();
+    }
+    true
+  }
+
+  /// Explores the positions of the root depth-first in preorder, that is, leftmost-outermost, and rewrites the first
+  /// one a rule applies to. Returns `false` if there is no such position.
+  fn rewrite_leftmost_outermost(&mut self) -> bool {
+    let root = match &self.root {
+      Some(root) => root.clone(),
+      None => return false,
+    };
+
+    // The redex stack only holds the arguments of the positions on the path from the root to the current position.
+    self.redex_stack.clear();
+    self.redex_stack.push(RedexPosition::new(root, UNDEFINED, UNDEFINED, true));
+    self.stale_marker = ROOT_OK;
+    self.lazy_marker = NONE;
+    self.rewrite_preorder(0)
+  }
+
+  /// Tries the position at `index` and then, from left to right, the positions below it. On success the spine above
+  /// the redex is rebuilt up to the root. Otherwise the position is marked unstackable, since nothing below it can be
+  /// rewritten either.
+  fn rewrite_preorder(&mut self, index: usize) -> bool {
+    let (dag_node, eager) = {
+      let position = &self.redex_stack[index];
+      (position.dag_node.clone(), position.is_eager())
+    };
+    if !eager && self.lazy_marker == NONE {
+      self.lazy_marker = index as i32;
+    }
+
+    if !dag_node.borrow().flags().is_unrewritable() {
+      // Subterms in lazy positions have not been normalized yet, and rules only apply to normal forms.
+      if !eager {
+        self.reduce_dag_node(dag_node.clone());
+      }
+      let symbol = dag_node.borrow().symbol();
+      if let Some(replacement) = symbol.rule_rewrite(dag_node.clone(), self) {
+        self.current_index = index as i32;
+        self.replace_redex(replacement);
+        return true;
+      }
+      dag_node.borrow_mut().set_flags(DagNodeFlag::Unrewritable.into());
+    }
+
+    let first_arg = self.redex_stack.len();
+    dag_node
+      .borrow()
+      .stack_arguments(&mut self.redex_stack, index as i32, true, eager);
+    let end_args = self.redex_stack.len();
+    for arg_index in first_arg..end_args {
+      if self.rewrite_preorder(arg_index) {
+        return true;
+      }
+      // Drop the arguments of the argument we just finished with, keeping its siblings.
+      self.redex_stack.truncate(end_args);
+    }
+
+    dag_node.borrow_mut().set_flags(DagNodeFlag::Unstackable.into());
+    false
+  }
+
+  /// The `frewrite [limit, gas]` command: rewrites the root with rules in position-fair traversals, performing at most
+  /// `limit` rule rewrites. Each traversal visits every position, outermost first, and gives each position `gas` rule
+  /// rewrites, so that no part of the state is starved by another in which rules always apply. The whole state is
+  /// normalized with equations after each traversal. Returns `true` if we stopped because the limit was reached.
+  pub fn fair_rewrite(&mut self, limit: Option<u64>, gas: u64) -> bool {
+    self.rewrite_limit = limit;
+    self.gas_per_node = gas;
+    self.reduce();
+    while self.rewrite_limit != Some(0) {
+      if !self.fair_traversal() {
+        return false;
+      }
+      self.reduce();
+    }
+    true
+  }
+
+  /// Makes one fair traversal of the root, returning whether any rule rewrite was done.
+  fn fair_traversal(&mut self) -> bool {
+    let root = match &self.root {
+      Some(root) => root.clone(),
+      None => return false,
+    };
+
+    self.redex_stack.clear();
+    self.redex_stack.push(RedexPosition::new(root, UNDEFINED, UNDEFINED, true));
+    self.stale_marker = ROOT_OK;
+    self.lazy_marker = NONE;
+    self.current_index = 0;
+    let mut progress = false;
+
+    while (self.current_index as usize) < self.redex_stack.len() && self.rewrite_limit != Some(0) {
+      let index = self.current_index as usize;
+      let eager = self.redex_stack[index].is_eager();
+      if !eager && self.lazy_marker == NONE {
+        self.lazy_marker = self.current_index;
+      }
+
+      self.current_gas = self.gas_per_node;
+      while self.current_gas > 0 && self.rewrite_limit != Some(0) {
+        let dag_node = self.redex_stack[index].dag_node.clone();
+        if dag_node.borrow().flags().is_unrewritable() {
+          break;
+        }
+        // Rules only apply to normal forms.
+        self.reduce_dag_node(dag_node.clone());
+
+        let symbol = dag_node.borrow().symbol();
+        match symbol.rule_rewrite(dag_node.clone(), self) {
+          Some(replacement) => {
+            self.redex_stack[index].dag_node = replacement;
+            self.mark_ancestors_stale(index);
+            self.current_gas -= 1;
+            self.rewrite_limit = self.rewrite_limit.map(|limit| limit - 1);
+            progress = true;
+          }
+          None => {
+            dag_node.borrow_mut().set_flags(DagNodeFlag::Unrewritable.into());
+            break;
+          }
+        }
+      }
+
+      // The arguments of whatever is at this position now get their turn later in the traversal.
+      let dag_node = self.redex_stack[index].dag_node.clone();
+      dag_node
+        .borrow()
+        .stack_arguments(&mut self.redex_stack, self.current_index, true, eager);
+      self.current_index += 1;
+    }
+
+    if self.stale_marker != ROOT_OK {
+      self.rebuild_stale_positions();
+    }
+    self.root = Some(self.redex_stack[0].dag_node.clone());
+    progress
+  }
+
+  /// Marks every position above the one at `index` as stale, since they no longer contain the node at `index`.
+  fn mark_ancestors_stale(&mut self, index: usize) {
+    let mut parent_index = self.redex_stack[index].parent_index;
+    while parent_index != UNDEFINED && !self.redex_stack[parent_index as usize].is_stale() {
+      self.redex_stack[parent_index as usize].set_stale(true);
+      self.stale_marker = parent_index;
+      parent_index = self.redex_stack[parent_index as usize].parent_index;
+    }
+  }
+
+  /// Rebuilds every stale position from its stacked arguments. Arguments are always stacked after their parent, so
+  /// working from the end of the stack rebuilds each position only after its arguments have been rebuilt.
+  fn rebuild_stale_positions(&mut self) {
+    for stale_index in (0..self.redex_stack.len()).rev() {
+      if !self.redex_stack[stale_index].is_stale() {
+        continue;
+      }
+      let child_index = match (stale_index + 1..self.redex_stack.len())
+        .find(|&i| self.redex_stack[i].parent_index == stale_index as i32)
+      {
+        Some(child_index) => child_index,
+        None => unreachable!("A stale position has no stacked arguments. This is a bug."),
+      };
+      self.remake_stale_dag_node(stale_index as i32, child_index as i32);
+      self.redex_stack[stale_index].set_stale(false);
+    }
+    self.stale_marker = ROOT_OK;
+  }
+
+  /// Puts `replacement` in the position at `current_index` and rebuilds the spine above it, which is now stale.
+  fn replace_redex(&mut self, replacement: RcDagNode) {
+    let index = self.current_index as usize;
+    self.redex_stack[index].dag_node = replacement;
+    self.stale_marker = self.redex_stack[index].parent_index;
+    self.rebuild_upto_root();
+  }
+
+  // endregion
+
+
   #[inline(always)]
   pub fn finished(&mut self) {
     self.substitution.finished()
   }
 
+  /// Replaces `old` by `replacement` as the result of a built-in operation, which counts as an equational rewrite.
+  #[inline(always)]
+  pub fn builtin_replace(&mut self, old: RcDagNode, replacement: RcDagNode) {
+    self.replace_at_top(old, replacement, RewriteType::Builtin);
+  }
+
+  /// Replaces `old` by `replacement` in place, counting it as an equational rewrite of the given type. This is for
+  /// rewrites that don't come from applying an equation, like built-in operations and memo table lookups.
+  pub fn replace_at_top(&mut self, old: RcDagNode, replacement: RcDagNode, rewrite_type: RewriteType) {
+    if self.trace_status() {
+      self.trace_pre_eq_application(Some(old.clone()), None, rewrite_type);
+      if self.trace_abort() {
+        return;
+      }
+    }
+    self.eq_count += 1;
+    replacement.borrow_mut().overwrite_with_clone(old.clone());
+    if self.trace_status() {
+      self.trace_post_eq_application(old);
+    }
+  }
+
   #[inline(always)]
   pub fn reduce(&mut self) {
     if let Some(root) = &self.root {
@@ -307,7 +553,7 @@ impl RewritingContext {
   #[inline(always)]
   pub fn reduce_dag_node(&mut self, dag_node: RcDagNode) {
     while !dag_node.borrow().is_reduced() {
-      let mut symbol = dag_node.borrow().symbol();
+      let symbol = dag_node.borrow().symbol();
 
       if !(symbol.rewrite(dag_node.clone(), self)) {
         dag_node.borrow_mut().set_reduced();
@@ -331,25 +577,261 @@ impl RewritingContext {
     }
   }
 
-  /// Computes the true sort of root.
+  /// Computes the true sort of `dag_node`: the sort computed from its arguments, lowered by the sort constraints of
+  /// its symbol.
   fn slow_compute_true_sort(&mut self, dag_node: RcDagNode) {
-    // let root = self.root.unwrap();
-    let mut symbol = dag_node.borrow_mut().symbol();
+    dag_node.borrow_mut().compute_base_sort();
+    let symbol = dag_node.borrow().symbol();
     symbol
       .sort_constraint_table()
       .constrain_to_smaller_sort(dag_node.clone(), self);
   }
-}
 
+  /// Computes the true sort of a DAG node that may not have been reduced, as needed by user strategies that try
+  /// equations before every argument has been evaluated. A reduced node already has its true sort.
+  pub fn compute_true_sort(&mut self, dag_node: RcDagNode) {
+    if dag_node.borrow().is_reduced() {
+      return;
+    }
+    let args = dag_node.borrow().dag_node_members().args.clone();
+    for arg in args.iter() {
+      self.compute_true_sort(arg.clone());
+    }
+    self.fast_compute_true_sort(dag_node);
+  }
+}
 
-pub fn make_subcontext(parent: RcRewritingContext, root: Option<RcDagNode>, purpose: Purpose) -> RewritingContext {
-  let parent_ref = parent.borrow();
 
+/// Makes a context for evaluating `root` on behalf of `parent`, as when solving a condition fragment. The parent is
+/// mutably borrowed while the subcontext is in use, so the subcontext doesn't keep a reference to it.
+pub fn make_subcontext(parent: &RewritingContext, root: Option<RcDagNode>, purpose: Purpose) -> RewritingContext {
   RewritingContext::with_parent(
     root,
-    Some(parent.downgrade()),
+    None,
     purpose,
-    parent_ref.attribute(ContextAttribute::LocalTrace),
-    parent_ref.interpreter.clone(),
+    parent.attribute(ContextAttribute::LocalTrace),
+    parent.interpreter.clone(),
   )
 }
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::{BigInteger, IString},
+    core::module::Module,
+    rc_cell,
+    theory::{
+      s_theory::SDagNode,
+      test_fixtures::{
+        add_constant,
+        add_equation,
+        add_free_symbol,
+        add_rule,
+        add_s_symbol,
+        arg_exponents,
+        is_equal,
+        make_context,
+        make_free_dag,
+        make_free_term,
+        make_s_term,
+        make_sorts,
+        make_two_step_subject,
+        make_variable,
+      },
+      Term,
+    },
+  };
+
+  #[test]
+  fn reduce_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let s = add_s_symbol(&module, &nat);
+    let zero = add_constant(&module, "0", &nat);
+    let x = make_variable("X", 0, &nat);
+
+    // The equation `s^3(X) = s(X)` takes off two at a time, leaving the parity.
+    add_equation(&module, make_s_term(&s, 3, &x), make_s_term(&s, 1, &x));
+    Module::close_theory(&module);
+
+    // s^5(0) rewrites to s^3(0) and then to s(0), which is in normal form. The subject is rewritten in place.
+    let subject = make_s_term(&s, 5, &zero).borrow().make_dag();
+    let mut context = make_context(&module, subject.clone());
+    context.reduce();
+
+    assert!(subject.borrow().is_reduced());
+    assert_eq!(context.eq_count(), 2);
+    match subject.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => assert_eq!(*s_dag_node.number(), BigInteger::one()),
+      None => panic!("s^5(0) did not reduce to an SDagNode"),
+    };
+  }
+
+  #[test]
+  fn collapse_reduce_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let f = add_free_symbol(&module, "f", 2, &nat);
+    let s = add_s_symbol(&module, &nat);
+    let zero = add_constant(&module, "0", &nat);
+    let x = make_variable("X", 0, &nat);
+
+    // The collapse equation `f(X, 0) = X` replaces the free node by its argument, whatever the argument's theory.
+    add_equation(&module, make_free_term(&f, vec![x.clone(), zero.clone()]), x.clone());
+    Module::close_theory(&module);
+
+    let subject = make_free_term(&f, vec![make_s_term(&s, 2, &zero), zero.clone()])
+      .borrow()
+      .make_dag();
+    let mut context = make_context(&module, subject.clone());
+    context.reduce();
+
+    assert!(subject.borrow().is_reduced());
+    assert_eq!(context.eq_count(), 1);
+    match subject.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => assert_eq!(*s_dag_node.number(), BigInteger::from(2)),
+      None => panic!("f(s^2(0), 0) did not collapse to an SDagNode"),
+    };
+  }
+
+  #[test]
+  fn cross_theory_reduce_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let double = add_free_symbol(&module, "double", 1, &nat);
+    let s = add_s_symbol(&module, &nat);
+    let zero = add_constant(&module, "0", &nat);
+    let x = make_variable("X", 0, &nat);
+
+    // The free-theory LHS `double(X)` rewrites to the S-theory RHS `s^2(X)`.
+    add_equation(&module, make_free_term(&double, vec![x.clone()]), make_s_term(&s, 2, &x));
+    Module::close_theory(&module);
+
+    // double(s(0)) rewrites in place to s^2(s(0)), which is then normalized to s^3(0).
+    let subject = make_free_term(&double, vec![make_s_term(&s, 1, &zero)])
+      .borrow()
+      .make_dag();
+    let mut context = make_context(&module, subject.clone());
+    context.reduce();
+
+    assert!(subject.borrow().is_reduced());
+    assert_eq!(context.eq_count(), 1);
+    match subject.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => {
+        assert_eq!(*s_dag_node.number(), BigInteger::from(3));
+        assert!(is_equal(&s_dag_node.arg(), &zero.borrow().make_dag()));
+      }
+      None => panic!("double(s(0)) did not reduce to an SDagNode"),
+    };
+  }
+
+  #[test]
+  fn rule_rewrite_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let f = add_free_symbol(&module, "f", 1, &nat);
+    let s = add_s_symbol(&module, &nat);
+    let zero = add_constant(&module, "0", &nat);
+    let x = make_variable("X", 0, &nat);
+
+    // The rule `s^2(X) => X` only applies below the top of f(s^2(0)).
+    add_rule(&module, make_s_term(&s, 2, &x), x.clone());
+    Module::close_theory(&module);
+
+    let subject = make_free_term(&f, vec![make_s_term(&s, 2, &zero)]).borrow().make_dag();
+    let mut context = make_context(&module, subject.clone());
+
+    // With a limit of zero we only normalize.
+    assert!(context.rule_rewrite(Some(0)));
+    assert_eq!(context.rl_count(), 0);
+
+    assert!(!context.rule_rewrite(None));
+    assert_eq!(context.rl_count(), 1);
+    let expected = make_free_term(&f, vec![zero.clone()]).borrow().make_dag();
+    assert!(is_equal(context.root.as_ref().unwrap(), &expected));
+
+    // Rules don't rewrite in place, so the original subject is unchanged.
+    let original_arg = subject.borrow().iter_args().next().unwrap();
+    match original_arg.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => assert_eq!(*s_dag_node.number(), BigInteger::from(2)),
+      None => panic!("the subject's argument is not an SDagNode"),
+    };
+  }
+
+  #[test]
+  fn leftmost_outermost_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let f = add_free_symbol(&module, "f", 1, &elt);
+    let g = add_free_symbol(&module, "g", 1, &elt);
+    let h = add_free_symbol(&module, "h", 2, &elt);
+    let a = add_constant(&module, "a", &elt);
+    let b = add_constant(&module, "b", &elt);
+    let x = make_variable("X", 0, &elt);
+
+    // The free rule `f(X) => g(X)` applies at three positions of h(f(f(a)), f(b)).
+    add_rule(&module, make_free_term(&f, vec![x.clone()]), make_free_term(&g, vec![x.clone()]));
+    Module::close_theory(&module);
+
+    let a_dag = a.borrow().make_dag();
+    let b_dag = b.borrow().make_dag();
+    let make_subject = || {
+      let f_a = make_free_dag(&f, &[&a_dag]);
+      make_free_dag(&h, &[&make_free_dag(&f, &[&f_a]), &make_free_dag(&f, &[&b_dag])])
+    };
+
+    // Leftmost-outermost, the outer f on the left goes first, then the inner one, and only then the one on the right.
+    let g_a = make_free_dag(&g, &[&a_dag]);
+    let g_b = make_free_dag(&g, &[&b_dag]);
+    let f_b = make_free_dag(&f, &[&b_dag]);
+    let expected = [
+      make_free_dag(&h, &[&make_free_dag(&g, &[&make_free_dag(&f, &[&a_dag])]), &f_b]),
+      make_free_dag(&h, &[&make_free_dag(&g, &[&g_a]), &f_b]),
+      make_free_dag(&h, &[&make_free_dag(&g, &[&g_a]), &g_b]),
+    ];
+    let mut context = make_context(&module, make_subject());
+    for expected in expected.iter() {
+      assert!(context.rule_rewrite(Some(1)));
+      assert!(is_equal(context.root.as_ref().unwrap(), expected));
+    }
+    assert!(!context.rule_rewrite(None));
+    assert_eq!(context.rl_count(), 3);
+
+    // A fair traversal visits the positions outermost first, so the right argument is rewritten before the inner f.
+    let mut context = make_context(&module, make_subject());
+    assert!(context.fair_rewrite(Some(2), 1));
+    let expected = make_free_dag(&h, &[&make_free_dag(&g, &[&make_free_dag(&f, &[&a_dag])]), &g_b]);
+    assert!(is_equal(context.root.as_ref().unwrap(), &expected));
+  }
+
+  #[test]
+  fn fair_rewrite_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let subject = make_two_step_subject(&module, &nat);
+
+    // Leftmost-outermost rewriting spends both rewrites on the first argument...
+    let mut context = make_context(&module, subject.make_dag());
+    context.rule_rewrite(Some(2));
+    assert_eq!(context.rl_count(), 2);
+    let root = context.root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(2), BigInteger::from(4)]);
+
+    // ...while fair rewriting with one unit of gas per position rewrites each argument once.
+    let mut context = make_context(&module, subject.make_dag());
+    assert!(context.fair_rewrite(Some(2), 1));
+    assert_eq!(context.rl_count(), 2);
+    let root = context.root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(3), BigInteger::from(3)]);
+
+    // Without a limit, both arguments are rewritten as far as they go.
+    assert!(!context.fair_rewrite(None, 1));
+    assert_eq!(context.rl_count(), 4);
+    let root = context.root.clone().unwrap();
+    assert_eq!(arg_exponents(&root), vec![BigInteger::from(2), BigInteger::from(2)]);
+  }
+}
@@ -33,6 +33,20 @@ pub struct VariantTraceInfo<'a> {
   original_variables:       &'a NarrowingVariableInfo,
 }
 
+impl<'a> VariantTraceInfo<'a> {
+  pub(crate) fn new(
+    old_variant_substitution: &'a Substitution,
+    new_variant_substitution: &'a Substitution,
+    original_variables: &'a NarrowingVariableInfo,
+  ) -> Self {
+    VariantTraceInfo {
+      old_variant_substitution,
+      new_variant_substitution,
+      original_variables,
+    }
+  }
+}
+
 /// Tracing status is global for all `RewritingContext`s.
 static TRACE_STATUS: AtomicBool = AtomicBool::new(false);
 
@@ -53,7 +67,7 @@ impl RewritingContext {
     let interpreter = self.interpreter.upgrade().unwrap();
     (interpreter.attribute(InterpreterAttribute::TraceSelect)
       && !(interpreter.trace_name(&symbol.name())
-        || (pe.is_some() && pe.unwrap().name.is_some() && interpreter.trace_name(&pe.unwrap().name.unwrap()))))
+        || pe.and_then(|pe| pe.name.as_ref()).map_or(false, |name| interpreter.trace_name(name))))
       || (pe.is_none() && !interpreter.attribute(InterpreterAttribute::TraceBuiltin))
       || interpreter.excluded_module(&symbol.get_module().upgrade().unwrap().borrow().name)
   }
@@ -108,22 +122,16 @@ impl RewritingContext {
     let equation = maybe_equation.unwrap();
 
     let redex: RcDagNode = redex.unwrap();
-    let redex_ref: &dyn DagNode = &*redex.borrow();
+    let redex_ref: &dyn DagNode = &**redex.borrow();
     let interpreter = self.interpreter.upgrade().unwrap();
 
     if interpreter.attribute(InterpreterAttribute::Profile) {
       // Todo: Is `self.root` gauranteed to exist?
-      let mut profile_module = self
-        .root
-        .unwrap()
-        .borrow()
-        .symbol()
-        .get_module()
-        .upgrade()
-        .unwrap()
-        .borrow_mut();
+      let profile_module = self.root.as_ref().unwrap().borrow().symbol().get_module().upgrade().unwrap();
       // TODO: Unify `profile_*_rewrite` code
-      profile_module.profile_eq_rewrite(redex.clone(), Some(equation), eq_type);
+      profile_module
+        .borrow_mut()
+        .profile_eq_rewrite(redex.clone(), Some(equation), eq_type);
     }
     // Print attributes are not implemented
     // if interpreter.attribute(InterpreterAttribute::PrintAttribute) {
@@ -418,16 +426,8 @@ impl RewritingContext {
     let interpreter: RcInterpreter = self.interpreter.upgrade().unwrap();
 
     if interpreter.attribute(InterpreterAttribute::Profile) {
-      let module: &mut Module = &mut *self
-        .root
-        .unwrap()
-        .borrow()
-        .symbol()
-        .get_module()
-        .upgrade()
-        .unwrap()
-        .borrow_mut();
-      module.profile_condition_start(pre_equation);
+      let module = self.root.as_ref().unwrap().borrow().symbol().get_module().upgrade().unwrap();
+      module.borrow_mut().profile_condition_start(pre_equation);
     }
 
     if self.handle_debug(subject.clone(), Some(pre_equation)) {
@@ -503,7 +503,7 @@ impl RewritingContext {
       .attribute(InterpreterAttribute::Profile)
     {
       if let Some(module) = pre_equation.parent_module.upgrade() {
-        module.borrow().profile_fragment(pre_equation, fragment_index, success);
+        module.borrow_mut().profile_fragment(pre_equation, fragment_index, success);
       }
     }
 
@@ -0,0 +1,570 @@
+/*!
+
+Breadth-first search of the states reachable from an initial state with variables by narrowing with rules. This is
+Maude's `narrow` command, `SearchKind::Narrow`. Where a rewrite step matches the lefthand side of a rule against a
+subterm of a ground state, a narrowing step unifies it with a subterm of a state that may have variables, at a position
+that is not a variable. The unifier instantiates the state as well as the rule, so a narrowing step from `t` to `t'`
+with unifier `σ` stands for the rewrite of every instance of `σ(t)`, and the path to a state stands for the rewrites
+of the instances of the initial state by the composition of the unifiers along it.
+
+Each state records the values of the variables of the initial state under this composition, its accumulated
+substitution. Rules are renamed apart before each step, so the variables of a state are those of the initial state that
+are still unbound and the fresh variables of the renamings and the unifiers. Only unconditional rules are used, and the
+rules that may apply at a position are those of the position's top symbol. A rule is unified with the whole subterm at a
+position, so narrowing modulo ACU does not use extension.
+
+The arrow of the search determines which reachable states are candidates, as for `RewriteSequenceSearch`, and a
+candidate is checked against the goal in one of two ways:
+
+| `GoalCheck` | A solution is                                               | Substitution                      |
+|:------------|:------------------------------------------------------------|:----------------------------------|
+| `Match`     | a candidate that matches the goal pattern                   | the state's accumulated one       |
+| `Unify`     | a unifier of a candidate with the goal's lefthand side      | composed with the unifier         |
+
+States are not identified with one another, since two states with variables may stand for different sets of ground
+states, so the search tree of an infinite narrowing space is infinite. It can be bounded in depth, and it is resumable:
+solutions are found one at a time by `find_next_solution`.
+
+*/
+
+use crate::{
+  core::{
+    interpreter::module::item::ModuleItem,
+    pre_equation::{pattern, PreEquationKind, RcPreEquation},
+    rewrite_context::RewritingContext,
+    rewrite_sequence_search::SearchType,
+    substitution::Substitution,
+    unification::{replace_args, FreshVariableSource, UnificationProblem},
+    NarrowingVariableInfo,
+    PathStep,
+  },
+  theory::{variable::VariableDagNode, DagNode, RcDagNode},
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GoalCheck {
+  Match,
+  Unify,
+}
+
+struct NarrowingState {
+  dag_node:     RcDagNode,
+  /// The values of the variables of the initial state, in the order of the search's `variable_info`.
+  substitution: Vec<RcDagNode>,
+  /// The state this state was reached from. Only the initial state has none.
+  parent:       Option<usize>,
+  /// The rule and position of the narrowing step from `parent` that reached this state.
+  arrival:      Option<(RcPreEquation, Vec<usize>)>,
+  depth:        usize,
+}
+
+/// A solution of the search: a state and the values of the variables of the initial state that make it a solution.
+#[derive(Clone)]
+pub struct NarrowingSolution {
+  pub state_nr:     usize,
+  pub substitution: Vec<RcDagNode>,
+}
+
+pub struct NarrowingSequenceSearch {
+  context:         RewritingContext,
+  goal:            RcPreEquation,
+  /// The lefthand side of the goal renamed apart from the states, for `GoalCheck::Unify`.
+  goal_dag:        RcDagNode,
+  goal_check:      GoalCheck,
+  search_type:     SearchType,
+  /// States at this depth are not explored.
+  max_depth:       Option<usize>,
+  /// The variables of the initial state.
+  variable_info:   NarrowingVariableInfo,
+  fresh_variables: FreshVariableSource,
+  states:          Vec<NarrowingState>,
+
+  // Search state
+  need_to_try_initial_state: bool,
+  next_candidate:            usize, // The first state that has not been offered as a candidate
+  explore_state:             usize, // The first state that has not been narrowed
+  goal_problem:              Option<(usize, UnificationProblem)>, // The candidate being unified with the goal
+  solutions:                 Vec<NarrowingSolution>,
+}
+
+impl NarrowingSequenceSearch {
+  /// Makes a search from the root of `context`, reduced by the equations, for states that match or unify with the
+  /// lefthand side of `goal`. With a `max_depth`, only states reachable in at most that many steps are considered.
+  pub(crate) fn new(
+    mut context: RewritingContext,
+    goal: RcPreEquation,
+    goal_check: GoalCheck,
+    search_type: SearchType,
+    max_depth: Option<usize>,
+  ) -> Self {
+    let max_depth = match search_type {
+      SearchType::OneStep => Some(1),
+      _ => max_depth,
+    };
+    context.reduce();
+    let root = match &context.root {
+      Some(root) => root.clone(),
+      None => unreachable!("A narrowing search needs an initial state. This is a bug."),
+    };
+
+    let mut variable_info = NarrowingVariableInfo::default();
+    variable_info.index_variables(&root);
+    let substitution = (0..variable_info.variable_count())
+      .map(|index| match variable_info.index_to_variable(index) {
+        Some(variable) => variable,
+        None => unreachable!("Unindexed variable in narrowing search. This is a bug."),
+      })
+      .collect();
+
+    let mut fresh_variables = FreshVariableSource::new();
+    fresh_variables.avoid(&root);
+    let goal_dag = fresh_variables.rename_apart(&goal.borrow().lhs_term.borrow().make_dag());
+
+    NarrowingSequenceSearch {
+      context,
+      goal,
+      goal_dag,
+      goal_check,
+      search_type,
+      max_depth,
+      variable_info,
+      fresh_variables,
+      states: vec![NarrowingState {
+        dag_node: root,
+        substitution,
+        parent: None,
+        arrival: None,
+        depth: 0,
+      }],
+      need_to_try_initial_state: search_type == SearchType::AnySteps,
+      next_candidate: 1,
+      explore_state: 0,
+      goal_problem: None,
+      solutions: Vec::new(),
+    }
+  }
+
+  // region Accessors
+
+  #[inline(always)]
+  pub fn search_type(&self) -> SearchType {
+    self.search_type
+  }
+
+  #[inline(always)]
+  pub fn goal_check(&self) -> GoalCheck {
+    self.goal_check
+  }
+
+  #[inline(always)]
+  pub fn state_count(&self) -> usize {
+    self.states.len()
+  }
+
+  #[inline(always)]
+  pub fn solution_count(&self) -> usize {
+    self.solutions.len()
+  }
+
+  /// The solutions found so far, in the order they were found.
+  #[inline(always)]
+  pub fn solutions(&self) -> &[NarrowingSolution] {
+    &self.solutions
+  }
+
+  #[inline(always)]
+  pub fn get_state_dag(&self, state_nr: usize) -> RcDagNode {
+    self.states[state_nr].dag_node.clone()
+  }
+
+  #[inline(always)]
+  pub fn get_state_depth(&self, state_nr: usize) -> usize {
+    self.states[state_nr].depth
+  }
+
+  /// The accumulated substitution of the given state, giving the values of the variables of the initial state in the
+  /// order of `variable_info`.
+  #[inline(always)]
+  pub fn get_state_substitution(&self, state_nr: usize) -> &[RcDagNode] {
+    &self.states[state_nr].substitution
+  }
+
+  /// The variables of the initial state.
+  #[inline(always)]
+  pub(crate) fn variable_info(&self) -> &NarrowingVariableInfo {
+    &self.variable_info
+  }
+
+  /// The context holding the bindings of the goal's variables in the most recent match, and the narrowing counts.
+  #[inline(always)]
+  pub(crate) fn context(&self) -> &RewritingContext {
+    &self.context
+  }
+
+  // endregion
+
+  /// The narrowing steps leading from the initial state to the given state. The path to the initial state is empty.
+  pub fn get_path(&self, state_nr: usize) -> Vec<PathStep> {
+    let mut path = Vec::with_capacity(self.states[state_nr].depth);
+    let mut current = state_nr;
+    while let Some((rule, position)) = &self.states[current].arrival {
+      path.push(PathStep {
+        state_nr: current,
+        rule:     rule.clone(),
+        position: position.clone(),
+      });
+      current = match self.states[current].parent {
+        Some(parent) => parent,
+        None => unreachable!("A state with an arrival step has no parent. This is a bug."),
+      };
+    }
+    path.reverse();
+    path
+  }
+
+  /// Finds the next solution, returning the number of its state, or `None` if there are no more solutions. The
+  /// solution itself is the last of `solutions`.
+  pub fn find_next_solution(&mut self) -> Option<usize> {
+    loop {
+      // A candidate may unify with the goal in several ways.
+      if let Some((state_nr, problem)) = self.goal_problem.as_mut() {
+        let state_nr = *state_nr;
+        if problem.find_next_unifier() {
+          let substitution = self.states[state_nr]
+            .substitution
+            .iter()
+            .map(|value| problem.instantiate(value))
+            .collect();
+          self.solutions.push(NarrowingSolution { state_nr, substitution });
+          return Some(state_nr);
+        }
+        self.goal_problem = None;
+      }
+
+      let state_nr = self.find_next_interesting_state()?;
+      let dag_node = self.states[state_nr].dag_node.clone();
+      match self.goal_check {
+        GoalCheck::Match => {
+          if pattern::match_(&self.goal, dag_node, &mut self.context) {
+            let substitution = self.states[state_nr].substitution.clone();
+            self.solutions.push(NarrowingSolution { state_nr, substitution });
+            return Some(state_nr);
+          }
+        }
+        GoalCheck::Unify => {
+          let problem = UnificationProblem::new(dag_node, self.goal_dag.clone());
+          self.goal_problem = Some((state_nr, problem));
+        }
+      }
+    }
+  }
+
+  /// Finds the next candidate state in breadth-first order, narrowing states as needed.
+  fn find_next_interesting_state(&mut self) -> Option<usize> {
+    if self.need_to_try_initial_state {
+      self.need_to_try_initial_state = false;
+      return Some(0);
+    }
+
+    loop {
+      if self.search_type != SearchType::NormalForm && self.next_candidate < self.states.len() {
+        self.next_candidate += 1;
+        return Some(self.next_candidate - 1);
+      }
+      if self.explore_state == self.states.len() {
+        return None;
+      }
+      // States are made in order of depth, so every remaining state is too deep as well.
+      if self
+        .max_depth
+        .map_or(false, |max_depth| self.states[self.explore_state].depth >= max_depth)
+      {
+        return None;
+      }
+
+      let state_nr = self.explore_state;
+      self.explore_state += 1;
+      let successor_count = self.explore(state_nr);
+      if self.search_type == SearchType::NormalForm && successor_count == 0 {
+        return Some(state_nr);
+      }
+    }
+  }
+
+  /// Adds a state for every narrowing step from the given state, returning how many there are. The new states are
+  /// reduced by the equations.
+  fn explore(&mut self, state_nr: usize) -> usize {
+    let dag_node = self.states[state_nr].dag_node.clone();
+    let mut positions = Vec::new();
+    collect_positions(&dag_node, &mut Vec::new(), &mut positions);
+
+    let mut successor_count = 0;
+    for (position, subterm) in positions {
+      let symbol = subterm.borrow().symbol();
+      for rule in symbol.symbol_members().rules().iter() {
+        let sides = match &rule.borrow().kind {
+          PreEquationKind::Rule { rhs_term, .. } if !rule.borrow().has_condition() => {
+            [rule.borrow().lhs_term.borrow().make_dag(), rhs_term.borrow().make_dag()]
+          }
+          _ => continue,
+        };
+        let (renamed, renaming) = self.fresh_variables.rename_all_apart(&sides);
+        let (lhs, rhs) = (renamed[0].clone(), renamed[1].clone());
+
+        let mut problem = UnificationProblem::new(subterm.clone(), lhs);
+        problem.avoid(&dag_node);
+        while problem.find_next_unifier() {
+          let replacement = problem.instantiate(&rhs);
+          let successor = problem.instantiate(&replace_at(&dag_node, &position, rhs.clone()));
+          let substitution: Vec<RcDagNode> = self.states[state_nr]
+            .substitution
+            .iter()
+            .map(|value| problem.instantiate(value))
+            .collect();
+
+          self.context.narrowing_count += 1;
+          if self.context.trace_status() {
+            self.trace_step(rule, &renaming, &problem, subterm.clone(), replacement, successor.clone());
+            if self.context.trace_abort() {
+              return successor_count;
+            }
+          }
+
+          self.context.reduce_dag_node(successor.clone());
+          self.fresh_variables.avoid(&successor);
+          self.states.push(NarrowingState {
+            dag_node: successor,
+            substitution,
+            parent: Some(state_nr),
+            arrival: Some((rule.clone(), position.clone())),
+            depth: self.states[state_nr].depth + 1,
+          });
+          successor_count += 1;
+        }
+      }
+    }
+    successor_count
+  }
+
+  /// Traces a narrowing step with `rule`, whose variables were renamed by `renaming`.
+  fn trace_step(
+    &mut self,
+    rule: &RcPreEquation,
+    renaming: &[(RcDagNode, RcDagNode)],
+    problem: &UnificationProblem,
+    redex: RcDagNode,
+    replacement: RcDagNode,
+    new_state: RcDagNode,
+  ) {
+    let substitution = match step_substitution(rule, renaming, problem, &self.variable_info) {
+      Some(substitution) => substitution,
+      None => return,
+    };
+    self.context.trace_narrowing_step(
+      &rule.borrow(),
+      redex,
+      replacement,
+      &self.variable_info,
+      &substitution,
+      new_state,
+      None,
+    );
+  }
+}
+
+/// The bindings of a narrowing step with `pre_equation`, whose variables were renamed by `renaming`, arranged as
+/// `trace_narrowing_step` expects them: the values of the pre-equation's variables at their indices in it, followed,
+/// after the module's substitution, by the values of the variables of `variable_info`. There are none if the
+/// pre-equation has no module.
+pub(crate) fn step_substitution(
+  pre_equation: &RcPreEquation,
+  renaming: &[(RcDagNode, RcDagNode)],
+  problem: &UnificationProblem,
+  variable_info: &NarrowingVariableInfo,
+) -> Option<Substitution> {
+  let variable_base = pre_equation.borrow().get_module().upgrade()?.borrow().minimum_substitution_size as usize;
+  let mut substitution = Substitution::with_capacity(variable_base + variable_info.variable_count());
+
+  for (variable, fresh) in renaming {
+    if let Some(variable) = variable.borrow().as_any().downcast_ref::<VariableDagNode>() {
+      substitution.bind(variable.index, Some(problem.instantiate(fresh)));
+    }
+  }
+  for (index, variable) in variable_info.iter() {
+    substitution.bind((variable_base + index) as i32, Some(problem.instantiate(&variable)));
+  }
+  Some(substitution)
+}
+
+/// Adds the positions of `dag_node` that are not variables, in preorder, each with the subterm there. A position
+/// is the list of argument indices leading to it from the root, and `position` is that of `dag_node`.
+pub(crate) fn collect_positions(
+  dag_node: &RcDagNode,
+  position: &mut Vec<usize>,
+  positions: &mut Vec<(Vec<usize>, RcDagNode)>,
+) {
+  if dag_node.borrow().symbol().is_variable() {
+    return;
+  }
+  positions.push((position.clone(), dag_node.clone()));
+  for (index, arg) in dag_node.borrow().iter_args().enumerate() {
+    position.push(index);
+    collect_positions(&arg, position, positions);
+    position.pop();
+  }
+}
+
+/// Builds a copy of `dag_node` in which `replacement` is at `position`.
+pub(crate) fn replace_at(dag_node: &RcDagNode, position: &[usize], replacement: RcDagNode) -> RcDagNode {
+  match position.split_first() {
+    None => replacement,
+    Some((&index, rest)) => {
+      let mut args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+      args[index] = replace_at(&args[index], rest, replacement);
+      replace_args(dag_node, args)
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::*;
+  use crate::{
+    abstractions::{IString, NatSet, RcCell},
+    core::{module::Module, sort::make_component, VariableInfo},
+    rc_cell,
+    theory::{
+      test_fixtures::{
+        add_constant,
+        add_free_symbol,
+        add_rule,
+        is_equal,
+        make_compiled_symbol,
+        make_free_dag,
+        make_free_term,
+        make_variable,
+      },
+      RcSymbol,
+      RcTerm,
+      Term,
+    },
+  };
+
+  fn make_search(
+    state: &RcTerm,
+    goal: &RcTerm,
+    goal_check: GoalCheck,
+    search_type: SearchType,
+    max_depth: Option<usize>,
+  ) -> NarrowingSequenceSearch {
+    let mut context = RewritingContext::new(Some(state.borrow().make_dag()), Weak::new());
+    context.substitution = Substitution::with_capacity(2);
+    goal.borrow_mut().normalize(true);
+    let (lhs_automaton, _) = goal
+      .borrow()
+      .compile_lhs(true, &VariableInfo::default(), &mut NatSet::default());
+    let mut goal = pattern::new(goal.clone(), vec![]);
+    goal.lhs_automaton = Some(lhs_automaton);
+    NarrowingSequenceSearch::new(context, rc_cell!(goal), goal_check, search_type, max_depth)
+  }
+
+  #[test]
+  fn accumulated_substitution_test() {
+    let sorts = make_component(&[("Nat", &[1])]);
+    let nat = &sorts[1];
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let s = add_free_symbol(&module, "s", 1, nat);
+    let f = add_free_symbol(&module, "f", 1, nat);
+    let g = add_free_symbol(&module, "g", 1, nat);
+    let x = make_variable("X", 0, nat);
+
+    // The rule f(s(X)) => g(X).
+    let lhs = make_free_term(&f, vec![make_free_term(&s, vec![x.clone()])]);
+    add_rule(&module, lhs, make_free_term(&g, vec![x]));
+    Module::close_theory(&module);
+
+    // Narrowing f(Y) unifies Y with s(X'), so the new state g(X') stands for the rewrites of the instances of f(s(X')).
+    let state = make_free_term(&f, vec![make_variable("Y", 0, nat)]);
+    let goal = make_free_term(&g, vec![make_variable("Z", 0, nat)]);
+    let mut search = make_search(&state, &goal, GoalCheck::Unify, SearchType::OneStep, None);
+    assert_eq!(search.find_next_solution(), Some(1));
+    assert_eq!(search.get_state_depth(1), 1);
+
+    let fresh = search.get_state_dag(1).borrow().iter_args().next().unwrap();
+    let y_value = search.get_state_substitution(1)[0].clone();
+    assert!(is_equal(&y_value, &make_free_dag(&s, &[&fresh])));
+    assert_eq!(search.get_path(1).len(), 1);
+
+    // The one step search doesn't narrow any further.
+    assert_eq!(search.find_next_solution(), None);
+    assert_eq!(search.state_count(), 2);
+  }
+
+  #[test]
+  fn depth_bound_test() {
+    let sorts = make_component(&[("Nat", &[1])]);
+    let nat = &sorts[1];
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let s = add_free_symbol(&module, "s", 1, nat);
+    let zero = add_constant(&module, "0", nat);
+    let x = make_variable("X", 0, nat);
+
+    // The rules g(X) => h(s(X)), h(X) => k(s(X)) and k(X) => l(s(X)) narrow g(0) three times, to l(s(s(s(0)))).
+    let steps: Vec<RcSymbol> = ["g", "h", "k", "l"]
+      .iter()
+      .map(|name| add_free_symbol(&module, name, 1, nat))
+      .collect();
+    for pair in steps.windows(2) {
+      let lhs = make_free_term(&pair[0], vec![x.clone()]);
+      let rhs = make_free_term(&pair[1], vec![make_free_term(&s, vec![x.clone()])]);
+      add_rule(&module, lhs, rhs);
+    }
+    Module::close_theory(&module);
+    let state = make_free_term(&steps[0], vec![zero]);
+
+    // The goal Z unifies with every state, so each state reached is a solution.
+    let goal = make_variable("Z", 0, nat);
+    let solve = |max_depth: Option<usize>| {
+      let mut search = make_search(&state, &goal, GoalCheck::Unify, SearchType::AnySteps, max_depth);
+      let mut state_nrs = Vec::new();
+      while let Some(state_nr) = search.find_next_solution() {
+        state_nrs.push(state_nr);
+      }
+      assert_eq!(search.state_count(), state_nrs.len());
+      state_nrs
+    };
+
+    assert_eq!(solve(None), vec![0, 1, 2, 3]);
+    assert_eq!(solve(Some(2)), vec![0, 1, 2]);
+    assert_eq!(solve(Some(0)), vec![0]);
+  }
+
+  #[test]
+  fn unify_goal_test() {
+    let sorts = make_component(&[("Nat", &[1])]);
+    let nat = &sorts[1];
+    let s: RcSymbol = Rc::new(make_compiled_symbol("s", 1, nat));
+    let f: RcSymbol = Rc::new(make_compiled_symbol("f", 1, nat));
+
+    // The state f(Y) is not an instance of the goal f(s(Z)), so it doesn't match it, but the two unify.
+    let state = make_free_term(&f, vec![make_variable("Y", 0, nat)]);
+    let goal = make_free_term(&f, vec![make_free_term(&s, vec![make_variable("Z", 0, nat)])]);
+    let mut search = make_search(&state, &goal, GoalCheck::Match, SearchType::AnySteps, None);
+    assert_eq!(search.find_next_solution(), None);
+
+    let mut search = make_search(&state, &goal, GoalCheck::Unify, SearchType::AnySteps, None);
+    assert_eq!(search.find_next_solution(), Some(0));
+    // The solution instantiates Y to s(Z'), where Z' is the goal's variable renamed apart from the state.
+    let y_value = search.solutions()[0].substitution[0].clone();
+    let args: Vec<RcDagNode> = y_value.borrow().iter_args().collect();
+    assert_eq!(args.len(), 1);
+    assert!(is_equal(&y_value, &make_free_dag(&s, &[&args[0]])));
+    assert_eq!(search.find_next_solution(), None);
+  }
+}
@@ -8,7 +8,7 @@ use std::{iter::FilterMap, slice::Iter};
 
 use crate::{core::substitution::MaybeDagNode, theory::RcDagNode};
 
-
+#[derive(Default)]
 pub struct NarrowingVariableInfo {
   variables: Vec<MaybeDagNode>,
 }
@@ -34,7 +34,7 @@ impl NarrowingVariableInfo {
     let idx = self
       .variables
       .iter()
-      .position(|v| v.is_some() && v.unwrap().borrow().compare(&*variable.borrow()).is_eq());
+      .position(|v| v.as_ref().map_or(false, |v| v.borrow().compare(&**variable.borrow()).is_eq()));
     match idx {
       Some(i) => i as i32,
       None => {
@@ -45,16 +45,33 @@ impl NarrowingVariableInfo {
   }
 
   #[inline(always)]
-  pub(crate) fn iter(&self) -> Box<dyn Iterator<Item = (usize, RcDagNode)>> {
-    Box::new(self.variables.iter().filter_map(|v| (*v).clone()).enumerate())
+  pub(crate) fn iter(&self) -> Box<dyn Iterator<Item = (usize, RcDagNode)> + '_> {
+    Box::new(
+      self
+        .variables
+        .iter()
+        .enumerate()
+        .filter_map(|(index, v)| v.clone().map(|v| (index, v))),
+    )
+  }
+
+  /// Numbers the variables of `dag_node` that are not numbered yet, in order of first appearance.
+  pub(crate) fn index_variables(&mut self, dag_node: &RcDagNode) {
+    if dag_node.borrow().symbol().is_variable() {
+      self.variable_to_index(dag_node.clone());
+      return;
+    }
+    for arg in dag_node.borrow().iter_args() {
+      self.index_variables(&arg);
+    }
   }
 
-  pub(crate) fn variable_to_index_without_insert(&mut self, variable: RcDagNode) -> Option<i32> {
+  pub(crate) fn variable_to_index_without_insert(&self, variable: RcDagNode) -> Option<i32> {
     // assert!(variable != &VariableTerm::default(), "null term");
     self
       .variables
       .iter()
-      .position(|v| v.is_some() && v.unwrap().borrow().compare(&*variable.borrow()).is_eq())
+      .position(|v| v.as_ref().map_or(false, |v| v.borrow().compare(&**variable.borrow()).is_eq()))
       .map(|i| i as i32)
   }
 }
@@ -115,7 +115,7 @@ impl BitOrAssign<PreEquationAttribute> for PreEquationAttributes {
 impl PreEquationAttributes {
   #[inline(always)]
   pub fn has_attribute(&self, attribute: PreEquationAttribute) -> bool {
-    (self.0 & attribute as u8) != 0
+    (self.0 & (1 << attribute as u8)) != 0
   }
 
   #[inline(always)]
@@ -31,12 +31,22 @@ use crate::{
       PreEquationAttribute,
       PreEquationAttributes,
       PreEquationKind,
+      RcPreEquation,
     },
-    rewrite_context::{ContextAttribute, RewritingContext},
+    rewrite_context::{ContextAttribute, RewriteType, RewritingContext},
     TermBag,
     VariableInfo,
   },
-  theory::{index_variables, term_compiler::compile_top_rhs, RcDagNode, RcLHSAutomaton, RcTerm},
+  theory::{
+    index_variables,
+    term_compiler::compile_top_rhs,
+    DagNode,
+    MaybeSubproblem,
+    RcDagNode,
+    RcExtensionInfo,
+    RcLHSAutomaton,
+    RcTerm,
+  },
   NONE,
 };
 
@@ -81,9 +91,8 @@ pub(crate) fn check(this: &mut PreEquation, bound_variables: NatSet) {
     }
     index_variables(rhs_term.clone(), &mut this.variable_info);
 
-    let mut unbound_variables = rhs_term.borrow_mut().occurs_below_mut();
-    unbound_variables.difference_in_place(&bound_variables);
-    this.variable_info.add_unbound_variables(unbound_variables);
+    let unbound_variables = rhs_term.borrow().occurs_below().difference(&bound_variables);
+    this.variable_info.add_unbound_variables(&unbound_variables);
 
     // The remainder just happens to be identical to the check for sort constraints.
     sort_constraint::check(this);
@@ -99,14 +108,10 @@ pub(crate) fn compile(this: &mut PreEquation, mut compile_lhs: bool) {
   let mut available_terms = TermBag::new(); // terms available for reuse
   this.compile_build(&mut available_terms, true);
 
+  let is_variant = this.is_variant();
   // Destructure
-  if let Equation {
-    rhs_term,
-    rhs_builder,
-    fast_variable_count,
-  } = &mut this.kind
-  {
-    if this.is_variant() {
+  if let Equation { rhs_term, rhs_builder, .. } = &mut this.kind {
+    if is_variant {
       //
       // If the equation has the variant attribute, we disallow left->right sharing so
       // that the rhs can still be instantiated, even if the substitution was made by
@@ -128,15 +133,156 @@ pub(crate) fn compile(this: &mut PreEquation, mut compile_lhs: bool) {
         &mut available_terms,
       ); // normal case
     }
+  } else {
+    unreachable!("Tried to compile nonequation as an equation. This is a bug.")
+  }
 
-    this.compile_match(compile_lhs, true);
+  this.compile_match(compile_lhs, true);
+  let protected_variable_count = if this.has_condition() {
+    NONE
+  } else {
+    this.variable_info.protected_variable_count()
+  }; // HACK
+  if let Equation {
+    rhs_builder,
+    fast_variable_count,
+    ..
+  } = &mut this.kind
+  {
     rhs_builder.remap_indices(&mut this.variable_info);
-    *fast_variable_count = if this.has_condition() {
-      NONE
-    } else {
-      this.variable_info.protected_variable_count()
-    }; // HACK
+    *fast_variable_count = protected_variable_count;
+  }
+}
+
+
+/// Tries to rewrite `subject` at the top with the equation `this`, returning whether it did. This is the part of
+/// Maude's `EquationTable::applyReplace` that concerns a single equation, so that both the equation table and the free
+/// theory's discrimination net can use it.
+pub(crate) fn apply_replace(this: &RcPreEquation, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+  let (lhs_automaton, fast_variable_count) = {
+    let equation = this.borrow();
+    match (&equation.lhs_automaton, &equation.kind) {
+      (Some(lhs_automaton), Equation { fast_variable_count, .. }) => (lhs_automaton.clone(), *fast_variable_count),
+      (None, _) => unreachable!("LHS automaton expected. This is a bug."),
+      _ => unreachable!("Destructured a nonequation as an equation. This is a bug."),
+    }
+  };
+
+  if fast_variable_count >= 0 {
+    // Fast case
+    context.substitution.clear_first_n(fast_variable_count as usize);
   } else {
-    unreachable!("Tried to compile nonequation as an equation. This is a bug.")
+    // General case
+    let variable_count = this.borrow().variable_info.protected_variable_count();
+    context.substitution.clear_first_n(variable_count as usize);
+  }
+
+  // Theories with extension may match just part of the subject at the top.
+  let extension_info = subject.borrow().make_extension_info();
+  let (matched, subproblem) = match &extension_info {
+    Some(extension_info) => {
+      lhs_automaton
+        .borrow_mut()
+        .match_with_extension(subject.clone(), &mut context.substitution, extension_info.clone())
+    }
+    None => {
+      lhs_automaton
+        .borrow_mut()
+        .match_(subject.clone(), &mut context.substitution)
+    }
+  };
+  if !matched {
+    context.finished();
+    return false;
+  }
+
+  if fast_variable_count >= 0 && subproblem.is_none() && !context.trace_status() {
+    replace(this, subject, context, extension_info);
+    context.eq_count += 1;
+    context.finished();
+    // Memory::ok_to_collect_garbage();
+    return true;
+  }
+  apply_replace_slow_case(this, subject, subproblem, context, extension_info)
+}
+
+/// Solves the matching subproblem and the condition, if any, and applies the equation with tracing.
+fn apply_replace_slow_case(
+  this: &RcPreEquation,
+  subject: RcDagNode,
+  mut subproblem: MaybeSubproblem,
+  context: &mut RewritingContext,
+  extension_info: Option<RcExtensionInfo>,
+) -> bool {
+  #[cfg(debug_assertions)]
+  log(
+    Channel::Debug,
+    5,
+    format!(
+      "EquationTable::applyReplace() slowCase:\nsubject = {}\neq = {}",
+      subject.borrow(),
+      this.borrow().repr(FormatStyle::Simple)
+    )
+    .as_str(),
+  );
+
+  if let Some(subproblem) = subproblem.as_mut() {
+    if !subproblem.solve(true, context) {
+      context.finished();
+      return false;
+    }
+  }
+
+  // Only a shared borrow of the equation is held while the condition is evaluated, since the evaluation may apply
+  // the equation again.
+  let has_condition = this.borrow().has_condition();
+  if has_condition
+    && !this
+      .borrow()
+      .check_condition_simple(subject.clone(), context, subproblem.as_deref_mut())
+  {
+    context.finished();
+    return false;
+  }
+
+  let trace = context.trace_status();
+  if trace {
+    context.trace_pre_eq_application(Some(subject.clone()), Some(&*this.borrow()), RewriteType::Normal);
+    if context.trace_abort() {
+      context.finished();
+      return false;
+    }
+  }
+  replace(this, subject.clone(), context, extension_info);
+  context.eq_count += 1;
+  if trace {
+    context.trace_post_eq_application(subject);
+  }
+  context.finished();
+  // MemoryCell::ok_to_collect_garbage();
+  true
+}
+
+/// Builds the right-hand side from the bindings in `context` and puts it in place of `subject`, or of the matched part
+/// of `subject` for a match with extension.
+fn replace(
+  this: &RcPreEquation,
+  subject: RcDagNode,
+  context: &mut RewritingContext,
+  extension_info: Option<RcExtensionInfo>,
+) {
+  let equation = this.borrow();
+  if let Equation { rhs_builder, .. } = &equation.kind {
+    match &extension_info {
+      Some(extension_info) if !extension_info.borrow().matched_whole() => {
+        let replacement = rhs_builder.construct(&mut context.substitution).unwrap();
+        subject
+          .borrow_mut()
+          .partial_replace(replacement, &*extension_info.borrow());
+      }
+      _ => {
+        rhs_builder.replace(subject, &mut context.substitution);
+      }
+    }
   }
 }
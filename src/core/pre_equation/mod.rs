@@ -9,7 +9,8 @@ appropriate module.
 
   * Equation
   * Rule
-  * Membership Axiom == SortConstraint - Not yet implemented
+  * Pattern (for search)
+  * Membership Axiom == SortConstraint
   * StrategyDefinition (Strategy Language) - Unimplemented
 
 ToDo: This needs a better name than `PreEquation`. Comparator? MatchClient?
@@ -17,9 +18,10 @@ ToDo: This needs a better name than `PreEquation`. Comparator? MatchClient?
 */
 
 mod attributes;
-mod equation;
-mod rule;
-mod sort_constraint;
+pub(crate) mod equation;
+pub(crate) mod pattern;
+pub(crate) mod rule;
+pub(crate) mod sort_constraint;
 pub mod sort_constraint_table;
 
 use std::fmt::{Debug, Formatter};
@@ -88,6 +90,9 @@ pub enum PreEquationKind {
     extension_lhs_automaton:     Option<RcLHSAutomaton>,
   },
 
+  /// A search pattern has no right-hand side.
+  Pattern {},
+
   SortConstraint {
     sort: RcSort,
   },
@@ -105,6 +110,9 @@ impl Debug for PreEquationKind {
       Rule { .. } => {
         write!(f, "Rule{{…}}")
       }
+      Pattern { .. } => {
+        write!(f, "Pattern{{…}}")
+      }
       SortConstraint { .. } => {
         write!(f, "SortConstraint{{…}}")
       }
@@ -131,6 +139,7 @@ impl PreEquationKind {
     match self {
       Equation { .. } => "equation",
       Rule { .. } => "rule",
+      Pattern { .. } => "pattern",
       SortConstraint { .. } => "sort constraint",
       StrategyDefinition { .. } => "strategy definition",
     }
@@ -140,6 +149,7 @@ impl PreEquationKind {
     match &self {
       Equation { .. } => InterpreterAttribute::TraceEq,
       Rule { .. } => InterpreterAttribute::TraceRl,
+      Pattern { .. } => InterpreterAttribute::TraceEq,
       SortConstraint { .. } => InterpreterAttribute::TraceMb,
       StrategyDefinition { .. } => InterpreterAttribute::TraceSd,
     }
@@ -209,8 +219,12 @@ impl PreEquation {
   // region  Attributes
   #[inline(always)]
   pub(crate) fn has_condition(&self) -> bool {
-    // ToDo: Can we not just check for empty?
-    self.condition.is_empty()
+    !self.condition.is_empty()
+  }
+
+  #[inline(always)]
+  pub(crate) fn is_owise(&self) -> bool {
+    self.attribute(PreEquationAttribute::Otherwise)
   }
 
   #[inline(always)]
@@ -224,7 +238,7 @@ impl PreEquation {
   }
 
   #[inline(always)]
-  fn is_variant(&self) -> bool {
+  pub(crate) fn is_variant(&self) -> bool {
     self.attribute(PreEquationAttribute::Variant)
   }
 
@@ -234,7 +248,7 @@ impl PreEquation {
   }
 
   #[inline(always)]
-  fn set_variant(&mut self) {
+  pub(crate) fn set_variant(&mut self) {
     self.attributes |= PreEquationAttribute::Variant;
   }
 
@@ -253,7 +267,7 @@ impl PreEquation {
   // region Check* functions
 
   /// Normalize lhs and recursively collect the indices and occurs sets of this term and its descendants
-  fn check(&mut self) {
+  pub(crate) fn check(&mut self) {
     self.lhs_term.borrow_mut().normalize(true);
     index_variables(self.lhs_term.clone(), &mut self.variable_info);
 
@@ -273,6 +287,10 @@ impl PreEquation {
       Rule { .. } => {
         rule::check(self, bound_variables);
       }
+      Pattern { .. } => {
+        // The condition may bind variables the pattern doesn't.
+        pattern::check(self);
+      }
       SortConstraint { .. } => {
         // Doesn't use bound_variables.
         sort_constraint::check(self);
@@ -286,11 +304,11 @@ impl PreEquation {
   ///  This is the most general condition checking function that allows multiple distinct successes; caller must provide
   ///  trial_ref variable and condition state stack in order to preserve this information between calls.
   fn check_condition(
-    &mut self,
+    &self,
     mut find_first: bool,
     subject: RcDagNode,
     context: &mut RewritingContext,
-    mut subproblem: Option<&mut dyn Subproblem>,
+    mut subproblem: Option<&mut (dyn Subproblem + 'static)>,
     trial_ref: &mut Option<i32>,
     state: &mut Vec<ConditionState>,
   ) -> bool {
@@ -350,10 +368,10 @@ impl PreEquation {
   /// Simplified interface to `check_condition(…)` for the common case where we only care
   /// if a condition succeeds at least once or fails.
   fn check_condition_simple(
-    &mut self,
+    &self,
     subject: RcDagNode,
     context: &mut RewritingContext,
-    subproblem: Option<&mut dyn Subproblem>,
+    subproblem: Option<&mut (dyn Subproblem + 'static)>,
   ) -> bool {
     let mut trial_ref: Option<i32> = None;
     let mut state: Vec<ConditionState> = Vec::new();
@@ -371,7 +389,7 @@ impl PreEquation {
 
   // region Compile Functions
 
-  fn compile(&mut self, compile_lhs: bool) {
+  pub(crate) fn compile(&mut self, compile_lhs: bool) {
     match self.kind {
       Equation { .. } => {
         equation::compile(self, compile_lhs);
@@ -381,6 +399,10 @@ impl PreEquation {
         rule::compile(self, compile_lhs);
       }
 
+      Pattern { .. } => {
+        pattern::compile(self, compile_lhs);
+      }
+
       SortConstraint { .. } => {
         sort_constraint::compile(self, compile_lhs);
       }
@@ -450,7 +472,7 @@ impl PreEquation {
   // endregion
 
   fn solve_condition(
-    &mut self,
+    &self,
     mut find_first: bool,
     trial_ref: &mut Option<i32>,
     solution: &mut RewritingContext,
@@ -472,7 +494,7 @@ impl PreEquation {
       }
 
       // A cute way to do backtracking.
-      find_first = self.condition[i].borrow_mut().solve(find_first, solution, state);
+      find_first = self.condition[i].borrow().solve(find_first, solution, state);
 
       if trace_status() {
         if solution.trace_abort() {
@@ -535,6 +557,9 @@ impl Formattable for PreEquation {
         Rule { .. } => {
           accumulator.push_str("rl ");
         }
+        Pattern { .. } => {
+          accumulator.push_str("pattern ");
+        }
         SortConstraint { .. } => {
           accumulator.push_str("mb ");
         }
@@ -559,6 +584,9 @@ impl Formattable for PreEquation {
       Rule { rhs_term, .. } => {
         accumulator.push_str(format!("{} => {}", self.lhs_term.borrow(), rhs_term.borrow()).as_str());
       }
+      Pattern { .. } => {
+        accumulator.push_str(format!("{}", self.lhs_term.borrow()).as_str());
+      }
       SortConstraint { sort, .. } => {
         accumulator.push_str(format!("{} : {}", self.lhs_term.borrow(), sort.borrow()).as_str());
       }
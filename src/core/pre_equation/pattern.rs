@@ -0,0 +1,150 @@
+/*!
+
+A `Pattern` is the target of a search: a term to match states against, with an optional condition. It is like a rule
+without a right-hand side.
+
+*/
+
+use tiny_logger::{log, Channel};
+use yansi::Paint;
+
+use crate::{
+  core::{
+    condition_fragment::Condition,
+    format::{FormatStyle, Formattable},
+    pre_equation::{ConditionState, Pattern, PreEquation, PreEquationAttribute, RcPreEquation},
+    rewrite_context::RewritingContext,
+    TermBag,
+  },
+  theory::{MaybeSubproblem, RcDagNode, RcTerm},
+  UNDEFINED,
+};
+
+pub fn new(lhs_term: RcTerm, condition: Condition) -> PreEquation {
+  PreEquation {
+    name: None,
+    attributes: Default::default(),
+    lhs_term,
+    lhs_automaton: None,
+    lhs_dag: None,
+    condition,
+    variable_info: Default::default(),
+    index_within_parent_module: UNDEFINED,
+    parent_module: Default::default(),
+    kind: Pattern {},
+  }
+}
+
+pub(crate) fn check(this: &mut PreEquation) {
+  if !this.variable_info.unbound_variables.is_empty() {
+    let mindex = this.variable_info.unbound_variables.min_value().unwrap();
+    let min_variable = this.variable_info.index_to_variable(mindex).unwrap();
+
+    let warning = format!(
+      "{}: variable {} is used before it is bound in {}:\n{}",
+      Paint::magenta(this.repr(FormatStyle::Simple)),
+      min_variable.borrow(),
+      this.kind.noun(),
+      this.repr(FormatStyle::Default)
+    );
+    log(Channel::Warning, 1, warning.as_str());
+
+    this.attributes |= PreEquationAttribute::Bad;
+  }
+}
+
+pub(crate) fn compile(this: &mut PreEquation, compile_lhs: bool) {
+  if this.is_compiled() {
+    return;
+  }
+  this.attributes.set(PreEquationAttribute::Compiled);
+  let mut available_terms = TermBag::new(); // terms available for reuse
+  this.compile_build(&mut available_terms, false);
+  this.compile_match(compile_lhs, false);
+}
+
+/// The match of a pattern against one subject, kept between solutions so that every way the pattern matches can be
+/// found in turn.
+pub(crate) struct MatchState {
+  subject:         RcDagNode,
+  subproblem:      MaybeSubproblem,
+  condition_state: Vec<ConditionState>,
+  trial_ref:       Option<i32>,
+}
+
+/// Matches the pattern `this` against `subject` at the top, leaving the bindings of the first solution that satisfies
+/// the condition in `context`. Returns whether there is such a solution.
+#[inline(always)]
+pub(crate) fn match_(this: &RcPreEquation, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+  first_solution(this, subject, context).is_some()
+}
+
+/// Like `match_`, but returns the state of the match, from which `next_solution` finds the remaining solutions.
+pub(crate) fn first_solution(
+  this: &RcPreEquation,
+  subject: RcDagNode,
+  context: &mut RewritingContext,
+) -> Option<MatchState> {
+  let lhs_automaton = match &this.borrow().lhs_automaton {
+    Some(lhs_automaton) => lhs_automaton.clone(),
+    None => unreachable!("LHS automaton expected. This is a bug."),
+  };
+  let variable_count = this.borrow().variable_info.protected_variable_count();
+  context.substitution.clear_first_n(variable_count as usize);
+
+  let (matched, subproblem) = lhs_automaton
+    .borrow_mut()
+    .match_(subject.clone(), &mut context.substitution);
+  if !matched {
+    return None;
+  }
+
+  let mut state = MatchState {
+    subject,
+    subproblem,
+    condition_state: Vec::new(),
+    trial_ref: None,
+  };
+  if let Some(subproblem) = state.subproblem.as_mut() {
+    if !subproblem.solve(true, context) {
+      return None;
+    }
+  }
+  if satisfies_condition(this, true, &mut state, context) {
+    Some(state)
+  } else {
+    None
+  }
+}
+
+/// Finds the next solution of the match in `state` that satisfies the condition, leaving its bindings in `context`.
+/// Returns `false` once the solutions are exhausted.
+pub(crate) fn next_solution(this: &RcPreEquation, state: &mut MatchState, context: &mut RewritingContext) -> bool {
+  if this.borrow().has_condition() {
+    return satisfies_condition(this, false, state, context);
+  }
+  match state.subproblem.as_mut() {
+    Some(subproblem) => subproblem.solve(false, context),
+    None => false,
+  }
+}
+
+/// Finds the first (if `find_first`) or next solution of the condition, trying further solutions of the match as
+/// needed. A pattern without a condition is satisfied by the current solution of the match.
+fn satisfies_condition(
+  this: &RcPreEquation,
+  find_first: bool,
+  state: &mut MatchState,
+  context: &mut RewritingContext,
+) -> bool {
+  let pattern = this.borrow();
+  !pattern.has_condition()
+    || pattern.check_condition(
+      find_first,
+      state.subject.clone(),
+      context,
+      state.subproblem.as_deref_mut(),
+      &mut state.trial_ref,
+      &mut state.condition_state,
+    )
+}
@@ -13,16 +13,25 @@ use crate::{
     condition_fragment::{repr_condition, Condition},
     format::{FormatStyle, Formattable},
     interpreter::InterpreterAttribute,
-    pre_equation::{PreEquation, PreEquationAttribute, PreEquationKind, Rule},
-    rewrite_context::{ContextAttribute, RewritingContext},
+    pre_equation::{PreEquation, PreEquationAttribute, PreEquationKind, RcPreEquation, Rule},
+    rewrite_context::{ContextAttribute, RewriteType, RewritingContext},
     TermBag,
   },
-  theory::{index_variables, term_compiler::compile_top_rhs, LHSAutomaton, RcDagNode, RcLHSAutomaton, RcTerm},
+  theory::{
+    index_variables,
+    term_compiler::compile_top_rhs,
+    DagNode,
+    LHSAutomaton,
+    RcDagNode,
+    RcExtensionInfo,
+    RcLHSAutomaton,
+    RcTerm,
+  },
   UNDEFINED,
 };
 
 
-fn new(name: Option<IString>, lhs_term: RcTerm, rhs_term: RcTerm, condition: Condition) -> PreEquation {
+pub fn new(name: Option<IString>, lhs_term: RcTerm, rhs_term: RcTerm, condition: Condition) -> PreEquation {
   // assert!(rhs.is_some(), "null rhs");
   PreEquation {
     name,
@@ -90,29 +99,27 @@ pub(crate) fn compile(this: &mut PreEquation, compile_lhs: bool) {
   // Since rules can be applied in non-eager subterms, if we have
   // a condition we must consider all variables to be non-eager
   // to avoid having a condition reduce a lazy subterm.
-  this.compile_build(&mut available_terms, !this.has_condition());
+  let has_condition = this.has_condition();
+  this.compile_build(&mut available_terms, !has_condition);
 
-  if let Rule {
-    rhs_term,
-    mut rhs_builder,
-    ..
-  } = &mut this.kind
-  {
+  if let Rule { rhs_term, rhs_builder, .. } = &mut this.kind {
     // HACK: we pessimize the compilation of unconditional rules to avoid
     // left->right subterm sharing that would break narrowing.
-    if !this.has_condition() {
+    if !has_condition {
       let mut dummy = TermBag::new();
-      compile_top_rhs(rhs_term.clone(), &mut rhs_builder, &mut this.variable_info, &mut dummy);
+      compile_top_rhs(rhs_term.clone(), rhs_builder, &mut this.variable_info, &mut dummy);
     } else {
       compile_top_rhs(
         rhs_term.clone(),
-        &mut rhs_builder,
+        rhs_builder,
         &mut this.variable_info,
         &mut available_terms,
       ); // original code
     }
+  }
 
-    this.compile_match(compile_lhs, true);
+  this.compile_match(compile_lhs, true);
+  if let Rule { rhs_builder, .. } = &mut this.kind {
     rhs_builder.remap_indices(&mut this.variable_info);
   }
 
@@ -124,3 +131,178 @@ pub(crate) fn compile(this: &mut PreEquation, compile_lhs: bool) {
     .variable_info
     .add_condition_variables(this.lhs_term.borrow().occurs_below());
 }
+
+
+/// The automaton to match the lhs of `this` with. Theories with extension need an automaton that matches with
+/// extension. The specialized automata are optional, in which case the general one is used.
+fn lhs_automaton(this: &PreEquation, with_extension: bool) -> RcLHSAutomaton {
+  let specialized = match &this.kind {
+    Rule {
+      non_extension_lhs_automaton,
+      extension_lhs_automaton,
+      ..
+    } => {
+      if with_extension {
+        extension_lhs_automaton
+      } else {
+        non_extension_lhs_automaton
+      }
+    }
+    _ => unreachable!("Destructured a nonrule as a rule. This is a bug."),
+  };
+
+  match specialized.as_ref().or(this.lhs_automaton.as_ref()) {
+    Some(lhs_automaton) => lhs_automaton.clone(),
+    None => unreachable!("LHS automaton expected. This is a bug."),
+  }
+}
+
+/// Tries to rewrite `subject` at the top with the rule `this`, returning the result. Unlike equations, rules do not
+/// rewrite in place, since the old state must survive the rewrite: it is up to the caller to put the result in place of
+/// `subject`. This is the part of Maude's `RuleTable::applyRules` that concerns a single rule.
+pub(crate) fn apply(this: &RcPreEquation, subject: RcDagNode, context: &mut RewritingContext) -> Option<RcDagNode> {
+  if this.borrow().is_nonexec() {
+    return None;
+  }
+
+  // Theories with extension may match just part of the subject at the top.
+  let extension_info = subject.borrow().make_extension_info();
+  let lhs_automaton = lhs_automaton(&this.borrow(), extension_info.is_some());
+  let variable_count = this.borrow().variable_info.protected_variable_count();
+  context.substitution.clear_first_n(variable_count as usize);
+
+  let (matched, mut subproblem) = match &extension_info {
+    Some(extension_info) => {
+      lhs_automaton
+        .borrow_mut()
+        .match_with_extension(subject.clone(), &mut context.substitution, extension_info.clone())
+    }
+    None => {
+      lhs_automaton
+        .borrow_mut()
+        .match_(subject.clone(), &mut context.substitution)
+    }
+  };
+  if !matched {
+    context.finished();
+    return None;
+  }
+
+  if let Some(subproblem) = subproblem.as_mut() {
+    if !subproblem.solve(true, context) {
+      context.finished();
+      return None;
+    }
+  }
+
+  // The condition may rewrite with this rule again, so only a shared borrow of it is held while it is evaluated.
+  let has_condition = this.borrow().has_condition();
+  if has_condition
+    && !this
+      .borrow()
+      .check_condition_simple(subject.clone(), context, subproblem.as_deref_mut())
+  {
+    context.finished();
+    return None;
+  }
+
+  let trace = context.trace_status();
+  if trace {
+    context.trace_pre_eq_application(Some(subject.clone()), Some(&*this.borrow()), RewriteType::Normal);
+    if context.trace_abort() {
+      context.finished();
+      return None;
+    }
+  }
+
+  let result = construct(this, &subject, context, &extension_info);
+  context.rl_count += 1;
+  if trace {
+    context.trace_post_eq_application(result.clone());
+  }
+  context.finished();
+  Some(result)
+}
+
+/// Like `apply`, but returns the result of every way `this` can rewrite `subject` at the top: one for each solution of
+/// the match for which the condition holds. This is what search needs to explore every successor of a state.
+pub(crate) fn apply_all(this: &RcPreEquation, subject: RcDagNode, context: &mut RewritingContext) -> Vec<RcDagNode> {
+  let mut results = Vec::new();
+  if this.borrow().is_nonexec() {
+    return results;
+  }
+
+  let extension_info = subject.borrow().make_extension_info();
+  let lhs_automaton = lhs_automaton(&this.borrow(), extension_info.is_some());
+  let variable_count = this.borrow().variable_info.protected_variable_count();
+  context.substitution.clear_first_n(variable_count as usize);
+
+  let (matched, mut subproblem) = match &extension_info {
+    Some(extension_info) => {
+      lhs_automaton
+        .borrow_mut()
+        .match_with_extension(subject.clone(), &mut context.substitution, extension_info.clone())
+    }
+    None => {
+      lhs_automaton
+        .borrow_mut()
+        .match_(subject.clone(), &mut context.substitution)
+    }
+  };
+  if !matched {
+    context.finished();
+    return results;
+  }
+
+  let has_condition = this.borrow().has_condition();
+  let mut find_first = true;
+  loop {
+    // Without a subproblem, the match has exactly one solution.
+    let solved = match subproblem.as_mut() {
+      Some(subproblem) => subproblem.solve(find_first, context),
+      None => find_first,
+    };
+    if !solved {
+      break;
+    }
+    find_first = false;
+
+    if !has_condition || this.borrow().check_condition_simple(subject.clone(), context, None) {
+      results.push(construct(this, &subject, context, &extension_info));
+      context.rl_count += 1;
+    }
+  }
+
+  context.finished();
+  results
+}
+
+/// Builds the result of rewriting `subject` with `this` from the bindings in `context`. For a match with extension,
+/// only the matched part of `subject` is replaced.
+fn construct(
+  this: &RcPreEquation,
+  subject: &RcDagNode,
+  context: &mut RewritingContext,
+  extension_info: &Option<RcExtensionInfo>,
+) -> RcDagNode {
+  let rule = this.borrow();
+  let replacement = match &rule.kind {
+    Rule { rhs_builder, .. } => {
+      let replacement = rhs_builder.construct(&mut context.substitution);
+      match replacement {
+        Some(replacement) => replacement,
+        None => unreachable!("A rule's rhs failed to construct. This is a bug."),
+      }
+    }
+    _ => unreachable!("Destructured a nonrule as a rule. This is a bug."),
+  };
+
+  match extension_info {
+    Some(extension_info) if !extension_info.borrow().matched_whole() => {
+      subject
+        .borrow()
+        .partial_construct(replacement, &*extension_info.borrow())
+    }
+    _ => replacement,
+  }
+}
@@ -8,8 +8,6 @@ equation::fast_variable_count(&this);
 
 */
 
-use std::assert_matches::assert_matches;
-
 use tiny_logger::{log, Channel};
 use yansi::Paint;
 
@@ -1,4 +1,16 @@
-use std::{assert_matches::assert_matches, cmp::Ordering};
+/*!
+
+The table of sort constraints (membership axioms) of a symbol. In Maude, `SortConstraintTable` is a superclass of
+`Symbol`. Here it is a member of `SymbolMembers`, and the symbol decides which of the sort constraints offered to it
+could ever apply to it.
+
+Once the module is complete, the sort constraints are ordered by their target sort, smallest sort first, and compiled.
+Whenever the true sort of a DAG node headed by the symbol is computed, they are tried in that order to lower the sort
+of the node below the sort computed from its arguments.
+
+*/
+
+use std::cmp::Ordering;
 
 use tiny_logger::{log, Channel};
 
@@ -8,21 +20,21 @@ use crate::{
     rewrite_context::{trace::trace_status, RewriteType, RewritingContext},
     sort::{index_leq_sort, sort_leq_index},
   },
-  theory::{DagNode, MaybeSubproblem, RcDagNode, RcSubproblem, Subproblem},
+  theory::{DagNode, RcDagNode},
 };
 
 #[derive(Default)]
 pub struct SortConstraintTable {
-  constraints: Vec<Option<RcSortConstraint>>,
+  constraints: Vec<RcSortConstraint>,
   complete:    bool,
 }
 
 impl SortConstraintTable {
+  /// Adds a sort constraint to the table. It is up to the owning symbol to only offer sort constraints that might
+  /// apply to it.
   #[inline(always)]
-  pub fn offer_sort_constraint(&mut self, sort_constraint: RcSortConstraint) {
-    if self.accept_sort_constraint(sort_constraint.clone()) {
-      self.constraints.push(Some(sort_constraint));
-    }
+  pub(crate) fn offer_sort_constraint(&mut self, sort_constraint: RcSortConstraint) {
+    self.constraints.push(sort_constraint);
   }
 
   #[inline(always)]
@@ -30,14 +42,10 @@ impl SortConstraintTable {
     self.constraints.is_empty()
   }
 
-  // This is identical to `constrain_to_smaller_sort`.
-  // #[inline(always)]
-  // pub fn constrain_to_exact_sort(&mut self, subject: RcDagNode, context: &mut RewritingContext) {
-  //   if !self.sort_constraint_free() {
-  //     self.constrain_to_smaller_sort(subject, context);
-  //   }
-  // }
-
+  #[inline(always)]
+  pub fn sort_constraints(&self) -> &Vec<RcSortConstraint> {
+    &self.constraints
+  }
 
   #[inline(always)]
   pub fn safe_to_inspect_sort_constraints(&self) -> bool {
@@ -58,32 +66,33 @@ impl SortConstraintTable {
     unreachable!("Non SortConstraint PreEquation used in SortConstraint context. This is a bug.")
   }
 
-  fn order_sort_constraints(&mut self) {
+  /// Keeps the sort constraints that `accept` accepts, ordered smallest target sort first. After this, the table is
+  /// complete, and `accept` may inspect it.
+  pub(crate) fn order_sort_constraints(&mut self, accept: impl Fn(&SortConstraint) -> bool) {
     // sort_constraints may contain sort constraints with variable lhs which have
     // too low a sort to ever match our symbol. However the sort of our symbol
     // is itself affected by sort constraints. So we "comb" out usable sort
     // constraints in successive passes; this is inefficient but we expect the number
     // of sort constraints to be very small so it's not worth doing anything smarter.
     self.complete = true; // not really complete until we've finished, but pretend it is
-    let sort_constraint_count = self.constraints.len();
-    if sort_constraint_count == 0 {
+    if self.constraints.is_empty() {
       return;
     }
-    let mut all = std::mem::take(&mut self.constraints);
-    let mut added_sort_constraint;
+    let mut all: Vec<Option<RcSortConstraint>> =
+      std::mem::take(&mut self.constraints).into_iter().map(Some).collect();
     loop {
-      added_sort_constraint = false;
-      for i in 0..sort_constraint_count {
-        if let Some(sc) = &all[i] {
-          // Because we set table_complete = true; accept_sort_constraint() may
-          // inspect the table of sort_constraints accepted so far and make
-          // a finer distinction than it could in offer_sort_constraint().
-          if self.accept_sort_constraint(sc.clone()) {
-            self.constraints.push(Some(sc.clone()));
-            added_sort_constraint = true;
-          } else {
-            all[i] = Some(sc.clone());
-          }
+      let mut added_sort_constraint = false;
+      for slot in all.iter_mut() {
+        // Because we set complete = true; accept() may inspect the table of
+        // sort_constraints accepted so far and make a finer distinction than
+        // it could in offer_sort_constraint().
+        let accepted = match slot {
+          Some(sort_constraint) => accept(&sort_constraint.borrow()),
+          None => false,
+        };
+        if accepted {
+          self.constraints.push(slot.take().unwrap());
+          added_sort_constraint = true;
         }
       }
       if !added_sort_constraint {
@@ -92,22 +101,18 @@ impl SortConstraintTable {
     }
     self
       .constraints
-      .sort_by(|a, b| Self::sort_constraint_lt(a.unwrap().as_ref(), b.unwrap().as_ref()));
+      .sort_by(|a, b| Self::sort_constraint_lt(&a.borrow(), &b.borrow()));
   }
 
   #[inline(always)]
-  fn compile_sort_constraints(&mut self) {
-    for constraint in self.constraints {
-      constraint.unwrap().borrow_mut().compile(true);
+  pub(crate) fn compile_sort_constraints(&self) {
+    for constraint in self.constraints.iter() {
+      constraint.borrow_mut().compile(true);
     }
   }
 
-  // Placeholder for the actual implementations of these methods
-  fn accept_sort_constraint(&self, _sort_constraint: RcSortConstraint) -> bool {
-    unimplemented!()
-  }
-
-  pub(crate) fn constrain_to_smaller_sort(&mut self, subject: RcDagNode, context: &mut RewritingContext) {
+  /// Lowers the sort of `subject`, as computed from its arguments, as far as the sort constraints allow.
+  pub(crate) fn constrain_to_smaller_sort(&self, subject: RcDagNode, context: &mut RewritingContext) {
     if self.sort_constraint_free() {
       return;
     }
@@ -135,66 +140,174 @@ impl SortConstraintTable {
     // with the new sort, because earlier sort constraints (via collapse
     // or variable lhs patterns) may be able to test this new sort.
     'retry: loop {
-      for sort_constraint in self.constraints {
-        let mut sort_constraint = sort_constraint.unwrap().borrow_mut();
-
-        if let PreEquationKind::SortConstraint { sort, .. } = &sort_constraint.kind {
-          if index_leq_sort(current_sort_index, sort.as_ref()) {
-            // Done!
-            return;
-          }
-
-          if sort_leq_index(sort.as_ref(), current_sort_index) {
-            // not equal because of previous test
-            let variable_count = sort_constraint.variable_info.protected_variable_count();
-            context.substitution.clear_first_n(variable_count as usize);
-
-            if let (true, mut subproblem) = sort_constraint
-              .lhs_automaton
-              .unwrap()
-              .borrow_mut()
-              .match_(subject.clone(), &mut context.substitution)
-            {
-              if subproblem.is_none() || subproblem.as_mut().unwrap().solve(true, context) {
-                // `subproblem` needs to be repackaged for `check_condition_simple`.
-                let mut subproblem = if let Some(mut boxed_sp) = subproblem {
-                  Some(boxed_sp.as_mut())
-                } else {
-                  None
-                };
-
-                if !sort_constraint.has_condition()
-                  || sort_constraint.check_condition_simple(subject.clone(), context, subproblem)
-                {
-                  subproblem.take(); // equivalent to delete sp in C++
-                  if trace_status() {
-                    context.trace_pre_eq_application(
-                      Some(subject.clone()),
-                      Some(&*sort_constraint),
-                      RewriteType::Normal,
-                    );
-                    if context.trace_abort() {
-                      context.finished();
-                      return;
-                    }
-                  }
-                  context.mb_count += 1;
-                  context.finished();
-
-                  current_sort_index = sort.borrow().sort_index;
-                  subject.borrow_mut().set_sort_index(current_sort_index);
-                  continue 'retry;
-                }
-              }
-            }
-          }
-          context.finished();
-        } else {
-          unreachable!("Found a non SortConstraint. This is a bug.");
+      for sort_constraint in self.constraints.iter() {
+        let sort = match &sort_constraint.borrow().kind {
+          PreEquationKind::SortConstraint { sort } => sort.clone(),
+          _ => unreachable!("Found a non SortConstraint. This is a bug."),
+        };
+
+        if index_leq_sort(current_sort_index, &sort.borrow()) {
+          // Done!
+          return;
+        }
+
+        // not equal because of previous test
+        if sort_leq_index(&sort.borrow(), current_sort_index)
+          && Self::apply(sort_constraint, subject.clone(), context)
+        {
+          current_sort_index = sort.borrow().sort_index;
+          subject.borrow_mut().set_sort_index(current_sort_index);
+          continue 'retry;
+        }
+        if context.trace_abort() {
+          return;
         }
       }
 
       break;
     }
   }
+
+  /// Tries the sort constraint against `subject`, returning whether its lhs matches and its condition holds.
+  fn apply(sort_constraint: &RcSortConstraint, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    let lhs_automaton = match &sort_constraint.borrow().lhs_automaton {
+      Some(lhs_automaton) => lhs_automaton.clone(),
+      None => unreachable!("LHS automaton expected. This is a bug."),
+    };
+    let variable_count = sort_constraint.borrow().variable_info.protected_variable_count();
+    context.substitution.clear_first_n(variable_count as usize);
+
+    let (matched, mut subproblem) = lhs_automaton
+      .borrow_mut()
+      .match_(subject.clone(), &mut context.substitution);
+    if !matched || !subproblem.as_mut().map_or(true, |subproblem| subproblem.solve(true, context)) {
+      context.finished();
+      return false;
+    }
+
+    // Evaluating the condition may compute the sort of a node headed by the same symbol and so try this sort
+    // constraint again, so only a shared borrow of it is held meanwhile.
+    let has_condition = sort_constraint.borrow().has_condition();
+    if has_condition
+      && !sort_constraint
+        .borrow()
+        .check_condition_simple(subject.clone(), context, subproblem.as_deref_mut())
+    {
+      context.finished();
+      return false;
+    }
+
+    if trace_status() {
+      context.trace_pre_eq_application(Some(subject), Some(&*sort_constraint.borrow()), RewriteType::Normal);
+      if context.trace_abort() {
+        context.finished();
+        return false;
+      }
+    }
+    context.mb_count += 1;
+    context.finished();
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::*;
+  use crate::{
+    abstractions::{BigInteger, IString, RcCell},
+    core::{
+      module::Module,
+      pre_equation::sort_constraint,
+      sort::make_component,
+      substitution::Substitution,
+      Strategy,
+    },
+    rc_cell,
+    theory::{
+      free_theory::{FreeSymbol, FreeTerm},
+      s_theory::{SSymbol, STerm},
+      test_fixtures::{make_constant, make_variable},
+      RcSymbol,
+      RcTerm,
+      Symbol,
+      Term,
+    },
+  };
+
+  #[test]
+  fn sort_constraint_test() {
+    // NzNat < Nat
+    let sorts = make_component(&[("Nat", &[1, 2]), ("NzNat", &[2])]);
+    let (nat, nz_nat) = (&sorts[1], &sorts[2]);
+    let x = make_variable("X", 0, nat);
+
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let mut s = SSymbol::new(IString::from("s"), Strategy::default(), false);
+    s.symbol_members_mut().parent_module = module.downgrade();
+    s.symbol_members_mut().index_within_parent_module = 0;
+    let s: RcSymbol = Rc::new(s);
+    let mut f = FreeSymbol::new(IString::from("f"), 1, false, Strategy::default());
+    f.symbol_members_mut().parent_module = module.downgrade();
+    f.symbol_members_mut().index_within_parent_module = 1;
+    let f: RcSymbol = Rc::new(f);
+
+    // mb s(X) : Nat .  mb s(X) : NzNat .  mb f(X) : NzNat .
+    let s_lhs = || -> RcTerm { rc_cell!(STerm::new(s.clone(), BigInteger::one(), x.clone())) };
+    let f_lhs: RcTerm = rc_cell!(FreeTerm::with_args(f.clone(), vec![x.clone()]));
+    {
+      let mut module = module.borrow_mut();
+      module.symbols = vec![s.clone(), f.clone()];
+      module.sort_constraints = vec![
+        rc_cell!(sort_constraint::new(None, s_lhs(), nat.clone(), vec![])),
+        rc_cell!(sort_constraint::new(None, s_lhs(), nz_nat.clone(), vec![])),
+        rc_cell!(sort_constraint::new(None, f_lhs, nz_nat.clone(), vec![])),
+      ];
+    }
+    Module::close_theory(&module);
+
+    // Each symbol keeps the memberships whose lhs it might head, compiled.
+    assert_eq!(f.sort_constraint_table().sort_constraints().len(), 1);
+    assert!(s
+      .sort_constraint_table()
+      .sort_constraints()
+      .iter()
+      .all(|mb| mb.borrow().lhs_automaton.is_some()));
+
+    // The accepted memberships are tried smallest sort first.
+    let table = s.sort_constraint_table();
+    assert!(table.safe_to_inspect_sort_constraints());
+    let target_sorts: Vec<i32> = table
+      .sort_constraints()
+      .iter()
+      .map(|mb| {
+        match &mb.borrow().kind {
+          PreEquationKind::SortConstraint { sort } => sort.borrow().sort_index,
+          _ => panic!("expected a sort constraint"),
+        }
+      })
+      .collect();
+    assert_eq!(target_sorts, vec![2, 1]);
+
+    // s(0), whose sort computed from its argument is Nat, is lowered to NzNat.
+    let zero = make_constant("0", nat);
+    let subject = STerm::new(s.clone(), BigInteger::one(), zero).make_dag();
+    let arg = subject.borrow().iter_args().next().unwrap();
+    arg.borrow_mut().set_sort_index(1);
+    subject.borrow_mut().set_sort_index(1);
+    let mut context = RewritingContext::new(Some(subject.clone()), Weak::new());
+    context.substitution = Substitution::with_capacity(1);
+    table.constrain_to_smaller_sort(subject.clone(), &mut context);
+    assert_eq!(subject.borrow().get_sort_index(), 2);
+    assert_eq!(context.mb_count(), 1);
+
+    // A node that already has the smallest sort is left alone.
+    table.constrain_to_smaller_sort(subject.clone(), &mut context);
+    assert_eq!(subject.borrow().get_sort_index(), 2);
+    assert_eq!(context.mb_count(), 1);
+  }
 }
@@ -24,6 +24,15 @@ pub struct RedexPosition {
 }
 
 impl RedexPosition {
+  pub fn new(dag_node: RcDagNode, parent_index: i32, arg_index: i32, eager: bool) -> Self {
+    RedexPosition {
+      dag_node,
+      parent_index,
+      arg_index,
+      flags: if eager { Eager as u8 } else { 0 },
+    }
+  }
+
   pub fn is_stale(&self) -> bool {
     (self.flags & Stale as u8) == (Stale as u8)
   }
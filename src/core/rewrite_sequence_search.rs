@@ -0,0 +1,341 @@
+/*!
+
+Breadth-first search of the states reachable from an initial state by rule rewriting for those that match a pattern.
+This is Maude's `search` command. The arrow of the command determines which reachable states are candidates:
+
+| Arrow | `SearchType`     | Candidates                                     |
+|:------|:-----------------|:-----------------------------------------------|
+| `=>1` | `OneStep`        | states reachable in exactly one step           |
+| `=>+` | `AtLeastOneStep` | states reachable in one or more steps          |
+| `=>*` | `AnySteps`       | states reachable in zero or more steps         |
+| `=>!` | `NormalForm`     | reachable states that cannot be rewritten      |
+
+Each state is a candidate at most once, however many ways it can be reached. For `=>1` and `=>+`, the initial state is a
+candidate only once a step leads back to it. Each way a candidate matches the pattern and satisfies the pattern's
+condition is a separate solution. The search can be bounded in depth, and it is resumable: solutions are found one at a
+time by `find_next_match`.
+
+*/
+
+use crate::{
+  core::{
+    pre_equation::{
+      pattern::{self, MatchState},
+      RcPreEquation,
+    },
+    rewrite_context::RewritingContext,
+    PathStep,
+    StateTransitionGraph,
+  },
+  theory::RcDagNode,
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SearchType {
+  OneStep,
+  AtLeastOneStep,
+  AnySteps,
+  NormalForm,
+}
+
+pub struct RewriteSequenceSearch {
+  graph:       StateTransitionGraph,
+  pattern:     RcPreEquation,
+  search_type: SearchType,
+  /// States at this depth are not explored.
+  max_depth:   Option<usize>,
+
+  // Search state
+  initial_state_tried: bool,
+  explore_state:       usize, // The state whose successors we are visiting
+  next_arc:            usize, // The index of the next successor of `explore_state` to visit
+  last_new_state:      usize, // States with larger numbers have not been visited yet
+  solutions:           Vec<usize>, // The states of the solutions found so far, in order
+  /// The match against the state of the most recent solution, which may have further solutions.
+  current_match:       Option<(usize, MatchState)>,
+}
+
+impl RewriteSequenceSearch {
+  /// Makes a search from the root of `context` for states matching `pattern`. With a `max_depth`, only states
+  /// reachable in at most that many steps are considered.
+  pub(crate) fn new(
+    context: RewritingContext,
+    pattern: RcPreEquation,
+    search_type: SearchType,
+    max_depth: Option<usize>,
+  ) -> Self {
+    let max_depth = match search_type {
+      SearchType::OneStep => Some(1),
+      _ => max_depth,
+    };
+
+    RewriteSequenceSearch {
+      graph: StateTransitionGraph::new(context),
+      pattern,
+      search_type,
+      max_depth,
+      initial_state_tried: false,
+      explore_state: 0,
+      next_arc: 0,
+      last_new_state: 0,
+      solutions: Vec::new(),
+      current_match: None,
+    }
+  }
+
+  // region Accessors
+
+  #[inline(always)]
+  pub fn graph(&self) -> &StateTransitionGraph {
+    &self.graph
+  }
+
+  #[inline(always)]
+  pub fn search_type(&self) -> SearchType {
+    self.search_type
+  }
+
+  #[inline(always)]
+  pub fn solution_count(&self) -> usize {
+    self.solutions.len()
+  }
+
+  /// The states of the solutions found so far, in the order they were found. A state appears once for each way it
+  /// matches the pattern.
+  #[inline(always)]
+  pub fn solutions(&self) -> &[usize] {
+    &self.solutions
+  }
+
+  /// The state of the most recent solution.
+  #[inline(always)]
+  pub fn matched_state(&self) -> Option<usize> {
+    self.solutions.last().copied()
+  }
+
+  #[inline(always)]
+  pub fn get_state_dag(&self, state_nr: usize) -> RcDagNode {
+    self.graph.get_state_dag(state_nr)
+  }
+
+  /// The path by which the search first reached the given state. Because the search is breadth-first, it is a
+  /// shortest path from the initial state.
+  #[inline(always)]
+  pub fn get_path(&self, state_nr: usize) -> Vec<PathStep> {
+    self.graph.get_path(state_nr)
+  }
+
+  /// The context holding the bindings of the pattern's variables in the most recent solution, and the rewrite counts.
+  #[inline(always)]
+  pub(crate) fn context(&self) -> &RewritingContext {
+    self.graph.context()
+  }
+
+  // endregion
+
+  /// Finds the next solution, returning the number of its state, or `None` if there are no more solutions. The other
+  /// ways the pattern matches the state of a solution are tried before the search moves on.
+  pub fn find_next_match(&mut self) -> Option<usize> {
+    if let Some((state_nr, match_state)) = self.current_match.as_mut() {
+      if pattern::next_solution(&self.pattern, match_state, self.graph.context_mut()) {
+        let state_nr = *state_nr;
+        self.solutions.push(state_nr);
+        return Some(state_nr);
+      }
+      self.current_match = None;
+    }
+
+    while let Some(state_nr) = self.find_next_interesting_state() {
+      let dag_node = self.graph.get_state_dag(state_nr);
+      if let Some(match_state) = pattern::first_solution(&self.pattern, dag_node, self.graph.context_mut()) {
+        self.current_match = Some((state_nr, match_state));
+        self.solutions.push(state_nr);
+        return Some(state_nr);
+      }
+    }
+    None
+  }
+
+  /// Finds the next candidate state in breadth-first order.
+  fn find_next_interesting_state(&mut self) -> Option<usize> {
+    if self.search_type == SearchType::AnySteps && !self.initial_state_tried {
+      self.initial_state_tried = true;
+      return Some(0);
+    }
+
+    while self.explore_state < self.graph.state_count() {
+      // States are discovered in order of depth, so every remaining state is too deep as well.
+      let depth = self.graph.get_state_depth(self.explore_state);
+      if self.max_depth.map_or(false, |max_depth| depth >= max_depth) {
+        break;
+      }
+
+      match self.graph.get_next_state(self.explore_state, self.next_arc) {
+        Some(next_state) => {
+          self.next_arc += 1;
+          if self.search_type == SearchType::NormalForm {
+            continue;
+          }
+          if next_state > self.last_new_state {
+            self.last_new_state = next_state;
+            return Some(next_state);
+          }
+          // The initial state was passed over at the start, but it has now been reached by a step.
+          if next_state == 0 && !self.initial_state_tried {
+            self.initial_state_tried = true;
+            return Some(0);
+          }
+        }
+
+        None => {
+          let state_nr = self.explore_state;
+          let is_normal_form = self.next_arc == 0;
+          self.explore_state += 1;
+          self.next_arc = 0;
+          if self.search_type == SearchType::NormalForm && is_normal_form {
+            return Some(state_nr);
+          }
+        }
+      }
+    }
+
+    None
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::{BigInteger, IString, RcCell},
+    core::{module::Module, Strategy},
+    rc_cell,
+    theory::{
+      c_theory::{CSymbol, CTerm},
+      test_fixtures::{
+        add_constant,
+        add_free_symbol,
+        add_rule,
+        add_symbol,
+        arg_exponents,
+        make_free_term,
+        make_search,
+        make_sorts,
+        make_two_step_subject,
+        make_variable,
+      },
+      DagNode,
+      RcTerm,
+      Term,
+    },
+  };
+
+  #[test]
+  fn search_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let subject = make_two_step_subject(&module, &nat);
+    let x = make_variable("X", 0, &nat);
+
+    // The pattern `X` matches every state, so the solutions are exactly the candidates.
+    let count_solutions = |mut search: RewriteSequenceSearch| {
+      let mut count = 0;
+      while search.find_next_match().is_some() {
+        count += 1;
+      }
+      count
+    };
+    let make_search = |search_type: SearchType, max_depth: Option<usize>| {
+      make_search(&module, &subject, &x, search_type, max_depth)
+    };
+
+    // Each argument has exponent 4, 3 or 2, so there are nine states.
+    assert_eq!(count_solutions(make_search(SearchType::AnySteps, None)), 9);
+    assert_eq!(count_solutions(make_search(SearchType::AtLeastOneStep, None)), 8);
+    assert_eq!(count_solutions(make_search(SearchType::OneStep, None)), 2);
+    assert_eq!(count_solutions(make_search(SearchType::AnySteps, Some(1))), 3);
+
+    // The only normal form has both arguments rewritten twice.
+    let mut search = make_search(SearchType::NormalForm, None);
+    let state_nr = search.find_next_match().unwrap();
+    assert!(search.find_next_match().is_none());
+    assert_eq!(search.graph().get_state_depth(state_nr), 4);
+    assert_eq!(
+      arg_exponents(&search.get_state_dag(state_nr)),
+      vec![BigInteger::from(2), BigInteger::from(2)]
+    );
+
+    // Breadth-first, the first argument is rewritten all the way before the second is.
+    let path = search.get_path(state_nr);
+    assert_eq!(path.last().unwrap().state_nr, state_nr);
+    let positions: Vec<Vec<usize>> = path.iter().map(|step| step.position.clone()).collect();
+    assert_eq!(positions, vec![vec![0], vec![0], vec![1], vec![1]]);
+    assert!(search.get_path(0).is_empty());
+  }
+
+  #[test]
+  fn every_match_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let f = add_symbol(&module, CSymbol::new(IString::from("f"), Strategy::default(), false), &elt);
+    let a = add_constant(&module, "a", &elt);
+    let b = add_constant(&module, "b", &elt);
+    Module::close_theory(&module);
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+
+    // The commutative pattern f(X, Y) matches f(a, b) in two ways, and each is a solution in the initial state.
+    let mut subject = CTerm::new(f.clone(), a, b);
+    subject.normalize(true);
+    let pattern: RcTerm = rc_cell!(CTerm::new(f, x, y));
+    let mut search = make_search(&module, &subject, &pattern, SearchType::AnySteps, None);
+
+    let mut x_values = Vec::new();
+    while let Some(state_nr) = search.find_next_match() {
+      assert_eq!(state_nr, 0);
+      x_values.push(search.context().substitution.value(0).unwrap());
+    }
+    assert_eq!(search.solutions(), &[0, 0]);
+    assert!(x_values[0].borrow().compare(&**x_values[1].borrow()).is_ne());
+  }
+
+  #[test]
+  fn initial_state_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let f = add_free_symbol(&module, "f", 2, &elt);
+    let a = add_constant(&module, "a", &elt);
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+
+    // The rule `f(X, Y) => f(Y, X)` rewrites f(a, a) to itself, so the initial state is its own successor.
+    add_rule(
+      &module,
+      make_free_term(&f, vec![x.clone(), y.clone()]),
+      make_free_term(&f, vec![y, x]),
+    );
+    Module::close_theory(&module);
+    let subject = make_free_term(&f, vec![a.clone(), a]);
+    let pattern = make_variable("Z", 0, &elt);
+
+    let count_solutions = |search_type: SearchType| {
+      let mut search = make_search(&module, &*subject.borrow(), &pattern, search_type, None);
+      let mut count = 0;
+      while let Some(state_nr) = search.find_next_match() {
+        assert_eq!(state_nr, 0);
+        count += 1;
+      }
+      assert_eq!(search.graph().state_count(), 1);
+      count
+    };
+
+    // Having been reached by a step, the initial state is a candidate of every search but the normal form one.
+    assert_eq!(count_solutions(SearchType::OneStep), 1);
+    assert_eq!(count_solutions(SearchType::AtLeastOneStep), 1);
+    assert_eq!(count_solutions(SearchType::AnySteps), 1);
+    assert_eq!(count_solutions(SearchType::NormalForm), 0);
+  }
+}
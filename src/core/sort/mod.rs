@@ -5,14 +5,19 @@ Items related to sorts (types).
 */
 
 mod component;
+pub mod signature;
 mod sort_table;
 
-use std::{fmt::Display, mem::size_of};
+use std::{cell::RefCell, fmt::Display, mem::size_of, rc::Rc};
 
 pub use component::{ConnectedComponent, RcConnectedComponent};
-pub use sort_table::SortTable;
+pub use signature::{check_signature, SignatureWarning};
+pub use sort_table::{ConstructorStatus, SortTable};
 
-use crate::abstractions::{IString, NatSet, RcCell, WeakCell};
+use crate::{
+  abstractions::{IString, NatSet, RcCell, WeakCell},
+  rc_cell,
+};
 
 
 pub type RcSort = RcCell<Sort>;
@@ -100,6 +105,42 @@ impl Sort {
       .leq_sorts
       .contains(self.sort_index as usize)
   }
+
+  /// The sorts at or below this one, itself included, in index order.
+  pub fn lower_sorts(&self) -> Vec<RcSort> {
+    let component = self.sort_component.borrow();
+    self
+      .leq_sorts
+      .iter()
+      .filter_map(|index| component.sort(index as i32).upgrade())
+      .collect()
+  }
+
+  /// The maximal sorts below both this sort and `other`, that is, their greatest lower bounds. There are none if the
+  /// sorts are in different kinds or have no common subsort, and there may be several if the sorts of the kind do not
+  /// form a lattice.
+  pub fn maximal_lower_bounds(&self, other: &Sort) -> Vec<RcSort> {
+    if !RcCell::ptr_eq(&self.sort_component, &other.sort_component) {
+      return Vec::new();
+    }
+    let component = self.sort_component.borrow();
+    let lower_bounds: Vec<RcSort> = self
+      .leq_sorts
+      .intersection(&other.leq_sorts)
+      .iter()
+      .filter_map(|index| component.sort(index as i32).upgrade())
+      .collect();
+
+    lower_bounds
+      .iter()
+      .filter(|sort| {
+        !lower_bounds
+          .iter()
+          .any(|above| !RcCell::ptr_eq(sort, above) && sort.borrow().leq(&above.borrow()))
+      })
+      .cloned()
+      .collect()
+  }
 }
 
 // region trait impls
@@ -169,6 +210,147 @@ pub fn sort_leq_index(sort: &Sort, index: i32) -> bool {
   )
 }
 
+/// Builds a connected component with a kind at index 0 and the given sorts, each of which is given with the indices
+/// of the sorts below it, itself included. Components only hold weak references to their sorts, so the sorts are
+/// returned, kind first.
+pub fn make_component(sorts: &[(&str, &[usize])]) -> Vec<RcSort> {
+  let component = rc_cell!(ConnectedComponent::default());
+  let sort_count = sorts.len() + 1;
+  let kind_name = format!("[{}]", sorts[0].0);
+  let all: Vec<usize> = (0..sort_count).collect();
+  let mut result = Vec::with_capacity(sort_count);
+  for (sort_index, (name, leq_sorts)) in std::iter::once((kind_name.as_str(), all.as_slice()))
+    .chain(sorts.iter().copied())
+    .enumerate()
+  {
+    let sort = rc_cell!(Sort {
+      name: IString::from(name),
+      sort_index: sort_index as i32,
+      // Only the sorts listed are below this one.
+      fast_test: sort_count as i32,
+      sort_component: component.clone(),
+      ..Default::default()
+    });
+    for index in leq_sorts.iter() {
+      sort.borrow_mut().leq_sorts.insert(*index);
+    }
+    component.borrow_mut().append_sort(sort.downgrade());
+    component.borrow_mut().register_sort();
+    result.push(sort);
+  }
+  result
+}
+
 // Equality is implemented in WeakCell as pointer equality.
 // impl PartialEq for WeakSort
 // impl Eq for WeakSort
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    core::{module::Module, Strategy},
+    rc_cell,
+    theory::{
+      free_theory::{FreeSymbol, FreeTerm},
+      DagNode,
+      RcSymbol,
+      RcTerm,
+      Symbol,
+      Term,
+    },
+  };
+
+  #[test]
+  fn sort_diagram_test() {
+    // NzNat < Nat
+    let sorts = make_component(&[("Nat", &[1, 2]), ("NzNat", &[2])]);
+    let (nat, nz_nat) = (&sorts[1], &sorts[2]);
+
+    // Without op declarations there is nothing to compile.
+    let mut zero = FreeSymbol::new(IString::from("0"), 0, false, Strategy::default());
+    assert!(zero.symbol_members_mut().compile_op_declarations().is_empty());
+
+    zero.symbol_members_mut().sort_table.add_op_declaration(vec![nat.clone()], true);
+    assert!(zero.symbol_members_mut().compile_op_declarations().is_empty());
+    assert_eq!(zero.symbol_members().sort_table.traverse(0, 0), 1);
+
+    // f : Nat -> Nat and f : NzNat -> NzNat
+    let mut f = FreeSymbol::new(IString::from("f"), 1, false, Strategy::default());
+    f.symbol_members_mut().sort_table.add_op_declaration(vec![nat.clone(), nat.clone()], true);
+    f.symbol_members_mut().sort_table.add_op_declaration(vec![nz_nat.clone(), nz_nat.clone()], true);
+    assert!(f.symbol_members_mut().compile_op_declarations().is_empty());
+    let sort_table = &f.symbol_members().sort_table;
+    assert_eq!(sort_table.traverse(0, 0), SpecialSort::ErrorSort as i32);
+    assert_eq!(sort_table.traverse(0, 1), 1);
+    assert_eq!(sort_table.traverse(0, 2), 2);
+
+    // The sort of a DAG node is computed with the diagram.
+    let f: RcSymbol = Rc::new(f);
+    let zero: RcTerm = rc_cell!(FreeTerm::new(Rc::new(zero)));
+    let dag_node = FreeTerm::with_args(f, vec![zero]).make_dag();
+    let arg = dag_node.borrow().iter_args().next().unwrap();
+    arg.borrow_mut().set_sort_index(2);
+    assert_eq!(dag_node.borrow_mut().compute_base_sort(), 2);
+  }
+
+  #[test]
+  fn signature_check_test() {
+    // C < A and C < B, with A and B incomparable
+    let sorts = make_component(&[("A", &[1, 3]), ("B", &[2, 3]), ("C", &[3])]);
+    let (a, b, c) = (&sorts[1], &sorts[2], &sorts[3]);
+
+    // f : A -> A and f : B -> B have no least sort on C.
+    let mut f = FreeSymbol::new(IString::from("f"), 1, false, Strategy::default());
+    f.symbol_members_mut().sort_table.add_op_declaration(vec![a.clone(), a.clone()], true);
+    f.symbol_members_mut().sort_table.add_op_declaration(vec![b.clone(), b.clone()], true);
+
+    // g : C -> A is declared both as a constructor and not.
+    let mut g = FreeSymbol::new(IString::from("g"), 1, false, Strategy::default());
+    g.symbol_members_mut().sort_table.add_op_declaration(vec![c.clone(), a.clone()], true);
+    g.symbol_members_mut().sort_table.add_op_declaration(vec![c.clone(), a.clone()], false);
+    assert_eq!(g.symbol_members().sort_table.get_constructor_status(), ConstructorStatus::Complex);
+
+    let mut module = Module::new(IString::from("M"));
+    module.symbols.push(Rc::new(f));
+    module.symbols.push(Rc::new(g));
+    let warnings = module.check_signature();
+    assert_eq!(
+      warnings,
+      vec![
+        SignatureWarning::NotPreregular {
+          symbol:    IString::from("f"),
+          arg_sorts: vec![IString::from("C")],
+          ranges:    vec![IString::from("A"), IString::from("B")],
+        },
+        SignatureWarning::ConstructorConflict {
+          symbol:    IString::from("g"),
+          arg_sorts: vec![IString::from("C")],
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn lower_bounds_test() {
+    // C and D are both below A and B, and neither is below the other.
+    let sorts = make_component(&[("A", &[1, 3, 4]), ("B", &[2, 3, 4]), ("C", &[3]), ("D", &[4])]);
+    let (a, b, c) = (sorts[1].borrow(), sorts[2].borrow(), sorts[3].borrow());
+
+    assert_eq!(a.lower_sorts().len(), 3);
+    let bounds = a.maximal_lower_bounds(&b);
+    assert_eq!(bounds.len(), 2);
+    assert!(RcCell::ptr_eq(&bounds[0], &sorts[3]));
+    assert!(RcCell::ptr_eq(&bounds[1], &sorts[4]));
+    // A sort is the only maximal lower bound of itself and a supersort.
+    let bounds = a.maximal_lower_bounds(&c);
+    assert_eq!(bounds.len(), 1);
+    assert!(RcCell::ptr_eq(&bounds[0], &sorts[3]));
+
+    let other_sorts = make_component(&[("E", &[1])]);
+    assert!(a.maximal_lower_bounds(&other_sorts[1].borrow()).is_empty());
+  }
+}
@@ -0,0 +1,107 @@
+/*!
+
+Checks of an order-sorted signature. A symbol may be overloaded with several op declarations. For the sort of a term
+to be well-defined, the signature must be preregular: for every tuple of argument sorts, the op declarations whose
+domain is above the tuple must have a least range sort. The checks are made while building each symbol's sort diagram,
+see `SortTable::compile_op_declarations`, and are reported as `SignatureWarning`s rather than printed, so that the
+caller can decide what to do with them.
+
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+  abstractions::IString,
+  theory::RcSymbol,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum SignatureWarning {
+  /// An op declaration has a sort in a different kind from the first op declaration of the symbol, at argument
+  /// `arg_index` or, if it is `None`, in the range.
+  KindClash {
+    symbol:      IString,
+    declaration: usize,
+    arg_index:   Option<usize>,
+  },
+
+  /// The op declarations that apply to arguments of sorts `arg_sorts` have no least range sort among `ranges`.
+  NotPreregular {
+    symbol:    IString,
+    arg_sorts: Vec<IString>,
+    ranges:    Vec<IString>,
+  },
+
+  /// Of the most specific op declarations that apply to arguments of sorts `arg_sorts`, some are constructors and some
+  /// are not.
+  ConstructorConflict {
+    symbol:    IString,
+    arg_sorts: Vec<IString>,
+  },
+}
+
+impl Display for SignatureWarning {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SignatureWarning::KindClash {
+        symbol,
+        declaration,
+        arg_index: Some(arg_index),
+      } => {
+        write!(
+          f,
+          "op declaration {} for operator {} has argument {} in a different kind from the first declaration.",
+          declaration, symbol, arg_index
+        )
+      }
+
+      SignatureWarning::KindClash {
+        symbol,
+        declaration,
+        arg_index: None,
+      } => {
+        write!(
+          f,
+          "op declaration {} for operator {} has a range in a different kind from the first declaration.",
+          declaration, symbol
+        )
+      }
+
+      SignatureWarning::NotPreregular {
+        symbol,
+        arg_sorts,
+        ranges,
+      } => {
+        write!(
+          f,
+          "sort declarations for operator {} failed preregularity check on ({}): no least sort among {}.",
+          symbol,
+          sort_list(arg_sorts),
+          sort_list(ranges)
+        )
+      }
+
+      SignatureWarning::ConstructorConflict { symbol, arg_sorts } => {
+        write!(
+          f,
+          "constructor declarations for operator {} are inconsistent on ({}).",
+          symbol,
+          sort_list(arg_sorts)
+        )
+      }
+    }
+  }
+}
+
+fn sort_list(sorts: &[IString]) -> String {
+  sorts.iter().map(|sort| sort.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+/// Checks the op declarations of every symbol in `symbols` that has any, returning the problems found.
+pub fn check_signature(symbols: &[RcSymbol]) -> Vec<SignatureWarning> {
+  symbols
+    .iter()
+    .filter(|symbol| !symbol.sort_table().get_op_declarations().is_empty())
+    .flat_map(|symbol| symbol.sort_table().check_op_declarations(&symbol.name()))
+    .collect()
+}
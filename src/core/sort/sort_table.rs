@@ -1,7 +1,7 @@
 use std::ops::{BitOr, BitOrAssign};
 
-use super::{OpDeclaration, RcConnectedComponent, RcSort, WeakSort};
-use crate::abstractions::{NatSet, WeakCell};
+use super::{signature::SignatureWarning, OpDeclaration, RcConnectedComponent, RcSort, SpecialSort, WeakSort};
+use crate::abstractions::{IString, NatSet, RcCell, WeakCell};
 
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
@@ -35,6 +35,7 @@ impl BitOrAssign for ConstructorStatus {
 pub struct SortTable {
   arg_count:                 usize,
   op_declarations:           Vec<OpDeclaration>,
+  constructor_flags:         Vec<bool>,         // whether each op declaration has the `ctor` attribute
   component_vector:          Vec<RcConnectedComponent>,
   sort_diagram:              Vec<i32>,
   single_non_error_sort:     Option<WeakSort>, // if we can only generate one non-error sort
@@ -44,6 +45,13 @@ pub struct SortTable {
 }
 
 impl SortTable {
+  pub fn new(arg_count: usize) -> Self {
+    SortTable {
+      arg_count,
+      ..Default::default()
+    }
+  }
+
   #[inline(always)]
   pub fn arity(&self) -> usize {
     self.arg_count
@@ -76,8 +84,8 @@ impl SortTable {
     self
       .op_declarations
       .resize(op_declaration_count + 1, OpDeclaration::default());
-    self.op_declarations[op_declaration_count] = domain_and_range.clone(); //.set_info(domain_and_range,
-                                                                           // constructor_flag);
+    self.op_declarations[op_declaration_count] = domain_and_range.clone();
+    self.constructor_flags.push(constructor_flag);
     self.constructor_status |= if constructor_flag {
       ConstructorStatus::Constructor
     } else {
@@ -90,6 +98,11 @@ impl SortTable {
     &self.op_declarations
   }
 
+  #[inline(always)]
+  pub fn is_constructor_declaration(&self, declaration: usize) -> bool {
+    self.constructor_flags[declaration]
+  }
+
   #[inline(always)]
   pub fn range_component(&self) -> RcConnectedComponent {
     // ToDo: Is this function fallible? Should it return `Option<RcConnectedComponent>`?
@@ -110,7 +123,7 @@ impl SortTable {
   }
 
   #[inline(always)]
-  pub fn domain_components_iter(&self) -> Box<dyn Iterator<Item = RcConnectedComponent>> {
+  pub fn domain_components_iter(&self) -> Box<dyn Iterator<Item = RcConnectedComponent> + '_> {
     // (&self.op_declarations[0])[arg_nr].borrow().sort_component.clone()
     Box::new(
       (&self.op_declarations[0])
@@ -185,4 +198,225 @@ impl SortTable {
       }
     }
   }
+
+  // region Sort Diagrams
+
+  /// Builds the sort diagram and the constructor diagram from the op declarations, replacing any built before. Returns
+  /// the problems found with the declarations, naming the symbol `symbol_name` in them. A symbol without op
+  /// declarations has nothing to compile, just as `check_signature` has nothing to check for it.
+  pub fn compile_op_declarations(&mut self, symbol_name: &IString) -> Vec<SignatureWarning> {
+    if self.op_declarations.is_empty() {
+      return Vec::new();
+    }
+    let (sort_diagram, constructor_diagram, warnings) = self.build_sort_diagram(symbol_name);
+    self.component_vector = self.declaration_components();
+    self.sort_diagram = sort_diagram;
+    self.constructor_diagram = constructor_diagram;
+    warnings
+  }
+
+  /// Checks the op declarations without compiling them, returning the problems found. See `build_sort_diagram`.
+  pub fn check_op_declarations(&self, symbol_name: &IString) -> Vec<SignatureWarning> {
+    self.build_sort_diagram(symbol_name).2
+  }
+
+  /// The connected components of the arguments and of the range, from the first op declaration.
+  fn declaration_components(&self) -> Vec<RcConnectedComponent> {
+    self.op_declarations[0]
+      .iter()
+      .map(|sort| sort.borrow().sort_component.clone())
+      .collect()
+  }
+
+  /// Builds the sort diagram and the constructor diagram of the op declarations, along with the problems found.
+  ///
+  /// The sort diagram is a finite automaton that reads the sort indices of the arguments one at a time, as in
+  /// `traverse`. Each state is the set of op declarations whose domain is above the argument sorts read so far, and
+  /// is a row of the diagram with an entry for each sort of the argument's component. The entries for the last
+  /// argument are the least range sort of the op declarations that apply. If there is no such least sort for some
+  /// tuple of argument sorts, the signature is not preregular. The constructor diagram has the same shape, and its
+  /// entries for the last argument say whether the most specific op declarations that apply are constructors. If they
+  /// disagree, the constructor status of the tuple is ambiguous.
+  fn build_sort_diagram(&self, symbol_name: &IString) -> (Vec<i32>, Vec<i32>, Vec<SignatureWarning>) {
+    let mut warnings = self.check_components(symbol_name);
+    if !warnings.is_empty() {
+      // The sorts of the declarations can't be compared, so there is nothing more to check.
+      return (Vec::new(), Vec::new(), warnings);
+    }
+
+    let components = self.declaration_components();
+    let mut all = NatSet::new();
+    for declaration in 0..self.op_declarations.len() {
+      all.insert(declaration);
+    }
+
+    if self.arg_count == 0 {
+      let (sort_index, is_constructor) = self.find_min_sort_index(&all, &[], symbol_name, &mut warnings);
+      return (vec![sort_index], vec![is_constructor as i32], warnings);
+    }
+
+    let mut sort_diagram = vec![0; components[0].borrow().sort_count as usize];
+    let mut constructor_diagram = sort_diagram.clone();
+    // Each state is the set of applicable declarations, the first tuple of argument sorts that reached it, and the
+    // position of its row.
+    let mut current_states: Vec<(NatSet, Vec<usize>, usize)> = vec![(all, Vec::new(), 0)];
+
+    for arg_index in 0..self.arg_count {
+      let sort_count = components[arg_index].borrow().sort_count as usize;
+      let is_last = arg_index + 1 == self.arg_count;
+      let mut next_states: Vec<(NatSet, Vec<usize>, usize)> = Vec::new();
+
+      for (state, tuple, position) in current_states.iter() {
+        for sort_index in 0..sort_count {
+          let mut next_state = NatSet::new();
+          for declaration in state.iter() {
+            let domain_sort = &self.op_declarations[declaration][arg_index];
+            if domain_sort.borrow().leq_sorts.contains(sort_index) {
+              next_state.insert(declaration);
+            }
+          }
+          let mut next_tuple = tuple.clone();
+          next_tuple.push(sort_index);
+
+          if is_last {
+            let (range_index, is_constructor) =
+              self.find_min_sort_index(&next_state, &next_tuple, symbol_name, &mut warnings);
+            sort_diagram[position + sort_index] = range_index;
+            constructor_diagram[position + sort_index] = is_constructor as i32;
+            continue;
+          }
+
+          let next_position = match next_states.iter().find(|(other, ..)| *other == next_state) {
+            Some((.., next_position)) => *next_position,
+            None => {
+              let next_position = sort_diagram.len();
+              let next_sort_count = components[arg_index + 1].borrow().sort_count as usize;
+              sort_diagram.resize(next_position + next_sort_count, 0);
+              constructor_diagram.resize(next_position + next_sort_count, 0);
+              next_states.push((next_state, next_tuple, next_position));
+              next_position
+            }
+          };
+          sort_diagram[position + sort_index] = next_position as i32;
+          constructor_diagram[position + sort_index] = next_position as i32;
+        }
+      }
+
+      current_states = next_states;
+    }
+
+    (sort_diagram, constructor_diagram, warnings)
+  }
+
+  /// Checks that every op declaration has its domain and range sorts in the same kinds as the first.
+  fn check_components(&self, symbol_name: &IString) -> Vec<SignatureWarning> {
+    let mut warnings = Vec::new();
+    if self.op_declarations.is_empty() {
+      return warnings;
+    }
+
+    let components = self.declaration_components();
+    for (declaration, domain_and_range) in self.op_declarations.iter().enumerate().skip(1) {
+      for (arg_index, sort) in domain_and_range.iter().enumerate() {
+        if !RcCell::ptr_eq(&sort.borrow().sort_component, &components[arg_index]) {
+          warnings.push(SignatureWarning::KindClash {
+            symbol: symbol_name.clone(),
+            declaration,
+            // The range comes after the arguments.
+            arg_index: if arg_index == self.arg_count { None } else { Some(arg_index) },
+          });
+        }
+      }
+    }
+    warnings
+  }
+
+  /// Finds the least range sort of the op declarations in `state`, which apply to arguments of the sorts in `tuple`,
+  /// and whether the most specific of them are constructors. Reports the tuple if either is ambiguous.
+  fn find_min_sort_index(
+    &self,
+    state: &NatSet,
+    tuple: &[usize],
+    symbol_name: &IString,
+    warnings: &mut Vec<SignatureWarning>,
+  ) -> (i32, bool) {
+    // No declaration applies, so the result is in the error sort.
+    if state.is_empty() {
+      return (SpecialSort::ErrorSort as i32, false);
+    }
+
+    let ranges: Vec<RcSort> = state
+      .iter()
+      .map(|declaration| self.op_declarations[declaration][self.arg_count].clone())
+      .collect();
+    let least_range = ranges
+      .iter()
+      .find(|candidate| ranges.iter().all(|range| candidate.borrow().leq(&range.borrow())));
+    let range_index = match least_range {
+      Some(least_range) => least_range.borrow().sort_index,
+      None => {
+        warnings.push(SignatureWarning::NotPreregular {
+          symbol:    symbol_name.clone(),
+          arg_sorts: self.sort_names(tuple),
+          ranges:    ranges.iter().map(|range| range.borrow().name.clone()).collect(),
+        });
+        // Like Maude, settle for a minimal range.
+        let is_minimal = |candidate: &&RcSort| {
+          !ranges
+            .iter()
+            .any(|range| !RcCell::ptr_eq(range, candidate) && range.borrow().leq(&candidate.borrow()))
+        };
+        ranges
+          .iter()
+          .find(is_minimal)
+          .unwrap_or(&ranges[0])
+          .borrow()
+          .sort_index
+      }
+    };
+
+    // The most specific declarations are those whose domain doesn't strictly subsume another's.
+    let mut constructor_status: Option<ConstructorStatus> = None;
+    for declaration in state.iter() {
+      let is_most_specific = !state.iter().any(|other| {
+        other != declaration && self.domain_subsumes(declaration, other) && !self.domain_subsumes(other, declaration)
+      });
+      if is_most_specific {
+        let status = if self.constructor_flags[declaration] {
+          ConstructorStatus::Constructor
+        } else {
+          ConstructorStatus::NonConstructor
+        };
+        constructor_status = Some(match constructor_status {
+          Some(constructor_status) => constructor_status | status,
+          None => status,
+        });
+      }
+    }
+    if constructor_status == Some(ConstructorStatus::Complex) {
+      warnings.push(SignatureWarning::ConstructorConflict {
+        symbol:    symbol_name.clone(),
+        arg_sorts: self.sort_names(tuple),
+      });
+    }
+
+    (range_index, constructor_status == Some(ConstructorStatus::Constructor))
+  }
+
+  /// The names of the argument sorts with the given indices.
+  fn sort_names(&self, tuple: &[usize]) -> Vec<IString> {
+    let components = self.declaration_components();
+    tuple
+      .iter()
+      .enumerate()
+      .map(|(arg_index, sort_index)| {
+        match components[arg_index].borrow().sort(*sort_index as i32).upgrade() {
+          Some(sort) => sort.borrow().name.clone(),
+          None => unreachable!("Sort dropped from its connected component. This is a bug."),
+        }
+      })
+      .collect()
+  }
+
+  // endregion Sort Diagrams
 }
@@ -0,0 +1,252 @@
+/*!
+
+The graph of states reachable from an initial state by rule rewriting. The graph is built lazily: the successors of a
+state are only computed when they are first asked for.
+
+States are identified up to structural equality by hash consing their DAGs, so that a state reached along different
+paths is a single node of the graph. States are numbered in the order they are discovered, with the initial state
+numbered 0. Each state remembers the state it was first reached from, so if states are explored in numeric order, as
+in a breadth-first search, the parent of a state is on a shortest path to it from the initial state.
+
+Along with its parent, each state records the rule and the redex position of the rewrite that first reached it, so that
+the path to any state can be reconstructed with `get_path`. This is what Maude's `show path` command displays.
+
+*/
+
+use std::collections::HashMap;
+
+use crate::{
+  abstractions::FastHasherBuilder,
+  core::{
+    hash_cons_set::HashConsSet,
+    pre_equation::{rule, RcPreEquation},
+    rewrite_context::RewritingContext,
+    RedexPosition,
+  },
+  theory::{DagNode, RcDagNode},
+  UNDEFINED,
+};
+
+struct State {
+  /// The index of the state's canonical DAG in the hash cons set.
+  hash_cons_index: usize,
+  /// The state this state was first reached from. Only the initial state has none.
+  parent:          Option<usize>,
+  /// The rule and redex position of the rewrite from `parent` that reached this state.
+  arrival:         Option<(RcPreEquation, Vec<usize>)>,
+  /// The length of the path to this state through its parents.
+  depth:           usize,
+  /// The distinct successors of this state in the order they were found.
+  next_states:     Vec<usize>,
+  explored:        bool,
+}
+
+/// A step of a path through the graph: the state reached, and the rewrite that reached it from the previous state.
+#[derive(Clone)]
+pub struct PathStep {
+  pub state_nr: usize,
+  pub rule:     RcPreEquation,
+  /// The argument indices leading from the root of the previous state to the redex. The root is the empty position.
+  pub position: Vec<usize>,
+}
+
+pub struct StateTransitionGraph {
+  seen:          Vec<State>,
+  hash_cons_set: HashConsSet,
+  /// Maps the index of a canonical DAG in `hash_cons_set` to the number of its state.
+  state_numbers: HashMap<usize, usize, FastHasherBuilder>,
+  /// The context of the initial state, used for all matching and reduction. It accumulates the rewrite counts.
+  context:       RewritingContext,
+}
+
+impl StateTransitionGraph {
+  /// Makes a graph whose initial state is the root of `context` reduced to normal form.
+  pub(crate) fn new(mut context: RewritingContext) -> Self {
+    context.reduce();
+    let root = match &context.root {
+      Some(root) => root.clone(),
+      None => unreachable!("A state transition graph needs an initial state. This is a bug."),
+    };
+
+    let mut graph = StateTransitionGraph {
+      seen: Vec::new(),
+      hash_cons_set: HashConsSet::new(),
+      state_numbers: HashMap::default(),
+      context,
+    };
+    graph.insert_state(root, None, None);
+    graph
+  }
+
+  // region Accessors
+
+  #[inline(always)]
+  pub fn state_count(&self) -> usize {
+    self.seen.len()
+  }
+
+  #[inline(always)]
+  pub fn get_state_dag(&self, state_nr: usize) -> RcDagNode {
+    self.hash_cons_set.get_canonical(self.seen[state_nr].hash_cons_index)
+  }
+
+  #[inline(always)]
+  pub fn get_state_parent(&self, state_nr: usize) -> Option<usize> {
+    self.seen[state_nr].parent
+  }
+
+  #[inline(always)]
+  pub fn get_state_depth(&self, state_nr: usize) -> usize {
+    self.seen[state_nr].depth
+  }
+
+  /// The rule of the rewrite that first reached the given state. The initial state has none.
+  #[inline(always)]
+  pub fn get_state_rule(&self, state_nr: usize) -> Option<RcPreEquation> {
+    self.seen[state_nr].arrival.as_ref().map(|(rule, _)| rule.clone())
+  }
+
+  /// The redex position of the rewrite that first reached the given state. The initial state has none.
+  #[inline(always)]
+  pub fn get_state_position(&self, state_nr: usize) -> Option<&[usize]> {
+    self.seen[state_nr].arrival.as_ref().map(|(_, position)| position.as_slice())
+  }
+
+  #[inline(always)]
+  pub(crate) fn context(&self) -> &RewritingContext {
+    &self.context
+  }
+
+  #[inline(always)]
+  pub(crate) fn context_mut(&mut self) -> &mut RewritingContext {
+    &mut self.context
+  }
+
+  // endregion
+
+  /// Returns the number of the `index`th successor of the given state, or `None` if it has no more successors. The
+  /// successors are computed the first time they are asked for.
+  pub fn get_next_state(&mut self, state_nr: usize, index: usize) -> Option<usize> {
+    if !self.seen[state_nr].explored {
+      self.explore(state_nr);
+    }
+    self.seen[state_nr].next_states.get(index).copied()
+  }
+
+  /// Computes the successors of a state, adding those that are new to the graph.
+  fn explore(&mut self, state_nr: usize) {
+    let successors = self.rewrite_all(self.get_state_dag(state_nr));
+    for (successor, rule, position) in successors {
+      self.context.reduce_dag_node(successor.clone());
+      let next_state_nr = self.insert_state(successor, Some(state_nr), Some((rule, position)));
+      let next_states = &mut self.seen[state_nr].next_states;
+      if !next_states.contains(&next_state_nr) {
+        next_states.push(next_state_nr);
+      }
+    }
+    self.seen[state_nr].explored = true;
+  }
+
+  /// Returns the path from the initial state to the given state through the parents of the states along it. The path
+  /// to the initial state is empty.
+  pub fn get_path(&self, state_nr: usize) -> Vec<PathStep> {
+    let mut path = Vec::with_capacity(self.seen[state_nr].depth);
+    let mut current = state_nr;
+    while let Some((rule, position)) = &self.seen[current].arrival {
+      path.push(PathStep {
+        state_nr: current,
+        rule: rule.clone(),
+        position: position.clone(),
+      });
+      current = match self.seen[current].parent {
+        Some(parent) => parent,
+        None => unreachable!("A state with an arrival rewrite has no parent. This is a bug."),
+      };
+    }
+    path.reverse();
+    path
+  }
+
+  /// Returns the number of the state of `dag_node`, making a new state reached from `parent` by the rewrite `arrival`
+  /// if there is none.
+  fn insert_state(
+    &mut self,
+    dag_node: RcDagNode,
+    parent: Option<usize>,
+    arrival: Option<(RcPreEquation, Vec<usize>)>,
+  ) -> usize {
+    let (_, hash_cons_index) = self.hash_cons_set.insert_copy(dag_node);
+    if let Some(state_nr) = self.state_numbers.get(&hash_cons_index) {
+      return *state_nr;
+    }
+
+    let state_nr = self.seen.len();
+    self.seen.push(State {
+      hash_cons_index,
+      parent,
+      arrival,
+      depth: parent.map_or(0, |parent| self.seen[parent].depth + 1),
+      next_states: Vec::new(),
+      explored: false,
+    });
+    self.state_numbers.insert(hash_cons_index, state_nr);
+    state_nr
+  }
+
+  /// Returns the result of every one-step rule rewrite of `dag_node`, at every position that isn't frozen, outermost
+  /// positions first, together with the rule and position of the rewrite. The results are not reduced.
+  fn rewrite_all(&mut self, dag_node: RcDagNode) -> Vec<(RcDagNode, RcPreEquation, Vec<usize>)> {
+    let mut successors = Vec::new();
+    let mut redex_stack = vec![RedexPosition::new(dag_node, UNDEFINED, UNDEFINED, true)];
+    let mut index = 0;
+
+    while index < redex_stack.len() {
+      let (dag_node, eager) = {
+        let position = &redex_stack[index];
+        (position.dag_node.clone(), position.is_eager())
+      };
+      dag_node
+        .borrow()
+        .stack_arguments(&mut redex_stack, index as i32, true, eager);
+
+      let symbol = dag_node.borrow().symbol();
+      for rule in symbol.symbol_members().rules().iter() {
+        for replacement in rule::apply_all(rule, dag_node.clone(), &mut self.context) {
+          let successor = rebuild(&redex_stack, index, replacement);
+          successors.push((successor, rule.clone(), position(&redex_stack, index)));
+        }
+      }
+      index += 1;
+    }
+
+    successors
+  }
+}
+
+/// Builds a copy of the DAG at the bottom of `redex_stack` in which `replacement` is in the position at `index`.
+fn rebuild(redex_stack: &[RedexPosition], mut index: usize, replacement: RcDagNode) -> RcDagNode {
+  let mut dag_node = replacement;
+  loop {
+    let position = &redex_stack[index];
+    if position.parent_index == UNDEFINED {
+      return dag_node;
+    }
+    let parent = &redex_stack[position.parent_index as usize];
+    dag_node = parent
+      .dag_node
+      .borrow()
+      .copy_with_replacement(dag_node, position.arg_index as usize);
+    index = position.parent_index as usize;
+  }
+}
+
+/// The argument indices leading from the bottom of `redex_stack` to the position at `index`.
+fn position(redex_stack: &[RedexPosition], mut index: usize) -> Vec<usize> {
+  let mut position = Vec::new();
+  while redex_stack[index].parent_index != UNDEFINED {
+    position.push(redex_stack[index].arg_index as usize);
+    index = redex_stack[index].parent_index as usize;
+  }
+  position.reverse();
+  position
+}
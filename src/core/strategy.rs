@@ -6,7 +6,7 @@ The execution strategy.
 
 use crate::abstractions::NatSet;
 
-#[derive(Default, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Strategy {
   pub is_standard:           bool,
   pub unevaluated_arguments: bool,
@@ -16,6 +16,20 @@ pub struct Strategy {
   pub frozen:                NatSet,
 }
 
+impl Default for Strategy {
+  /// The standard strategy: evaluate every argument, then try equations at the top.
+  fn default() -> Self {
+    Strategy {
+      is_standard:           true,
+      unevaluated_arguments: false,
+      strategy:              Vec::new(),
+      eager:                 NatSet::default(),
+      evaluated:             NatSet::default(),
+      frozen:                NatSet::default(),
+    }
+  }
+}
+
 impl Strategy {
   pub fn get_frozen(&self) -> &NatSet {
     &self.frozen
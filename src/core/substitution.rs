@@ -105,10 +105,9 @@ impl Substitution {
     let mut local_bindings = LocalBindings::new();
     for (idx, (i, j)) in self.bindings.iter().zip(original.iter()).enumerate() {
       assert!(j.is_none() || i == j, "substitution inconsistency at index {}", idx);
-      if let (Some(a), Some(b)) = (i, j) {
-        if a != b {
-          local_bindings.add_binding(idx as i32, (*a).clone());
-        }
+      // Only bindings made since `original` belong in the difference.
+      if let (Some(a), None) = (i, j) {
+        local_bindings.add_binding(idx as i32, (*a).clone());
       }
     }
 
@@ -52,17 +52,15 @@ impl TermBag {
     Self::default()
   }
 
-  /// Inserts the matched term if it is not already present in the `TermBag`. If it is already in the `TermBag`, no
-  /// action is taken. In debug mode, attempting to insert an existing term results in an error.
+  /// Inserts the matched term if it is not already present in the `TermBag`. If it is already in the `TermBag`, as when
+  /// a subterm occurs more than once in a pattern, no action is taken.
   #[inline(always)]
   pub(crate) fn insert_matched_term(&mut self, term: RcTerm, eager_context: bool) {
     // New matched terms can never replace built terms (which are available at zero cost) nor existing matched terms
     // (for which the cost of storing the extra pointer may already have been paid).
-    let (_, b) = self.terms_usable_in_lazy_context.insert_no_replace(term.clone());
-    assert!(!b, "TermBag should not insert a term that is already in the bag");
+    self.terms_usable_in_lazy_context.insert_no_replace(term.clone());
     if eager_context {
-      let (_, b) = self.terms_usable_in_eager_context.insert_no_replace(term.clone());
-      assert!(!b, "TermBag should not insert a term that is already in the bag");
+      self.terms_usable_in_eager_context.insert_no_replace(term);
     }
   }
 
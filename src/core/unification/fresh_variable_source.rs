@@ -0,0 +1,150 @@
+/*!
+
+A source of fresh variables. Fresh variables are named `#1`, `#2`, … so that they cannot clash with the variables of a
+user's terms, whose names are identifiers. Each fresh variable has its own variable symbol carrying its sort, as the
+variables made by the parser do.
+
+Renaming a DAG apart replaces each of its variables by a fresh variable of the same sort, so that the DAG shares no
+variables with any other. This is needed before unifying the lefthand side of a rule with a term that may have
+variables of the same names, as in narrowing. A source can be told to avoid the fresh variables of terms made by
+another source.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{IString, RcCell},
+  core::sort::{RcSort, SpecialSort},
+  rc_cell,
+  theory::{
+    acu_theory::{ACUDagNode, NormalizationStatus},
+    make_rc_dag_node,
+    variable::{VariableDagNode, VariableSymbol},
+    DagNode,
+    DagNodeFlags,
+    RcDagNode,
+    RcSymbol,
+    Symbol,
+  },
+};
+
+#[derive(Default)]
+pub struct FreshVariableSource {
+  /// The number of fresh variables made so far.
+  variable_count: usize,
+}
+
+impl FreshVariableSource {
+  #[inline(always)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  #[inline(always)]
+  pub fn variable_count(&self) -> usize {
+    self.variable_count
+  }
+
+  /// Makes a variable of sort `sort` that is distinct from every other variable made by this source.
+  pub fn make_variable(&mut self, sort: &RcSort) -> RcDagNode {
+    self.variable_count += 1;
+    let name = IString::from(format!("#{}", self.variable_count).as_str());
+    let mut symbol = VariableSymbol::new(name.clone());
+    symbol.symbol_members_mut().sort_table.add_op_declaration(vec![sort.clone()], true);
+
+    make_rc_dag_node(VariableDagNode::new(Rc::new(symbol), name, self.variable_count as i32 - 1))
+  }
+
+  /// Makes sure the variables made from now on differ from the variables of `dag_node`, which may be fresh variables
+  /// made by another source.
+  pub fn avoid(&mut self, dag_node: &RcDagNode) {
+    if dag_node.borrow().symbol().is_variable() {
+      let name = dag_node.borrow().symbol().name();
+      if let Some(number) = name.strip_prefix('#').and_then(|number| number.parse::<usize>().ok()) {
+        self.variable_count = self.variable_count.max(number);
+      }
+      return;
+    }
+    for arg in dag_node.borrow().iter_args() {
+      self.avoid(&arg);
+    }
+  }
+
+  /// Returns a copy of `dag_node` in which each variable is replaced by a fresh variable of the same sort. Different
+  /// occurrences of a variable are replaced by the same fresh variable.
+  pub fn rename_apart(&mut self, dag_node: &RcDagNode) -> RcDagNode {
+    let mut renaming = Vec::new();
+    self.rename(dag_node, &mut renaming)
+  }
+
+  /// Renames the DAGs of `dag_nodes` apart together, so that a variable they share is replaced by the same fresh
+  /// variable in each, as the variables of a rule's two sides must be. The renaming is returned as well, as pairs of a
+  /// variable and the fresh variable replacing it.
+  pub fn rename_all_apart(&mut self, dag_nodes: &[RcDagNode]) -> (Vec<RcDagNode>, Vec<(RcDagNode, RcDagNode)>) {
+    let mut renaming = Vec::new();
+    let renamed = dag_nodes
+      .iter()
+      .map(|dag_node| self.rename(dag_node, &mut renaming))
+      .collect();
+    (renamed, renaming)
+  }
+
+  fn rename(&mut self, dag_node: &RcDagNode, renaming: &mut Vec<(RcDagNode, RcDagNode)>) -> RcDagNode {
+    if dag_node.borrow().symbol().is_variable() {
+      let renamed = renaming
+        .iter()
+        .find(|(variable, _)| variable.borrow().compare(&**dag_node.borrow()).is_eq());
+      if let Some((_, fresh)) = renamed {
+        return fresh.clone();
+      }
+      let fresh = self.make_variable(&variable_sort(dag_node));
+      renaming.push((dag_node.clone(), fresh.clone()));
+      return fresh;
+    }
+
+    let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+    if args.is_empty() {
+      return dag_node.clone();
+    }
+    let new_args: Vec<RcDagNode> = args.iter().map(|arg| self.rename(arg, renaming)).collect();
+    replace_args(dag_node, new_args)
+  }
+}
+
+/// The sort of a variable, which is carried by its symbol.
+pub(crate) fn variable_sort(variable: &RcDagNode) -> RcSort {
+  let symbol: RcSymbol = variable.borrow().symbol();
+  match symbol.as_any().downcast_ref::<VariableSymbol>() {
+    Some(symbol) => symbol.sort(),
+    None => unreachable!("Failed to downcast to VariableSymbol. This is a bug."),
+  }
+}
+
+/// Makes a copy of `dag_node` with the arguments `new_args`, which correspond to the arguments `iter_args` gives. The
+/// copy is a new term, so it is not reduced and its sort is unknown. An ACU node is renormalized, since its new
+/// arguments may flatten into it or be its identity, so the copy may collapse to one of them.
+pub(crate) fn replace_args(dag_node: &RcDagNode, new_args: Vec<RcDagNode>) -> RcDagNode {
+  if let Some(acu_dag_node) = dag_node.borrow().as_any().downcast_ref::<ACUDagNode>() {
+    let mut copy = ACUDagNode::new(dag_node.borrow().symbol(), new_args.len(), NormalizationStatus::Fresh);
+    for ((_, multiplicity), arg) in acu_dag_node.iter_pairs().zip(new_args) {
+      copy.push_arg(arg, multiplicity);
+    }
+    return match copy.normalize_at_top() {
+      Some(collapsed) => collapsed,
+      None => make_rc_dag_node(copy),
+    };
+  }
+
+  let copy = dag_node.borrow().shallow_copy();
+  {
+    let mut copy = copy.borrow_mut();
+    let members = copy.dag_node_members_mut();
+    for (index, arg) in new_args.into_iter().enumerate() {
+      members.args[index] = arg;
+    }
+    members.flags = DagNodeFlags::default();
+    members.sort_index = SpecialSort::Unknown as i32;
+  }
+  copy
+}
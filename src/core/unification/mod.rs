@@ -0,0 +1,284 @@
+/*!
+
+Unification: solving equations between terms with variables on both sides. Where matching finds a substitution that
+makes a pattern equal to a ground subject, unification finds substitutions that make two terms with variables equal.
+It is the basis of narrowing and of the computation of critical pairs.
+
+A `UnificationProblem` finds unifiers one at a time. In the free theory there is a single most general unifier. Modulo
+associativity and commutativity there may be several, and the equations concerned are deferred to a
+`UnificationSubproblem` of the theory, which enumerates its solutions lazily; the problem backtracks into the
+subproblems to find a complete set of unifiers. A problem may be a single equation or a system of equations. Unifiers
+respect the sorts of variables: unifying variables of incomparable sorts introduces fresh variables at their greatest
+lower bounds, and each unifier of the unsorted terms is specialized into its order-sorted unifiers. Terms to be unified
+should not share variables unintentionally; a `FreshVariableSource` renames a term's variables apart.
+
+*/
+
+mod fresh_variable_source;
+mod order_sorted;
+mod unification_problem;
+mod unification_subproblem;
+
+pub use fresh_variable_source::FreshVariableSource;
+pub(crate) use fresh_variable_source::{replace_args, variable_sort};
+pub use order_sorted::SortIntersectionSubproblem;
+pub use unification_problem::UnificationProblem;
+pub use unification_subproblem::{Equation, UnificationSubproblem};
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    abstractions::RcCell,
+    core::sort::{make_component, RcSort},
+    theory::{
+      test_fixtures::{
+        is_equal,
+        make_acu_dag,
+        make_acu_symbol,
+        make_acu_symbol_with_identity,
+        make_constant_symbol,
+        make_free_dag,
+        make_free_symbol,
+        make_free_symbol_with_declarations,
+        make_free_term,
+        make_variable_dag,
+      },
+      DagNode,
+      RcDagNode,
+      RcSymbol,
+    },
+  };
+
+  /// The signature `0 : -> Nat`, `s : Nat -> NzNat` and `f : Nat Nat -> Nat`, with NzNat < Nat. The sorts are returned
+  /// to keep them alive.
+  fn make_signature() -> (Vec<RcSort>, RcSymbol, RcSymbol, RcSymbol) {
+    let sorts = make_component(&[("Nat", &[1, 2]), ("NzNat", &[2])]);
+    let (nat, nz_nat) = (&sorts[1], &sorts[2]);
+    let zero = make_constant_symbol("0", nat);
+    let s = make_free_symbol_with_declarations("s", &[&[nat, nz_nat]]);
+    let f = make_free_symbol("f", 2, nat);
+    (sorts, zero, s, f)
+  }
+
+  #[test]
+  fn most_general_unifier_test() {
+    let (sorts, _zero, s, f) = make_signature();
+    let x = make_variable_dag("X", 0, &sorts[1]);
+    let y = make_variable_dag("Y", 1, &sorts[1]);
+    let z = make_variable_dag("Z", 2, &sorts[2]);
+
+    // f(X, s(X)) =? f(s(Y), Z)
+    let lhs = make_free_dag(&f, &[&x, &make_free_dag(&s, &[&x])]);
+    let rhs = make_free_dag(&f, &[&make_free_dag(&s, &[&y]), &z]);
+    let mut problem = UnificationProblem::new(lhs.clone(), rhs.clone());
+    assert!(problem.find_next_unifier());
+
+    // X |-> s(Y), Y |-> Y, Z |-> s(s(Y))
+    let solution = problem.solution();
+    let s_y = make_free_dag(&s, &[&y]);
+    assert!(is_equal(&solution.value(0).unwrap(), &s_y));
+    assert!(is_equal(&solution.value(1).unwrap(), &y));
+    assert!(is_equal(&solution.value(2).unwrap(), &make_free_dag(&s, &[&s_y])));
+    assert!(is_equal(&problem.instantiate(&lhs), &problem.instantiate(&rhs)));
+
+    // A most general unifier is the only one needed.
+    assert!(!problem.find_next_unifier());
+  }
+
+  #[test]
+  fn unification_failure_test() {
+    let (sorts, zero, s, f) = make_signature();
+    let x = make_variable_dag("X", 0, &sorts[1]);
+    let y = make_variable_dag("Y", 1, &sorts[1]);
+    let z = make_variable_dag("Z", 2, &sorts[2]);
+    let zero = make_free_dag(&zero, &[]);
+
+    // Occurs check: X =? s(X)
+    let mut problem = UnificationProblem::new(x.clone(), make_free_dag(&s, &[&x]));
+    assert!(!problem.find_next_unifier());
+
+    // Symbol clash: f(X, 0) =? f(0, s(Y))
+    let lhs = make_free_dag(&f, &[&x, &zero]);
+    let rhs = make_free_dag(&f, &[&zero, &make_free_dag(&s, &[&y])]);
+    assert!(!UnificationProblem::new(lhs, rhs).find_next_unifier());
+
+    // Sorts: 0 has sort Nat, which is not below NzNat.
+    assert!(!UnificationProblem::new(z.clone(), zero.clone()).find_next_unifier());
+    assert!(UnificationProblem::new(x.clone(), zero).find_next_unifier());
+
+    // Of two variables, the one of larger sort is bound.
+    let mut problem = UnificationProblem::new(x.clone(), z.clone());
+    assert!(problem.find_next_unifier());
+    assert!(is_equal(&problem.solution().value(0).unwrap(), &z));
+    assert!(is_equal(&problem.solution().value(1).unwrap(), &z));
+  }
+
+  #[test]
+  fn unification_system_test() {
+    let (sorts, zero, s, f) = make_signature();
+    let x = make_variable_dag("X", 0, &sorts[1]);
+    let y = make_variable_dag("Y", 1, &sorts[1]);
+    let w = make_variable_dag("W", 2, &sorts[1]);
+    let zero = make_free_dag(&zero, &[]);
+
+    // X =? 0 and f(X, Y) =? f(Y, W) together bind every variable to 0.
+    let equations = vec![(x.clone(), zero.clone()), (make_free_dag(&f, &[&x, &y]), make_free_dag(&f, &[&y, &w]))];
+    let mut problem = UnificationProblem::with_equations(equations);
+    assert!(problem.find_next_unifier());
+    for index in 0..3 {
+      assert!(is_equal(&problem.solution().value(index).unwrap(), &zero));
+    }
+
+    // Each equation is solvable on its own, but not both at once.
+    let equations = vec![(x.clone(), zero), (x.clone(), make_free_dag(&s, &[&y]))];
+    assert!(!UnificationProblem::with_equations(equations).find_next_unifier());
+  }
+
+  #[test]
+  fn rename_apart_test() {
+    let (sorts, _zero, s, f) = make_signature();
+    let x = make_variable_dag("X", 0, &sorts[1]);
+    let dag_node = make_free_dag(&f, &[&x, &make_free_dag(&s, &[&x])]);
+
+    let mut fresh_variables = FreshVariableSource::new();
+    let renamed = fresh_variables.rename_apart(&dag_node);
+    assert_eq!(fresh_variables.variable_count(), 1);
+    let args: Vec<RcDagNode> = renamed.borrow().iter_args().collect();
+    let fresh = args[0].clone();
+    assert!(!is_equal(&fresh, &x));
+    assert!(RcCell::ptr_eq(&variable_sort(&fresh), &sorts[1]));
+    assert!(is_equal(&args[1], &make_free_dag(&s, &[&fresh])));
+
+    // A source that avoids the renamed term doesn't remake its fresh variable.
+    let mut other_source = FreshVariableSource::new();
+    other_source.avoid(&renamed);
+    assert!(!is_equal(&other_source.make_variable(&sorts[1]), &fresh));
+
+    // Terms renamed together share their fresh variables.
+    let (both, renaming) = fresh_variables.rename_all_apart(&[dag_node.clone(), x.clone()]);
+    assert_eq!(renaming.len(), 1);
+    assert!(is_equal(&both[1], &renaming[0].1));
+    assert!(is_equal(&both[0], &make_free_dag(&f, &[&both[1], &make_free_dag(&s, &[&both[1]])])));
+
+    // The renamed term is an instance of the original and vice versa.
+    let mut problem = UnificationProblem::new(renamed, dag_node);
+    assert!(problem.find_next_unifier());
+    assert_eq!(problem.variable_info().variable_count(), 2);
+  }
+
+  #[test]
+  fn acu_unification_test() {
+    let sorts = make_component(&[("Elt", &[1])]);
+    let elt = &sorts[1];
+    let zero = make_constant_symbol("0", elt);
+    let a = make_free_dag(&make_constant_symbol("a", elt), &[]);
+    let b = make_free_dag(&make_constant_symbol("b", elt), &[]);
+    let g = make_free_symbol("g", 1, elt);
+    let plus = make_acu_symbol_with_identity(elt, &make_free_term(&zero, vec![]));
+    let x = make_variable_dag("X", 0, elt);
+    let y = make_variable_dag("Y", 1, elt);
+    let z = make_variable_dag("Z", 2, elt);
+    let w = make_variable_dag("W", 3, elt);
+
+    // X + Y =? Z + W has a most general unifier, in which each variable is the sum of two of four fresh variables.
+    let lhs = make_acu_dag(&plus, &[&x, &y]);
+    let rhs = make_acu_dag(&plus, &[&z, &w]);
+    let mut problem = UnificationProblem::new(lhs.clone(), rhs.clone());
+    assert!(problem.find_next_unifier());
+    assert_eq!(problem.solution().value(0).unwrap().borrow().len(), 2);
+    assert_eq!(problem.instantiate(&lhs).borrow().len(), 4);
+    assert!(is_equal(&problem.instantiate(&lhs), &problem.instantiate(&rhs)));
+    assert!(!problem.find_next_unifier());
+
+    // X =? X + Y fails the occurs check, but is solved by binding Y to the identity.
+    let mut problem = UnificationProblem::new(x.clone(), make_acu_dag(&plus, &[&x, &y]));
+    assert!(problem.find_next_unifier());
+    assert!(is_equal(&problem.solution().value(1).unwrap(), &make_free_dag(&zero, &[])));
+
+    // Aliens are unified in their own theory: X + g(Y) =? a + g(b) has the single unifier X |-> a, Y |-> b.
+    let lhs = make_acu_dag(&plus, &[&x, &make_free_dag(&g, &[&y])]);
+    let rhs = make_acu_dag(&plus, &[&a, &make_free_dag(&g, &[&b])]);
+    let mut problem = UnificationProblem::new(lhs, rhs);
+    assert!(problem.find_next_unifier());
+    assert!(is_equal(&problem.solution().value(0).unwrap(), &a));
+    assert!(is_equal(&problem.solution().value(1).unwrap(), &b));
+    assert!(!problem.find_next_unifier());
+  }
+
+  #[test]
+  fn ac_unification_test() {
+    let sorts = make_component(&[("Elt", &[1])]);
+    let elt = &sorts[1];
+    let a = make_free_dag(&make_constant_symbol("a", elt), &[]);
+    let b = make_free_dag(&make_constant_symbol("b", elt), &[]);
+    let plus = make_acu_symbol(elt);
+    let x = make_variable_dag("X", 0, elt);
+    let y = make_variable_dag("Y", 1, elt);
+
+    // Without an identity, X + Y =? a + b has two unifiers, neither an instance of the other.
+    let lhs = make_acu_dag(&plus, &[&x, &y]);
+    let rhs = make_acu_dag(&plus, &[&a, &b]);
+    let mut problem = UnificationProblem::new(lhs.clone(), rhs.clone());
+    let mut x_values = Vec::new();
+    while problem.find_next_unifier() {
+      assert!(is_equal(&problem.instantiate(&lhs), &problem.instantiate(&rhs)));
+      x_values.push(problem.solution().value(0).unwrap());
+    }
+    assert_eq!(x_values.len(), 2);
+    assert!(x_values.iter().any(|value| is_equal(value, &a)));
+    assert!(x_values.iter().any(|value| is_equal(value, &b)));
+  }
+
+  #[test]
+  fn sort_intersection_test() {
+    // C and D are both below A and B, and neither is below the other.
+    let sorts = make_component(&[("A", &[1, 3, 4]), ("B", &[2, 3, 4]), ("C", &[3]), ("D", &[4])]);
+    let x = make_variable_dag("X", 0, &sorts[1]);
+    let y = make_variable_dag("Y", 1, &sorts[2]);
+
+    // X:A =? Y:B has a unifier for each maximal lower bound of A and B.
+    let mut problem = UnificationProblem::new(x.clone(), y.clone());
+    let mut fresh_sorts = Vec::new();
+    while problem.find_next_unifier() {
+      let value = problem.solution().value(0).unwrap();
+      assert!(is_equal(&value, &problem.solution().value(1).unwrap()));
+      fresh_sorts.push(variable_sort(&value));
+    }
+    assert_eq!(fresh_sorts.len(), 2);
+    assert!(RcCell::ptr_eq(&fresh_sorts[0], &sorts[3]));
+    assert!(RcCell::ptr_eq(&fresh_sorts[1], &sorts[4]));
+
+    // Nothing is in two kinds.
+    let other_sorts = make_component(&[("E", &[1])]);
+    let w = make_variable_dag("W", 2, &other_sorts[1]);
+    assert!(!UnificationProblem::new(x.clone(), w).find_next_unifier());
+    let e = make_free_dag(&make_constant_symbol("e", &other_sorts[1]), &[]);
+    assert!(!UnificationProblem::new(x, e).find_next_unifier());
+  }
+
+  #[test]
+  fn sort_refinement_test() {
+    let sorts = make_component(&[("Nat", &[1, 2]), ("NzNat", &[2])]);
+    let (nat, nz_nat) = (&sorts[1], &sorts[2]);
+    // f : Nat Nat -> Nat and f : NzNat NzNat -> NzNat
+    let f = make_free_symbol_with_declarations("f", &[&[nat, nat, nat], &[nz_nat, nz_nat, nz_nat]]);
+    let x = make_variable_dag("X", 0, nat);
+    let y = make_variable_dag("Y", 1, nat);
+    let z = make_variable_dag("Z", 2, nz_nat);
+
+    // Z:NzNat =? f(X, Y) only has a unifier once X and Y are specialized to NzNat.
+    let rhs = make_free_dag(&f, &[&x, &y]);
+    let mut problem = UnificationProblem::new(z.clone(), rhs.clone());
+    assert!(problem.find_next_unifier());
+    let x_value = problem.solution().value(1).unwrap();
+    let y_value = problem.solution().value(2).unwrap();
+    assert!(RcCell::ptr_eq(&variable_sort(&x_value), nz_nat));
+    assert!(RcCell::ptr_eq(&variable_sort(&y_value), nz_nat));
+    assert!(!is_equal(&x_value, &y_value));
+    assert!(is_equal(&problem.instantiate(&z), &make_free_dag(&f, &[&x_value, &y_value])));
+    assert!(is_equal(&problem.instantiate(&z), &problem.instantiate(&rhs)));
+    assert!(!problem.find_next_unifier());
+  }
+}
@@ -0,0 +1,182 @@
+/*!
+
+Order-sorted unification. A unifier must bind each variable to a term whose sort is at most the variable's sort, so a
+unifier of the unsorted terms may have to be specialized, and a single unsorted unifier may give several order-sorted
+ones or none.
+
+Sorts come in at two places. When two variables of incomparable sorts are unified, they are both bound to a fresh
+variable whose sort is a greatest lower bound of theirs. If the sorts of the kind don't form a lattice there may be
+several maximal lower bounds, each giving a different unifier, so the choice between them is a
+`SortIntersectionSubproblem`. Once an unsorted unifier has been found, `refine_sorts` specializes the variables in its
+range to subsorts until every variable is bound to a term of a small enough sort. Each maximal way of doing so gives an
+order-sorted unifier, and the unifiers found this way form a complete set.
+
+*/
+
+use crate::{
+  abstractions::RcCell,
+  core::sort::{index_leq_sort, RcSort, SpecialSort},
+  theory::RcDagNode,
+};
+
+use super::{
+  fresh_variable_source::{replace_args, variable_sort},
+  Equation,
+  FreshVariableSource,
+  UnificationSubproblem,
+};
+
+/// The equation `lhs =? rhs` between two variables whose sorts have several maximal lower bounds. Each alternative
+/// binds both variables to a fresh variable of one of the bounds.
+pub struct SortIntersectionSubproblem {
+  lhs:       RcDagNode,
+  rhs:       RcDagNode,
+  /// The maximal lower bounds of the sorts of `lhs` and `rhs`.
+  sorts:     Vec<RcSort>,
+  next_sort: usize,
+}
+
+impl SortIntersectionSubproblem {
+  pub fn new(lhs: RcDagNode, rhs: RcDagNode, sorts: Vec<RcSort>) -> Self {
+    SortIntersectionSubproblem {
+      lhs,
+      rhs,
+      sorts,
+      next_sort: 0,
+    }
+  }
+}
+
+impl UnificationSubproblem for SortIntersectionSubproblem {
+  fn next_alternative(&mut self, fresh_variables: &mut FreshVariableSource) -> Option<Vec<Equation>> {
+    let sort = self.sorts.get(self.next_sort)?.clone();
+    self.next_sort += 1;
+    let variable = fresh_variables.make_variable(&sort);
+    Some(vec![(self.lhs.clone(), variable.clone()), (self.rhs.clone(), variable)])
+  }
+}
+
+/// Specializes the unsorted unifier that binds each of `variables` to the corresponding term of `values`, returning
+/// the values of each of the maximal order-sorted unifiers it has. A variable in the range of the unifier may be
+/// replaced by a fresh variable of any of its subsorts, and a replacement is admissible if it gives every one of
+/// `variables` a value of at most its sort. The values of the admissible replacements that no other admissible
+/// replacement lies above, sort by sort, are returned. A variable that keeps its sort is not replaced.
+pub(crate) fn refine_sorts(
+  variables: &[RcDagNode],
+  values: &[RcDagNode],
+  fresh_variables: &mut FreshVariableSource,
+) -> Vec<Vec<RcDagNode>> {
+  let mut range_variables = Vec::new();
+  for value in values {
+    collect_variables(value, &mut range_variables);
+  }
+  let candidates: Vec<Vec<RcSort>> = range_variables
+    .iter()
+    .map(|variable| variable_sort(variable).borrow().lower_sorts())
+    .collect();
+  let variable_sorts: Vec<RcSort> = variables.iter().map(variable_sort).collect();
+
+  // Try every choice of sorts for the range variables, counting through them in mixed radix.
+  let mut admissible: Vec<Vec<usize>> = Vec::new();
+  let mut choice = vec![0; range_variables.len()];
+  loop {
+    let mut scratch_variables = FreshVariableSource::new();
+    let renaming: Vec<(RcDagNode, RcDagNode)> = range_variables
+      .iter()
+      .zip(choice.iter().zip(candidates.iter()))
+      .map(|(variable, (&sort, sorts))| (variable.clone(), scratch_variables.make_variable(&sorts[sort])))
+      .collect();
+    let is_admissible = values.iter().zip(variable_sorts.iter()).all(|(value, sort)| {
+      let sort_index = compute_base_sort(&substitute(value, &renaming));
+      index_leq_sort(sort_index, &sort.borrow())
+    });
+    if is_admissible {
+      admissible.push(choice.clone());
+    }
+
+    let next = choice.iter().zip(candidates.iter()).position(|(&sort, sorts)| sort + 1 < sorts.len());
+    match next {
+      Some(position) => {
+        choice[position] += 1;
+        for sort in choice[..position].iter_mut() {
+          *sort = 0;
+        }
+      }
+      None => break,
+    }
+  }
+
+  let is_below = |lower: &Vec<usize>, upper: &Vec<usize>| {
+    lower
+      .iter()
+      .zip(upper.iter())
+      .zip(candidates.iter())
+      .all(|((&lower, &upper), sorts)| sorts[lower].borrow().leq(&sorts[upper].borrow()))
+  };
+  let maximal = admissible
+    .iter()
+    .filter(|choice| !admissible.iter().any(|other| other != *choice && is_below(*choice, other)));
+
+  let mut refinements = Vec::new();
+  for choice in maximal {
+    let mut renaming = Vec::new();
+    for ((variable, &sort), sorts) in range_variables.iter().zip(choice.iter()).zip(candidates.iter()) {
+      if !RcCell::ptr_eq(&sorts[sort], &variable_sort(variable)) {
+        renaming.push((variable.clone(), fresh_variables.make_variable(&sorts[sort])));
+      }
+    }
+    refinements.push(values.iter().map(|value| substitute(value, &renaming)).collect());
+  }
+  refinements
+}
+
+/// Adds the variables of `dag_node` that are not in `variables` yet, in order of first appearance.
+fn collect_variables(dag_node: &RcDagNode, variables: &mut Vec<RcDagNode>) {
+  if dag_node.borrow().symbol().is_variable() {
+    if !variables
+      .iter()
+      .any(|variable| variable.borrow().compare(&**dag_node.borrow()).is_eq())
+    {
+      variables.push(dag_node.clone());
+    }
+    return;
+  }
+  for arg in dag_node.borrow().iter_args() {
+    collect_variables(&arg, variables);
+  }
+}
+
+/// Replaces the variables of `dag_node` according to `renaming`, copying only the parts of it that change.
+fn substitute(dag_node: &RcDagNode, renaming: &[(RcDagNode, RcDagNode)]) -> RcDagNode {
+  if dag_node.borrow().symbol().is_variable() {
+    return match renaming
+      .iter()
+      .find(|(variable, _)| variable.borrow().compare(&**dag_node.borrow()).is_eq())
+    {
+      Some((_, replacement)) => replacement.clone(),
+      None => dag_node.clone(),
+    };
+  }
+
+  let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+  let new_args: Vec<RcDagNode> = args.iter().map(|arg| substitute(arg, renaming)).collect();
+  if args.iter().zip(new_args.iter()).all(|(arg, new_arg)| RcCell::ptr_eq(arg, new_arg)) {
+    return dag_node.clone();
+  }
+  replace_args(dag_node, new_args)
+}
+
+/// Computes the sort of `dag_node` from the sorts of its variables and the sort diagrams of its symbols, for any part
+/// of it whose sort is not yet known.
+fn compute_base_sort(dag_node: &RcDagNode) -> i32 {
+  let sort_index = dag_node.borrow().get_sort_index();
+  if sort_index != SpecialSort::Unknown as i32 {
+    return sort_index;
+  }
+
+  let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+  for arg in args.iter() {
+    compute_base_sort(arg);
+  }
+  dag_node.borrow_mut().compute_base_sort()
+}
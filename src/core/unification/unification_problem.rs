@@ -0,0 +1,455 @@
+/*!
+
+A `UnificationProblem` is an equation `lhs =? rhs` between two DAGs whose solutions are unifiers: substitutions that
+make the two sides identical when applied to them. A problem may also be a system of equations to be solved
+simultaneously. As in Maude, the unifiers are found one at a time by `find_next_unifier`.
+
+In the free theory a unifiable problem has a single most general unifier, from which every other unifier is an instance.
+It is computed by the classic algorithm: an equation between two terms headed by the same symbol is decomposed into
+equations between their arguments, an equation between a variable and a term binds the variable, and anything else
+fails. Bindings are kept in triangular form, that is, the term a variable is bound to may contain bound variables, and
+are only fully applied once a unifier has been found.
+
+An equation modulo an ACU symbol may have several incomparable solutions. It is deferred to a `UnificationSubproblem`,
+and the bindings and the equations still to be solved are saved with it in a choice point. Each solution of the
+subproblem is a list of equations that replace the deferred one. When the remaining equations fail, or when the next
+unifier is asked for, the most recent choice point is resumed with its next solution, and it is discarded once it has no
+more. The unifiers found this way form a complete set, though not necessarily a minimal one.
+
+A variable may only be bound to a term that doesn't contain it, the occurs check, and that lies in the variable's kind.
+Unifiers are order-sorted (see the `order_sorted` module). When two variables are unified, the one of larger sort is
+bound to the other, and if their sorts are incomparable both are bound to a fresh variable of a greatest lower bound
+of their sorts. Each unsorted unifier found is then specialized into the order-sorted unifiers it has, which may be
+several or none. The sort of a term with variables is computed from the sorts of its variables and the sort diagrams
+of its symbols, so the symbols' op declarations must have been compiled. Membership axioms are not taken into account.
+Symbols in theories other than the free and ACU theories are only unified with terms equal to them. The DAGs of the
+problem must be in normal form.
+
+*/
+
+use crate::{
+  abstractions::RcCell,
+  core::{
+    substitution::Substitution,
+    NarrowingVariableInfo,
+  },
+  theory::{
+    acu_theory::{ACUSymbol, ACUUnificationSubproblem},
+    free_theory::FreeDagNode,
+    DagNode,
+    RcDagNode,
+    RcSymbol,
+  },
+};
+
+use super::{
+  fresh_variable_source::{replace_args, variable_sort},
+  order_sorted::{refine_sorts, SortIntersectionSubproblem},
+  Equation,
+  FreshVariableSource,
+  UnificationSubproblem,
+};
+
+/// A subproblem together with the state of the problem when it was deferred.
+struct ChoicePoint {
+  subproblem: Box<dyn UnificationSubproblem>,
+  bindings:   Substitution,
+  /// The equations that were still to be solved besides the subproblem.
+  equations:  Vec<Equation>,
+}
+
+pub struct UnificationProblem {
+  equations:         Vec<Equation>,
+  /// The variables of `equations`, numbered in order of first appearance, followed by the fresh variables introduced
+  /// by subproblems.
+  variable_info:     NarrowingVariableInfo,
+  /// The number of variables of `equations`.
+  variable_count:    usize,
+  fresh_variables:   FreshVariableSource,
+  /// The bindings being built, in triangular form, indexed by variable number.
+  bindings:          Substitution,
+  choice_points:     Vec<ChoicePoint>,
+  /// The most recent unifier found, with each variable bound to a term free of bound variables.
+  solution:          Substitution,
+  /// The order-sorted unifiers of the most recent unsorted unifier that have not been returned yet, as the values of
+  /// the variables of `equations`, the next one last.
+  pending_solutions: Vec<Vec<RcDagNode>>,
+  started:           bool,
+}
+
+impl UnificationProblem {
+  pub fn new(lhs: RcDagNode, rhs: RcDagNode) -> Self {
+    Self::with_equations(vec![(lhs, rhs)])
+  }
+
+  /// Makes the problem of solving all of `equations` at once.
+  pub fn with_equations(equations: Vec<Equation>) -> Self {
+    let mut variable_info = NarrowingVariableInfo::default();
+    let mut fresh_variables = FreshVariableSource::new();
+    for (lhs, rhs) in equations.iter() {
+      variable_info.index_variables(lhs);
+      variable_info.index_variables(rhs);
+      fresh_variables.avoid(lhs);
+      fresh_variables.avoid(rhs);
+    }
+    let variable_count = variable_info.variable_count();
+
+    UnificationProblem {
+      equations,
+      variable_info,
+      variable_count,
+      fresh_variables,
+      bindings: Substitution::with_capacity(variable_count),
+      choice_points: Vec::new(),
+      solution: Substitution::with_capacity(variable_count),
+      pending_solutions: Vec::new(),
+      started: false,
+    }
+  }
+
+  // region Accessors
+
+  #[inline(always)]
+  pub fn equations(&self) -> &[Equation] {
+    &self.equations
+  }
+
+  #[inline(always)]
+  pub(crate) fn variable_info(&self) -> &NarrowingVariableInfo {
+    &self.variable_info
+  }
+
+  /// The number of variables of the equations, which are the variables `solution` binds.
+  #[inline(always)]
+  pub fn variable_count(&self) -> usize {
+    self.variable_count
+  }
+
+  /// The most recent unifier found, indexed by the variable numbers of `variable_info`. A variable the unifier leaves
+  /// unconstrained is bound to itself.
+  #[inline(always)]
+  pub fn solution(&self) -> &Substitution {
+    &self.solution
+  }
+
+  // endregion
+
+  /// Makes sure the fresh variables in the unifiers found from now on differ from the variables of `dag_node`, which
+  /// is needed if the unifiers are to be applied to terms besides those of the equations.
+  #[inline(always)]
+  pub fn avoid(&mut self, dag_node: &RcDagNode) {
+    self.fresh_variables.avoid(dag_node);
+  }
+
+  /// Finds the next unifier, returning `false` if there are no more. The unifier can then be read from `solution`.
+  pub fn find_next_unifier(&mut self) -> bool {
+    loop {
+      if let Some(values) = self.pending_solutions.pop() {
+        self.solution.clear_first_n(self.variable_count);
+        for (index, value) in values.into_iter().enumerate() {
+          self.solution.bind(index as i32, Some(value));
+        }
+        return true;
+      }
+
+      let found = if self.started {
+        self.solve(None)
+      } else {
+        self.started = true;
+        self.bindings.clear_first_n(self.variable_count);
+        self.solve(Some(self.equations.clone()))
+      };
+      if !found {
+        return false;
+      }
+
+      // An unsorted unifier without order-sorted specializations is skipped.
+      let variables: Vec<RcDagNode> = (0..self.variable_count)
+        .map(|index| match self.variable_info.index_to_variable(index) {
+          Some(variable) => variable,
+          None => unreachable!("Unindexed variable in unification problem. This is a bug."),
+        })
+        .collect();
+      let values: Vec<RcDagNode> = variables.iter().map(|variable| self.resolve(variable)).collect();
+      let mut refinements = refine_sorts(&variables, &values, &mut self.fresh_variables);
+      refinements.reverse();
+      self.pending_solutions = refinements;
+    }
+  }
+
+  /// Applies the most recent unifier to `dag_node`, copying only the parts of it that change. Variables that don't
+  /// occur in the equations are left alone.
+  pub fn instantiate(&self, dag_node: &RcDagNode) -> RcDagNode {
+    if dag_node.borrow().symbol().is_variable() {
+      return match self.variable_info.variable_to_index_without_insert(dag_node.clone()) {
+        Some(index) if (index as usize) < self.variable_count => match self.solution.value(index as usize) {
+          Some(value) => value,
+          None => dag_node.clone(),
+        },
+        _ => dag_node.clone(),
+      };
+    }
+
+    let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+    let new_args: Vec<RcDagNode> = args.iter().map(|arg| self.instantiate(arg)).collect();
+    if args.iter().zip(new_args.iter()).all(|(arg, new_arg)| RcCell::ptr_eq(arg, new_arg)) {
+      return dag_node.clone();
+    }
+    replace_args(dag_node, new_args)
+  }
+
+  /// Applies the current bindings to `dag_node`, following them through the bound variables of their values.
+  fn resolve(&self, dag_node: &RcDagNode) -> RcDagNode {
+    if dag_node.borrow().symbol().is_variable() {
+      return match self.binding(dag_node) {
+        Some(value) => self.resolve(&value),
+        None => dag_node.clone(),
+      };
+    }
+
+    let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+    let new_args: Vec<RcDagNode> = args.iter().map(|arg| self.resolve(arg)).collect();
+    if args.iter().zip(new_args.iter()).all(|(arg, new_arg)| RcCell::ptr_eq(arg, new_arg)) {
+      return dag_node.clone();
+    }
+    replace_args(dag_node, new_args)
+  }
+
+  // region Unification
+
+  /// Solves `equations`, if given, and otherwise resumes the most recent choice point, backtracking until a unifier
+  /// is found or there are no choice points left.
+  fn solve(&mut self, mut equations: Option<Vec<Equation>>) -> bool {
+    loop {
+      if let Some(equations) = equations.take() {
+        if self.unify(equations) {
+          return true;
+        }
+      }
+      match self.next_alternative() {
+        Some(alternative) => equations = Some(alternative),
+        None => return false,
+      }
+    }
+  }
+
+  /// Restores the state of the most recent choice point and returns its equations with those of the next solution of
+  /// its subproblem. Choice points without more solutions are discarded.
+  fn next_alternative(&mut self) -> Option<Vec<Equation>> {
+    while let Some(choice_point) = self.choice_points.last_mut() {
+      if let Some(alternative) = choice_point.subproblem.next_alternative(&mut self.fresh_variables) {
+        self.bindings = choice_point.bindings.clone();
+        self.bindings.resize(self.variable_info.variable_count());
+        let mut equations = choice_point.equations.clone();
+        equations.extend(alternative);
+        return Some(equations);
+      }
+      self.choice_points.pop();
+    }
+    None
+  }
+
+  /// Solves `equations` under the current bindings, extending them to a unifier. Returns `false` if an equation fails
+  /// or is deferred to a new choice point; either way the search continues from the most recent choice point.
+  fn unify(&mut self, mut equations: Vec<Equation>) -> bool {
+    while let Some((lhs, rhs)) = equations.pop() {
+      let lhs = self.dereference(lhs);
+      let rhs = self.dereference(rhs);
+      if RcCell::ptr_eq(&lhs, &rhs) {
+        continue;
+      }
+
+      let lhs_is_variable = lhs.borrow().symbol().is_variable();
+      let rhs_is_variable = rhs.borrow().symbol().is_variable();
+      let unified = match (lhs_is_variable, rhs_is_variable) {
+        (true, true) => self.unify_variables(&lhs, &rhs, &mut equations),
+        (true, false) => self.bind_variable(&lhs, &rhs, &mut equations),
+        (false, true) => self.bind_variable(&rhs, &lhs, &mut equations),
+        (false, false) => self.unify_nonvariables(&lhs, &rhs, &mut equations),
+      };
+      if !unified {
+        return false;
+      }
+    }
+
+    true
+  }
+
+  /// Unifies two distinct unbound variables by binding the one of larger sort to the other. Variables of incomparable
+  /// sorts are both bound to a fresh variable of a maximal lower bound of their sorts, and if there are several, the
+  /// choice between them is deferred to a new choice point.
+  fn unify_variables(&mut self, lhs: &RcDagNode, rhs: &RcDagNode, equations: &mut Vec<Equation>) -> bool {
+    if lhs.borrow().compare(&**rhs.borrow()).is_eq() {
+      return true;
+    }
+    let lhs_sort = variable_sort(lhs);
+    let rhs_sort = variable_sort(rhs);
+
+    // Sorts are only compared by index, which says nothing across kinds.
+    if !RcCell::ptr_eq(&lhs_sort.borrow().sort_component, &rhs_sort.borrow().sort_component) {
+      return false;
+    }
+    if rhs_sort.borrow().leq(&lhs_sort.borrow()) {
+      self.bind(lhs, rhs.clone());
+      return true;
+    }
+    if lhs_sort.borrow().leq(&rhs_sort.borrow()) {
+      self.bind(rhs, lhs.clone());
+      return true;
+    }
+
+    let mut lower_bounds = lhs_sort.borrow().maximal_lower_bounds(&rhs_sort.borrow());
+    match lower_bounds.len() {
+      0 => false,
+      1 => {
+        let variable = self.fresh_variables.make_variable(&lower_bounds.pop().unwrap());
+        self.bind(lhs, variable.clone());
+        self.bind(rhs, variable);
+        true
+      }
+      _ => {
+        self.choice_points.push(ChoicePoint {
+          subproblem: Box::new(SortIntersectionSubproblem::new(lhs.clone(), rhs.clone(), lower_bounds)),
+          bindings:   self.bindings.clone(),
+          equations:  std::mem::take(equations),
+        });
+        false
+      }
+    }
+  }
+
+  /// Binds the unbound `variable` to the nonvariable `dag_node` if the occurs check passes and `dag_node` is in the
+  /// kind of `variable`. Whether its sort is small enough is only known once its variables are bound for good, so it
+  /// is checked when the unsorted unifier is specialized.
+  fn bind_variable(&mut self, variable: &RcDagNode, dag_node: &RcDagNode, equations: &mut Vec<Equation>) -> bool {
+    if self.occurs(variable, dag_node) {
+      // X =? f(X, Y) still has a solution if f has an identity that Y can be bound to.
+      return match collapse_symbol(dag_node) {
+        Some(symbol) => self.unify_modulo_acu(symbol, variable, dag_node, equations),
+        None => false,
+      };
+    }
+
+    let kind = dag_node.borrow().symbol().sort_table().range_component();
+    if !RcCell::ptr_eq(&kind, &variable_sort(variable).borrow().sort_component) {
+      return false;
+    }
+
+    self.bind(variable, dag_node.clone());
+    true
+  }
+
+  /// Unifies two nonvariables, modulo the axioms of their top symbols.
+  fn unify_nonvariables(&mut self, lhs: &RcDagNode, rhs: &RcDagNode, equations: &mut Vec<Equation>) -> bool {
+    match acu_symbol(lhs, rhs) {
+      Some(symbol) => self.unify_modulo_acu(symbol, lhs, rhs, equations),
+      None => decompose(lhs, rhs, equations),
+    }
+  }
+
+  /// Defers `lhs =? rhs` modulo the ACU symbol `symbol` to a new choice point, together with the other `equations`.
+  fn unify_modulo_acu(
+    &mut self,
+    symbol: RcSymbol,
+    lhs: &RcDagNode,
+    rhs: &RcDagNode,
+    equations: &mut Vec<Equation>,
+  ) -> bool {
+    // The arguments of either side may be bound to terms that flatten into it, or to the identity, so the subproblem
+    // is made from the instances of the two sides.
+    let lhs = self.resolve(lhs);
+    let rhs = self.resolve(rhs);
+    if lhs.borrow().compare(&**rhs.borrow()).is_eq() {
+      return true;
+    }
+
+    self.choice_points.push(ChoicePoint {
+      subproblem: Box::new(ACUUnificationSubproblem::new(symbol, &lhs, &rhs)),
+      bindings:   self.bindings.clone(),
+      equations:  std::mem::take(equations),
+    });
+    false
+  }
+
+  /// Whether `variable` occurs in `dag_node` under the current bindings.
+  fn occurs(&self, variable: &RcDagNode, dag_node: &RcDagNode) -> bool {
+    if dag_node.borrow().symbol().is_variable() {
+      return variable.borrow().compare(&**dag_node.borrow()).is_eq()
+        || self.binding(dag_node).map_or(false, |value| self.occurs(variable, &value));
+    }
+
+    let args: Vec<RcDagNode> = dag_node.borrow().iter_args().collect();
+    args.iter().any(|arg| self.occurs(variable, arg))
+  }
+
+  /// Follows the bindings of `dag_node` until reaching a nonvariable or an unbound variable.
+  fn dereference(&self, mut dag_node: RcDagNode) -> RcDagNode {
+    while dag_node.borrow().symbol().is_variable() {
+      match self.binding(&dag_node) {
+        Some(value) => dag_node = value,
+        None => break,
+      }
+    }
+    dag_node
+  }
+
+  /// The current binding of `variable`, if it is a variable of the problem and is bound.
+  fn binding(&self, variable: &RcDagNode) -> Option<RcDagNode> {
+    self
+      .variable_info
+      .variable_to_index_without_insert(variable.clone())
+      .and_then(|index| self.bindings.get(index))
+  }
+
+  /// Binds `variable`, numbering it first if it is a fresh variable the problem hasn't seen yet.
+  fn bind(&mut self, variable: &RcDagNode, value: RcDagNode) {
+    let index = self.variable_info.variable_to_index(variable.clone());
+    self.bindings.resize(self.variable_info.variable_count());
+    self.bindings.bind(index, Some(value));
+  }
+
+  // endregion
+}
+
+/// The ACU symbol modulo which `lhs =? rhs` is to be solved, if any. It is the top symbol of a side if the other side
+/// has the same top symbol or the symbol has an identity, so that the side could collapse to the other. Idempotent
+/// symbols are not supported.
+fn acu_symbol(lhs: &RcDagNode, rhs: &RcDagNode) -> Option<RcSymbol> {
+  for (side, other) in [(lhs, rhs), (rhs, lhs)] {
+    let symbol = side.borrow().symbol();
+    if let Some(acu_symbol) = symbol.as_any().downcast_ref::<ACUSymbol>() {
+      if !acu_symbol.is_idempotent() && (acu_symbol.has_identity() || *other.borrow().symbol() == *symbol) {
+        return Some(symbol.clone());
+      }
+    }
+  }
+  None
+}
+
+/// The top symbol of `dag_node` if it is an ACU symbol with an identity, so that `dag_node` could collapse to one of
+/// its arguments.
+fn collapse_symbol(dag_node: &RcDagNode) -> Option<RcSymbol> {
+  let symbol = dag_node.borrow().symbol();
+  match symbol.as_any().downcast_ref::<ACUSymbol>() {
+    Some(acu_symbol) if acu_symbol.has_identity() && !acu_symbol.is_idempotent() => Some(symbol.clone()),
+    _ => None,
+  }
+}
+
+/// Replaces an equation between two nonvariables by equations between their arguments, if they have the same top
+/// symbol. Returns `false` if the equation has no solution.
+fn decompose(lhs: &RcDagNode, rhs: &RcDagNode, equations: &mut Vec<Equation>) -> bool {
+  let symbol = lhs.borrow().symbol();
+  if *symbol != *rhs.borrow().symbol() {
+    return false;
+  }
+  if !lhs.borrow().as_any().is::<FreeDagNode>() {
+    // Syntactic decomposition is unsound modulo axioms, so only equal terms are unified.
+    return lhs.borrow().compare(&**rhs.borrow()).is_eq();
+  }
+
+  let lhs_args: Vec<RcDagNode> = lhs.borrow().iter_args().collect();
+  let rhs_args: Vec<RcDagNode> = rhs.borrow().iter_args().collect();
+  equations.extend(lhs_args.into_iter().zip(rhs_args));
+  true
+}
@@ -0,0 +1,24 @@
+/*!
+
+A `UnificationSubproblem` is the part of a unification problem that has more than one solution, such as an equation
+between two terms modulo associativity and commutativity. It plays the role for unification that a `Subproblem` plays
+for matching: the `UnificationProblem` defers the equation to the subproblem and asks it for solutions one at a time,
+backtracking into it whenever the rest of the problem fails or the next unifier is wanted.
+
+Each solution of a subproblem is given as a list of equations that are solved in its place, usually between the
+arguments of the original equation and terms over fresh variables. Because the equations are solved by the problem
+itself, a theory's subproblem only has to deal with its own symbol; the arguments may be in any theory.
+
+*/
+
+use super::FreshVariableSource;
+use crate::theory::RcDagNode;
+
+/// An equation `lhs =? rhs`.
+pub type Equation = (RcDagNode, RcDagNode);
+
+pub trait UnificationSubproblem {
+  /// Returns the equations of the next solution of the subproblem, or `None` once there are no more. The first call
+  /// returns the first solution. The equations may use fresh variables from `fresh_variables`.
+  fn next_alternative(&mut self, fresh_variables: &mut FreshVariableSource) -> Option<Vec<Equation>>;
+}
@@ -76,7 +76,7 @@ impl VariableInfo {
     let idx = self
       .variables
       .iter()
-      .position(|v| v.is_some() && v.unwrap().borrow().compare(&*variable.borrow()).is_eq());
+      .position(|v| v.is_some() && v.as_ref().unwrap().borrow().compare(&*variable.borrow()).is_eq());
     match idx {
       Some(i) => i as i32,
       None => {
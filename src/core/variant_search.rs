@@ -0,0 +1,377 @@
+/*!
+
+Variant generation by folding variant narrowing \[Escobar, Sasse and Meseguer 2012]. This is Maude's `get variants`
+command. A variant of a term `t` is a pair `(u, σ)` of a substitution `σ` and the normal form `u`, by the equations
+marked `variant`, of `σ(t)`. The variants of `t` describe the normal forms of all of its instances: each is an instance
+of one of the most general variants, so a finite set of most general variants is a finite description of the
+equational theory's effect on `t`. A theory in which every term has one has the finite variant property, which is what
+variant unification relies on.
+
+Variants are computed by narrowing with the variant equations. Starting from the variant `(t, id)`, each variant is
+narrowed at every position that is not a variable with every unconditional variant equation of the position's top
+symbol, and the result and the composed substitution are normalized to give a new variant. Narrowing is breadth
+first. A new variant that is an instance of a variant found earlier is dropped rather than narrowed, and a variant found
+earlier that is an instance of a new one is removed, so the variants kept are the most general ones found. This is
+folding: when the theory has the finite variant property for `t`, eventually every new variant is dropped and the
+search ends.
+
+A variant `(u, σ)` is an instance of `(u', σ')` if a single substitution takes `u'` to `u` and `σ'` to `σ`, variable by
+variable. This is checked by unification modulo the theories of the symbols: with the variables of `(u', σ')` renamed
+apart, the equations `u' =? u` and `σ'(x) =? σ(x)` have a unifier that only renames the variables of `(u, σ)` exactly
+when there is a matching substitution.
+
+Theories without the finite variant property have an infinite narrowing tree, so the search can be bounded in depth.
+Whether it ended before the bound, and so found all the most general variants, is reported by `is_complete`.
+
+*/
+
+use crate::{
+  abstractions::RcCell,
+  core::{
+    narrowing_sequence_search::{collect_positions, replace_at, step_substitution},
+    pre_equation::{PreEquationKind, RcPreEquation},
+    rewrite_context::{trace::VariantTraceInfo, RewritingContext},
+    substitution::Substitution,
+    unification::{variable_sort, Equation, FreshVariableSource, UnificationProblem},
+    NarrowingVariableInfo,
+  },
+  theory::{DagNode, RcDagNode},
+};
+
+/// A variant `(dag_node, substitution)` of the term of a search.
+#[derive(Clone)]
+pub struct Variant {
+  pub dag_node:     RcDagNode,
+  /// The values of the variables of the term, in the order of the search's `variable_info`.
+  pub substitution: Vec<RcDagNode>,
+}
+
+struct VariantNode {
+  variant:  Variant,
+  depth:    usize,
+  /// Whether the variant is an instance of a variant found later, so that it is not one of the most general variants.
+  subsumed: bool,
+}
+
+pub struct VariantSearch {
+  context:         RewritingContext,
+  /// The variables of the term.
+  variable_info:   NarrowingVariableInfo,
+  fresh_variables: FreshVariableSource,
+  /// Every variant found that was not dropped, in the order found.
+  nodes:           Vec<VariantNode>,
+  complete:        bool,
+}
+
+impl VariantSearch {
+  /// Computes the most general variants of the root of `context`, reduced by the equations. With a `max_depth`,
+  /// variants reached in that many narrowing steps are not narrowed further.
+  pub(crate) fn new(mut context: RewritingContext, max_depth: Option<usize>) -> Self {
+    context.reduce();
+    let root = match &context.root {
+      Some(root) => root.clone(),
+      None => unreachable!("A variant search needs a term. This is a bug."),
+    };
+
+    let mut variable_info = NarrowingVariableInfo::default();
+    variable_info.index_variables(&root);
+    let substitution = (0..variable_info.variable_count())
+      .map(|index| match variable_info.index_to_variable(index) {
+        Some(variable) => variable,
+        None => unreachable!("Unindexed variable in variant search. This is a bug."),
+      })
+      .collect();
+    let mut fresh_variables = FreshVariableSource::new();
+    fresh_variables.avoid(&root);
+
+    let mut search = VariantSearch {
+      context,
+      variable_info,
+      fresh_variables,
+      nodes: vec![VariantNode {
+        variant: Variant {
+          dag_node: root,
+          substitution,
+        },
+        depth: 0,
+        subsumed: false,
+      }],
+      complete: true,
+    };
+    search.fold(max_depth);
+    search
+  }
+
+  // region Accessors
+
+  /// The variables of the term, which the substitutions of the variants bind.
+  #[inline(always)]
+  pub(crate) fn variable_info(&self) -> &NarrowingVariableInfo {
+    &self.variable_info
+  }
+
+  /// The context holding the variant narrowing count.
+  #[inline(always)]
+  pub(crate) fn context(&self) -> &RewritingContext {
+    &self.context
+  }
+
+  /// Whether the search ended without reaching the depth bound, so that the variants found are all the most general
+  /// variants of the term, finitely many.
+  #[inline(always)]
+  pub fn is_complete(&self) -> bool {
+    self.complete
+  }
+
+  // endregion
+
+  /// The most general variants of the term, in the order they were found. The first is the term itself with the
+  /// identity substitution, unless it was found to be an instance of another variant.
+  pub fn get_variants(&self) -> Vec<Variant> {
+    self
+      .nodes
+      .iter()
+      .filter(|node| !node.subsumed)
+      .map(|node| node.variant.clone())
+      .collect()
+  }
+
+  /// Narrows the variants breadth first, folding each new variant into those found so far.
+  fn fold(&mut self, max_depth: Option<usize>) {
+    let mut next_node = 0;
+    while next_node < self.nodes.len() {
+      let node = next_node;
+      next_node += 1;
+      // A subsumed variant's successors are instances of those of the variant subsuming it.
+      if self.nodes[node].subsumed {
+        continue;
+      }
+      if max_depth.map_or(false, |max_depth| self.nodes[node].depth >= max_depth) {
+        self.complete = false;
+        continue;
+      }
+
+      for successor in self.narrow(node) {
+        let is_new = !self
+          .nodes
+          .iter()
+          .any(|other| !other.subsumed && self.is_instance(&successor, &other.variant));
+        if !is_new {
+          continue;
+        }
+        for index in 0..self.nodes.len() {
+          if !self.nodes[index].subsumed && self.is_instance(&self.nodes[index].variant, &successor) {
+            self.nodes[index].subsumed = true;
+          }
+        }
+        self.nodes.push(VariantNode {
+          variant: successor,
+          depth: self.nodes[node].depth + 1,
+          subsumed: false,
+        });
+      }
+    }
+  }
+
+  /// Returns the variants reached from the given variant in one variant narrowing step.
+  fn narrow(&mut self, node: usize) -> Vec<Variant> {
+    let variant = self.nodes[node].variant.clone();
+    let mut positions = Vec::new();
+    collect_positions(&variant.dag_node, &mut Vec::new(), &mut positions);
+
+    let mut successors = Vec::new();
+    for (position, subterm) in positions {
+      let symbol = subterm.borrow().symbol();
+      for equation in symbol.symbol_members().equations().iter() {
+        let sides = match &equation.borrow().kind {
+          PreEquationKind::Equation { rhs_term, .. }
+            if equation.borrow().is_variant() && !equation.borrow().has_condition() =>
+          {
+            [equation.borrow().lhs_term.borrow().make_dag(), rhs_term.borrow().make_dag()]
+          }
+          _ => continue,
+        };
+        let (renamed, renaming) = self.fresh_variables.rename_all_apart(&sides);
+        let (lhs, rhs) = (renamed[0].clone(), renamed[1].clone());
+
+        let mut problem = UnificationProblem::new(subterm.clone(), lhs);
+        problem.avoid(&variant.dag_node);
+        while problem.find_next_unifier() {
+          let replacement = problem.instantiate(&rhs);
+          let dag_node = problem.instantiate(&replace_at(&variant.dag_node, &position, rhs.clone()));
+          let substitution: Vec<RcDagNode> = variant
+            .substitution
+            .iter()
+            .map(|value| problem.instantiate(value))
+            .collect();
+
+          self.context.variant_narrowing_count += 1;
+          let successor = Variant { dag_node, substitution };
+          if self.context.trace_status() {
+            let rewrite = (subterm.clone(), replacement);
+            self.trace_step(equation, &renaming, &problem, &variant, &successor, rewrite);
+            if self.context.trace_abort() {
+              return successors;
+            }
+          }
+
+          self.context.reduce_dag_node(successor.dag_node.clone());
+          for value in successor.substitution.iter() {
+            self.context.reduce_dag_node(value.clone());
+          }
+          self.fresh_variables.avoid(&successor.dag_node);
+          successors.push(successor);
+        }
+      }
+    }
+    successors
+  }
+
+  /// Whether `variant` is an instance of `general`.
+  fn is_instance(&self, variant: &Variant, general: &Variant) -> bool {
+    // Rename `general` apart from `variant`, its term and substitution together.
+    let mut fresh_variables = FreshVariableSource::new();
+    fresh_variables.avoid(&variant.dag_node);
+    for value in variant.substitution.iter() {
+      fresh_variables.avoid(value);
+    }
+    let general_dags: Vec<RcDagNode> = std::iter::once(general.dag_node.clone())
+      .chain(general.substitution.iter().cloned())
+      .collect();
+    let (renamed, _) = fresh_variables.rename_all_apart(&general_dags);
+
+    let variant_dags = std::iter::once(variant.dag_node.clone()).chain(variant.substitution.iter().cloned());
+    let equations: Vec<Equation> = renamed.into_iter().zip(variant_dags).collect();
+    let mut variables = NarrowingVariableInfo::default();
+    for (_, dag_node) in equations.iter() {
+      variables.index_variables(dag_node);
+    }
+
+    // A unifier that only renames the variables of `variant` is a matching substitution up to that renaming.
+    let mut problem = UnificationProblem::with_equations(equations);
+    while problem.find_next_unifier() {
+      let mut images: Vec<RcDagNode> = Vec::with_capacity(variables.variable_count());
+      let is_renaming = variables.iter().all(|(_, variable)| {
+        let image = problem.instantiate(&variable);
+        let is_new_variable = image.borrow().symbol().is_variable()
+          && RcCell::ptr_eq(&variable_sort(&image), &variable_sort(&variable))
+          && !images.iter().any(|other| other.borrow().compare(&**image.borrow()).is_eq());
+        images.push(image);
+        is_new_variable
+      });
+      if is_renaming {
+        return true;
+      }
+    }
+    false
+  }
+
+  /// Traces a variant narrowing step from `variant` to `successor` with `equation`, whose variables were renamed by
+  /// `renaming`, that rewrote the redex `rewrite.0` to `rewrite.1`.
+  fn trace_step(
+    &mut self,
+    equation: &RcPreEquation,
+    renaming: &[(RcDagNode, RcDagNode)],
+    problem: &UnificationProblem,
+    variant: &Variant,
+    successor: &Variant,
+    (redex, replacement): (RcDagNode, RcDagNode),
+  ) {
+    let mut variant_variables = NarrowingVariableInfo::default();
+    variant_variables.index_variables(&variant.dag_node);
+    let substitution = match step_substitution(equation, renaming, problem, &variant_variables) {
+      Some(substitution) => substitution,
+      None => return,
+    };
+    let old_variant_substitution = make_substitution(&variant.substitution);
+    let new_variant_substitution = make_substitution(&successor.substitution);
+
+    self.context.trace_narrowing_step(
+      &equation.borrow(),
+      redex,
+      replacement,
+      &variant_variables,
+      &substitution,
+      successor.dag_node.clone(),
+      Some(VariantTraceInfo::new(
+        &old_variant_substitution,
+        &new_variant_substitution,
+        &self.variable_info,
+      )),
+    );
+  }
+}
+
+/// A substitution binding the variable numbered `i` to `values[i]`.
+fn make_substitution(values: &[RcDagNode]) -> Substitution {
+  let mut substitution = Substitution::with_capacity(values.len());
+  for (index, value) in values.iter().enumerate() {
+    substitution.bind(index as i32, Some(value.clone()));
+  }
+  substitution
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::*;
+  use crate::{
+    abstractions::IString,
+    core::{module::Module, sort::make_component},
+    rc_cell,
+    theory::{
+      test_fixtures::{add_equation, add_free_symbol, is_equal, make_free_dag, make_free_term, make_variable},
+      Term,
+    },
+  };
+
+  #[test]
+  fn get_variants_test() {
+    let sorts = make_component(&[("Elt", &[1])]);
+    let elt = &sorts[1];
+    let module = rc_cell!(Module::new(IString::from("M")));
+    let zero = add_free_symbol(&module, "0", 0, elt);
+    let a = add_free_symbol(&module, "a", 0, elt);
+    let g = add_free_symbol(&module, "g", 2, elt);
+    let h = add_free_symbol(&module, "h", 2, elt);
+
+    // The variant equation h(0, X) = X.
+    let x = make_variable("X", 0, elt);
+    add_equation(&module, make_free_term(&h, vec![make_free_term(&zero, vec![]), x.clone()]), x);
+    module.borrow().equations[0].borrow_mut().set_variant();
+    Module::close_theory(&module);
+
+    // g(h(Y, a), h(Y, a)) narrows at either argument with Y |-> 0, and both steps normalize to g(a, a).
+    let h_term = || make_free_term(&h, vec![make_variable("Y", 0, elt), make_free_term(&a, vec![])]);
+    let term = make_free_term(&g, vec![h_term(), h_term()]);
+    let make_search = |max_depth: Option<usize>| {
+      let mut context = RewritingContext::new(Some(term.borrow().make_dag()), Weak::new());
+      context.substitution = Substitution::with_capacity(2);
+      VariantSearch::new(context, max_depth)
+    };
+
+    let search = make_search(None);
+    assert!(search.is_complete());
+    assert_eq!(search.context().variant_narrowing_count, 2);
+    // The second step gives an instance of the variant found by the first, which is dropped.
+    assert_eq!(search.nodes.len(), 2);
+
+    let variants = search.get_variants();
+    assert_eq!(variants.len(), 2);
+    assert!(is_equal(&variants[0].dag_node, &term.borrow().make_dag()));
+    let a_dag = make_free_dag(&a, &[]);
+    assert!(is_equal(&variants[1].dag_node, &make_free_dag(&g, &[&a_dag, &a_dag])));
+    assert_eq!(variants[1].substitution.len(), 1);
+    assert!(is_equal(&variants[1].substitution[0], &make_free_dag(&zero, &[])));
+
+    // Without narrowing, only the term itself is found, and the search is not known to be complete.
+    let search = make_search(Some(0));
+    assert!(!search.is_complete());
+    assert_eq!(search.get_variants().len(), 1);
+  }
+}
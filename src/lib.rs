@@ -4,6 +4,7 @@
 #![allow(dead_code)]
 #![allow(non_snake_case)]
 pub mod abstractions;
+pub mod builtin;
 pub mod core;
 pub mod parser;
 pub mod theory;
@@ -2,15 +2,17 @@
 
 Provides the parser for the expression language. The expression language is defined by this grammar:
 
-    expr := symbol | variable | application ;
+    expr := symbol | variable | application | literal ;
     symbol : = [a-z][a-zA-Z]* ;
+    literal := integer | real | string ;
     variable := [A-Z][a-zA-Z]* ;
     application := '(' (expr_list | ε) ')' ;
 
     expr_list := expr ',' expr_list | expr ;
 
 The parser itself is implemented in the Pratt library. This module transforms the expression tree from the Pratt
-parser into a tree of `Term`s.
+parser into a tree of `Term`s. Integer literals become numbers of a `NumberSystem` (`s_^n(0)`, or `-_(s_^n(0))` for a
+negative integer), while real and string literals become `LiteralTerm`s of the `<Floats>` and `<Strings>` symbols.
 
 */
 
@@ -21,12 +23,21 @@ use simple_error::simple_error;
 use unicode_blocks;
 
 use crate::{
-  abstractions::{IString, RcCell},
+  abstractions::{BigInteger, IString, RcCell},
+  builtin::{
+    declare_builtin_op,
+    literal::{Literal, LiteralSymbol, LiteralTerm},
+    BuiltinSorts,
+    MinusSymbol,
+    NumberSystem,
+  },
   core::Strategy,
   rc_cell,
   theory::{
     free_theory::{FreeSymbol, FreeTerm},
+    s_theory::SSymbol,
     variable::{VariableSymbol, VariableTerm},
+    BasicSymbolTypes,
     RcSymbol,
     RcTerm,
   },
@@ -35,22 +46,62 @@ use crate::{
 static OPERATOR_TABLE_PATH: &str = "resources/operators.csv";
 
 
-pub(crate) struct Parser<'t>(ParserCore<'t>);
+pub(crate) struct Parser<'t> {
+  core:     ParserCore<'t>,
+  literals: LiteralSymbols,
+}
 
 impl<'t> Parser<'t> {
   pub fn new() -> Parser<'t> {
-    Parser(ParserCore::<'t>::with_operator_file(OPERATOR_TABLE_PATH))
+    Parser {
+      core:     ParserCore::<'t>::with_operator_file(OPERATOR_TABLE_PATH),
+      literals: LiteralSymbols::new(),
+    }
   }
 
   pub fn parse(&mut self, text: &str) -> Result<RcTerm, Box<dyn Error>> {
-    match self.0.parse(text) {
-      Ok(atom) => Ok(termify_atom(atom)),
+    match self.core.parse(text) {
+      Ok(atom) => termify_atom(atom, &self.literals),
       Err(()) => Err(Box::new(simple_error!("Parse failed."))),
     }
   }
 }
 
 
+/// The built-in symbols that literals are made of. Each parser makes its own, just as it makes a new symbol for each
+/// identifier; symbols are compared by name. The symbols are declared with the built-in sorts:
+///
+///     0 : -> Nat          s_ : Nat -> NzNat       -_ : Int -> Int
+///     <Floats> : -> Float                         <Strings> : -> String
+struct LiteralSymbols {
+  numbers:       NumberSystem,
+  float_symbol:  RcSymbol,
+  string_symbol: RcSymbol,
+  sorts:         BuiltinSorts,
+}
+
+impl LiteralSymbols {
+  fn new() -> Self {
+    let sorts = BuiltinSorts::new();
+
+    let mut succ_symbol = SSymbol::new(IString::from("s_"), Strategy::default(), false);
+    declare_builtin_op(&mut succ_symbol, vec![sorts.nat().clone(), sorts.nz_nat().clone()]);
+    let mut zero_symbol = FreeSymbol::new(IString::from("0"), 0, false, Strategy::default());
+    declare_builtin_op(&mut zero_symbol, vec![sorts.nat().clone()]);
+    let naturals = NumberSystem::new(Rc::new(succ_symbol), Rc::new(zero_symbol));
+    let mut minus_symbol = MinusSymbol::new(IString::from("-_"), naturals.clone());
+    declare_builtin_op(&mut minus_symbol, vec![sorts.int().clone(), sorts.int().clone()]);
+
+    LiteralSymbols {
+      numbers:       naturals.with_minus(Rc::new(minus_symbol)),
+      float_symbol:  Rc::new(LiteralSymbol::new(BasicSymbolTypes::Float, &sorts)),
+      string_symbol: Rc::new(LiteralSymbol::new(BasicSymbolTypes::String, &sorts)),
+      sorts,
+    }
+  }
+}
+
+
 fn is_greek_letter(s: char) -> bool {
   unicode_blocks::ANCIENT_GREEK_MUSICAL_NOTATION.contains(s)
     || unicode_blocks::ANCIENT_GREEK_NUMBERS.contains(s)
@@ -66,16 +117,32 @@ fn is_greek_letter(s: char) -> bool {
     .contains(&(s as i32))
 }
 
-fn termify_atom(atom: Atom) -> RcTerm {
-  // let term: RcTerm = // the following match
-  match atom {
-    Atom::String(_) | Atom::Integer(_) | Atom::Real(_) => {
-      // No literals implemented for this simple matching.
-      unimplemented!("Literals are not implemented.");
+fn termify_atom(atom: Atom, literals: &LiteralSymbols) -> Result<RcTerm, Box<dyn Error>> {
+  let term: RcTerm = match atom {
+    Atom::String(value) => {
+      rc_cell!(LiteralTerm::new(
+        literals.string_symbol.clone(),
+        Literal::String(IString::from(value.to_string()))
+      ))
+    }
+
+    Atom::Integer(value) => {
+      let text = value.to_string();
+      let value: BigInteger = text.parse().map_err(|_| simple_error!("Bad integer literal: {}", text))?;
+      match literals.numbers.make_integer_term(&value) {
+        Some(term) => term,
+        None => unreachable!("The parser's number system has no minus symbol. This is a bug."),
+      }
+    }
+
+    Atom::Real(value) => {
+      let text = value.to_string();
+      let value: f64 = text.parse().map_err(|_| simple_error!("Bad real literal: {}", text))?;
+      rc_cell!(LiteralTerm::new(literals.float_symbol.clone(), Literal::Float(value)))
     }
 
     Atom::Symbol(name) => {
-      let (is_variable, symbol) = name_to_symbol(name, 0);
+      let (is_variable, symbol) = name_to_symbol(name.clone(), 0);
 
       // Variable
       if is_variable {
@@ -92,7 +159,9 @@ fn termify_atom(atom: Atom) -> RcTerm {
       let head = child_iter.next().unwrap();
       // Destructure
       if let Atom::Symbol(name) = head {
-        let rest = child_iter.map(|a| termify_atom(a)).collect::<Vec<_>>();
+        let rest = child_iter
+          .map(|a| termify_atom(a, literals))
+          .collect::<Result<Vec<_>, _>>()?;
         let arity = rest.len() as u32;
 
         // ToDo: How do I represent a "function variable"?
@@ -102,7 +171,9 @@ fn termify_atom(atom: Atom) -> RcTerm {
         unreachable!("Could not destructure head as a symbol. This is a bug.");
       }
     }
-  }
+  };
+
+  Ok(term)
 }
 
 
@@ -126,6 +197,7 @@ fn name_to_symbol(name: IString, arity: u32) -> (bool, RcSymbol) {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::theory::{DagNode, RcDagNode, Symbol, Term};
 
   #[test]
   fn parse_symbol_expr_test() {
@@ -145,4 +217,55 @@ mod tests {
     let dag = term.borrow_mut().make_dag();
     println!("DAG: {}", dag.borrow());
   }
+
+  #[test]
+  fn parse_literal_expr_test() {
+    let mut parser = Parser::new();
+    let term = match parser.parse("f(3, 42, 2.5, \"abc\", 0)") {
+      Ok(term) => term,
+      Err(_err) => {
+        panic!("FAILED TO PARSE.");
+      }
+    };
+    let args: Vec<RcTerm> = term.borrow().iter_args().collect();
+    assert_eq!(args.len(), 5);
+
+    let numbers = &parser.literals.numbers;
+    for (arg, expected) in args.iter().zip([3, 42]) {
+      let dag = arg.borrow().make_dag();
+      assert_eq!(numbers.get_integer(&**dag.borrow()), Some(BigInteger::from(expected)));
+    }
+    assert_eq!(args[2].borrow().to_string(), "2.5");
+    assert_eq!(args[3].borrow().to_string(), "\"abc\"");
+    assert!(numbers.is_zero(&**args[4].borrow().make_dag().borrow()));
+
+    // Equal literals are equal terms, even when they come from different parses.
+    let again = parser.parse("f(3, 42, 2.5, \"abc\", 0)").unwrap();
+    assert!(term.borrow().compare(&*again.borrow()).is_eq());
+    assert_eq!(term.borrow().semantic_hash(), again.borrow().semantic_hash());
+  }
+
+  /// Computes the sorts of `dag` and of the DAG nodes below it, bottom up, and returns the name of the sort of `dag`.
+  fn compute_sort(dag: &RcDagNode) -> String {
+    for arg in dag.borrow().iter_args() {
+      compute_sort(&arg);
+    }
+    let sort_index = dag.borrow_mut().compute_base_sort();
+    let component = dag.borrow().symbol().sort_table().range_component();
+    let sort = component.borrow().sort(sort_index).upgrade().unwrap();
+    let name = sort.borrow().name.to_string();
+    name
+  }
+
+  #[test]
+  fn literal_sort_test() {
+    let mut parser = Parser::new();
+    let term = parser.parse("f(0, 3, -4, 2.5, \"abc\")").unwrap();
+    let sorts: Vec<String> = term
+      .borrow()
+      .iter_args()
+      .map(|arg| compute_sort(&arg.borrow().make_dag()))
+      .collect();
+    assert_eq!(sorts, ["Nat", "NzNat", "Int", "Float", "String"]);
+  }
 }
@@ -0,0 +1,231 @@
+/*!
+
+The LHS automaton for the A theory.
+
+In the greedy case, at most one subterm of the pattern is an unbound variable when the matcher is entered. Every other
+subterm is rigid, in the sense that it matches a block of known length: aliens match a single argument, and a bound
+variable matches exactly the arguments of its value. So we match the rigid subterms from the left end of the subject up
+to the unbound variable, then from the right end back to it, and bind the variable to whatever is left in the middle.
+
+Otherwise, and whenever we match at the top with extension, an `ASubproblem` does the work.
+
+*/
+
+use super::{match_bound_value, MatchStrategy, Subterm};
+use crate::{
+  core::substitution::Substitution,
+  theory::{
+    a_theory::{ADagNode, ASubproblem},
+    DagNode,
+    LHSAutomaton,
+    MaybeSubproblem,
+    RcDagNode,
+    RcExtensionInfo,
+    RcSymbol,
+    Subproblem,
+    SubproblemSequence,
+  },
+};
+
+
+pub struct ALHSAutomaton {
+  top_symbol:     RcSymbol,
+  match_at_top:   bool,
+  match_strategy: MatchStrategy,
+  subterms:       Vec<Subterm>,
+}
+
+impl ALHSAutomaton {
+  pub(crate) fn new(top_symbol: RcSymbol, match_at_top: bool, match_strategy: MatchStrategy, subterms: Vec<Subterm>) -> Self {
+    ALHSAutomaton {
+      top_symbol,
+      match_at_top,
+      match_strategy,
+      subterms,
+    }
+  }
+
+  /// Extracts the arguments of `subject`, or returns `None` if it does not have our top symbol.
+  fn subject_args(&self, subject: &RcDagNode) -> Option<Vec<RcDagNode>> {
+    let subject_ref = subject.borrow();
+    if subject_ref.symbol().as_ref() != self.top_symbol.as_ref() {
+      return None;
+    }
+    match subject_ref.as_any().downcast_ref::<ADagNode>() {
+      Some(a_dag_node) => Some(a_dag_node.args_vec()),
+      None => unreachable!("Could not downcast to ADagNode. This is a bug."),
+    }
+  }
+
+  /// Matches the rigid subterm at `level` against the block of `args` starting at `position`, or ending just before
+  /// it if `from_right` is set. Returns the length of the block.
+  fn match_rigid(
+    &self,
+    level: usize,
+    args: &[RcDagNode],
+    position: usize,
+    from_right: bool,
+    solution: &mut Substitution,
+    subproblems: &mut Vec<Box<dyn Subproblem>>,
+  ) -> Option<usize> {
+    match &self.subterms[level] {
+      Subterm::Variable { index, .. } => {
+        let value = solution.get(*index)?;
+        let value = value.borrow();
+        if from_right {
+          // The value covers its own arguments if it has our top symbol, and a single argument otherwise.
+          let length = match value.as_any().downcast_ref::<ADagNode>() {
+            Some(a_dag_node) if value.symbol().as_ref() == self.top_symbol.as_ref() => a_dag_node.len(),
+            _ => 1,
+          };
+          if length > position {
+            return None;
+          }
+          match_bound_value(&self.top_symbol, &**value, args, position - length)
+        } else {
+          match_bound_value(&self.top_symbol, &**value, args, position)
+        }
+      }
+
+      Subterm::GroundAlien(term) => {
+        let arg = if from_right { args.get(position.checked_sub(1)?)? } else { args.get(position)? };
+        term.borrow().compare_dag_node(&**arg.borrow()).is_eq().then_some(1)
+      }
+
+      Subterm::NonGroundAlien(automaton) => {
+        let arg = if from_right { args.get(position.checked_sub(1)?)? } else { args.get(position)? };
+        let (matched, subproblem) = automaton.borrow_mut().match_(arg.clone(), solution);
+        if !matched {
+          return None;
+        }
+        subproblems.extend(subproblem);
+        Some(1)
+      }
+    }
+  }
+
+  /// Tries the greedy strategy. Returns `None` if it does not apply, in which case `solution` is untouched.
+  fn match_greedy(&self, args: &[RcDagNode], solution: &mut Substitution) -> Option<(bool, MaybeSubproblem)> {
+    let unbound: Vec<usize> = self
+      .subterms
+      .iter()
+      .enumerate()
+      .filter(|(_, subterm)| matches!(subterm, Subterm::Variable { index, .. } if solution.get(*index).is_none()))
+      .map(|(level, _)| level)
+      .collect();
+    if unbound.len() > 1 {
+      return None;
+    }
+
+    // We work on a copy, so that we can give up on the greedy strategy if an alien leaves a subproblem that might
+    // bind the unbound variable behind our back.
+    let mut local = solution.clone();
+    let mut subproblems: Vec<Box<dyn Subproblem>> = Vec::new();
+    let n = self.subterms.len();
+    let failure = Some((false, None));
+
+    let middle = unbound.first().copied().unwrap_or(n);
+    let mut left = 0;
+    for level in 0..middle {
+      match self.match_rigid(level, args, left, false, &mut local, &mut subproblems) {
+        Some(length) => left += length,
+        None => return failure,
+      }
+    }
+
+    if middle == n {
+      if left != args.len() {
+        return failure;
+      }
+    } else {
+      let mut right = args.len();
+      for level in (middle + 1..n).rev() {
+        match self.match_rigid(level, args, right, true, &mut local, &mut subproblems) {
+          Some(length) if length <= right => right -= length,
+          _ => return failure,
+        }
+      }
+      if left >= right {
+        // The variable must take at least one argument.
+        return failure;
+      }
+      if !subproblems.is_empty() {
+        return None;
+      }
+
+      let (index, sort) = match &self.subterms[middle] {
+        Subterm::Variable { index, sort } => (*index, sort.clone()),
+        _ => unreachable!("Unbound subterm is not a variable. This is a bug."),
+      };
+      // An alien may have bound the variable in the meantime, which `match_variable` checks for us.
+      let value = ADagNode::make_from_slice(self.top_symbol.clone(), &args[left..right]);
+      let (matched, subproblem) = self.match_variable(value, index, sort, false, &mut local);
+      if !matched {
+        return failure;
+      }
+      subproblems.extend(subproblem);
+    }
+
+    *solution = local;
+    let subproblem: MaybeSubproblem = match subproblems.len() {
+      0 => None,
+      1 => subproblems.pop(),
+      _ => {
+        let mut sequence = SubproblemSequence::new();
+        for subproblem in subproblems {
+          sequence.add(subproblem);
+        }
+        Some(Box::new(sequence))
+      }
+    };
+    Some((true, subproblem))
+  }
+}
+
+
+impl LHSAutomaton for ALHSAutomaton {
+  fn match_(&mut self, subject: RcDagNode, solution: &mut Substitution) -> (bool, MaybeSubproblem) {
+    let args = match self.subject_args(&subject) {
+      Some(args) => args,
+      None => return (false, None),
+    };
+    if args.len() < self.subterms.len() {
+      return (false, None);
+    }
+
+    if self.match_strategy == MatchStrategy::Greedy {
+      if let Some(result) = self.match_greedy(&args, solution) {
+        return result;
+      }
+    }
+
+    let subproblem = ASubproblem::new(self.top_symbol.clone(), args, self.subterms.clone(), None);
+    (true, Some(Box::new(subproblem) as Box<dyn Subproblem>))
+  }
+
+  fn match_with_extension(
+    &mut self,
+    subject: RcDagNode,
+    solution: &mut Substitution,
+    extension_info: RcExtensionInfo,
+  ) -> (bool, MaybeSubproblem) {
+    if !self.match_at_top {
+      extension_info.borrow_mut().set_valid_after_match(true);
+      extension_info.borrow_mut().set_matched_whole(true);
+      return self.match_(subject, solution);
+    }
+
+    let args = match self.subject_args(&subject) {
+      Some(args) => args,
+      None => return (false, None),
+    };
+    if args.len() < self.subterms.len() {
+      return (false, None);
+    }
+
+    // Which part of the subject we matched is only known once the subproblem is solved.
+    extension_info.borrow_mut().set_valid_after_match(false);
+    let subproblem = ASubproblem::new(self.top_symbol.clone(), args, self.subterms.clone(), Some(extension_info));
+    (true, Some(Box::new(subproblem)))
+  }
+}
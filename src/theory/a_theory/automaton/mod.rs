@@ -0,0 +1,73 @@
+/*!
+
+A collection of structs used in the LHS and RHS automata of the A theory.
+
+*/
+mod lhs_automaton;
+mod rhs_automaton;
+
+use crate::{
+  core::sort::RcSort,
+  theory::{a_theory::ADagNode, DagNode, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm},
+};
+
+
+// Re-exports
+pub use lhs_automaton::ALHSAutomaton;
+pub use rhs_automaton::ARHSAutomaton;
+
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub(crate) enum MatchStrategy {
+  //	Greedy case: at most one top variable is unbound when the matcher is entered, so every other subterm is rigid
+  //	and is matched from the ends of the subject inwards. The unbound variable, if any, takes whatever is left. This
+  //	is decided again at match time, since variables may be bound by then that weren't known to be at compile time.
+  Greedy,
+
+  //	Full case: if the greedy case doesn't apply. An `ASubproblem` enumerates the ways of cutting the subject into
+  //	blocks, one for each subterm.
+  Full,
+}
+
+
+/// An argument of an A pattern, as seen by the matcher. Variables match a nonempty block of consecutive subject
+/// arguments; aliens match exactly one subject argument.
+#[derive(Clone)]
+pub(crate) enum Subterm {
+  Variable { index: i32, sort: RcSort },
+  GroundAlien(RcTerm),
+  NonGroundAlien(RcLHSAutomaton),
+}
+
+
+/// If `value` occurs in `args` starting at `position`, returns the number of arguments it covers. A value with our top
+/// symbol covers its own arguments; anything else covers a single argument.
+pub(crate) fn match_bound_value(
+  symbol: &RcSymbol,
+  value: &dyn DagNode,
+  args: &[RcDagNode],
+  position: usize,
+) -> Option<usize> {
+  if value.symbol().as_ref() == symbol.as_ref() {
+    let a_dag_node = match value.as_any().downcast_ref::<ADagNode>() {
+      Some(a_dag_node) => a_dag_node,
+      None => unreachable!("Could not downcast to ADagNode. This is a bug."),
+    };
+
+    let length = a_dag_node.len();
+    if position + length > args.len() {
+      return None;
+    }
+    let matches = a_dag_node
+      .iter_args()
+      .zip(args[position..position + length].iter())
+      .all(|(value_arg, subject_arg)| value_arg.borrow().compare(&**subject_arg.borrow()).is_eq());
+    matches.then_some(length)
+  } else {
+    match args.get(position) {
+      Some(subject_arg) if value.compare(&**subject_arg.borrow()).is_eq() => Some(1),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,86 @@
+/*!
+
+The RHS automaton for the A theory. Each instruction builds one A DAG node from arguments that have already been
+constructed (or bound) in the substitution. An argument may itself have our top symbol, so the new node is flattened.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{rc_cell, RcCell},
+  core::{substitution::Substitution, VariableInfo},
+  theory::{a_theory::ADagNode, dag_node::MaybeDagNode, make_rc_dag_node, DagNode, RHSAutomaton, RcDagNode, RcSymbol},
+};
+
+
+pub struct ARHSAutomaton {
+  top_symbol:  RcSymbol,
+  sources:     Vec<i32>,
+  destination: i32,
+}
+
+impl ARHSAutomaton {
+  pub fn new(top_symbol: RcSymbol) -> Self {
+    ARHSAutomaton {
+      top_symbol,
+      sources: vec![],
+      destination: -1,
+    }
+  }
+
+  pub fn add_argument(&mut self, source: i32) {
+    self.sources.push(source);
+  }
+
+  pub fn close(&mut self, destination: i32) {
+    self.destination = destination;
+  }
+
+  /// Builds the normalized node from the bindings in `matcher`.
+  fn build_arguments(&self, matcher: &mut Substitution) -> ADagNode {
+    let args: Vec<RcDagNode> = self
+      .sources
+      .iter()
+      .map(|&source| {
+        let value = matcher.value(source as usize);
+        assert!(value.is_some(), "unbound source {} in A RHS. This is a bug.", source);
+        value.unwrap()
+      })
+      .collect();
+
+    let mut new_dag_node = ADagNode::with_args(self.top_symbol.clone(), args);
+    new_dag_node.normalize_at_top();
+    new_dag_node
+  }
+}
+
+
+impl RHSAutomaton for ARHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    for source in &mut self.sources {
+      *source = variable_info.remap_index(*source);
+    }
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let new_dag_node: RcDagNode = make_rc_dag_node(self.build_arguments(matcher));
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let mut new_dag_node = self.build_arguments(matcher);
+    new_dag_node.overwrite_with_clone(old);
+  }
+}
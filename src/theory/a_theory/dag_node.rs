@@ -0,0 +1,335 @@
+/*!
+
+Concrete types for the A theory implementing the DagNode trait.
+
+An `ADagNode` stores its arguments as a flat vector in `DagNodeMembers::args`. Normal form for an A node is simply that
+no argument has the same top symbol as the node, that is, nested applications of an associative symbol are flattened
+into a single argument list. Since there is no identity, a normalized node always has at least two arguments and never
+collapses.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{AExtensionInfo, ASymbol, ATerm};
+use crate::{
+  abstractions::RcCell,
+  core::{hash_cons_set::HashConsSet, numeric_ordering, sort::SpecialSort, RedexPosition},
+  rc_cell,
+  theory::{
+    make_rc_dag_node,
+    DagNode,
+    DagNodeFlags,
+    DagNodeMembers,
+    ExtensionInfo,
+    NodeList,
+    RcDagNode,
+    RcExtensionInfo,
+    RcSymbol,
+    RcTerm,
+    Symbol,
+  },
+};
+
+
+pub type RcADagNode = RcCell<ADagNode>;
+
+pub struct ADagNode {
+  pub(crate) members: DagNodeMembers,
+}
+
+impl ADagNode {
+  pub fn new(symbol: RcSymbol) -> Self {
+    ADagNode {
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args:       Default::default(),
+        flags:      Default::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc:  None,
+        hash:       0,
+      },
+    }
+  }
+
+  /// Makes a node over the given arguments, which are assumed to be in normal form already.
+  pub fn with_args(symbol: RcSymbol, args: Vec<RcDagNode>) -> Self {
+    let mut node = ADagNode::new(symbol);
+    node.set_args(args);
+    node
+  }
+
+  #[inline(always)]
+  pub fn a_symbol(&self) -> &ASymbol {
+    match self.members.top_symbol.as_any().downcast_ref::<ASymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ASymbol. This is a bug."),
+    }
+  }
+
+  /// Copies the arguments out into a vector.
+  #[inline(always)]
+  pub fn args_vec(&self) -> Vec<RcDagNode> {
+    self.members.args.iter().cloned().collect()
+  }
+
+  fn set_args(&mut self, args: Vec<RcDagNode>) {
+    let mut new_args = NodeList::default();
+    new_args.extend(args);
+    self.members.args = new_args;
+  }
+
+  /// Puts the node into A normal form at the top by splicing the arguments of arguments with our top symbol into our
+  /// own argument list. Returns `true` if anything was flattened.
+  pub fn normalize_at_top(&mut self) -> bool {
+    let symbol = self.symbol();
+    let mut flattened: Vec<RcDagNode> = Vec::with_capacity(self.members.args.len());
+    let mut changed = false;
+
+    for arg in self.members.args.iter() {
+      let arg_ref = arg.borrow();
+      if arg_ref.symbol().as_ref() == symbol.as_ref() {
+        // Arguments with our symbol are already in normal form, so we can splice their arguments directly.
+        if let Some(a_arg) = arg_ref.as_any().downcast_ref::<ADagNode>() {
+          flattened.extend(a_arg.members.args.iter().cloned());
+          changed = true;
+          continue;
+        }
+      }
+      flattened.push(arg.clone());
+    }
+
+    if changed {
+      self.set_args(flattened);
+    }
+    changed
+  }
+
+  /// Makes an A node over `args`, or returns the sole argument if there is only one. The arguments must be a
+  /// contiguous run of arguments of a normalized node, so the result is normalized too.
+  pub(crate) fn make_from_slice(symbol: RcSymbol, args: &[RcDagNode]) -> RcDagNode {
+    assert!(!args.is_empty(), "cannot make an A node without arguments");
+    if args.len() == 1 {
+      return args[0].clone();
+    }
+    make_rc_dag_node(ADagNode::with_args(symbol, args.to_vec()))
+  }
+}
+
+
+impl DagNode for ADagNode {
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
+  }
+
+  fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<ADagNode>() {
+      Some(a_dag_node) => {
+        // Fail fast if lengths differ.
+        let r = self.len() as isize - a_dag_node.len() as isize;
+        if r != 0 {
+          return numeric_ordering(r);
+        }
+        // Compare corresponding terms.
+        for (this_child, other_child) in self.iter_args().zip(a_dag_node.iter_args()) {
+          let r = this_child.borrow().compare(&**other_child.borrow());
+          if r != Ordering::Equal {
+            return r;
+          }
+        }
+        // Identical
+        return Ordering::Equal;
+      }
+      None => unreachable!("Could not downcast a DagNode to an ADagNode. This is a bug."),
+    }
+  }
+
+  fn compute_base_sort(&mut self) -> i32 {
+    let symbol = self.symbol();
+    let sort_table = symbol.sort_table();
+    let mut sort_index = SpecialSort::Unknown as i32;
+
+    // The sort diagram is binary, so we fold it over the argument list from the left.
+    for arg in self.iter_args() {
+      let index = arg.borrow().get_sort_index();
+      assert_ne!(
+        index,
+        SpecialSort::Unknown as i32,
+        "unknown sort encountered for argument of {}",
+        symbol.name()
+      );
+
+      if sort_index == SpecialSort::Unknown as i32 {
+        sort_index = index;
+      } else if sort_index == SpecialSort::ErrorSort as i32 || index == SpecialSort::ErrorSort as i32 {
+        sort_index = SpecialSort::ErrorSort as i32;
+      } else {
+        sort_index = sort_table.traverse(sort_table.traverse(0, sort_index as usize) as usize, index as usize);
+      }
+    }
+
+    self.set_sort_index(sort_index);
+    sort_index
+  }
+
+  fn termify(&self) -> RcTerm {
+    let args: Vec<RcTerm> = self.iter_args().map(|dag_node| dag_node.borrow().termify()).collect();
+    rc_cell!(ATerm::new(self.symbol(), args))
+  }
+
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = ADagNode::with_args(self.symbol(), self.args_vec());
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacements(&self, redex_stack: &[RedexPosition], mut first_idx: usize, last_idx: usize) -> RcDagNode {
+    assert!(
+      first_idx <= last_idx && last_idx < redex_stack.len(),
+      "bad replacement range"
+    );
+    let mut args: Vec<RcDagNode> = Vec::with_capacity(self.len());
+    let mut next_replacement_index = redex_stack[first_idx].arg_index;
+
+    for (i, dag_node) in self.iter_args().enumerate() {
+      if i as i32 == next_replacement_index {
+        args.push(redex_stack[first_idx].dag_node.clone());
+        first_idx += 1;
+        next_replacement_index = if first_idx <= last_idx {
+          redex_stack[first_idx].arg_index
+        } else {
+          -1
+        };
+      } else {
+        args.push(dag_node);
+      }
+    }
+
+    // A replacement may have our top symbol.
+    let mut new_node = ADagNode::with_args(self.symbol(), args);
+    new_node.normalize_at_top();
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
+    assert!(arg_index < self.len(), "bad argIndex");
+    let mut args = self.args_vec();
+    args[arg_index] = replacement;
+
+    let mut new_node = ADagNode::with_args(self.symbol(), args);
+    new_node.normalize_at_top();
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    // Associative symbols have the standard strategy, so every argument is eager.
+    let args: Vec<RcDagNode> = self
+      .iter_args()
+      .map(|dag_node| {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = dag_node.borrow_mut().copy_eager_upto_reduced();
+        copy.unwrap_or(dag_node)
+      })
+      .collect();
+    make_rc_dag_node(ADagNode::with_args(self.symbol(), args))
+  }
+
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    let args: Vec<RcDagNode> = self
+      .iter_args()
+      .map(|dag_node| {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = dag_node.borrow_mut().copy_all();
+        copy.unwrap_or(dag_node)
+      })
+      .collect();
+    make_rc_dag_node(ADagNode::with_args(self.symbol(), args))
+  }
+
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = ADagNode::with_args(self.symbol(), self.args_vec());
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+
+    *old.borrow_mut() = Box::new(new_node);
+  }
+
+  fn make_extension_info(&self) -> Option<RcExtensionInfo> {
+    let extension_info: RcExtensionInfo = rc_cell!(AExtensionInfo::new());
+    Some(extension_info)
+  }
+
+  fn partial_replace(&mut self, replacement: RcDagNode, extension_info: &dyn ExtensionInfo) {
+    let extension_info = match extension_info.as_any().downcast_ref::<AExtensionInfo>() {
+      Some(extension_info) => extension_info,
+      None => unreachable!("Could not downcast to AExtensionInfo. This is a bug."),
+    };
+    let (first, last) = (extension_info.first(), extension_info.last());
+    assert!(first <= last && last < self.len(), "bad extension range");
+
+    let old_args = self.args_vec();
+    let mut args: Vec<RcDagNode> = Vec::with_capacity(old_args.len() - (last - first));
+    args.extend_from_slice(&old_args[..first]);
+    args.push(replacement);
+    args.extend_from_slice(&old_args[last + 1..]);
+    self.set_args(args);
+    self.normalize_at_top();
+
+    // We are a different term now, so nothing we knew about our reduction or sort is valid.
+    self.members.flags = Default::default();
+    self.set_sort_index(SpecialSort::Unknown as i32);
+  }
+
+  /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let args = self.args_vec();
+
+    for (i, dag_node) in args.iter().enumerate() {
+      let (canonical_dag_node, _) = hash_cons_set.insert(dag_node.clone());
+      if RcCell::ptr_eq(&canonical_dag_node, dag_node) {
+        // The child node was already canonical.
+        continue;
+      }
+
+      // Detected a non-canonical argument, need to make a new copy.
+      let mut new_args: Vec<RcDagNode> = Vec::with_capacity(args.len());
+      new_args.extend_from_slice(&args[0..i]);
+      new_args.push(canonical_dag_node);
+      for dag_node in &args[i + 1..] {
+        let (canonical, _) = hash_cons_set.insert(dag_node.clone());
+        new_args.push(canonical);
+      }
+
+      let mut new_node = ADagNode::with_args(self.symbol(), new_args);
+      new_node.members.flags.set_copied_flags(self.members.flags);
+      new_node.members.sort_index = self.members.sort_index;
+
+      return make_rc_dag_node(new_node);
+    }
+
+    rc_dag_node // Can use the original DAG node as the canonical version
+  }
+}
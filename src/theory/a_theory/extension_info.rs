@@ -0,0 +1,82 @@
+/*!
+
+Information about an extension of a match in the A theory. When a pattern with an associative top symbol is matched at
+the top with extension, it may match any contiguous run of the subject's arguments, leaving the arguments on either
+side as context. We record the run by the indices of its first and last arguments.
+
+  * `valid_after_match`: whether the fields are valid as soon as the match returns, or only after the returned
+    subproblem has been solved.
+  * `matched_whole`: whether the run covers every argument of the subject.
+  * `first`, `last`: the indices of the first and last subject arguments matched.
+
+*/
+
+use std::any::Any;
+
+use crate::theory::{ExtensionInfo, RcDagNode};
+
+
+#[derive(Default)]
+pub struct AExtensionInfo {
+  valid_after_match: bool,
+  matched_whole:     bool,
+  first:             usize,
+  last:              usize,
+}
+
+impl AExtensionInfo {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  #[inline(always)]
+  pub fn first(&self) -> usize {
+    self.first
+  }
+
+  #[inline(always)]
+  pub fn last(&self) -> usize {
+    self.last
+  }
+
+  /// Records that the subject arguments `first..=last` were matched, out of `subject_len` in all.
+  pub fn set_first_last(&mut self, first: usize, last: usize, subject_len: usize) {
+    self.first = first;
+    self.last = last;
+    self.matched_whole = first == 0 && last + 1 == subject_len;
+  }
+}
+
+impl ExtensionInfo for AExtensionInfo {
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn valid_after_match(&self) -> bool {
+    self.valid_after_match
+  }
+
+  #[inline(always)]
+  fn matched_whole(&self) -> bool {
+    self.matched_whole
+  }
+
+  fn set_valid_after_match(&mut self, value: bool) {
+    self.valid_after_match = value;
+  }
+
+  fn set_matched_whole(&mut self, value: bool) {
+    self.matched_whole = value;
+  }
+
+  fn set_unmatched(&mut self, _value: RcDagNode) {
+    unreachable!("The A theory records the matched range rather than an unmatched node. This is a bug.")
+  }
+}
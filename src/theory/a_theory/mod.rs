@@ -0,0 +1,155 @@
+/*!
+
+The A theory: binary symbols that are associative, but neither commutative nor have an identity element. Terms and DAG
+nodes with an A top symbol are flattened, so they are represented as a list of at least two arguments.
+
+Matching is done by the `ALHSAutomaton`, which handles patterns with at most one unbound variable at the top greedily
+and hands everything else to an `ASubproblem`. At the top of a rule, equation, or sort constraint, A patterns are
+matched with extension: `f(X, a)` may rewrite just the `b, a` part of `f(b, a, c)`, with the matched range recorded in
+an `AExtensionInfo`.
+
+*/
+
+mod automaton;
+mod dag_node;
+mod extension_info;
+mod subproblem;
+mod symbol;
+mod term;
+
+pub use automaton::{ALHSAutomaton, ARHSAutomaton};
+pub use dag_node::{ADagNode, RcADagNode};
+pub use extension_info::AExtensionInfo;
+pub use subproblem::ASubproblem;
+pub use symbol::{ASymbol, RcASymbol};
+pub use term::{ATerm, RcATerm};
+
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::*;
+  use crate::{
+    abstractions::{NatSet, RcCell},
+    core::{rewrite_context::RewritingContext, substitution::Substitution, VariableInfo},
+    rc_cell,
+    theory::{
+      free_theory::FreeTerm,
+      test_fixtures::{make_a_symbol, make_constant_symbol, make_sorts, make_variable, solutions},
+      DagNode,
+      ExtensionInfo,
+      LHSAutomaton,
+      RcDagNode,
+      RcExtensionInfo,
+      RcLHSAutomaton,
+      RcSymbol,
+      RcTerm,
+      Term,
+    },
+  };
+
+  fn constant_term(symbol: &RcSymbol) -> RcTerm {
+    rc_cell!(FreeTerm::new(symbol.clone()))
+  }
+
+  /// Makes the DAG for `f(args)`, giving every argument the sort `Elt`.
+  fn make_subject(f: &RcSymbol, args: &[&RcSymbol]) -> RcDagNode {
+    let args: Vec<RcTerm> = args.iter().map(|symbol| constant_term(symbol)).collect();
+    let mut term = ATerm::new(f.clone(), args);
+    term.normalize(true);
+
+    let subject = term.make_dag();
+    for arg in subject.borrow().iter_args() {
+      arg.borrow_mut().set_sort_index(1);
+    }
+    subject
+  }
+
+  fn compile(pattern: &mut ATerm, match_at_top: bool) -> RcLHSAutomaton {
+    pattern.normalize(true);
+    let (automaton, _subproblem_likely) = pattern.compile_lhs(match_at_top, &VariableInfo::default(), &mut NatSet::default());
+    automaton
+  }
+
+  #[test]
+  fn match_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_a_symbol(&elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+    let c = make_constant_symbol("c", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+
+    // f(X, a, Y) against f(b, a, c) has the single solution X=b, Y=c. Two unbound variables need a subproblem.
+    let mut pattern = ATerm::new(f.clone(), vec![x.clone(), constant_term(&a), y.clone()]);
+    let automaton = compile(&mut pattern, false);
+    let subject = make_subject(&f, &[&b, &a, &c]);
+    let mut solution = Substitution::with_capacity(2);
+    let (matched, subproblem) = automaton.borrow_mut().match_(subject.clone(), &mut solution);
+    assert!(matched);
+    assert!(subproblem.is_some());
+
+    let found = solutions(subject, solution, subproblem, 2);
+    assert_eq!(found.len(), 1);
+    assert!(found[0][0].borrow().symbol().as_ref() == b.as_ref());
+    assert!(found[0][1].borrow().symbol().as_ref() == c.as_ref());
+
+    // Order matters: f(X, a, Y) does not match f(a, b, c).
+    let subject = make_subject(&f, &[&a, &b, &c]);
+    let mut solution = Substitution::with_capacity(2);
+    let (matched, subproblem) = automaton.borrow_mut().match_(subject.clone(), &mut solution);
+    assert!(!matched || solutions(subject, solution, subproblem, 2).is_empty());
+
+    // f(X, a) against f(b, a) is matched greedily.
+    let mut pattern = ATerm::new(f.clone(), vec![x.clone(), constant_term(&a)]);
+    let automaton = compile(&mut pattern, false);
+    let subject = make_subject(&f, &[&b, &a]);
+    let mut solution = Substitution::with_capacity(1);
+    let (matched, subproblem) = automaton.borrow_mut().match_(subject, &mut solution);
+    assert!(matched);
+    assert!(subproblem.is_none());
+    assert!(solution.value(0).unwrap().borrow().symbol().as_ref() == b.as_ref());
+  }
+
+  #[test]
+  fn match_with_extension_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_a_symbol(&elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+    let c = make_constant_symbol("c", &elt);
+
+    let x = make_variable("X", 0, &elt);
+
+    // f(X, a) matches the `b, a` part of f(b, a, c).
+    let mut pattern = ATerm::new(f.clone(), vec![x, constant_term(&a)]);
+    let automaton = compile(&mut pattern, true);
+    let subject = make_subject(&f, &[&b, &a, &c]);
+    let extension_info: RcExtensionInfo = subject.borrow().make_extension_info().unwrap();
+
+    let mut solution = Substitution::with_capacity(1);
+    let (matched, subproblem) =
+      automaton
+        .borrow_mut()
+        .match_with_extension(subject.clone(), &mut solution, extension_info.clone());
+    assert!(matched);
+    assert!(!extension_info.borrow().valid_after_match());
+
+    let mut context = RewritingContext::new(Some(subject), Weak::new());
+    context.substitution = solution;
+    assert!(subproblem.unwrap().solve(true, &mut context));
+    assert!(context.substitution.value(0).unwrap().borrow().symbol().as_ref() == b.as_ref());
+
+    let extension_info = extension_info.borrow();
+    let extension_info = extension_info.as_any().downcast_ref::<AExtensionInfo>().unwrap();
+    assert!(extension_info.valid_after_match());
+    assert!(!extension_info.matched_whole());
+    assert_eq!((extension_info.first(), extension_info.last()), (0, 1));
+  }
+}
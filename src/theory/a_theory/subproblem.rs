@@ -0,0 +1,280 @@
+/*!
+
+The matching subproblem for the A theory. Matching `f(p_1, …, p_n)` against `f(s_1, …, s_m)` amounts to cutting the
+subject's argument list into `n` consecutive nonempty blocks, the `i`th of which is matched by `p_i`. An alien always
+takes a block of length one, and a variable can take a block of any length, so the search is over the lengths of the
+blocks taken by unbound variables.
+
+Blocks are chosen left to right by backtracking, and the search is resumed from where it left off whenever the next
+solution is requested. When matching with extension, the blocks need not cover the whole subject: the search also runs
+over the index of the first subject argument matched, and the last block may end before the end of the subject.
+
+ */
+
+use super::{
+  automaton::{match_bound_value, Subterm},
+  AExtensionInfo,
+  ADagNode,
+};
+use crate::{
+  core::{rewrite_context::RewritingContext, LocalBindings},
+  theory::{
+    DagNode,
+    ExtensionInfo,
+    MaybeSubproblem,
+    Outcome,
+    RcDagNode,
+    RcExtensionInfo,
+    RcSymbol,
+    Subproblem,
+    Term,
+  },
+};
+
+
+/// The match-time state of one subterm of the pattern.
+#[derive(Default)]
+struct Block {
+  /// The index of the first subject argument in the block.
+  start:      usize,
+  length:     usize,
+  /// Whether we bound the subterm's variable ourselves, as opposed to finding it already bound.
+  bound_here: bool,
+  /// The bindings made by matching a non-ground alien.
+  difference: Option<LocalBindings>,
+  subproblem: MaybeSubproblem,
+}
+
+
+pub struct ASubproblem {
+  top_symbol:     RcSymbol,
+  args:           Vec<RcDagNode>,
+  subterms:       Vec<Subterm>,
+  blocks:         Vec<Block>,
+  /// The index of the first subject argument matched. Always 0 without extension.
+  first:          usize,
+  extension_info: Option<RcExtensionInfo>,
+}
+
+impl ASubproblem {
+  pub(crate) fn new(
+    top_symbol: RcSymbol,
+    args: Vec<RcDagNode>,
+    subterms: Vec<Subterm>,
+    extension_info: Option<RcExtensionInfo>,
+  ) -> Self {
+    let blocks = (0..subterms.len()).map(|_| Block::default()).collect();
+    ASubproblem {
+      top_symbol,
+      args,
+      subterms,
+      blocks,
+      first: 0,
+      extension_info,
+    }
+  }
+
+  /// The index of the subject argument at which the block for `level` starts.
+  #[inline(always)]
+  fn start_of(&self, level: usize) -> usize {
+    if level == 0 {
+      self.first
+    } else {
+      let previous = &self.blocks[level - 1];
+      previous.start + previous.length
+    }
+  }
+
+  /// The range of lengths a variable's block at `level` may have, given that every later subterm needs at least one
+  /// argument. Without extension, the last block must reach the end of the subject.
+  fn length_bounds(&self, level: usize, start: usize) -> Option<(usize, usize)> {
+    let remaining = self.args.len() - start;
+    let rest = self.subterms.len() - level - 1;
+    if remaining < rest + 1 {
+      return None;
+    }
+
+    if self.extension_info.is_none() && rest == 0 {
+      Some((remaining, remaining))
+    } else {
+      Some((1, remaining - rest))
+    }
+  }
+
+  /// Finds the first (if `find_first`) or next way of matching the subterm at `level`.
+  fn solve_level(&mut self, level: usize, find_first: bool, context: &mut RewritingContext) -> bool {
+    let start = self.start_of(level);
+
+    match self.subterms[level].clone() {
+      Subterm::Variable { index, sort } => {
+        let mut from = 1;
+
+        if find_first {
+          self.blocks[level] = Block {
+            start,
+            ..Block::default()
+          };
+
+          if let Some(value) = context.substitution.get(index) {
+            // Bound by an earlier subterm or before we were called: the block is forced.
+            return match match_bound_value(&self.top_symbol, &**value.borrow(), &self.args, start) {
+              Some(length) => {
+                self.blocks[level].length = length;
+                true
+              }
+              None => false,
+            };
+          }
+        } else {
+          if !self.blocks[level].bound_here {
+            return false;
+          }
+          context.substitution.bind(index, None);
+          self.blocks[level].bound_here = false;
+          from = self.blocks[level].length + 1;
+        }
+
+        let (lower, upper) = match self.length_bounds(level, start) {
+          Some(bounds) => bounds,
+          None => return false,
+        };
+
+        for length in from.max(lower)..=upper {
+          let value = ADagNode::make_from_slice(self.top_symbol.clone(), &self.args[start..start + length]);
+          // `check_sort` does not produce sort constraint subproblems yet, so success is all we need.
+          let (outcome, _) = value.borrow_mut().check_sort(sort.clone());
+          if outcome == Outcome::Success {
+            context.substitution.bind(index, Some(value));
+            let block = &mut self.blocks[level];
+            block.length = length;
+            block.bound_here = true;
+            return true;
+          }
+        }
+        false
+      }
+
+      Subterm::GroundAlien(term) => {
+        if !find_first || start >= self.args.len() {
+          return false;
+        }
+        self.blocks[level] = Block {
+          start,
+          length: 1,
+          ..Block::default()
+        };
+        term.borrow().compare_dag_node(&**self.args[start].borrow()).is_eq()
+      }
+
+      Subterm::NonGroundAlien(automaton) => {
+        if !find_first {
+          let block = &mut self.blocks[level];
+          if let Some(subproblem) = block.subproblem.as_mut() {
+            if subproblem.solve(false, context) {
+              return true;
+            }
+          }
+          if let Some(difference) = block.difference.as_mut() {
+            difference.retract(&mut context.substitution);
+          }
+          *block = Block::default();
+          return false;
+        }
+
+        if start >= self.args.len() {
+          return false;
+        }
+        let mut local = context.substitution.clone();
+        let (matched, mut subproblem) = automaton
+          .borrow_mut()
+          .match_(self.args[start].clone(), &mut local);
+        if !matched {
+          return false;
+        }
+
+        let mut difference = local.subtract(&context.substitution);
+        if let Some(difference) = difference.as_mut() {
+          if !difference.assert(&mut context.substitution) {
+            return false;
+          }
+        }
+        if let Some(subproblem) = subproblem.as_mut() {
+          if !subproblem.solve(true, context) {
+            if let Some(difference) = difference.as_mut() {
+              difference.retract(&mut context.substitution);
+            }
+            return false;
+          }
+        }
+
+        self.blocks[level] = Block {
+          start,
+          length: 1,
+          bound_here: false,
+          difference,
+          subproblem,
+        };
+        true
+      }
+    }
+  }
+
+  /// Records the matched range in the extension information, if we are matching with extension.
+  fn record_extension(&self) {
+    if let Some(extension_info) = &self.extension_info {
+      let last = self.start_of(self.subterms.len()) - 1;
+      let mut extension_info = extension_info.borrow_mut();
+      match extension_info.as_any_mut().downcast_mut::<AExtensionInfo>() {
+        Some(extension_info) => {
+          extension_info.set_first_last(self.first, last, self.args.len());
+          extension_info.set_valid_after_match(true);
+        }
+        None => unreachable!("Could not downcast to AExtensionInfo. This is a bug."),
+      }
+    }
+  }
+}
+
+impl Subproblem for ASubproblem {
+  fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    let subterm_count = self.subterms.len();
+    let mut level: usize;
+    let mut forward: bool;
+
+    if find_first {
+      self.first = 0;
+      level = 0;
+      forward = true;
+    } else {
+      level = subterm_count - 1;
+      forward = false;
+    }
+
+    loop {
+      if level == subterm_count {
+        // Without extension, the blocks must cover the whole subject.
+        if self.extension_info.is_some() || self.start_of(level) == self.args.len() {
+          self.record_extension();
+          return true;
+        }
+        level -= 1;
+        forward = false;
+        continue;
+      }
+
+      if self.solve_level(level, forward, context) {
+        level += 1;
+        forward = true;
+      } else if level > 0 {
+        level -= 1;
+        forward = false;
+      } else if self.extension_info.is_some() && self.first + subterm_count < self.args.len() {
+        // Try the next starting position.
+        self.first += 1;
+        forward = true;
+      } else {
+        return false;
+      }
+    }
+  }
+}
@@ -0,0 +1,90 @@
+/*!
+
+A symbol belonging to the A theory, that is, a binary symbol that is associative but not commutative and has no
+identity. Lists and strings are modeled with such a symbol as concatenation.
+
+ */
+
+use std::{any::Any, rc::Rc};
+
+use super::{ADagNode, ATerm};
+use crate::{
+  abstractions::IString,
+  core::{rewrite_context::RewritingContext, Strategy},
+  theory::{AssociativeSymbolStructure, BinarySymbol, RcDagNode, RcTerm, Symbol, SymbolMembers},
+};
+
+
+pub type RcASymbol = Rc<ASymbol>;
+
+
+pub struct ASymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  // `AssociativeSymbol` members
+  pub(crate) sort_structure: AssociativeSymbolStructure,
+}
+
+impl ASymbol {
+  pub fn new(name: IString, strategy: Strategy, memo_flag: bool) -> ASymbol {
+    let mut symbol_members = SymbolMembers::new(name, 2, memo_flag);
+    symbol_members.strategy = strategy;
+
+    ASymbol {
+      symbol_members,
+      sort_structure: AssociativeSymbolStructure::Unstructured,
+    }
+  }
+
+  pub fn make_term_with_args(self, args: Vec<RcTerm>) -> ATerm {
+    ATerm::new(Rc::new(self), args)
+  }
+}
+
+impl Symbol for ASymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    // Associative symbols only support the standard strategy: every argument is evaluated before we try equations.
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
+    }
+
+    // Arguments may have rewritten into our theory, in which case they need to be flattened.
+    match subject.borrow_mut().as_any_mut().downcast_mut::<ADagNode>() {
+      Some(a_dag_node) => a_dag_node.normalize_at_top(),
+      None => unreachable!("Could not downcast to ADagNode. This is a bug."),
+    };
+
+    self.symbol_members.eq_rewrite(subject, context)
+  }
+}
+
+impl BinarySymbol for ASymbol {
+  #[inline(always)]
+  fn get_identity(&self) -> Option<RcTerm> {
+    None
+  }
+
+  #[inline(always)]
+  fn get_identity_dag(&self) -> Option<RcDagNode> {
+    None
+  }
+}
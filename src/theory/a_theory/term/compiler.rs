@@ -0,0 +1,170 @@
+/*!
+
+The implementation of the compiler-related methods of the `Term` trait for `ATerm`.
+
+ */
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::ATerm;
+use crate::{
+  abstractions::{rc_cell, NatSet, RcCell},
+  core::{automata::RHSBuilder, BindingLHSAutomaton, TermBag, VariableInfo},
+  theory::{
+    a_theory::{
+      automaton::{MatchStrategy, Subterm},
+      ALHSAutomaton,
+      ARHSAutomaton,
+    },
+    find_available_terms,
+    term_compiler::compile_rhs,
+    variable::VariableTerm,
+    RcLHSAutomaton,
+    Term,
+  },
+  NONE,
+};
+
+impl ATerm {
+  pub fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    let mut subterms: Vec<Subterm> = Vec::with_capacity(self.args.len());
+    let mut unbound_variable_count = 0;
+
+    // Which arguments a subterm is matched against depends on how long the variables' blocks are, so nothing a
+    // subterm binds is bound uniquely. We compile them against a local copy of `bound_uniquely`.
+    let mut local_bound_uniquely = bound_uniquely.clone();
+
+    for arg in &self.args {
+      let term = arg.borrow();
+
+      if let Some(variable_term) = term.as_any().downcast_ref::<VariableTerm>() {
+        if !bound_uniquely.contains(variable_term.index as usize) {
+          unbound_variable_count += 1;
+        }
+        subterms.push(Subterm::Variable {
+          index: variable_term.index,
+          sort:  variable_term.sort(),
+        });
+      } else if term.ground() {
+        subterms.push(Subterm::GroundAlien(arg.clone()));
+      } else {
+        let (lhs_automaton, _subproblem_likely) = term.compile_lhs(false, variable_info, &mut local_bound_uniquely);
+        subterms.push(Subterm::NonGroundAlien(lhs_automaton));
+      }
+    }
+
+    let match_strategy = if unbound_variable_count <= 1 {
+      MatchStrategy::Greedy
+    } else {
+      MatchStrategy::Full
+    };
+
+    if match_strategy == MatchStrategy::Greedy && !match_at_top {
+      // The unbound variable, if any, is forced to take whatever is left of the subject.
+      for subterm in &subterms {
+        if let Subterm::Variable { index, .. } = subterm {
+          bound_uniquely.insert(*index as usize);
+        }
+      }
+    }
+    // Matching at the top always goes through a subproblem, since we match with extension.
+    let subproblem_likely = match_at_top || match_strategy == MatchStrategy::Full;
+
+    let mut automaton: RcLHSAutomaton = rc_cell!(ALHSAutomaton::new(self.symbol(), match_at_top, match_strategy, subterms));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    (automaton, subproblem_likely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  pub fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    // A symbols have a single strategy for both arguments.
+    let arg_eager = eager_context && self.symbol().strategy().eager_argument(0);
+
+    // We build the largest arguments first, as in the free theory.
+    let mut order: Vec<(i32, usize)> = self
+      .args
+      .iter()
+      .enumerate()
+      .map(|(i, arg)| (-arg.borrow_mut().compute_size(), i))
+      .collect();
+    order.sort_unstable();
+
+    let mut sources: Vec<i32> = vec![0; self.args.len()];
+    for (_, idx) in order {
+      sources[idx] = compile_rhs(self.args[idx].clone(), rhs_builder, variable_info, available_terms, arg_eager);
+    }
+
+    // Need to flag last use of each source.
+    for source in &sources {
+      variable_info.use_index(*source);
+    }
+
+    let mut automaton = ARHSAutomaton::new(self.symbol());
+    for source in sources {
+      automaton.add_argument(source);
+    }
+    let index = variable_info.make_construction_index();
+    automaton.close(index);
+
+    rhs_builder.add_rhs_automaton(Box::new(automaton));
+    index
+  }
+
+  pub fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    // Without variables at the top, every argument is matched against the subject argument in the same position, so
+    // whatever the arguments bind uniquely, we bind uniquely. If everything except a single variable is ground, that
+    // variable must take whatever is left of the subject.
+    let has_top_variable = self
+      .args
+      .iter()
+      .any(|arg| arg.borrow().as_any().downcast_ref::<VariableTerm>().is_some());
+
+    if !has_top_variable {
+      for arg in &self.args {
+        arg.borrow_mut().analyse_constraint_propagation(bound_uniquely);
+      }
+      return;
+    }
+
+    let mut non_ground = self.args.iter().filter(|arg| !arg.borrow().ground());
+    if let (Some(lone), None) = (non_ground.next(), non_ground.next()) {
+      if let Some(variable_term) = lone.borrow().as_any().downcast_ref::<VariableTerm>() {
+        bound_uniquely.insert(variable_term.index as usize);
+      }
+    }
+  }
+
+  /// The theory-specific part of find_available_terms
+  pub fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    if self.ground() {
+      return;
+    }
+
+    let symbol = self.symbol();
+    let arg_eager = if at_top {
+      eager_context && symbol.strategy().eager_argument(0)
+    } else {
+      eager_context && symbol.strategy().evaluated_argument(0)
+    };
+
+    for arg in &self.args {
+      find_available_terms(arg.clone(), available_terms, arg_eager, false);
+    }
+  }
+}
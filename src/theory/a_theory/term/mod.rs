@@ -0,0 +1,261 @@
+/*!
+
+`Term` implementation for the A theory.
+
+An `ATerm` holds its arguments as a flat list. Normalization splices the arguments of subterms with the same top symbol
+into the list, so `f(a, f(b, c))` and `f(f(a, b), c)` both become `f(a, b, c)`. The compiler for the matcher is in
+`compiler.rs`.
+
+*/
+
+mod compiler;
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{ADagNode, ASymbol};
+use crate::{
+  abstractions::{hash2 as term_hash, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    numeric_ordering,
+    TermBag,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, NodeCache, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm, Term, TermMembers},
+};
+
+pub type RcATerm = RcCell<ATerm>;
+
+pub struct ATerm {
+  pub(crate) term_members: TermMembers,
+  pub(crate) args:         Vec<RcTerm>,
+}
+
+// Constructors
+impl ATerm {
+  pub fn new(symbol: RcSymbol, args: Vec<RcTerm>) -> ATerm {
+    assert!(args.len() >= 2, "an A term needs at least two arguments");
+    ATerm {
+      term_members: TermMembers::new(symbol),
+      args,
+    }
+  }
+
+  #[inline(always)]
+  pub fn a_symbol(&self) -> &ASymbol {
+    match self.term_members.top_symbol.as_any().downcast_ref::<ASymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ASymbol. This is a bug."),
+    }
+  }
+
+  /// Splices the arguments of subterms with our top symbol into our own argument list. Returns `true` if anything was
+  /// flattened.
+  fn flatten(&mut self) -> bool {
+    let symbol = self.symbol();
+    let mut flattened: Vec<RcTerm> = Vec::with_capacity(self.args.len());
+    let mut changed = false;
+
+    for arg in self.args.drain(..) {
+      let term = arg.borrow();
+      if term.symbol().as_ref() == symbol.as_ref() {
+        if let Some(a_term) = term.as_any().downcast_ref::<ATerm>() {
+          flattened.extend(a_term.args.iter().cloned());
+          changed = true;
+          continue;
+        }
+      }
+      drop(term);
+      flattened.push(arg);
+    }
+
+    self.args = flattened;
+    changed
+  }
+}
+
+
+impl Term for ATerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  /// In sync with `normalize`.
+  fn semantic_hash(&self) -> u32 {
+    let mut hash_value: u32 = self.symbol().semantic_hash();
+
+    for arg in &self.args {
+      hash_value = term_hash(hash_value, arg.borrow().semantic_hash());
+    }
+
+    hash_value
+  }
+
+  /// In sync with `semantic_hash`.
+  fn normalize(&mut self, full: bool) -> (u32, bool) {
+    let mut changed = false;
+
+    for arg in &self.args {
+      let (_, child_changed) = arg.borrow_mut().normalize(full);
+      changed = changed || child_changed;
+    }
+
+    changed |= self.flatten();
+
+    (self.semantic_hash(), changed)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(self.args.iter().cloned())
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    assert_eq!(&self.symbol(), &other.symbol(), "symbols differ");
+
+    if let Some(other) = other.as_any().downcast_ref::<ATerm>() {
+      let r = self.args.len() as isize - other.args.len() as isize;
+      if r != 0 {
+        return numeric_ordering(r);
+      }
+
+      for (arg_self, arg_other) in self.args.iter().zip(other.args.iter()) {
+        let r = arg_self.borrow().compare(&*arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast Term to ATerm. This is a bug.")
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    if let Some(other) = other.as_any().downcast_ref::<ADagNode>() {
+      let r = self.args.len() as isize - other.len() as isize;
+      if r != 0 {
+        return numeric_ordering(r);
+      }
+
+      for (arg_self, arg_other) in self.args.iter().zip(other.iter_args()) {
+        let r = arg_self.borrow().compare_dag_node(&**arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast DagNode to ADagNode. This is a bug.")
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, sub_dags: &mut NodeCache, set_sort_info: bool) -> RcDagNode {
+    let args: Vec<RcDagNode> = self
+      .args
+      .iter()
+      .map(|arg| arg.borrow().dagify(sub_dags, set_sort_info))
+      .collect();
+
+    // Subterms are shared through `sub_dags`, and a shared subterm may have been rewritten into our theory, so we
+    // renormalize the node.
+    let mut node = ADagNode::with_args(self.symbol(), args);
+    node.normalize_at_top();
+    make_rc_dag_node(node)
+  }
+
+  // region Compiler-related
+  #[inline(always)]
+  fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    ATerm::compile_lhs(self, match_at_top, variable_info, bound_uniquely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  #[inline(always)]
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    ATerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
+  }
+
+  #[inline(always)]
+  fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    ATerm::analyse_constraint_propagation(self, bound_uniquely)
+  }
+
+  #[inline(always)]
+  fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    ATerm::find_available_terms_aux(self, available_terms, eager_context, at_top);
+  }
+  // endregion
+}
+
+
+impl Formattable for ATerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut accumulator = String::new();
+    match style {
+      FormatStyle::Debug => {
+        accumulator.push_str(format!("a<{}>", self.term_members.top_symbol.repr(style)).as_str());
+      }
+
+      _ => {
+        accumulator.push_str(self.term_members.top_symbol.repr(style).as_str());
+      }
+    }
+
+    accumulator.push('(');
+    accumulator.push_str(
+      self
+        .args
+        .iter()
+        .map(|arg| arg.borrow().repr(style))
+        .collect::<Vec<String>>()
+        .join(", ")
+        .as_str(),
+    );
+    accumulator.push(')');
+
+    accumulator
+  }
+}
@@ -3,22 +3,21 @@
 A collection of structs used in LHSAutomaton/RHSAutomaton.
 
 */
-pub(crate) mod lhs_automaton;
+mod lhs_automaton;
+mod rhs_automaton;
 
-use crate::core::RcSort;
-
-use crate::theory::{
-  AssociativeSymbolStructure,
-  LHSAutomaton,
-  Term
+use crate::{
+  core::sort::RcSort,
+  theory::{RcLHSAutomaton, RcTerm},
 };
 
 
 // Re-exports
 pub use lhs_automaton::ACULHSAutomaton;
+pub use rhs_automaton::ACURHSAutomaton;
 
 
-#[derive(PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub(crate) enum MatchStrategy {
   //	Ground out case: no extension and everything grounds out;
@@ -51,51 +50,31 @@ pub(crate) enum MatchStrategy {
   //	Full case: if other cases don't apply. Alien sequence will be ordered to
   //	optimize solve-time weak constraint propagation. The field nrIndependents
   //	is unused.
-  Full
+  Full,
 }
 
 
-
-// Todo: Who owns this term? Can it be immutable during the lifetime of the `GroundAlien`?
-struct GroundAlien<'t> {
-  pub(crate) term        : &'t dyn Term,
-  pub(crate) multiplicity: u32
+#[derive(Clone)]
+pub(crate) struct GroundAlien {
+  pub(crate) term:         RcTerm,
+  pub(crate) multiplicity: u32,
 }
 
 
-struct NonGroundAlien<'t> {
-  pub(crate) term         : Option<&'t dyn Term>,
-  pub(crate) multiplicity : u32,
-  pub(crate) lhs_automaton: Box<dyn LHSAutomaton>
+#[derive(Clone)]
+pub(crate) struct NonGroundAlien {
+  pub(crate) term:          RcTerm,
+  pub(crate) multiplicity:  u32,
+  pub(crate) lhs_automaton: RcLHSAutomaton,
 }
 
 
-struct Subject {
-  pub(crate) multiplicity: u32,
-  pub(crate) assignee    : u32
-}
-
-
-
-/// The `TopVariable` type used in `lhs_automaton`
-struct TopVariable {
-  pub(crate) index        : u32,
-  pub(crate) multiplicity : u32,
-  pub(crate) sort         : RcSort,
-  upper_bound  : u32,
-  structure    : AssociativeSymbolStructure,
+/// The `TopVariable` type used in `lhs_automaton` and `subproblem`
+#[derive(Clone)]
+pub(crate) struct TopVariable {
+  pub(crate) index:         i32,
+  pub(crate) multiplicity:  u32,
+  pub(crate) sort:          RcSort,
+  /// The variable may be bound to our identity element.
   pub(crate) take_identity: bool,
-  pub(crate) abstracted   : Option<Box<dyn LHSAutomaton>>, // automaton for abstracted term
-
-  //	Data storage for match-time use
-  pub(crate) previous_unbound: Option<u32>,
-  first_subject   : u32,
-  subject_count   : u32,
-}
-
-impl TopVariable {
-  pub fn sort_constraint_free(&self) -> bool {
-    // Todo: Implement TopVariable::sort_constraint_free()
-    true
-  }
 }
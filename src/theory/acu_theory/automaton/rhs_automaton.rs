@@ -0,0 +1,111 @@
+/*!
+
+The RHS automaton for the ACU theory. Each instruction builds one ACU DAG node from arguments that have already been
+constructed (or bound) in the substitution. The new node is normalized at the top, so it may collapse to one of its
+arguments or to the identity.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{rc_cell, RcCell},
+  core::{substitution::Substitution, VariableInfo},
+  theory::{
+    acu_theory::{ACUDagNode, NormalizationStatus},
+    dag_node::MaybeDagNode,
+    make_rc_dag_node,
+    DagNode,
+    RHSAutomaton,
+    RcDagNode,
+    RcSymbol,
+  },
+};
+
+
+struct Argument {
+  source:       i32,
+  multiplicity: u32,
+}
+
+
+pub struct ACURHSAutomaton {
+  top_symbol:  RcSymbol,
+  arguments:   Vec<Argument>,
+  destination: i32,
+}
+
+impl ACURHSAutomaton {
+  pub fn new(top_symbol: RcSymbol) -> Self {
+    ACURHSAutomaton {
+      top_symbol,
+      arguments: vec![],
+      destination: -1,
+    }
+  }
+
+  pub fn add_argument(&mut self, source: i32, multiplicity: u32) {
+    self.arguments.push(Argument { source, multiplicity });
+  }
+
+  pub fn close(&mut self, destination: i32) {
+    self.destination = destination;
+  }
+
+  /// Builds the (unnormalized) node from the bindings in `matcher`.
+  fn build_arguments(&self, matcher: &mut Substitution) -> ACUDagNode {
+    let mut new_dag_node = ACUDagNode::new(self.top_symbol.clone(), self.arguments.len(), NormalizationStatus::Fresh);
+
+    for argument in &self.arguments {
+      let value = matcher.value(argument.source as usize);
+      assert!(value.is_some(), "unbound source {} in ACU RHS. This is a bug.", argument.source);
+      new_dag_node.push_arg(value.unwrap(), argument.multiplicity);
+    }
+
+    new_dag_node
+  }
+}
+
+
+impl RHSAutomaton for ACURHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    for argument in &mut self.arguments {
+      argument.source = variable_info.remap_index(argument.source);
+    }
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let mut new_dag_node = self.build_arguments(matcher);
+
+    let new_dag_node: RcDagNode = match new_dag_node.normalize_at_top() {
+      Some(collapsed) => collapsed,
+      None => make_rc_dag_node(new_dag_node),
+    };
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let mut new_dag_node = self.build_arguments(matcher);
+
+    match new_dag_node.normalize_at_top() {
+      Some(collapsed) => {
+        collapsed.borrow_mut().overwrite_with_clone(old);
+      }
+      None => {
+        let new_dag_node: RcDagNode = make_rc_dag_node(new_dag_node);
+        new_dag_node.borrow_mut().overwrite_with_clone(old);
+      }
+    }
+  }
+}
@@ -0,0 +1,105 @@
+/*!
+
+A bipartite graph between the non-ground aliens of an ACU pattern (the left nodes) and the arguments of the subject (the
+right nodes). There is an edge from an alien to a subject argument whenever the alien matches that argument in
+isolation.
+
+Before enumerating alien assignments, `ACUSubproblem` asks whether the graph has a matching that covers every alien. An
+alien of multiplicity `m` occupies `m` units of the multiplicity of the subject argument it is assigned to, and a subject
+argument cannot give away more units than it has. We find a maximum matching with augmenting paths (Kuhn's algorithm),
+treating each alien of multiplicity `m` as `m` unit nodes and each subject argument as a node of capacity equal to its
+multiplicity.
+
+The unit relaxation does not insist that the `m` copies of an alien land on the same subject argument, and it ignores
+conflicting variable bindings between aliens, so a complete matching is a necessary, not a sufficient, condition for
+the subproblem to have a solution. That is all we need to reject hopeless subproblems before any enumeration happens.
+
+ */
+
+pub(crate) struct BipartiteGraph {
+  /// The capacity of each right node, that is, the remaining multiplicity of each subject argument.
+  capacities: Vec<u32>,
+  /// `adjacency[u]` is the list of right nodes adjacent to the left unit node `u`.
+  adjacency:  Vec<Vec<usize>>,
+}
+
+impl BipartiteGraph {
+  pub fn new(capacities: Vec<u32>) -> Self {
+    BipartiteGraph {
+      capacities,
+      adjacency: Vec::new(),
+    }
+  }
+
+  /// Adds a left node of the given multiplicity adjacent to each of `targets`.
+  pub fn add_left_node(&mut self, targets: Vec<usize>, multiplicity: u32) {
+    for _ in 0..multiplicity {
+      self.adjacency.push(targets.clone());
+    }
+  }
+
+  /// Returns `true` if there is a matching saturating every left unit node.
+  pub fn has_complete_matching(&self) -> bool {
+    // `assigned[v]` holds the left unit nodes currently matched to right node `v`.
+    let mut assigned: Vec<Vec<usize>> = vec![Vec::new(); self.capacities.len()];
+
+    for u in 0..self.adjacency.len() {
+      let mut visited = vec![false; self.capacities.len()];
+      if !self.augment(u, &mut visited, &mut assigned) {
+        return false;
+      }
+    }
+    true
+  }
+
+  /// Searches for an augmenting path starting at the left unit node `u`, flipping it if one is found.
+  fn augment(&self, u: usize, visited: &mut Vec<bool>, assigned: &mut Vec<Vec<usize>>) -> bool {
+    for &v in &self.adjacency[u] {
+      if visited[v] {
+        continue;
+      }
+      visited[v] = true;
+
+      if (assigned[v].len() as u32) < self.capacities[v] {
+        assigned[v].push(u);
+        return true;
+      }
+
+      for k in 0..assigned[v].len() {
+        let other = assigned[v][k];
+        if self.augment(other, visited, assigned) {
+          assigned[v][k] = u;
+          return true;
+        }
+      }
+    }
+    false
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn augmenting_path_test() {
+    // The greedy choice 0 -> 0 has to be undone for 1 to be matched.
+    let mut graph = BipartiteGraph::new(vec![1, 1]);
+    graph.add_left_node(vec![0, 1], 1);
+    graph.add_left_node(vec![0], 1);
+    assert!(graph.has_complete_matching());
+
+    // Three aliens competing for two subjects.
+    graph.add_left_node(vec![0, 1], 1);
+    assert!(!graph.has_complete_matching());
+
+    // Multiplicity counts against capacity.
+    let mut graph = BipartiteGraph::new(vec![2, 1]);
+    graph.add_left_node(vec![0], 2);
+    graph.add_left_node(vec![0, 1], 1);
+    assert!(graph.has_complete_matching());
+    graph.add_left_node(vec![1], 1);
+    assert!(!graph.has_complete_matching());
+  }
+}
@@ -2,46 +2,54 @@
 
 Concrete types for the ACU theory implementing the DagNode trait.
 
+An `ACUDagNode` stores its arguments as a sorted multiset of `(DagNode, multiplicity)` pairs. Small argument lists are
+kept in a vector (`ACUArguments::List`); large ones are moved into a red-black tree (`ACUArguments::Tree`). Normal form
+for an ACU node is:
+  1. no argument has the same top symbol as the node (flattened),
+  2. no argument is the identity element,
+  3. the arguments are sorted by `DagNode::compare` with equal arguments merged into a single pair, and
+  4. there are at least two arguments counting multiplicity (otherwise the node collapses), and
+  5. if the symbol is idempotent, every argument has multiplicity one.
+
 */
 
-use std::{
-  any::Any,
-  borrow::BorrowMut,
-  cmp::Ordering, rc::Rc
-};
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
 
+use super::{red_black_tree::RedBlackTree, ACUSymbol, ACUTerm};
 use crate::{
+  abstractions::RcCell,
   core::{
-    Sort,
-    SpecialSort,
-    RcSort,
-    Substitution,
+    hash_cons_set::HashConsSet,
+    numeric_ordering,
+    sort::SpecialSort,
+    substitution::Substitution,
+    OrderingValue,
+    RedexPosition,
   },
+  rc_cell,
   theory::{
-    RcDagNode,
+    make_rc_dag_node,
+    BinarySymbol,
     DagNode,
+    DagNodeFlag,
+    DagNodeFlags,
+    DagNodeMembers,
     DagPair,
-    Term,
+    RcDagNode,
+    RcSymbol,
+    RcTerm,
     Symbol,
-    BinarySymbol,
-    dag_node::{DagNodeFlags, DagNodeFlag}
+    Term,
   },
-  core::{
-    OrderingValue,
-    numeric_ordering
-  }, abstractions::RcCell
-};
-
-use super::{
-  RcRedBlackTree,
-  symbol::RcACUSymbol,
-  red_black_tree::RedBlackTree
 };
 
 
 pub type RcACUDagNode = RcCell<ACUDagNode>;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+/// Argument lists at least this long are converted to a red-black tree after normalization.
+pub(crate) const CONVERT_THRESHOLD: usize = 16;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum NormalizationStatus {
   ///	Default: no guarantees.
   Fresh,
@@ -51,363 +59,429 @@ pub enum NormalizationStatus {
   ///	(b) all arguments have the correct sort; and
   ///	(c) argument list in theory normal form.
   Assignment,
-  ///	As above but arguments are stored in a red-black (ACU_TreeDagNode)
-  ///	rather than in an ArgVec (ACU_DagNode).
-  Tree
+  ///	As above but arguments are stored in a red-black tree rather than in a vector.
+  Tree,
 }
 
+#[derive(Clone)]
 pub enum ACUArguments {
   List(Vec<DagPair>),
-  Tree(RedBlackTree)
+  Tree(RedBlackTree),
 }
 
 impl ACUArguments {
-  pub fn size(&self) -> usize {
+  /// The number of distinct arguments.
+  pub fn len(&self) -> usize {
     match self {
-      ACUArguments::List(v) => { v.len() }
-      ACUArguments::Tree(t) => { t.size }
+      ACUArguments::List(v) => v.len(),
+      ACUArguments::Tree(t) => t.len(),
     }
   }
-  
-  /// Alias for size()
-  pub fn len(&self) -> usize {
-    self.size()
+
+  /// The number of arguments counting multiplicity.
+  pub fn total_multiplicity(&self) -> u32 {
+    self.iter().map(|(_, multiplicity)| multiplicity).sum()
   }
 
   /// Searches for the given term and returns its multiplicity if found.
   pub fn search_for_term(&self, term: &dyn Term) -> Option<u32> {
     match self {
-
       ACUArguments::List(v) => {
-        v.binary_search_by(
-          |pair| term.compare_dag_node(pair.dag_node.as_ref())  // Supposed to be term.compare_dag_node? or pair.dag_node.compare?
-        ).map(
-          |idx| v[idx].multiplicity
-        ).ok()
-      },
-
-      ACUArguments::Tree(t) => {
-        // find_term(..) returns `None` if nothing is found, so unwrapping the result of cursor.get never panics.
-        t.find_term(term).map(|c| *c.multiplicity.borrow())
+        v.binary_search_by(|pair| term.compare_dag_node(&**pair.dag_node.borrow()).reverse())
+          .map(|idx| v[idx].multiplicity)
+          .ok()
       }
+
+      ACUArguments::Tree(t) => t.find_term(term).map(|(_, multiplicity)| multiplicity),
     }
   }
 
-  pub fn iter(&self) -> Box<dyn Iterator<Item=(RcDagNode, u32)>>{
+  pub fn iter(&self) -> Box<dyn Iterator<Item = (RcDagNode, u32)> + '_> {
     match self {
-      ACUArguments::List(v) => {
-        Box::new(v.iter().map(|pair| (pair.dag_node.clone(), pair.multiplicity)))
-      },
-      ACUArguments::Tree(t) => {
-        Box::new(t.iter().map(|(dn, m)| (dn.clone(), m) ))
-      }
+      ACUArguments::List(v) => Box::new(v.iter().map(|pair| (pair.dag_node.clone(), pair.multiplicity))),
+      ACUArguments::Tree(t) => Box::new(t.iter()),
     }
   }
-
 }
 
 
 pub struct ACUDagNode {
-  pub(crate) top_symbol: RcACUSymbol,
-  pub(crate) args      : ACUArguments,
-  pub(crate) sort      : RcSort,
-  pub(crate) flags      : DagNodeFlags,
-  pub(crate) is_reduced: bool,
-  pub(crate) sort_index: i32,
+  pub(crate) members:              DagNodeMembers,
+  pub(crate) args:                 ACUArguments,
   pub(crate) normalization_status: NormalizationStatus,
 }
 
-
-
 impl ACUDagNode {
-
-  pub const ASSIGNMENT: NormalizationStatus = NormalizationStatus::Assignment;
-
-  pub fn new(symbol: RcACUSymbol, size: usize, normalization_status: NormalizationStatus) -> Self {
+  pub fn new(symbol: RcSymbol, capacity: usize, normalization_status: NormalizationStatus) -> Self {
     ACUDagNode {
-      top_symbol: symbol,
-      args: ACUArguments::List(Vec::with_capacity(size)),
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args:       Default::default(),
+        flags:      Default::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc:  None,
+        hash:       0,
+      },
+      args: ACUArguments::List(Vec::with_capacity(capacity)),
       normalization_status,
-      sort: RcCell::new(Sort::default()),
-      flags: DagNodeFlag::Reduced.into(),
-      is_reduced: true,
-      sort_index: 0,
+    }
+  }
+
+  pub fn with_pairs(symbol: RcSymbol, pairs: Vec<DagPair>, normalization_status: NormalizationStatus) -> Self {
+    let mut node = ACUDagNode::new(symbol, 0, normalization_status);
+    node.args = ACUArguments::List(pairs);
+    node
+  }
+
+  pub fn with_tree(symbol: RcSymbol, tree: RedBlackTree) -> Self {
+    let mut node = ACUDagNode::new(symbol, 0, NormalizationStatus::Tree);
+    node.args = ACUArguments::Tree(tree);
+    node
+  }
 
+  #[inline(always)]
+  pub fn acu_symbol(&self) -> &ACUSymbol {
+    match self.members.top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
     }
   }
 
-  pub fn new_tree(symbol: RcACUSymbol, tree: RedBlackTree) -> Self {
-    ACUDagNode {
-      top_symbol: symbol,
-      args: ACUArguments::Tree(tree),
-      sort: RcCell::new(Sort::default()),
-      flags: DagNodeFlag::Reduced.into(),
-      is_reduced: true,
-      sort_index: 0,
-      normalization_status: NormalizationStatus::Fresh,
+  #[inline(always)]
+  pub fn is_fresh(&self) -> bool {
+    self.normalization_status == NormalizationStatus::Fresh
+  }
+
+  /// Adds `multiplicity` copies of `dag_node` to the end of the argument list. The node is no longer normalized.
+  pub fn push_arg(&mut self, dag_node: RcDagNode, multiplicity: u32) {
+    self.to_list_arguments();
+    if let ACUArguments::List(args) = &mut self.args {
+      args.push(DagPair { dag_node, multiplicity });
     }
+    self.normalization_status = NormalizationStatus::Fresh;
   }
 
-  /// Computes the base sort index for self.
-  pub fn compute_base_sort(&self) -> i32 {
-    // Todo: Implement uniform_sort()
-    let s = self.symbol();
-    if let Some(uni_sort) = s.uniform_sort() {
-      // If symbol has a uniform sort structure do a fast sort computation.
-      // Todo: Implement component(), error_free()
-      if !uni_sort.component().error_free() {
-        let mut last_index = SpecialSort::Unknown as i32;
-
-        for (dag_node, multiplicity) in self.args.iter() {
-          let index = dag_node.get_sort_index();
-          assert!(index >= (SpecialSort::ErrorSort as i32));
-          if index != last_index {
-            if index >= uni_sort {
-              return SpecialSort::ErrorSort as i32;
-            }
-            last_index = index;
-          }
-        }
+  /// Iterates over `(argument, multiplicity)` pairs in order.
+  #[inline(always)]
+  pub fn iter_pairs(&self) -> Box<dyn Iterator<Item = (RcDagNode, u32)> + '_> {
+    self.args.iter()
+  }
+
+  /// Converts self.args into `ACUArguments::List(..)` if necessary. Conversion is done in place.
+  pub fn to_list_arguments(&mut self) {
+    if let ACUArguments::Tree(t) = &self.args {
+      self.args = ACUArguments::List(t.vectorize());
+      if self.normalization_status == NormalizationStatus::Tree {
+        self.normalization_status = NormalizationStatus::Assignment;
       }
-      return uni_sort.index();
     }
+  }
 
-    // Standard sort calculation.
-    let mut arg_iter = self.iter_args();
-    // The initial value is a special case.
-    let mut sort_index = {
-      let (dag_node, multiplicity) = arg_iter.next().unwrap();
-      let index = dag_node.get_sort_index();
-      assert!(index >= SpecialSort::ErrorSort as i32);
-      // The first case subtracts 1 from node.multiplicity.
-      // Todo: Implement `Symbol::compute_multisort_index(..)`
-      s.compute_multisort_index(index, index, multiplicity - 1)
-    };
-    // Now do the remaining args.
-    for (dag_node, multiplicity) in arg_iter {
-      let index = dag_node.get_sort_index();
-      assert!(index >= SpecialSort::ErrorSort as i32);
-      sort_index = s.compute_multisort_index(index, index, multiplicity);
+  /// Converts self.args into `ACUArguments::Tree(..)` if the node is normalized and large enough.
+  pub fn to_tree_arguments(&mut self) {
+    if self.is_fresh() {
+      return;
+    }
+    if let ACUArguments::List(args) = &self.args {
+      if args.len() >= CONVERT_THRESHOLD {
+        self.args = ACUArguments::Tree(RedBlackTree::from_pairs(args.iter().cloned()));
+        self.normalization_status = NormalizationStatus::Tree;
+      }
     }
+  }
 
-    sort_index
+  /// Sorts the argument list and merges equal arguments by adding their multiplicities.
+  pub fn sort_and_uniquize(&mut self) {
+    self.to_list_arguments();
+    if let ACUArguments::List(args) = &mut self.args {
+      args.sort_by(|a, b| a.dag_node.borrow().compare(&**b.dag_node.borrow()));
+
+      let mut merged: Vec<DagPair> = Vec::with_capacity(args.len());
+      for pair in args.drain(..) {
+        match merged.last_mut() {
+          Some(last) if last.dag_node.borrow().compare(&**pair.dag_node.borrow()).is_eq() => {
+            last.multiplicity += pair.multiplicity;
+          }
+          _ => merged.push(pair),
+        }
+      }
+      *args = merged;
+    }
   }
 
-  ///	Returns index of argument equal key, or a -ve value pos otherwise.
-  ///	In the latter case ~pos is the index of the smallest element greater
-  ///	than key, and can be argArray.length() if key is greater than all elements
-  ///	in the array.
-  pub fn binary_search_by_term(&self, key: &dyn Term) -> isize {
-    // The Maude source seems to suggest that this method is only called when the args is a vector.
-    if let ACUArguments::List(args) = &self.args {
+  /// Puts the node into ACU normal form at the top: arguments with our top symbol are flattened into the argument
+  /// list, identity elements are removed, and the remaining arguments are sorted and merged, dropping repeats if the
+  /// symbol is idempotent. If the node collapses, the node it collapses to is returned.
+  pub fn normalize_at_top(&mut self) -> Option<RcDagNode> {
+    self.to_list_arguments();
+    let symbol = self.symbol();
+
+    if let ACUArguments::List(args) = &mut self.args {
+      let mut flattened: Vec<DagPair> = Vec::with_capacity(args.len());
+
+      for pair in args.drain(..) {
+        let arg = pair.dag_node.borrow();
+        if arg.symbol().as_ref() == symbol.as_ref() {
+          // Arguments with our symbol are already in normal form, so we can splice their arguments directly.
+          if let Some(acu_arg) = arg.as_any().downcast_ref::<ACUDagNode>() {
+            for (dag_node, multiplicity) in acu_arg.iter_pairs() {
+              flattened.push(DagPair {
+                dag_node,
+                multiplicity: multiplicity * pair.multiplicity,
+              });
+            }
+            continue;
+          }
+        }
+        drop(arg);
+        flattened.push(pair);
+      }
 
-      let mut upper = args.len();
-      let mut lower: usize = 0;
+      let acu_symbol = match symbol.as_any().downcast_ref::<ACUSymbol>() {
+        Some(s) => s,
+        None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+      };
+      if acu_symbol.has_identity() {
+        flattened.retain(|pair| !acu_symbol.is_identity(&**pair.dag_node.borrow()));
+      }
 
-      loop {
-        let sum = upper + lower;
-        let probe = sum/2;
+      *args = flattened;
+    }
 
-        let r =  key.compare_dag_node(args[probe].dag_node.as_ref());
-        match r {
-          Ordering::Equal => {
-            return probe as isize;
-          }
-          Ordering::Less => {
-            upper = probe - 1;
-          }
-          Ordering::Greater => {
-            lower = probe + 1;
-          }
+    self.sort_and_uniquize();
+    if self.acu_symbol().is_idempotent() {
+      if let ACUArguments::List(args) = &mut self.args {
+        for pair in args.iter_mut() {
+          pair.multiplicity = 1;
         }
+      }
+    }
 
-        if lower > upper  {
-          break;
-        }
+    // Check for collapse.
+    if let ACUArguments::List(args) = &self.args {
+      if args.is_empty() {
+        return self.acu_symbol().get_identity_dag();
+      }
+      if args.len() == 1 && args[0].multiplicity == 1 {
+        return Some(args[0].dag_node.clone());
       }
-      return !(lower as isize);
-    } else {
-      panic!("Error: binary_search_by_term called on an ACUDagNode with tree args. This is a bug.");
     }
+
+    self.normalization_status = NormalizationStatus::Assignment;
+    self.to_tree_arguments();
+    None
   }
 
+  /// Returns `Ok(index)` of the argument equal to `key`, or `Err(index)` where `index` is the position at which `key`
+  /// would be inserted, that is, the index of the smallest argument greater than `key`.
+  pub fn binary_search_by_term(&self, key: &dyn Term) -> Result<usize, usize> {
+    // Only called on vectorized nodes.
+    if let ACUArguments::List(args) = &self.args {
+      args.binary_search_by(|pair| key.compare_dag_node(&**pair.dag_node.borrow()).reverse())
+    } else {
+      unreachable!("binary_search_by_term called on an ACUDagNode with tree args. This is a bug.");
+    }
+  }
 
-  ///	Returns index of argument equal key, or a -ve value pos otherwise.
-  ///	In the latter case ~pos is the index of the smallest element greater
-  ///	than key, and can be argArray.length() if key is greater than all elements
-  ///	in the array.
-  pub fn binary_search_by_dagnode(&self, key: &dyn DagNode) -> isize {
-    // Todo: This method is identical to `binary_search_by_term` except for the type of `key`.
+  /// Same as `binary_search_by_term`, but the key is a `DagNode`.
+  pub fn binary_search_by_dag_node(&self, key: &dyn DagNode) -> Result<usize, usize> {
+    if let ACUArguments::List(args) = &self.args {
+      args.binary_search_by(|pair| pair.dag_node.borrow().compare(key))
+    } else {
+      unreachable!("binary_search_by_dag_node called on an ACUDagNode with tree args. This is a bug.");
+    }
+  }
 
-    // The Maude source seems to suggest that this method is only called when the args is a vector.
+  /// Subtracts `multiplicity` copies of `target` from `subject_multiplicity`, which is indexed like our argument
+  /// list. If `target` has our top symbol, its arguments are subtracted instead. Returns `false` if this proves that
+  /// the subject cannot match.
+  pub fn eliminate_subject(&self, target: &dyn DagNode, multiplicity: u32, subject_multiplicity: &mut Vec<u32>) -> bool {
     if let ACUArguments::List(args) = &self.args {
+      eliminate_subject(self.acu_symbol(), args, target, multiplicity, subject_multiplicity)
+    } else {
+      unreachable!("eliminate_subject called on an ACUDagNode with tree args. This is a bug.");
+    }
+  }
 
-      let mut upper = args.len();
-      let mut lower: usize = 0;
+  ///	Return the smallest index whose subdag is a potential match for key, given the partial substitution for key's
+  /// variables. If we know that no subdag can match we return an index 1 beyond the maximal index.
+  pub(crate) fn find_first_potential_match(&self, key: &dyn Term, partial: &mut Substitution) -> usize {
+    if let ACUArguments::List(args) = &self.args {
+      let mut first = args.len();
+      let mut lower: isize = 0;
+      let mut upper: isize = args.len() as isize - 1;
 
-      loop {
-        let sum = upper + lower;
-        let probe = sum/2;
+      while lower <= upper {
+        let probe = (upper + lower) / 2;
+        let r = key.partial_compare(partial, &**args[probe as usize].dag_node.borrow());
 
-        let r =  key.compare(args[probe].dag_node.as_ref());
         match r {
-          Ordering::Equal => {
-            return probe as isize;
+          OrderingValue::Greater => {
+            lower = probe + 1;
           }
-          Ordering::Less => {
+          OrderingValue::Less => {
             upper = probe - 1;
           }
-          Ordering::Greater => {
-            lower = probe + 1;
+          OrderingValue::Equal => {
+            return probe as usize;
+          }
+          OrderingValue::Unknown => {
+            //	We need to treat probe as a potential match, and search to see if there
+            //	is one with a smaller index.
+            first = probe as usize;
+            upper = probe - 1;
           }
-        }
-
-        if lower > upper  {
-          break;
         }
       }
-      return !(lower as isize);
+
+      first
     } else {
-      panic!("Error: binary_search_by_dagnode called on an ACUDagNode with tree args. This is a bug.");
+      unreachable!("find_first_potential_match called when self is not vectorized. This is a bug.");
     }
   }
 
-  /// Converts self.args into `ACUArguments::List(..)` if necessary. Conversion is done in place.
-  pub fn to_list_arguments(&mut self){
-    if let ACUArguments::Tree(t) = &mut self.args {
-      self.args = ACUArguments::List(t.vectorize())
+  /// Makes an ACU node over the given pairs, or returns the sole argument if there is only one with multiplicity one.
+  /// The pairs must already be in normal form.
+  pub(crate) fn make_from_pairs(symbol: RcSymbol, pairs: Vec<DagPair>) -> RcDagNode {
+    if pairs.len() == 1 && pairs[0].multiplicity == 1 {
+      return pairs[0].dag_node.clone();
     }
+    let mut node = ACUDagNode::with_pairs(symbol, pairs, NormalizationStatus::Assignment);
+    node.to_tree_arguments();
+    make_rc_dag_node(node)
   }
+}
 
-  /// Matches the subject or else proves that the subject cannot match.
-  pub fn eliminate_subject(
-    &mut self,
-    target: &dyn DagNode,
-    multiplicity: u32,
-    subject_multiplicity: &mut Vec<i32>
-  ) -> bool
-  {
-    if let Some(identity) = Rc::as_ref(&self.top_symbol).get_identity() {
-      if identity.compare_dag_node(target) == Ordering::Equal{
-        return true;
-      }
-    }
-    if target.symbol() == Rc::as_ref(&self.top_symbol) as &dyn Symbol {
-      // Since self.top_symbol is an ACUDagNode, so must be target.
-      if let Some(acu_dag_node) = target.as_any().downcast_ref::<ACUDagNode>(){
-        // Todo: Why do we vectorize here?
-        acu_dag_node.to_list_arguments();
-        if let ACUDagNode{args: ACUArguments::List(args), ..} = acu_dag_node {
-          for (arg_dag_node, arg_multiplicity) in acu_dag_node.iter_args() {
-            let pos = self.binary_search_by_dagnode(arg_dag_node.as_ref());
-            if pos < 0 {
-              return false;
-            }
-            subject_multiplicity[pos as usize] -= (arg_multiplicity * multiplicity) as i32;
-            if subject_multiplicity[pos as usize] < 0 {
-              return false;
-            }
-          } // end iter over arg pairs
-        } // end destructure target args
-        else {
-          // Should be infallible.
-          panic!("ACUArguments::Tree after vectorizing. This is a bug.");
-        }
-      } else {
-        // Should be infallible.
-        panic!("DagNode could not be downcast to ACUNode despite being equal to one. This is a bug.");
-      }
 
-    } // end if self.top_symbol == target.top_symbol
-    else {
-      let pos = self.binary_search_by_dagnode(target);
-      if pos < 0 {
-        return false;
-      }
-      subject_multiplicity[pos as usize] -= multiplicity as i32;
-      if subject_multiplicity[pos as usize] < 0 {
-        return false;
+/// Subtracts `multiplicity` copies of `target` from `subject_multiplicity`, which is indexed like `subjects`. If
+/// `target` has the top symbol `symbol`, its arguments are subtracted instead, and if it is the identity, nothing is
+/// subtracted. Returns `false` if there are not enough copies, in which case `subject_multiplicity` is left in an
+/// unspecified state.
+pub(crate) fn eliminate_subject(
+  symbol: &ACUSymbol,
+  subjects: &[DagPair],
+  target: &dyn DagNode,
+  multiplicity: u32,
+  subject_multiplicity: &mut [u32],
+) -> bool {
+  if symbol.is_identity(target) {
+    return true;
+  }
+
+  let mut subtract = |dag_node: &dyn DagNode, needed: u32| -> bool {
+    match subjects.binary_search_by(|pair| pair.dag_node.borrow().compare(dag_node)) {
+      Ok(pos) if subject_multiplicity[pos] >= needed => {
+        subject_multiplicity[pos] -= needed;
+        true
       }
+      _ => false,
+    }
+  };
+
+  if target.symbol().as_ref() == symbol as &dyn Symbol {
+    // Since target has our top symbol, it must be an ACUDagNode.
+    if let Some(acu_dag_node) = target.as_any().downcast_ref::<ACUDagNode>() {
+      acu_dag_node
+        .iter_pairs()
+        .all(|(arg, arg_multiplicity)| subtract(&**arg.borrow(), arg_multiplicity * multiplicity))
+    } else {
+      unreachable!("DagNode could not be downcast to ACUDagNode despite having an ACU symbol. This is a bug.");
     }
-    true
+  } else {
+    subtract(target, multiplicity)
   }
+}
 
+/// The idempotent counterpart of `eliminate_subject`: copies of an argument are not used up, so instead of subtracting we
+/// count in `subject_cover`, which is indexed like `subjects`, how often each subject has been accounted for. Returns
+/// `false` if `target` has an argument that is not among the subjects.
+pub(crate) fn cover_subject(
+  symbol: &ACUSymbol,
+  subjects: &[DagPair],
+  target: &dyn DagNode,
+  subject_cover: &mut [u32],
+) -> bool {
+  if symbol.is_identity(target) {
+    return true;
+  }
 
-  ///	Return the smallest index whose subdag is a potential match for key, given the partial substitution
-  /// for key's variables. If we know that no subdag can match we return an index 1 beyond the maximal index.
-  ///
-  /// There are two versions of this function: One on `RedBlackTree` and one on `DagNode`. The `DagNode` version
-  /// operates on `ACUArguments::List(args)` , while the `RedBlackTree` obviously operates on trees.
-  pub(crate) fn find_first_potential_match(&self, key: &dyn Term, partial: &mut Substitution) -> u32  {
-    // I think self is already guaranteed to be vectorized.
-    if let ACUArguments::List(args) = &self.args {
-      let mut first = args.len();
-      let mut upper = first - 1;
-      let mut lower = 0;
-
-      while lower <= upper {
-        let mut probe = (upper + lower)/2;
-        let r = key.partial_compare(partial, args[probe].dag_node.as_ref());
-
-        match r {
-          OrderingValue::Greater => { lower = probe + 1; }
-          OrderingValue::Less => { upper = probe - 1; }
-          OrderingValue::Equal => { return probe as u32;}
-          OrderingValue::Unknown => {
-            //	We need to treat probe as a potential match, and search to see if there
-            //	is one with a smaller index.
-            first = probe;
-            upper = probe - 1;
-          }
-        }
+  let mut cover = |dag_node: &dyn DagNode| -> bool {
+    match subjects.binary_search_by(|pair| pair.dag_node.borrow().compare(dag_node)) {
+      Ok(pos) => {
+        subject_cover[pos] += 1;
+        true
       }
+      Err(_) => false,
+    }
+  };
 
-      first as u32
+  if target.symbol().as_ref() == symbol as &dyn Symbol {
+    if let Some(acu_dag_node) = target.as_any().downcast_ref::<ACUDagNode>() {
+      acu_dag_node.iter_pairs().all(|(arg, _)| cover(&**arg.borrow()))
     } else {
-      panic!("Error: find_first_potential_match called when self is not vectorized. This is a bug.");
+      unreachable!("DagNode could not be downcast to ACUDagNode despite having an ACU symbol. This is a bug.");
     }
+  } else {
+    cover(target)
   }
 }
 
-/// If the given DagNode is an ACUDagNode and it has tree args, vectorize it. Modifies the node in place.
-// fn to_acu_list_args(dag_node: &mut dyn DagNode) {
-  //   if let Some(acu_dag_node) = dag_node.as_any().downcast_mut::<ACUDagNode>(){
-    //     acu_dag_node.to_list_arguments();
-    //   }
-    // }
 
 impl DagNode for ACUDagNode {
-  fn symbol(&self) -> &dyn Symbol {
-    // Rc::as_ref(&self.top_symbol) as &dyn Symbol
-    self.top_symbol.clone()
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
   }
 
-  // Todo: Is this needed?
-  fn symbol_mut(&mut self) -> &mut dyn Symbol {
-    Rc::get_mut(&mut self.top_symbol).unwrap()   //.borrow_mut()
+  /// Iterates over the distinct arguments. Use `iter_pairs` to get multiplicities.
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcDagNode> + '_> {
+    Box::new(self.args.iter().map(|(dag_node, _)| dag_node))
   }
 
-  fn iter_args(&self) -> Box<dyn Iterator<Item=(RcDagNode, u32)>> {
-    Box::new(self.args.iter())
+  #[inline(always)]
+  fn len(&self) -> usize {
+    self.args.len()
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
   }
 
   fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
     match other.as_any().downcast_ref::<ACUDagNode>() {
       Some(acu_dag_node) => {
         // Fail fast if lengths differ.
-        let r: i32 = self.args.len() as i32 - acu_dag_node.len() as i32;
+        let r = self.args.len() as isize - acu_dag_node.args.len() as isize;
         if r != 0 {
-          return numeric_ordering(r as usize);
+          return numeric_ordering(r);
         }
         // Compare corresponding terms.
-        for ((this_child, this_multiplicity), (other_child, other_multiplicity))
-        in self.iter_args().zip(acu_dag_node.iter_args()) {
-          let r: i32 = this_multiplicity as i32 - other_multiplicity as i32;
+        for ((this_child, this_multiplicity), (other_child, other_multiplicity)) in
+          self.iter_pairs().zip(acu_dag_node.iter_pairs())
+        {
+          let r = this_multiplicity as isize - other_multiplicity as isize;
           if r != 0 {
-            return numeric_ordering(r as usize);
+            return numeric_ordering(r);
           }
 
-          let r = this_child.compare(other_child.as_ref());
+          let r = this_child.borrow().compare(&**other_child.borrow());
           if r != Ordering::Equal {
             return r;
           }
@@ -415,41 +489,183 @@ impl DagNode for ACUDagNode {
         // Identical
         return Ordering::Equal;
       }
-      None => panic!("Could not downcast a Term to an ACUTerm."),
-    };
+      None => unreachable!("Could not downcast a DagNode to an ACUDagNode. This is a bug."),
+    }
   }
 
-  fn get_sort(&self) -> RcSort {
-    self.sort.clone()
+  fn compute_base_sort(&mut self) -> i32 {
+    let symbol = self.symbol();
+    let acu_symbol = self.acu_symbol();
+    let mut sort_index = SpecialSort::Unknown as i32;
+
+    for (dag_node, multiplicity) in self.iter_pairs() {
+      let index = dag_node.borrow().get_sort_index();
+      assert_ne!(
+        index,
+        SpecialSort::Unknown as i32,
+        "unknown sort encountered for argument of {}",
+        symbol.name()
+      );
+
+      if sort_index == SpecialSort::Unknown as i32 {
+        // The first argument is a special case: it seeds the computation.
+        sort_index = acu_symbol.compute_multisort_index(index, index, multiplicity - 1);
+      } else {
+        sort_index = acu_symbol.compute_multisort_index(sort_index, index, multiplicity);
+      }
+    }
+
+    self.set_sort_index(sort_index);
+    sort_index
   }
 
-  fn set_sort_index(&mut self, sort_index: i32) {
-    self.sort_index = sort_index;
+  fn termify(&self) -> RcTerm {
+    let args: Vec<(RcTerm, u32)> = self
+      .iter_pairs()
+      .map(|(dag_node, multiplicity)| (dag_node.borrow().termify(), multiplicity))
+      .collect();
+    rc_cell!(ACUTerm::with_multiplicities(self.symbol(), args))
   }
 
-  fn get_sort_index(&self) -> i32 {
-    self.sort_index
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = ACUDagNode::new(self.symbol(), 0, self.normalization_status);
+    new_node.args = self.args.clone();
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
   }
 
-  fn len(&self) -> usize {
-    self.args.len()
+  /// The `arg_index` of a redex position is the index of the argument in the (vectorized) argument list.
+  fn copy_with_replacements(&self, redex_stack: &[RedexPosition], mut first_idx: usize, last_idx: usize) -> RcDagNode {
+    assert!(
+      first_idx <= last_idx && last_idx < redex_stack.len(),
+      "bad replacement range"
+    );
+    let mut new_node = ACUDagNode::new(self.symbol(), self.args.len(), NormalizationStatus::Fresh);
+    let mut next_replacement_index = redex_stack[first_idx].arg_index;
+
+    for (i, (dag_node, multiplicity)) in self.iter_pairs().enumerate() {
+      if i as i32 == next_replacement_index {
+        new_node.push_arg(redex_stack[first_idx].dag_node.clone(), multiplicity);
+        first_idx += 1;
+        next_replacement_index = if first_idx <= last_idx {
+          redex_stack[first_idx].arg_index
+        } else {
+          -1
+        };
+      } else {
+        new_node.push_arg(dag_node, multiplicity);
+      }
+    }
+    make_rc_dag_node(new_node)
   }
 
-  fn as_any(&self) -> &dyn Any{
-    self
+  fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
+    assert!(arg_index < self.args.len(), "bad argIndex");
+    let mut new_node = ACUDagNode::new(self.symbol(), self.args.len(), NormalizationStatus::Fresh);
+
+    for (i, (dag_node, multiplicity)) in self.iter_pairs().enumerate() {
+      if i == arg_index {
+        new_node.push_arg(replacement.clone(), multiplicity);
+      } else {
+        new_node.push_arg(dag_node, multiplicity);
+      }
+    }
+    make_rc_dag_node(new_node)
   }
 
-  fn as_any_mut(&mut self) -> &mut dyn Any{
-    self
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    // ACU symbols have the standard strategy, so every argument is eager.
+    let mut new_node = ACUDagNode::new(self.symbol(), self.args.len(), self.normalization_status);
+    let pairs: Vec<DagPair> = self
+      .iter_pairs()
+      .map(|(dag_node, multiplicity)| {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = dag_node.borrow_mut().copy_eager_upto_reduced();
+        DagPair {
+          dag_node: copy.unwrap_or(dag_node),
+          multiplicity,
+        }
+      })
+      .collect();
+    new_node.args = ACUArguments::List(pairs);
+    if new_node.normalization_status == NormalizationStatus::Tree {
+      new_node.normalization_status = NormalizationStatus::Assignment;
+      new_node.to_tree_arguments();
+    }
+    make_rc_dag_node(new_node)
   }
 
-  fn compute_base_sort(&self) -> i32 {
-    todo!()
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    let mut new_node = ACUDagNode::new(self.symbol(), self.args.len(), self.normalization_status);
+    let pairs: Vec<DagPair> = self
+      .iter_pairs()
+      .map(|(dag_node, multiplicity)| {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = dag_node.borrow_mut().copy_all();
+        DagPair {
+          dag_node: copy.unwrap_or(dag_node),
+          multiplicity,
+        }
+      })
+      .collect();
+    new_node.args = ACUArguments::List(pairs);
+    if new_node.normalization_status == NormalizationStatus::Tree {
+      new_node.normalization_status = NormalizationStatus::Assignment;
+      new_node.to_tree_arguments();
+    }
+    make_rc_dag_node(new_node)
   }
 
-  fn flags(&self) -> DagNodeFlags {
-    self.flags
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = ACUDagNode::new(self.symbol(), 0, self.normalization_status);
+    new_node.args = self.args.clone();
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+
+    *old.borrow_mut() = Box::new(new_node);
   }
-}
 
+  /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let pairs: Vec<(RcDagNode, u32)> = self.iter_pairs().collect();
 
+    for (i, (dag_node, _)) in pairs.iter().enumerate() {
+      let (canonical_dag_node, _) = hash_cons_set.insert(dag_node.clone());
+      if RcCell::ptr_eq(&canonical_dag_node, dag_node) {
+        // The child node was already canonical.
+        continue;
+      }
+
+      // Detected a non-canonical argument, need to make a new copy.
+      let mut new_pairs: Vec<DagPair> = Vec::with_capacity(pairs.len());
+      for (dag_node, multiplicity) in &pairs[0..i] {
+        new_pairs.push(DagPair {
+          dag_node:     dag_node.clone(),
+          multiplicity: *multiplicity,
+        });
+      }
+      new_pairs.push(DagPair {
+        dag_node:     canonical_dag_node,
+        multiplicity: pairs[i].1,
+      });
+      for (dag_node, multiplicity) in &pairs[i + 1..] {
+        let (canonical, _) = hash_cons_set.insert(dag_node.clone());
+        new_pairs.push(DagPair {
+          dag_node: canonical,
+          multiplicity: *multiplicity,
+        });
+      }
+
+      let mut new_node = ACUDagNode::with_pairs(self.symbol(), new_pairs, NormalizationStatus::Assignment);
+      new_node.members.flags.set_copied_flags(self.members.flags);
+      new_node.members.sort_index = self.members.sort_index;
+      new_node.to_tree_arguments();
+
+      return make_rc_dag_node(new_node);
+    }
+
+    rc_dag_node // Can use the original DAG node as the canonical version
+  }
+}
@@ -0,0 +1,142 @@
+/*!
+
+The subproblem of distributing what remains of an ACU subject among the unbound top variables of the pattern. Each way
+of doing so is a solution of a bounded system of linear Diophantine equations (see `DiophantineSystem`): a variable of
+multiplicity `m` that is assigned `k` copies of a subject argument accounts for `m·k` units of that argument's
+multiplicity, and the units of every argument must be accounted for exactly. A variable must be assigned at least one
+argument unless it can take our identity.
+
+Solutions are enumerated lazily, so matching a pattern like `f(X, X, Y)` against a large multiset only ever builds the
+values for the current solution.
+
+ */
+
+use super::{automaton::TopVariable, ACUDagNode, ACUSymbol};
+use crate::{
+  abstractions::DiophantineSystem,
+  core::rewrite_context::RewritingContext,
+  theory::{BinarySymbol, DagNode, DagPair, Outcome, RcDagNode, RcSymbol, Subproblem},
+};
+
+
+pub struct ACUDistributionSubproblem {
+  top_symbol: RcSymbol,
+  /// The subject arguments with what remains of their multiplicity, one per row of the system.
+  subjects:   Vec<DagPair>,
+  /// The unbound top variables, one per column of the system.
+  variables:  Vec<TopVariable>,
+  system:     DiophantineSystem,
+  /// The variable indices we have bound in the substitution for the current solution.
+  bound:      Vec<i32>,
+}
+
+impl ACUDistributionSubproblem {
+  /// Subjects whose remaining multiplicity is zero are dropped.
+  pub(crate) fn new(top_symbol: RcSymbol, subjects: &[DagPair], remaining: &[u32], variables: Vec<TopVariable>) -> Self {
+    let subjects: Vec<DagPair> = subjects
+      .iter()
+      .zip(remaining.iter())
+      .filter(|(_, &multiplicity)| multiplicity > 0)
+      .map(|(pair, &multiplicity)| {
+        DagPair {
+          dag_node: pair.dag_node.clone(),
+          multiplicity,
+        }
+      })
+      .collect();
+
+    let mut system = DiophantineSystem::new();
+    for pair in &subjects {
+      system.insert_row(pair.multiplicity);
+    }
+
+    let has_identity = match top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol.has_identity(),
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+    };
+    for variable in &variables {
+      let lower = if variable.take_identity && has_identity { 0 } else { 1 };
+      system.insert_column(variable.multiplicity, lower, u32::MAX);
+    }
+
+    ACUDistributionSubproblem {
+      top_symbol,
+      subjects,
+      variables,
+      system,
+      bound: vec![],
+    }
+  }
+
+  #[inline(always)]
+  fn acu_symbol(&self) -> &ACUSymbol {
+    match self.top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+    }
+  }
+
+  /// Binds each variable to its share of the subject under the current solution of the system.
+  fn bind_solution(&mut self, context: &mut RewritingContext) -> bool {
+    for (v, variable) in self.variables.iter().enumerate() {
+      let pairs: Vec<DagPair> = self
+        .subjects
+        .iter()
+        .enumerate()
+        .filter_map(|(j, pair)| {
+          match self.system.solution(j, v) {
+            0 => None,
+            multiplicity => {
+              Some(DagPair {
+                dag_node: pair.dag_node.clone(),
+                multiplicity,
+              })
+            }
+          }
+        })
+        .collect();
+
+      let value: RcDagNode = if pairs.is_empty() {
+        match self.acu_symbol().get_identity_dag() {
+          Some(identity) => identity,
+          None => return false,
+        }
+      } else {
+        ACUDagNode::make_from_pairs(self.top_symbol.clone(), pairs)
+      };
+
+      // `check_sort` does not produce sort constraint subproblems yet, so success is all we need.
+      let (outcome, _) = value.borrow_mut().check_sort(variable.sort.clone());
+      if outcome != Outcome::Success {
+        return false;
+      }
+      context.substitution.bind(variable.index, Some(value));
+      self.bound.push(variable.index);
+    }
+
+    true
+  }
+
+  fn retract_solution(&mut self, context: &mut RewritingContext) {
+    for index in self.bound.drain(..) {
+      context.substitution.bind(index, None);
+    }
+  }
+}
+
+impl Subproblem for ACUDistributionSubproblem {
+  fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    self.retract_solution(context);
+    if find_first {
+      self.system.reset();
+    }
+
+    while self.system.solve() {
+      if self.bind_solution(context) {
+        return true;
+      }
+      self.retract_solution(context);
+    }
+    false
+  }
+}
@@ -1,21 +1,21 @@
 /*!
 
-This struct is used to store information about an extension of a match in an ACU
-(Associative Commutative Unification) automaton. It has the following fields:
+Information about an extension of a match in an ACU (Associative Commutative with Unit) automaton, that is, a match of
+a pattern against only part of the subject's arguments. It has the following fields:
 
   * `valid_after_match`: a boolean flag that indicates whether the extension is valid after the match.
   * `matched_whole`: a boolean flag that indicates whether the entire subject of the extension matched.
-  * `subject`: the subject of the extension, represented as a reference-counted (Rc) `ACUDagNode`.
+  * `subject`: the subject of the extension.
   * `unmatched`: the unmatched part of the subject, represented as a red-black tree.
   * `unmatched_multiplicity`: a vector that stores the multiplicity of the unmatched part of the subject.
   * `upper_bound`: an unsigned 32-bit integer that represents the upper bound of the unmatched multiplicity.
 
 */
 
+use std::any::Any;
 
-use std::rc::Rc;
-use crate::theory::acu_theory::{ACUDagNode, RedBlackTree};
-use crate::theory::{DagNode, ExtensionInfo, RcDagNode};
+use super::RedBlackTree;
+use crate::theory::{ExtensionInfo, RcDagNode};
 
 /// A struct that stores information about an extension of a match in an ACU automaton.
 pub struct ACUExtensionInfo {
@@ -26,7 +26,7 @@ pub struct ACUExtensionInfo {
   matched_whole: bool,
 
   /// The subject of the extension.
-  pub subject: Rc<ACUDagNode>,
+  pub subject: RcDagNode,
 
   /// The unmatched part of the subject, represented as a red-black tree.
   pub unmatched: RedBlackTree,
@@ -38,7 +38,40 @@ pub struct ACUExtensionInfo {
   pub upper_bound: u32,
 }
 
+impl ACUExtensionInfo {
+  pub fn new(subject: RcDagNode) -> Self {
+    ACUExtensionInfo {
+      valid_after_match: false,
+      matched_whole: false,
+      subject,
+      unmatched: RedBlackTree::default(),
+      unmatched_multiplicity: vec![],
+      upper_bound: 0,
+    }
+  }
+}
+
 impl ExtensionInfo for ACUExtensionInfo {
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn valid_after_match(&self) -> bool {
+    self.valid_after_match
+  }
+
+  #[inline(always)]
+  fn matched_whole(&self) -> bool {
+    self.matched_whole
+  }
+
   fn set_valid_after_match(&mut self, value: bool) {
     self.valid_after_match = value;
   }
@@ -46,10 +79,8 @@ impl ExtensionInfo for ACUExtensionInfo {
   fn set_matched_whole(&mut self, value: bool) {
     self.matched_whole = value;
   }
-  
+
   fn set_unmatched(&mut self, value: RcDagNode) {
     self.unmatched.insert(value)
   }
 }
-
-
@@ -0,0 +1,351 @@
+/*!
+
+The matching subproblem for idempotent ACU symbols. The arguments of an idempotent subject form a set, so a subject
+argument is not used up when a piece of the pattern takes it: `f(X, Y)` matches `f(a, b)` with `X = f(a, b)` and
+`Y = a`, among others. What must hold is that every subject argument is accounted for by at least one piece of the
+pattern. We keep a count for each subject argument of how many pieces currently account for it (its cover).
+
+The `ACULHSAutomaton` has already dealt with ground aliens and bound top variables. What is left is to
+
+  1. match each non-ground alien against some subject argument, and then
+  2. give each unbound top variable a subset of the subject arguments, such that every subject argument with no cover
+     is in at least one of the subsets.
+
+The aliens are searched depth first as in `ACUSubproblem`, except that aliens may share a subject argument, so there is
+no bipartite graph to check. For the variables, each subject argument is assigned the set of variables it goes to,
+encoded as a bit mask, and the masks are enumerated like the digits of a counter. A variable that cannot take our
+identity must appear in at least one mask.
+
+ */
+
+use super::{
+  automaton::{NonGroundAlien, TopVariable},
+  dag_node::cover_subject,
+  ACUDagNode,
+  ACUSymbol,
+};
+use crate::{
+  core::{rewrite_context::RewritingContext, LocalBindings},
+  theory::{BinarySymbol, DagNode, DagPair, MaybeSubproblem, Outcome, RcDagNode, RcSymbol, Subproblem},
+};
+
+
+/// An edge from a non-ground alien to a subject argument it matches.
+struct Edge {
+  /// The index of the subject argument in `ACUIdempotentSubproblem::subjects`.
+  target:     usize,
+  /// The bindings made by matching the alien against the target.
+  difference: Option<LocalBindings>,
+  subproblem: MaybeSubproblem,
+}
+
+/// A non-ground alien together with its edges.
+struct PatternNode {
+  edges:    Vec<Edge>,
+  /// The index in `edges` of the edge currently in use.
+  selected: usize,
+}
+
+
+pub struct ACUIdempotentSubproblem {
+  top_symbol:     RcSymbol,
+  subjects:       Vec<DagPair>,
+  initial_cover:  Vec<u32>,
+  current_cover:  Vec<u32>,
+  aliens:         Vec<NonGroundAlien>,
+  /// Rebuilt whenever we are asked for a first solution.
+  pattern_nodes:  Vec<PatternNode>,
+  top_variables:  Vec<TopVariable>,
+  /// The top variables left unbound once the aliens are matched, one bit each in `masks`.
+  unbound:        Vec<TopVariable>,
+  /// For each subject argument, the set of unbound variables it is given to.
+  masks:          Vec<u32>,
+  /// The cover once the aliens are matched and the variables they bound are accounted for.
+  variable_cover: Vec<u32>,
+  /// The variable indices we have bound in the substitution for the current solution.
+  bound:          Vec<i32>,
+}
+
+impl ACUIdempotentSubproblem {
+  pub(crate) fn new(
+    top_symbol: RcSymbol,
+    subjects: Vec<DagPair>,
+    cover: Vec<u32>,
+    aliens: Vec<NonGroundAlien>,
+    top_variables: Vec<TopVariable>,
+  ) -> Self {
+    ACUIdempotentSubproblem {
+      top_symbol,
+      subjects,
+      current_cover: cover.clone(),
+      initial_cover: cover,
+      aliens,
+      pattern_nodes: Vec::new(),
+      top_variables,
+      unbound: Vec::new(),
+      masks: Vec::new(),
+      variable_cover: Vec::new(),
+      bound: Vec::new(),
+    }
+  }
+
+  #[inline(always)]
+  fn acu_symbol(&self) -> &ACUSymbol {
+    match self.top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+    }
+  }
+
+  /// Matches every alien against every subject argument under the current substitution. Returns `false` if some alien
+  /// matches nothing.
+  fn build_edges(&mut self, context: &mut RewritingContext) -> bool {
+    self.pattern_nodes.clear();
+
+    for alien in &self.aliens {
+      let mut edges = Vec::new();
+
+      for (j, subject) in self.subjects.iter().enumerate() {
+        let mut local = context.substitution.clone();
+        let (matched, subproblem) = alien
+          .lhs_automaton
+          .borrow_mut()
+          .match_(subject.dag_node.clone(), &mut local);
+        if matched {
+          edges.push(Edge {
+            target: j,
+            difference: local.subtract(&context.substitution),
+            subproblem,
+          });
+        }
+      }
+
+      if edges.is_empty() {
+        return false;
+      }
+      self.pattern_nodes.push(PatternNode { edges, selected: 0 });
+    }
+
+    // Fail early: search the most constrained aliens first.
+    self.pattern_nodes.sort_by_key(|node| node.edges.len());
+    true
+  }
+
+  /// Finds the first (if `find_first`) or next edge for the alien at `level` that is consistent with the choices made
+  /// at earlier levels.
+  fn solve_alien(&mut self, level: usize, find_first: bool, context: &mut RewritingContext) -> bool {
+    let node = &mut self.pattern_nodes[level];
+    let mut start = 0;
+
+    if !find_first {
+      let edge = &mut node.edges[node.selected];
+      // First try for another solution to the edge's own subproblem.
+      if let Some(subproblem) = edge.subproblem.as_mut() {
+        if subproblem.solve(false, context) {
+          return true;
+        }
+      }
+      // Undo the current choice.
+      if let Some(difference) = edge.difference.as_mut() {
+        difference.retract(&mut context.substitution);
+      }
+      self.current_cover[edge.target] -= 1;
+      start = node.selected + 1;
+    }
+
+    for e in start..node.edges.len() {
+      let edge = &mut node.edges[e];
+
+      if let Some(difference) = edge.difference.as_mut() {
+        if !difference.assert(&mut context.substitution) {
+          continue;
+        }
+      }
+      if let Some(subproblem) = edge.subproblem.as_mut() {
+        if !subproblem.solve(true, context) {
+          if let Some(difference) = edge.difference.as_mut() {
+            difference.retract(&mut context.substitution);
+          }
+          continue;
+        }
+      }
+
+      self.current_cover[edge.target] += 1;
+      node.selected = e;
+      return true;
+    }
+
+    false
+  }
+
+  /// The least mask for the subject argument at `index`: an argument with no cover must go to some variable.
+  #[inline(always)]
+  fn least_mask(&self, index: usize) -> u32 {
+    if self.variable_cover[index] == 0 {
+      1
+    } else {
+      0
+    }
+  }
+
+  /// Resets the masks to their least values. Returns `false` if there are none, which happens when an argument with no
+  /// cover is left and there are no variables to give it to.
+  fn first_masks(&mut self) -> bool {
+    let limit = 1u32 << self.unbound.len();
+    self.masks = (0..self.subjects.len()).map(|j| self.least_mask(j)).collect();
+    self.masks.iter().all(|&mask| mask < limit)
+  }
+
+  /// Advances the masks like the digits of a counter. Returns `false` once every combination has been seen.
+  fn next_masks(&mut self) -> bool {
+    let limit = 1u32 << self.unbound.len();
+    for j in (0..self.masks.len()).rev() {
+      self.masks[j] += 1;
+      if self.masks[j] < limit {
+        return true;
+      }
+      self.masks[j] = self.least_mask(j);
+    }
+    false
+  }
+
+  /// Are the current masks a solution, that is, does every variable that cannot take our identity get something?
+  fn masks_valid(&self) -> bool {
+    let used = self.masks.iter().fold(0, |used, &mask| used | mask);
+    let has_identity = self.acu_symbol().has_identity();
+
+    self
+      .unbound
+      .iter()
+      .enumerate()
+      .all(|(v, variable)| (variable.take_identity && has_identity) || used & (1 << v) != 0)
+  }
+
+  /// Binds each unbound variable to the subject arguments the current masks give it.
+  fn bind_solution(&mut self, context: &mut RewritingContext) -> bool {
+    for (v, variable) in self.unbound.iter().enumerate() {
+      let pairs: Vec<DagPair> = self
+        .subjects
+        .iter()
+        .zip(self.masks.iter())
+        .filter(|(_, &mask)| mask & (1 << v) != 0)
+        .map(|(pair, _)| {
+          DagPair {
+            dag_node:     pair.dag_node.clone(),
+            multiplicity: 1,
+          }
+        })
+        .collect();
+
+      let value: RcDagNode = if pairs.is_empty() {
+        match self.acu_symbol().get_identity_dag() {
+          Some(identity) => identity,
+          None => return false,
+        }
+      } else {
+        ACUDagNode::make_from_pairs(self.top_symbol.clone(), pairs)
+      };
+
+      // `check_sort` does not produce sort constraint subproblems yet, so success is all we need.
+      let (outcome, _) = value.borrow_mut().check_sort(variable.sort.clone());
+      if outcome != Outcome::Success {
+        return false;
+      }
+      context.substitution.bind(variable.index, Some(value));
+      self.bound.push(variable.index);
+    }
+
+    true
+  }
+
+  fn retract_solution(&mut self, context: &mut RewritingContext) {
+    for index in self.bound.drain(..) {
+      context.substitution.bind(index, None);
+    }
+  }
+
+  /// Finds the first (if `find_first`) or next assignment of subject arguments to the unbound top variables.
+  fn solve_variables(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    self.retract_solution(context);
+
+    if find_first {
+      // Aliens may have bound some of our top variables, so account for those first.
+      let mut cover = self.current_cover.clone();
+      let mut unbound: Vec<TopVariable> = Vec::with_capacity(self.top_variables.len());
+
+      for top_variable in &self.top_variables {
+        match context.substitution.get(top_variable.index) {
+          Some(value) => {
+            if !cover_subject(self.acu_symbol(), &self.subjects, &**value.borrow(), &mut cover) {
+              return false;
+            }
+          }
+          None => unbound.push(top_variable.clone()),
+        }
+      }
+      assert!(unbound.len() < 32, "too many top variables in idempotent ACU pattern");
+
+      self.variable_cover = cover;
+      self.unbound = unbound;
+      if !self.first_masks() {
+        return false;
+      }
+    } else if !self.next_masks() {
+      return false;
+    }
+
+    loop {
+      if self.masks_valid() {
+        if self.bind_solution(context) {
+          return true;
+        }
+        self.retract_solution(context);
+      }
+      if !self.next_masks() {
+        return false;
+      }
+    }
+  }
+}
+
+impl Subproblem for ACUIdempotentSubproblem {
+  fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    let alien_count = self.aliens.len() as isize;
+    // Level `alien_count` is the variable stage.
+    let mut level: isize;
+    let mut forward = find_first;
+
+    if find_first {
+      self.current_cover = self.initial_cover.clone();
+      if !self.build_edges(context) {
+        return false;
+      }
+      level = 0;
+    } else {
+      level = alien_count;
+    }
+
+    loop {
+      if level < 0 {
+        return false;
+      }
+
+      let solved = if level == alien_count {
+        self.solve_variables(forward, context)
+      } else {
+        self.solve_alien(level as usize, forward, context)
+      };
+
+      if solved {
+        if level == alien_count {
+          return true;
+        }
+        level += 1;
+        forward = true;
+      } else {
+        level -= 1;
+        forward = false;
+      }
+    }
+  }
+}
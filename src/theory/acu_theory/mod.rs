@@ -1,22 +1,237 @@
-#![allow(unused_imports)]
 /*!
 
+The ACU theory: binary symbols that are associative, commutative, and optionally have an identity element. Terms and
+DAG nodes with an ACU top symbol are flattened, so they are represented as a multiset of arguments, each paired with
+its multiplicity.
 
+Matching is done by the `ACULHSAutomaton`, which eliminates whatever it can deterministically (ground aliens, bound
+variables, the lone variable case) and hands the rest to an `ACUSubproblem`, or, if only top variables are left, to an
+`ACUDistributionSubproblem`. Idempotent symbols are matched by an `ACUIdempotentSubproblem` instead.
 
- */
+Unification modulo ACU is done by an `ACUUnificationSubproblem`, which enumerates a complete set of unifiers from the
+basis of a linear Diophantine equation.
 
+*/
 
+mod automaton;
+mod bipartite_graph;
 mod dag_node;
+mod distribution_subproblem;
+mod extension_info;
+mod idempotent_subproblem;
 mod red_black_tree;
-mod term;
 mod subproblem;
-mod automaton;
 mod symbol;
-mod extension_info;
+mod term;
+mod unification_subproblem;
+
+pub use automaton::{ACULHSAutomaton, ACURHSAutomaton};
+pub use dag_node::{ACUArguments, ACUDagNode, NormalizationStatus, RcACUDagNode};
+pub use distribution_subproblem::ACUDistributionSubproblem;
+pub use extension_info::ACUExtensionInfo;
+pub use idempotent_subproblem::ACUIdempotentSubproblem;
+pub use red_black_tree::{RcRedBlackTree, RedBlackTree};
+pub use subproblem::ACUSubproblem;
+pub use symbol::{ACUSymbol, RcACUSymbol};
+pub use term::{ACUTerm, ACUTermRecord, RcACUTerm};
+pub use unification_subproblem::ACUUnificationSubproblem;
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::RcCell,
+    rc_cell,
+    theory::{
+      free_theory::FreeTerm,
+      test_fixtures::{
+        all_matches,
+        make_acu_symbol,
+        make_acu_symbol_with_type,
+        make_constant_symbol,
+        make_free_symbol,
+        make_sorts,
+        make_variable,
+      },
+      DagNode,
+      RcDagNode,
+      RcSymbol,
+      RcTerm,
+      SymbolAttribute,
+      SymbolType,
+      Term,
+    },
+  };
+
+  /// Makes the DAG for `f(args)`, giving every argument the sort `Elt`.
+  fn make_subject(f: &RcSymbol, args: &[&RcSymbol]) -> RcDagNode {
+    let args: Vec<RcTerm> = args
+      .iter()
+      .map(|symbol| {
+        let term: RcTerm = rc_cell!(FreeTerm::new((*symbol).clone()));
+        term
+      })
+      .collect();
+    make_subject_from_terms(f, args)
+  }
+
+  /// Makes the DAG for `f(args)`, giving every node below the top the sort `Elt`.
+  fn make_subject_from_terms(f: &RcSymbol, args: Vec<RcTerm>) -> RcDagNode {
+    fn set_sorts(dag_node: &RcDagNode) {
+      for arg in dag_node.borrow().iter_args() {
+        arg.borrow_mut().set_sort_index(1);
+        set_sorts(&arg);
+      }
+    }
+
+    let mut term = ACUTerm::new(f.clone(), args);
+    term.normalize(true);
+
+    let subject = term.make_dag();
+    set_sorts(&subject);
+    subject
+  }
+
+  #[test]
+  fn match_with_ground_alien_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol(&elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+    let c = make_constant_symbol("c", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+    let a_term: RcTerm = rc_cell!(FreeTerm::new(a.clone()));
+
+    // f(X, a, Y) against f(a, b, c) has the two solutions X=b, Y=c and X=c, Y=b.
+    let mut pattern = ACUTerm::new(f.clone(), vec![x.clone(), a_term.clone(), y.clone()]);
+    let subject = make_subject(&f, &[&a, &b, &c]);
+    let solutions = all_matches(&mut pattern, subject, 2);
+
+    assert_eq!(solutions.len(), 2);
+    for solution in &solutions {
+      let x_value = solution[0].borrow();
+      let y_value = solution[1].borrow();
+      assert!(x_value.compare(&**y_value).is_ne());
+      for value in [&x_value, &y_value] {
+        let symbol = value.symbol();
+        assert!(symbol.as_ref() == b.as_ref() || symbol.as_ref() == c.as_ref());
+      }
+    }
+    assert!(solutions[0][0].borrow().compare(&**solutions[1][0].borrow()).is_ne());
+
+    // f(X, a, Y) does not match f(b, c), because there is no `a` to match the ground alien.
+    let mut pattern = ACUTerm::new(f.clone(), vec![x.clone(), a_term.clone(), y.clone()]);
+    let subject = make_subject(&f, &[&b, &c]);
+    assert!(all_matches(&mut pattern, subject, 2).is_empty());
+  }
+
+  #[test]
+  fn match_lone_variable_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol(&elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let a_term: RcTerm = rc_cell!(FreeTerm::new(a.clone()));
+
+    // f(X, a) against f(a, b) binds X to b without a subproblem.
+    let mut pattern = ACUTerm::new(f.clone(), vec![x, a_term]);
+    let subject = make_subject(&f, &[&a, &b]);
+    let solutions = all_matches(&mut pattern, subject, 1);
+
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().symbol().as_ref() == b.as_ref());
+  }
+
+  #[test]
+  fn match_distribution_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol(&elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+    let c = make_constant_symbol("c", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+
+    // f(X, X, Y) against f(a, a, b) has the single solution X=a, Y=b.
+    let mut pattern = ACUTerm::new(f.clone(), vec![x.clone(), x.clone(), y.clone()]);
+    let subject = make_subject(&f, &[&a, &a, &b]);
+    let solutions = all_matches(&mut pattern, subject, 2);
+
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().symbol().as_ref() == a.as_ref());
+    assert!(solutions[0][1].borrow().symbol().as_ref() == b.as_ref());
+
+    // No argument of f(a, b, c) occurs twice, so X has nothing to take.
+    let mut pattern = ACUTerm::new(f.clone(), vec![x.clone(), x.clone(), y.clone()]);
+    let subject = make_subject(&f, &[&a, &b, &c]);
+    assert!(all_matches(&mut pattern, subject, 2).is_empty());
+  }
+
+  #[test]
+  fn match_non_ground_alien_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol(&elt);
+    let g = make_free_symbol("g", 1, &elt);
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+    let c = make_constant_symbol("c", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+    let constant = |symbol: &RcSymbol| -> RcTerm { rc_cell!(FreeTerm::new(symbol.clone())) };
+    let g_of = |arg: RcTerm| -> RcTerm { rc_cell!(FreeTerm::with_args(g.clone(), vec![arg])) };
+
+    // The alien g(X) leaves f(g(X), Y) to an ACUSubproblem. Against f(g(a), b, c), g(X) can only take g(a), so the
+    // single solution is X=a, Y=f(b, c).
+    let mut pattern = ACUTerm::new(f.clone(), vec![g_of(x.clone()), y.clone()]);
+    let subject = make_subject_from_terms(&f, vec![g_of(constant(&a)), constant(&b), constant(&c)]);
+    let solutions = all_matches(&mut pattern, subject, 2);
+
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().symbol().as_ref() == a.as_ref());
+    assert!(solutions[0][1].borrow().compare(&**make_subject(&f, &[&b, &c]).borrow()).is_eq());
+
+    // Without a g(...) in the subject, the alien matches nothing.
+    let mut pattern = ACUTerm::new(f.clone(), vec![g_of(x.clone()), y.clone()]);
+    let subject = make_subject(&f, &[&a, &b, &c]);
+    assert!(all_matches(&mut pattern, subject, 2).is_empty());
+  }
+
+  #[test]
+  fn idempotent_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol_with_type(&elt, SymbolType::from(SymbolAttribute::Idem));
+    let a = make_constant_symbol("a", &elt);
+    let b = make_constant_symbol("b", &elt);
+
+    // f(a, a, b) normalizes to f(a, b), and f(a, a) collapses to a.
+    let subject = make_subject(&f, &[&a, &a, &b]);
+    assert!(subject.borrow().compare(&**make_subject(&f, &[&a, &b]).borrow()).is_eq());
+    let collapsed = make_subject(&f, &[&a, &a]);
+    assert!(collapsed.borrow().symbol().as_ref() == a.as_ref());
+    collapsed.borrow_mut().set_sort_index(1);
+
+    // f(X, a) matches a by collapsing, with X=a, since f(a, a) is a.
+    let x = make_variable("X", 0, &elt);
+    let a_term: RcTerm = rc_cell!(FreeTerm::new(a.clone()));
+    let mut pattern = ACUTerm::new(f.clone(), vec![x.clone(), a_term.clone()]);
+    let solutions = all_matches(&mut pattern, collapsed, 1);
+
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().symbol().as_ref() == a.as_ref());
 
-pub(crate) use dag_node::ACUDagNode;
-pub(crate) use red_black_tree::{RedBlackTree, RcRedBlackTree};
-pub(crate) use term::ACUTerm;
-pub(crate) use symbol::{ACUSymbol, RcACUSymbol};
-pub(crate) use subproblem::ACUSubproblem;
-pub(crate) use automaton::lhs_automaton::ACULHSAutomaton;
+    // The pattern f(a, b, a) is f(a, b), so it matches f(a, b).
+    let ground = make_subject(&f, &[&a, &b]);
+    let b_term: RcTerm = rc_cell!(FreeTerm::new(b.clone()));
+    let mut pattern = ACUTerm::new(f.clone(), vec![a_term.clone(), b_term, a_term]);
+    assert_eq!(all_matches(&mut pattern, ground, 0).len(), 1);
+  }
+}
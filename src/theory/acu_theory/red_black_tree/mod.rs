@@ -1,16 +1,11 @@
 /*!
 
-
+The red-black tree argument representation used by `ACUDagNode`s with many arguments.
 
  */
 
-
-
-
-
-
-mod red_black_tree;
 mod node;
+mod red_black_tree;
 
-pub use red_black_tree::{RedBlackTree, RcRedBlackTree, RBTreeAdapter};
-pub use node::RedBlackNode;
+pub use node::{DagNodeKey, RedBlackNode};
+pub use red_black_tree::{RBTreeAdapter, RcRedBlackTree, RedBlackTree};
@@ -1,93 +1,82 @@
 /*!
 
-
+A node of the red-black tree used to store the arguments of large ACU DAG nodes. Nodes are ordered by their
+`DagNode`, so the tree is a sorted multiset with the multiplicity stored in the node.
 
  */
-use std::borrow::Borrow;
-use std::cmp::Ordering;
+use std::{
+  borrow::Borrow,
+  cell::{Cell, Ref},
+  cmp::Ordering,
+};
 
 use intrusive_collections::RBTreeLink;
 
-use crate::theory::dag_node::{RcDagNode, DagNode};
+use crate::theory::{DagNode, RcDagNode};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum RedBlackNodeFlags {
-  Color = 0,
+  Color   = 0,
 
-  Marked = 6,
+  Marked  = 6,
   Collect = 7,
 }
 
-#[derive(Clone)]
 pub struct RedBlackNode {
-  pub dag_node        : RcDagNode,
-  pub multiplicity    : u32,
-  // pub max_multiplicity: u32,
-  pub link            : RBTreeLink,
-  pub flags            : u8
+  pub dag_node:     RcDagNode,
+  /// Multiplicities are updated in place, so they live in a `Cell`.
+  pub multiplicity: Cell<u32>,
+  pub link:         RBTreeLink,
+  pub flags:        u8,
 }
 
 impl RedBlackNode {
-
   pub fn new(dag_node: RcDagNode, multiplicity: u32) -> Self {
-    RedBlackNode{
+    RedBlackNode {
       dag_node,
-      multiplicity,
-      // max_multiplicity: 0,
+      multiplicity: Cell::new(multiplicity),
       link: RBTreeLink::default(),
-      flags: 0
+      flags: 0,
     }
   }
-/*
-  pub fn get_child(&self, sign: u32) -> Option<&Box<RedBlackNode>> {
-    if sign < 0 {
-      if let Some(&left) = self.left {
-        return Some(left);
-      }
-    } else {
-      if let Some(&right) = self.right {
-        return Some(right);
-      }
-    }
 
-    return None;
+  #[inline(always)]
+  pub fn multiplicity(&self) -> u32 {
+    self.multiplicity.get()
   }
 
-  /// Moves all `DagNode`s onto the `nodes` vector in order, consuming self.
-  pub fn vectorize(self, nodes: &mut Vec<DagPair>) {
-    if let Some(left) = self.left {
-      left.vectorize(nodes);
-    }
-    let dag_pair = DagPair{
-      dag_node: self.dag_node,
-      multiplicity: self.multiplicity
-    };
-    nodes.push(dag_pair);
-    if let Some(right) = self.right {
-      right.vectorize(nodes);
-    }
+  #[inline(always)]
+  pub fn set_multiplicity(&self, multiplicity: u32) {
+    self.multiplicity.set(multiplicity)
   }
-  */
 }
 
-impl Eq for RedBlackNode{}
+/// The key used by the tree adapter. It holds a borrow of the node's `DagNode` for the duration of the comparison.
+pub struct DagNodeKey<'a>(pub(crate) Ref<'a, dyn DagNode + 'static>);
 
-impl PartialEq<Self> for RedBlackNode {
+impl<'a> Borrow<dyn DagNode> for DagNodeKey<'a> {
+  fn borrow(&self) -> &(dyn DagNode + 'static) {
+    &*self.0
+  }
+}
+
+impl<'a> PartialEq for DagNodeKey<'a> {
   fn eq(&self, other: &Self) -> bool {
-    self.dag_node.as_ref().borrow().eq(other.dag_node.as_ref())
+    self.0.compare(&*other.0) == Ordering::Equal
   }
 }
 
-impl PartialOrd for RedBlackNode {
-  fn partial_cmp(&self, other: &RedBlackNode) -> Option<Ordering> {
+impl<'a> Eq for DagNodeKey<'a> {}
+
+impl<'a> PartialOrd for DagNodeKey<'a> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
     Some(self.cmp(other))
   }
 }
 
-impl Ord for RedBlackNode {
+impl<'a> Ord for DagNodeKey<'a> {
   fn cmp(&self, other: &Self) -> Ordering {
-    self.dag_node.as_ref().borrow().cmp(other.dag_node.as_ref())
+    self.0.compare(&*other.0)
   }
 }
-
@@ -1,81 +1,115 @@
 /*!
 
-
+A red-black tree of `(DagNode, multiplicity)` pairs ordered by `DagNode::compare`. Maude stores the arguments of
+large ACU DAG nodes in a persistent red-black tree so that matching and rewriting can delete a few arguments from a
+huge multiset without copying the whole argument vector. We use `intrusive_collections` for the balancing logic.
 
  */
-use std::cell::{Cell, RefCell};
-use std::cmp::Ordering;
-use std::rc::Rc;
+use std::{cell::Ref, cmp::Ordering, rc::Rc};
 
 use intrusive_collections::{
-  RBTreeLink,
-  RBTree,
-  KeyAdapter,
   intrusive_adapter,
-  Adapter,
+  rbtree::{Cursor, CursorMut},
   Bound,
-  rbtree::{Cursor, CursorMut}
+  KeyAdapter,
+  RBTree,
+  RBTreeLink,
 };
 
-use crate::abstractions::RcCell;
+use super::{node::DagNodeKey, RedBlackNode};
 use crate::{
-  core::Substitution,
-  theory::{
-    DagNode,
-    Term,
-    DagPair,
-    dag_node::RcDagNode
-  }
+  abstractions::RcCell,
+  theory::{DagNode, DagPair, RcDagNode, Term},
 };
-use super::RedBlackNode;
 
 
 pub type RcRedBlackTree = RcCell<RedBlackTree>;
 
 
 intrusive_adapter!(pub RBTreeAdapter = Rc<RedBlackNode>: RedBlackNode { link: RBTreeLink });
+
 impl<'a> KeyAdapter<'a> for RBTreeAdapter {
-  type Key = u32;
-  fn get_key(&self, x: &'a RedBlackNode) -> u32 { x.dag_node.symbol().get_hash_value() }
+  type Key = DagNodeKey<'a>;
+
+  fn get_key(&self, x: &'a RedBlackNode) -> DagNodeKey<'a> {
+    DagNodeKey(Ref::map(x.dag_node.borrow(), |dag_node| &**dag_node))
+  }
 }
 
 
 pub struct RedBlackTree {
-  // root: &'a Cell<RedBlackNode>,
-  rb_tree: RBTree<RBTreeAdapter>,
-  pub(crate) size: usize, // Todo: Construction methods need to update this.
+  rb_tree:         RBTree<RBTreeAdapter>,
+  pub(crate) size: usize,
 }
 
-impl RedBlackTree {
+impl Default for RedBlackTree {
+  fn default() -> Self {
+    RedBlackTree {
+      rb_tree: RBTree::new(RBTreeAdapter::new()),
+      size:    0,
+    }
+  }
+}
 
-  pub fn new(root: RcDagNode, multiplicity: u32) -> Self {
-    let root = RcCell::new(
-      RedBlackNode::new(root, multiplicity)
-    );
+impl Clone for RedBlackTree {
+  /// The nodes are intrusive, so a clone has to rebuild the tree. The `DagNode`s themselves are shared.
+  fn clone(&self) -> Self {
+    RedBlackTree::from_pairs(self.iter().map(|(dag_node, multiplicity)| DagPair { dag_node, multiplicity }))
+  }
+}
 
-    let mut tree = RedBlackTree {
-      // root: root.borrow(),
-      rb_tree: RBTree::new(RBTreeAdapter::new()),
-      size   : 0,
-    };
+impl RedBlackTree {
+  pub fn new(root: RcDagNode, multiplicity: u32) -> Self {
+    let mut tree = RedBlackTree::default();
+    tree.insert_multiplicity(root, multiplicity);
+    tree
+  }
 
-    tree.rb_tree.insert(root);
+  /// Builds a tree from pairs. The pairs need not be sorted or unique.
+  pub fn from_pairs(pairs: impl IntoIterator<Item = DagPair>) -> Self {
+    let mut tree = RedBlackTree::default();
+    for pair in pairs {
+      tree.insert_multiplicity(pair.dag_node, pair.multiplicity);
+    }
     tree
   }
 
+  #[inline(always)]
+  pub fn len(&self) -> usize {
+    self.size
+  }
+
+  #[inline(always)]
+  pub fn is_empty(&self) -> bool {
+    self.size == 0
+  }
+
   pub fn clear(&mut self) {
     self.rb_tree.clear();
     self.size = 0;
   }
 
+  #[inline(always)]
   pub fn insert(&mut self, dag_node: RcDagNode) {
-    let node = Rc::new(RedBlackNode::new(dag_node, 1));
-    self.rb_tree.insert(node);
+    self.insert_multiplicity(dag_node, 1);
+  }
+
+  /// Inserts `multiplicity` copies of `dag_node`, merging with an existing equal node if there is one.
+  pub fn insert_multiplicity(&mut self, dag_node: RcDagNode, multiplicity: u32) {
+    if multiplicity == 0 {
+      return;
+    }
+    if let Some(found) = self.find(&**dag_node.borrow()).and_then(|cursor| cursor.get()) {
+      found.set_multiplicity(found.multiplicity() + multiplicity);
+      return;
+    }
+    self.rb_tree.insert(Rc::new(RedBlackNode::new(dag_node, multiplicity)));
+    self.size += 1;
   }
 
   /// Gets the multiplicity of the first node in the tree. If size==1, that would be the only multiplicity in the tree.
   pub fn get_sole_multiplicity(&self) -> u32 {
-    self.rb_tree.front().get().unwrap().multiplicity
+    self.rb_tree.front().get().unwrap().multiplicity()
   }
 
   /// Gets the first node in the tree. If size==1, that would be the only node in the tree.
@@ -83,102 +117,56 @@ impl RedBlackTree {
     self.rb_tree.front().get().unwrap().dag_node.clone()
   }
 
-  pub fn is_reduced(&self) -> bool {
-    // Todo: Imnplement `is_reduced()` for RedBlackTree. In Maude it is a property of a DagNode, and a tree is reduced if its
-    //       root is.
-    true
-  }
-
   pub fn max_multiplicity(&self) -> u32 {
-    self.rb_tree
-        .iter()
-        .map(|node| node.multiplicity)
-        .max()
-        .unwrap_or(0)
+    self.rb_tree.iter().map(|node| node.multiplicity()).max().unwrap_or(0)
   }
 
-  // Todo: Why not have `find*` methods take a `&Symbol` instead of a `Term` or `DagNode`? Then we'd only need one set.
-
   /// If found, returns a cursor to the node for the key.
-  pub fn find_term(&self, key: &dyn Term) -> Option<Cursor<RBTreeAdapter>> {
-    let mut cursor: Cursor<RBTreeAdapter> = self.rb_tree.find(&key.symbol().get_hash_value());
-    if let Some(found) = cursor.get() {
-      // The result, if exists, just has same top symbol. Now compare arguments as well.
-      let r =  key.compare_dag_arguments(found.dag_node.as_ref());
-      if r == Ordering::Equal {
-        return Some(cursor);
-      }
-    }
-
-    return None;
-  }
-
-  /// If found, returns a cursor to the node for the key.
-  pub fn find(&self, key: &dyn DagNode) -> Option<Cursor<RBTreeAdapter>> {
-    let mut cursor: Cursor<RBTreeAdapter> = self.rb_tree.find(&key.symbol().get_hash_value());
-    if let Some(found) = cursor.get() {
-      // The result, if exists, just has same top symbol. Now compare arguments as well.
-      let r =  key.compare(found.dag_node.as_ref());
-      if r ==  Ordering::Equal {
-        return Some(cursor);
-      }
-    }
-
-    return None;
-  }
-
-  /// Same as above, but returns a `CursorMut` instead of a `Cursor`.
-  pub fn find_term_mut(&mut self, key: &dyn Term) -> Option<CursorMut<RBTreeAdapter>> {
-    let mut cursor: CursorMut<RBTreeAdapter> = self.rb_tree.find_mut(&key.symbol().get_hash_value());
-    if let Some(found) = cursor.get() {
-      // The result, if exists, just has same top symbol. Now compare arguments as well.
-      let r =  key.compare_dag_node(found.dag_node.as_ref());
-      if r == Ordering::Equal {
-        return Some(cursor);
-      }
+  pub fn find(&self, key: &(dyn DagNode + 'static)) -> Option<Cursor<RBTreeAdapter>> {
+    let cursor = self.rb_tree.find(key);
+    if cursor.is_null() {
+      None
+    } else {
+      Some(cursor)
     }
-
-    return None;
   }
 
   /// Same as above, but returns a `CursorMut` instead of a `Cursor`.
-  pub fn find_mut(&mut self, key: &dyn DagNode) -> Option<CursorMut<RBTreeAdapter>> {
-    let mut cursor: CursorMut<RBTreeAdapter> = self.rb_tree.find_mut(&key.symbol().get_hash_value());
-    if let Some(found) = cursor.get() {
-      // The result, if exists, just has same top symbol. Now compare arguments as well.
-      let r =  key.compare(found.dag_node.as_ref());
-      if r == Ordering::Equal {
-        return Some(cursor);
-      }
-    }
-
-    return None;
-  }
-
-  ///	Return a cursor to the leftmost RedBlackNode that is a potential match for key, given the partial substitution
-  /// for key's variables.
-  // Todo: It looks like FreeTerm is the only subclass that overrides `Term::partialCompare()`. Nothing else seems to
-  //       use the `partial` `Substitution` parameter at all, and as far as I can tell, `Term::partialCompare()`
-  //       never returns `UNDECIDED`. Therefore, "partialCompare" is actually just "compare", and this method is find
-  //       the g.l.b. of `key` in `self`.
-  // Todo: What we need to do is return an iterator pointing to the mutable result. Then we can modify the multiplicity
-  //       the result and advance the iterator.
-  pub(crate) fn find_first_potential_match(&mut self, key: &dyn Term, _partial: &mut Substitution)
-    -> Option<CursorMut<RBTreeAdapter>>
-  {
-    let mut cursor = self.rb_tree.upper_bound_mut(Bound::Included(&key.symbol().get_hash_value()));
-    if cursor.is_null(){
+  pub fn find_mut(&mut self, key: &(dyn DagNode + 'static)) -> Option<CursorMut<RBTreeAdapter>> {
+    let cursor = self.rb_tree.find_mut(key);
+    if cursor.is_null() {
       None
     } else {
       Some(cursor)
     }
   }
 
+  /// Returns the multiplicity of `key` in the tree, which is zero if `key` is absent.
+  pub fn multiplicity_of(&self, key: &(dyn DagNode + 'static)) -> u32 {
+    self
+      .find(key)
+      .and_then(|cursor| cursor.get().map(|node| node.multiplicity()))
+      .unwrap_or(0)
+  }
+
+  /// Searches for a `DagNode` equal to the given term. Terms and `DagNode`s are ordered consistently, so we can stop
+  /// as soon as we pass the place the term would be.
+  // Todo: Terms can't be used as keys of the tree, so this is a linear scan. Ground aliens could be dagified at compile
+  //       time instead.
+  pub fn find_term(&self, key: &dyn Term) -> Option<(RcDagNode, u32)> {
+    for node in self.rb_tree.iter() {
+      match key.compare_dag_node(&**node.dag_node.borrow()) {
+        Ordering::Equal => return Some((node.dag_node.clone(), node.multiplicity())),
+        Ordering::Less => return None,
+        Ordering::Greater => continue,
+      }
+    }
+    None
+  }
 
-  /// Return a mutable cursor pointing to the first node with multiplicity >= the given multiplicity.
-  pub(crate) fn find_greater_equal_multiplicity(&mut self, multiplicity: u32) -> Option<CursorMut<RBTreeAdapter>> {
-    let mut cursor = self.rb_tree.lower_bound_mut(Bound::Included(&multiplicity));
-
+  /// Returns the first node with a `DagNode` at least as large as `key`.
+  pub(crate) fn lower_bound(&self, key: &(dyn DagNode + 'static)) -> Option<Cursor<RBTreeAdapter>> {
+    let cursor = self.rb_tree.lower_bound(Bound::Included(key));
     if cursor.is_null() {
       None
     } else {
@@ -186,117 +174,55 @@ impl RedBlackTree {
     }
   }
 
-
-  /// Moves all nodes of the tree into a vector, consuming self, and returns the vector.
-  pub fn vectorize(mut self) -> Vec<DagPair> {
+  /// Returns the pairs of the tree as a sorted vector. The tree is left intact.
+  pub fn vectorize(&self) -> Vec<DagPair> {
     let mut vector = Vec::with_capacity(self.size);
-    let mut cursor = self.rb_tree.front_mut();
-
-    // This isn't very efficient, but deletion has amortized complexity O(1), so it's probably okay.
-    while let Some(mut node_ptr) = cursor.remove() {
-      // let mut node = Cell::<RedBlackNode>::get(node_ptr);
-      vector.push(
-        DagPair {
-          dag_node: node_ptr.dag_node,
-          multiplicity: node_ptr.multiplicity
-        }
-      );
+    for node in self.rb_tree.iter() {
+      vector.push(DagPair {
+        dag_node:     node.dag_node.clone(),
+        multiplicity: node.multiplicity(),
+      });
     }
-
     vector
   }
 
-  /// Deletes `multiplicity` copies of key.
-  pub fn delete_multiplicity(&mut self, key: &dyn DagNode, multiplicity: u32) {
-    let mut cursor = self.rb_tree.find_mut(&key.symbol().get_hash_value());
-    if !cursor.is_null(){
-      self.delete_multiplicity_at_cursor(cursor, multiplicity);
+  /// Deletes `multiplicity` copies of key. Returns `false` if there are fewer than `multiplicity` copies, in which
+  /// case the tree is unchanged.
+  pub fn delete_multiplicity(&mut self, key: &(dyn DagNode + 'static), multiplicity: u32) -> bool {
+    let cursor = self.rb_tree.find_mut(key);
+    if cursor.is_null() {
+      return multiplicity == 0;
     }
-  }
-
-  /// Same as `delete_multiplicity(..)` but takes a cursor pointing to the dagnode to delete. This avoids a search of
-  /// the tree for the DagNode. Whether or not the original node
-  pub fn delete_multiplicity_at_cursor(&mut self, mut cursor: CursorMut<RBTreeAdapter>, multiplicity: u32) {
-    // let mut cursor = self.rb_tree.find_mut(&key.symbol().get_hash_value());
-    if let Some(victim_ref) = cursor.get() {
-      // CursorMut::get(&self)
-
-      let new_multiplicity = victim_ref.multiplicity - multiplicity;
+    RedBlackTree::delete_multiplicity_at_cursor(cursor, &mut self.size, multiplicity)
+  }
+
+  /// Same as `delete_multiplicity(..)` but takes a cursor pointing to the `DagNode` to delete. This avoids a search
+  /// of the tree for the `DagNode`. The cursor borrows the tree, so the caller passes in the tree's `size` separately.
+  pub fn delete_multiplicity_at_cursor(
+    mut cursor: CursorMut<RBTreeAdapter>,
+    size: &mut usize,
+    multiplicity: u32,
+  ) -> bool {
+    let current = match cursor.get() {
+      Some(victim) => victim.multiplicity(),
+      None => return false,
+    };
+    if current < multiplicity {
       // Cannot delete more than exist.
-      assert!(new_multiplicity >= 0);
-
-      if new_multiplicity > 0 {
-        // Leaving some remaining
-
-        // Todo: There is no way to update max_multiplicity with this Red-Black tree implementation, because we don't
-        //       have access to the left and right child nodes.
-        // let mut max_multiplicity = new_multiplicity;
-
-        let replacement = RedBlackNode::new(victim_ref.dag_node.clone(), new_multiplicity);
-        cursor.replace_with(Rc::new(replacement));
-
-      } else {
-        // We remove the node.
-        cursor.remove();
-        // And adjust the size accordingly.
-        self.size -= 1;
-      }
-
-    }
-  }
-
-
-  /// Iterates over the nodes and their multiplicities.
-  pub fn iter(&self) -> impl Iterator<Item=(RcDagNode, u32)> + '_ {
-    // RedBlackTreeIterator::new(
-    //   self.root.as_ref()
-    // ).map(|node| (node.dag_node.as_ref(), node.multiplicity))
-    self.rb_tree
-        .iter()
-        .map(|node|
-            (
-              node.dag_node.clone(),
-              node.multiplicity
-            )
-        )
-  }
-}
-
-/*
-struct RedBlackTreeIterator<'a>{
-  stack: Vec<&'a RedBlackNode>,
-}
-
-impl<'a> RedBlackTreeIterator<'a> {
-  pub fn new(root: &'a RedBlackNode) -> Self {
-    RedBlackTreeIterator {
-      stack: vec![root],
-    }
-  }
-
-  fn stack_leftmost_path(&mut self, mut root: &'a RedBlackNode) {
-    self.stack.push(root);
-    while let Some(node)  = &root.left {
-      self.stack.push(node);
-      root = node;
+      return false;
     }
-  }
-}
 
-impl<'a> Iterator for RedBlackTreeIterator<'a> {
-  type Item = &'a RedBlackNode;
-
-  fn next(&mut self) -> Option<&'a RedBlackNode> {
-    if let Some(top_node) = self.stack.pop().unwrap(){
-      if let Some(r) = top_node.right.as_ref() {
-        self.stack_leftmost_path(r);
-      }
-      Some(top_node)
+    if current > multiplicity {
+      cursor.get().unwrap().set_multiplicity(current - multiplicity);
     } else {
-      None
+      cursor.remove();
+      *size -= 1;
     }
-
+    true
   }
 
+  /// Iterates over the nodes and their multiplicities in order.
+  pub fn iter(&self) -> impl Iterator<Item = (RcDagNode, u32)> + '_ {
+    self.rb_tree.iter().map(|node| (node.dag_node.clone(), node.multiplicity()))
+  }
 }
-*/
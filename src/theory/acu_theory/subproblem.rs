@@ -1,130 +1,394 @@
 /*!
 
- The structures described here correspond roughly to Fig. 1 of [Eker 1995].
+The matching subproblem for the ACU theory. The `ACULHSAutomaton` has already eliminated ground aliens and bound top
+variables from the subject multiset. What is left is to
 
- [Eker 1995]: Eker, Steven. “Associative-Commutative Matching Via Bipartite Graph Matching.” Comput. J. 38 (1995): 381-399.
+  1. match each non-ground alien against some subject with enough remaining multiplicity, and then
+  2. distribute what remains of the subject among the unbound top variables.
 
+For the first stage we build a bipartite graph with an edge from each alien to each subject it matches in isolation.
+Each edge remembers the bindings the match made and any subproblem it left behind. If the graph has no matching that
+covers every alien, found with augmenting paths, there is no solution and we never start enumerating. Otherwise the
+aliens are searched depth first over their edges, most constrained alien first, so the individual alien matches are
+computed only once no matter how often the search revisits them.
 
+For each complete choice of aliens the distributions of what is left among the unbound top variables are enumerated by
+an `ACUDistributionSubproblem`.
 
+ The structures described here correspond roughly to Fig. 1 of [Eker 1995].
 
- */
+ [Eker 1995]: Eker, Steven. “Associative-Commutative Matching Via Bipartite Graph Matching.” Comput. J. 38 (1995): 381-399.
 
-use diophantine::DiophantineSystem;
+ */
 
+use super::{
+  automaton::{NonGroundAlien, TopVariable},
+  bipartite_graph::BipartiteGraph,
+  dag_node::eliminate_subject,
+  distribution_subproblem::ACUDistributionSubproblem,
+  ACUSymbol,
+};
 use crate::{
-  theory::{
-    LHSAutomaton,
-    RcDagNode,
-    Term,
-    Subproblem
-  },
-  core::{
-    Substitution,
-    RewritingContext,
-    LocalBindings, RcSort
-  }
+  core::{rewrite_context::RewritingContext, LocalBindings},
+  theory::{DagPair, MaybeSubproblem, RcSymbol, Subproblem},
 };
 
-use super::red_black_tree::RedBlackTree;
-use super::extension_info::ACUExtensionInfo;
-
-
-/// Private TopVariable type for subproblem.
-struct TopVariable {
-  index: u32,
-  multiplicity: u32,
-  lower_bound: u32,
-  upper_bound: u32,
-  sort: RcSort
-}
-
 
+/// An edge of the bipartite graph, from a non-ground alien to a subject it matches.
 struct Edge {
-  target: u32,
-  difference: Box<LocalBindings>,
-  subproblem: Box<dyn Subproblem>
+  /// The index of the subject in `ACUSubproblem::subjects`.
+  target:     usize,
+  /// The bindings made by matching the alien against the target.
+  difference: Option<LocalBindings>,
+  subproblem: MaybeSubproblem,
 }
 
-
+/// A non-ground alien together with its edges in the bipartite graph.
 struct PatternNode {
-  multiplicity : u32,
-  edges        : Vec<Edge>,
-  selected_edge: u32
-}
-
-impl PatternNode {
-  pub fn solve(&mut self, find_first: bool, solutuion: &mut RewritingContext, current_multiplicity: &mut Vec<u32>) -> bool {
-    true
-  }
+  alien:    NonGroundAlien,
+  edges:    Vec<Edge>,
+  /// The index in `edges` of the edge currently in use.
+  selected: usize,
 }
 
 
 pub struct ACUSubproblem {
-  subject: RcDagNode,
-  extension_info: Option<ACUExtensionInfo>,
-
-  solved: bool,
-
+  top_symbol:           RcSymbol,
+  subjects:             Vec<DagPair>,
+  initial_multiplicity: Vec<u32>,
   current_multiplicity: Vec<u32>,
-  top_variables: Vec<TopVariable>,
-  pattern_nodes: Vec<PatternNode>, // Todo: Implement PatternNode
-
-  // Structures needed for building and solving diophantine problem
-  system: Option<DiophantineSystem>, // ToDo: Implement DiophantineSystem
-
-  /// For efficiency, the set of variable bindings at each stage in the recursion in both simplify and build_hierarchy
-  /// can be tracked by a single global array indexed by small integers representing variables.
-  variable_map: Vec<u32>,
-  subject_map: Vec<u32>,
-  after_multiplicity: Vec<u32>,
+  aliens:               Vec<NonGroundAlien>,
+  /// The bipartite graph, rebuilt whenever we are asked for a first solution.
+  pattern_nodes:        Vec<PatternNode>,
+  top_variables:        Vec<TopVariable>,
+  /// The variable stage for the current choice of aliens.
+  distribution:         Option<ACUDistributionSubproblem>,
 }
 
-/// The following are part of DelayedSubproblem in Maude, but ACUSubproblem is the only implementor.
 impl ACUSubproblem {
-
-  pub fn delayed_solve(&mut self, _find_first: bool, _context: &mut RewritingContext) -> bool {
-    // Todo: Implement ACUSubproblem::delayed_solve(..).
-    return false;
+  pub(crate) fn new(
+    top_symbol: RcSymbol,
+    subjects: Vec<DagPair>,
+    multiplicity: Vec<u32>,
+    aliens: Vec<NonGroundAlien>,
+    top_variables: Vec<TopVariable>,
+  ) -> Self {
+    ACUSubproblem {
+      top_symbol,
+      subjects,
+      current_multiplicity: multiplicity.clone(),
+      initial_multiplicity: multiplicity,
+      aliens,
+      pattern_nodes: Vec::new(),
+      top_variables,
+      distribution: None,
+    }
   }
 
   #[inline(always)]
-  pub fn is_solved(&self) -> bool{
-    return self.solved;
+  fn acu_symbol(&self) -> &ACUSymbol {
+    match self.top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+    }
   }
 
-  #[inline(always)]
-  pub fn set_solved(&mut self, solved: bool) {
-    self.solved = solved;
+  /// Matches every alien against every subject under the current substitution to build the bipartite graph. Returns
+  /// `false` if some alien matches nothing or the graph has no matching covering all aliens.
+  fn build_graph(&mut self, context: &mut RewritingContext) -> bool {
+    self.pattern_nodes.clear();
+
+    for alien in &self.aliens {
+      let mut edges = Vec::new();
+
+      for (j, subject) in self.subjects.iter().enumerate() {
+        if self.current_multiplicity[j] < alien.multiplicity {
+          continue;
+        }
+
+        let mut local = context.substitution.clone();
+        let (matched, subproblem) = alien
+          .lhs_automaton
+          .borrow_mut()
+          .match_(subject.dag_node.clone(), &mut local);
+        if matched {
+          edges.push(Edge {
+            target: j,
+            difference: local.subtract(&context.substitution),
+            subproblem,
+          });
+        }
+      }
+
+      if edges.is_empty() {
+        return false;
+      }
+      self.pattern_nodes.push(PatternNode {
+        alien: alien.clone(),
+        edges,
+        selected: 0,
+      });
+    }
+
+    let mut graph = BipartiteGraph::new(self.current_multiplicity.clone());
+    for node in &self.pattern_nodes {
+      graph.add_left_node(
+        node.edges.iter().map(|edge| edge.target).collect(),
+        node.alien.multiplicity,
+      );
+    }
+    if !graph.has_complete_matching() {
+      return false;
+    }
+
+    // Fail early: search the most constrained aliens first.
+    self.pattern_nodes.sort_by_key(|node| node.edges.len());
+    true
   }
 
-  #[inline(always)]
-  pub fn no_patterns(&self) -> bool{
-    self.pattern_nodes.is_empty()
+  /// Finds the first (if `find_first`) or next edge for the alien at `level` that is consistent with the choices made
+  /// at earlier levels.
+  fn solve_alien(&mut self, level: usize, find_first: bool, context: &mut RewritingContext) -> bool {
+    let node = &mut self.pattern_nodes[level];
+    let multiplicity = node.alien.multiplicity;
+    let mut start = 0;
+
+    if !find_first {
+      let edge = &mut node.edges[node.selected];
+      // First try for another solution to the edge's own subproblem.
+      if let Some(subproblem) = edge.subproblem.as_mut() {
+        if subproblem.solve(false, context) {
+          return true;
+        }
+      }
+      // Undo the current choice.
+      if let Some(difference) = edge.difference.as_mut() {
+        difference.retract(&mut context.substitution);
+      }
+      self.current_multiplicity[edge.target] += multiplicity;
+      start = node.selected + 1;
+    }
+
+    for e in start..node.edges.len() {
+      let edge = &mut node.edges[e];
+      if self.current_multiplicity[edge.target] < multiplicity {
+        continue;
+      }
+
+      if let Some(difference) = edge.difference.as_mut() {
+        if !difference.assert(&mut context.substitution) {
+          continue;
+        }
+      }
+      if let Some(subproblem) = edge.subproblem.as_mut() {
+        if !subproblem.solve(true, context) {
+          if let Some(difference) = edge.difference.as_mut() {
+            difference.retract(&mut context.substitution);
+          }
+          continue;
+        }
+      }
+
+      self.current_multiplicity[edge.target] -= multiplicity;
+      node.selected = e;
+      return true;
+    }
+
+    false
   }
 
+  /// Finds the first (if `find_first`) or next distribution of the remaining subjects among the top variables.
+  fn solve_variables(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    if find_first {
+      // Aliens may have bound some of our top variables, so eliminate those first.
+      let mut remaining = self.current_multiplicity.clone();
+      let mut unbound: Vec<TopVariable> = Vec::with_capacity(self.top_variables.len());
+
+      for top_variable in &self.top_variables {
+        match context.substitution.get(top_variable.index) {
+          Some(value) => {
+            if !eliminate_subject(
+              self.acu_symbol(),
+              &self.subjects,
+              &**value.borrow(),
+              top_variable.multiplicity,
+              &mut remaining,
+            ) {
+              return false;
+            }
+          }
+          None => unbound.push(top_variable.clone()),
+        }
+      }
+
+      self.distribution = Some(ACUDistributionSubproblem::new(
+        self.top_symbol.clone(),
+        &self.subjects,
+        &remaining,
+        unbound,
+      ));
+    }
+
+    let solved = match self.distribution.as_mut() {
+      Some(distribution) => distribution.solve(find_first, context),
+      None => false,
+    };
+    if !solved {
+      self.distribution = None;
+    }
+    solved
+  }
 }
 
 impl Subproblem for ACUSubproblem {
   fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
-    // Todo: Implement ACUSubproblem::solve(..).
-    false
+    let alien_count = self.aliens.len() as isize;
+    // Level `alien_count` is the variable distribution stage.
+    let mut level: isize;
+    let mut forward = find_first;
+
+    if find_first {
+      self.current_multiplicity = self.initial_multiplicity.clone();
+      if !self.build_graph(context) {
+        return false;
+      }
+      level = 0;
+    } else {
+      level = alien_count;
+    }
+
+    loop {
+      if level < 0 {
+        return false;
+      }
+
+      let solved = if level == alien_count {
+        self.solve_variables(forward, context)
+      } else {
+        self.solve_alien(level as usize, forward, context)
+      };
+
+      if solved {
+        if level == alien_count {
+          return true;
+        }
+        level += 1;
+        forward = true;
+      } else {
+        level -= 1;
+        forward = false;
+      }
+    }
   }
 }
 
-pub struct ACULazySubproblem<'a> {
-  pub(crate) subject      : &'a RedBlackTree,
-  pub(crate) current      : &'a mut RedBlackTree,
-  pub(crate) solution     : &'a mut Substitution,
-  pub(crate) lhs_automaton: &'a mut dyn LHSAutomaton,
-  pub(crate) term         : Option<&'a dyn Term>,
-  pub(crate) index        : u32,
-  pub(crate) sort         : RcSort
-}
 
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+  };
+
+  use super::*;
+  use crate::{
+    abstractions::{NatSet, RcCell},
+    core::{rewrite_context::RewritingContext, substitution::Substitution, VariableInfo},
+    rc_cell,
+    theory::{
+      free_theory::FreeTerm,
+      test_fixtures::{make_acu_symbol, make_constant_symbol, make_free_symbol, make_sorts, make_variable, solutions},
+      DagNode,
+      RcDagNode,
+      RcTerm,
+      Term,
+    },
+  };
+
+  /// Makes the DAG for `term`, giving every node below the top the sort `Elt`.
+  fn make_subject(term: &RcTerm) -> RcDagNode {
+    let dag_node = term.borrow().make_dag();
+    for arg in dag_node.borrow().iter_args() {
+      arg.borrow_mut().set_sort_index(1);
+    }
+    dag_node
+  }
 
-impl<'a> Subproblem for ACULazySubproblem<'a> {
-  fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
-    // Todo: Implement ACULazySubproblem::solve(..).
-    false
+  fn make_alien(term: RcTerm) -> NonGroundAlien {
+    term.borrow_mut().normalize(true);
+    let (lhs_automaton, _) = term
+      .borrow()
+      .compile_lhs(false, &VariableInfo::default(), &mut NatSet::default());
+    NonGroundAlien {
+      term,
+      multiplicity: 1,
+      lhs_automaton,
+    }
+  }
+
+  #[test]
+  fn build_graph_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_acu_symbol(&elt);
+    let g = make_free_symbol("g", 1, &elt);
+    let constant = |name: &str| -> RcTerm { rc_cell!(FreeTerm::new(make_constant_symbol(name, &elt))) };
+    let g_of = |arg: RcTerm| -> RcTerm { rc_cell!(FreeTerm::with_args(g.clone(), vec![arg])) };
+    let pair = |term: RcTerm, multiplicity: u32| {
+      DagPair {
+        dag_node: make_subject(&term),
+        multiplicity,
+      }
+    };
+
+    // The pattern f(g(X), g(Y), Z), after the automaton has found nothing to eliminate.
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+    let z = TopVariable {
+      index:         2,
+      multiplicity:  1,
+      sort:          elt.clone(),
+      take_identity: false,
+    };
+    let make_subproblem = |subjects: Vec<DagPair>| {
+      let multiplicity = subjects.iter().map(|pair| pair.multiplicity).collect();
+      ACUSubproblem::new(
+        f.clone(),
+        subjects,
+        multiplicity,
+        vec![make_alien(g_of(x.clone())), make_alien(g_of(y.clone()))],
+        vec![z.clone()],
+      )
+    };
+
+    // Against f(g(a), g(b), c), each alien has an edge to g(a) and to g(b), and both assignments are solutions.
+    let c = constant("c");
+    let subjects = vec![pair(g_of(constant("a")), 1), pair(g_of(constant("b")), 1), pair(c.clone(), 1)];
+    let subject = subjects[2].dag_node.clone();
+    let mut subproblem = make_subproblem(subjects);
+    let mut context = RewritingContext::new(Some(subject.clone()), Weak::new());
+    context.substitution = Substitution::with_capacity(3);
+    assert!(subproblem.build_graph(&mut context));
+    assert!(subproblem.pattern_nodes.iter().all(|node| node.edges.len() == 2));
+
+    let solutions = solutions(subject, Substitution::with_capacity(3), Some(Box::new(subproblem)), 3);
+    assert_eq!(solutions.len(), 2);
+    assert!(solutions[0][0].borrow().compare(&**solutions[1][1].borrow()).is_eq());
+    assert!(solutions[0][1].borrow().compare(&**solutions[1][0].borrow()).is_eq());
+    for solution in &solutions {
+      assert!(solution[0].borrow().compare(&**solution[1].borrow()).is_ne());
+      assert!(solution[2].borrow().compare(&**make_subject(&c).borrow()).is_eq());
+    }
+
+    // Against f(g(a), c, c), both aliens only match g(a). Each has an edge, but they cannot both have g(a), so the
+    // graph is pruned before any alien assignment is tried.
+    let subjects = vec![pair(g_of(constant("a")), 1), pair(c, 2)];
+    let subject = subjects[1].dag_node.clone();
+    let mut subproblem = make_subproblem(subjects);
+    let mut context = RewritingContext::new(Some(subject), Weak::new());
+    context.substitution = Substitution::with_capacity(3);
+    assert!(!subproblem.build_graph(&mut context));
+    assert!(subproblem.pattern_nodes.iter().all(|node| node.edges.len() == 1));
+    assert!(!subproblem.solve(true, &mut context));
   }
 }
+
@@ -1,87 +1,225 @@
 /*!
 
+A symbol belonging to the ACU theory, that is, a binary symbol that is associative, commutative, and (optionally) has
+an identity element. A symbol declared with the `Idem` attribute is also idempotent, so its arguments form a set rather
+than a multiset.
 
+An ACU symbol may also have a built-in AC operation on integers attached, such as `_+_` or `_*_` on `Nat`. After
+normalization, the arguments that are numbers are then combined into a single number before equations are tried.
 
  */
 
-use std::rc::Rc;
-
-use dyn_clone::{clone_trait_object, DynClone};
+use std::{any::Any, cell::RefCell, rc::Rc};
 
+use super::{ACUDagNode, ACUTerm, NormalizationStatus};
 use crate::{
-  core::RcSort,
-  core::{SortConstraintTable, ModuleItem},
-  core::CachedDag,
+  abstractions::{BigInteger, IString, RcCell},
+  builtin::{NumberOp, NumberSystem},
+  core::{rewrite_context::RewritingContext, sort::SpecialSort, CachedDag, Strategy},
+  rc_cell,
   theory::{
+    make_rc_dag_node,
+    AssociativeSymbolStructure,
+    BasicSymbolTypes,
     BinarySymbol,
     DagNode,
+    DagPair,
     RcDagNode,
     RcTerm,
-    Symbol, AssociativeSymbolStructure,
-  }
+    Symbol,
+    SymbolAttribute,
+    SymbolMembers,
+    SymbolType,
+  },
 };
 
 
 pub type RcACUSymbol = Rc<ACUSymbol>;
 
 
-#[derive(Clone)]
 pub struct ACUSymbol {
-  identity : CachedDag, // Supposed to be a CachedDag
-
-  // AssociativeSymbol members
-  pub sort_bounds       : Vec<u32>,
-  pub sort_structure    : Vec<AssociativeSymbolStructure>,
-  pub uniform_sort      : RcSort,
-  sort                  : RcSort,
-  sort_constraint_table : SortConstraintTable,
-  pub hash_value        : u32, // Unique integer for comparing symbols, also called order
-  pub unique_sort_index : i32, // Slow Case: 0, Fast Case: -1, positive for symbols that only produce an unique sort
-  pub match_index       : u32, // For fast matching
-  pub arity             : u32,
-  pub memo_flag         : u32,
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  identity:    CachedDag,
+  symbol_type: SymbolType,
+  /// A built-in AC operation on integers, if one is attached.
+  number_op:   Option<(NumberOp, NumberSystem)>,
+
+  // `AssociativeSymbol` members
+  pub(crate) sort_structure: AssociativeSymbolStructure,
 }
 
+impl ACUSymbol {
+  pub fn new(
+    name: IString,
+    symbol_type: SymbolType,
+    strategy: Strategy,
+    memo_flag: bool,
+    identity: Option<RcTerm>,
+  ) -> ACUSymbol {
+    let mut symbol_members = SymbolMembers::new(name, 2, memo_flag);
+    symbol_members.strategy = strategy;
+
+    // The identity is dagified once, up front, so that `get_identity_dag` can be a simple accessor.
+    let identity_dag = identity.as_ref().map(|term| term.borrow().make_dag());
+
+    ACUSymbol {
+      symbol_members,
+      identity: CachedDag {
+        term:     identity,
+        dag_node: identity_dag,
+      },
+      symbol_type,
+      number_op: None,
+      sort_structure: AssociativeSymbolStructure::Unstructured,
+    }
+  }
 
-impl Symbol for ACUSymbol {
-    fn get_hash_value(&self) -> u32 {
-        self.hash_value
+  pub fn make_term_with_args(self, args: Vec<RcTerm>) -> ACUTerm {
+    ACUTerm::new(Rc::new(self), args)
+  }
+
+  /// Makes the symbol a built-in AC operation on the integers of `numbers`.
+  pub fn attach_number_op(&mut self, op: NumberOp, numbers: NumberSystem) {
+    assert!(op.is_ac(), "{:?} is not an AC operation", op);
+    self.symbol_type.set_basic_type(BasicSymbolTypes::AcuNumberOpSymbol);
+    self.number_op = Some((op, numbers));
+  }
+
+  #[inline(always)]
+  pub fn has_identity(&self) -> bool {
+    self.identity.term.is_some()
+  }
+
+  /// Idempotent symbols keep every argument with multiplicity one.
+  #[inline(always)]
+  pub fn is_idempotent(&self) -> bool {
+    self.symbol_type.is_set(SymbolAttribute::Idem)
+  }
+
+  /// Is `dag_node` equal to our identity element?
+  pub fn is_identity(&self, dag_node: &dyn DagNode) -> bool {
+    match &self.identity.dag_node {
+      Some(identity) => identity.borrow().compare(dag_node).is_eq(),
+      None => false,
     }
+  }
 
-    // TODO: Weird code smell. Doesn't even use `self`.
-    // fn compute_base_sort(&self, subject: &mut dyn DagNode) {
-    //     // #[cfg(feature="DEBUG")]
-    //     assert!(*self==*subject.symbol());
-    //     let sort_index = subject.compute_base_sort();
-    //     subject.set_sort_index(sort_index);
-    // }
+  /// If we have a built-in operation and `subject` has at least two arguments that are numbers, counting multiplicity,
+  /// replaces them by their combined value. Returns `true` if the subject was rewritten.
+  fn fold_number_args(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    let (op, numbers) = match &self.number_op {
+      Some((op, numbers)) => (op, numbers),
+      None => return false,
+    };
+
+    let mut values: Vec<(BigInteger, u32)> = Vec::new();
+    let mut others: Vec<DagPair> = Vec::new();
+    match subject.borrow().as_any().downcast_ref::<ACUDagNode>() {
+      Some(acu_dag_node) => {
+        for (dag_node, multiplicity) in acu_dag_node.iter_pairs() {
+          let value = numbers.get_integer(&**dag_node.borrow());
+          match value {
+            Some(value) => values.push((value, multiplicity)),
+            None => others.push(DagPair { dag_node, multiplicity }),
+          }
+        }
+      }
+      None => unreachable!("Could not downcast to ACUDagNode. This is a bug."),
+    }
 
-    fn get_sort_constraint_table(&self) -> &SortConstraintTable {
-        &self.sort_constraint_table
+    if values.iter().map(|(_, multiplicity)| multiplicity).sum::<u32>() < 2 {
+      return false;
     }
+    let result = match op.fold(&values).and_then(|value| numbers.make_integer_dag(&value)) {
+      Some(result) => result,
+      None => return false,
+    };
+
+    if others.is_empty() {
+      context.builtin_replace(subject, result);
+      return true;
+    }
+    others.push(DagPair {
+      dag_node:     result,
+      multiplicity: 1,
+    });
+    let mut replacement = ACUDagNode::with_pairs(subject.borrow().symbol(), others, NormalizationStatus::Fresh);
+    // The combined value may be our identity, in which case we could collapse.
+    let replacement: RcDagNode = match replacement.normalize_at_top() {
+      Some(remaining) => remaining,
+      None => make_rc_dag_node(replacement),
+    };
+    context.builtin_replace(subject, replacement);
+    true
+  }
 
+  /// Computes the sort index of `multiplicity` copies of an argument with sort index `arg_index` combined with an
+  /// accumulated sort index `index`. We just walk the binary sort diagram once per copy.
+  pub(crate) fn compute_multisort_index(&self, mut index: i32, arg_index: i32, multiplicity: u32) -> i32 {
+    let sort_table = self.sort_table();
+    for _ in 0..multiplicity {
+      if index == SpecialSort::ErrorSort as i32 || arg_index == SpecialSort::ErrorSort as i32 {
+        return SpecialSort::ErrorSort as i32;
+      }
+      index = sort_table.traverse(sort_table.traverse(0, index as usize) as usize, arg_index as usize);
+    }
+    index
+  }
 }
 
-impl BinarySymbol for ACUSymbol {
-  fn get_identity(&self) -> Option<RcTerm> {
-    self.identity.term.clone()
+impl Symbol for ACUSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
   }
 
-  fn get_identity_dag(&self) -> Option<RcDagNode> {
-    self.identity.dag_node.clone()
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
   }
-}
 
-impl ModuleItem for ACUSymbol {
-    fn get_index_within_module(&self) -> u32 {
-        todo!()
-    }
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
 
-    fn set_module_information(&mut self, module: WeakModule, index_within_module: u32) {
-        todo!()
+    // ACU symbols only support the standard strategy: every argument is evaluated before we try equations.
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
     }
 
-    fn get_module(&self) -> WeakModule {
-        todo!()
+    // We always need to renormalize at the top because shared subterms may have rewritten into our theory.
+    let collapse = match subject.borrow_mut().as_any_mut().downcast_mut::<ACUDagNode>() {
+      Some(acu_dag_node) => acu_dag_node.normalize_at_top(),
+      None => unreachable!("Could not downcast to ACUDagNode. This is a bug."),
+    };
+    if let Some(remaining) = collapse {
+      // The subject collapsed to one of its arguments, which is already reduced.
+      remaining.borrow_mut().overwrite_with_clone(subject.clone());
+      return false;
     }
+    if self.fold_number_args(subject.clone(), context) {
+      return true;
+    }
+
+    self.symbol_members.eq_rewrite(subject, context)
+  }
+}
+
+impl BinarySymbol for ACUSymbol {
+  #[inline(always)]
+  fn get_identity(&self) -> Option<RcTerm> {
+    self.identity.term.clone()
+  }
+
+  #[inline(always)]
+  fn get_identity_dag(&self) -> Option<RcDagNode> {
+    self.identity.dag_node.clone()
+  }
 }
@@ -0,0 +1,203 @@
+/*!
+
+The implementation of the compiler-related methods of the `Term` trait for `ACUTerm`.
+
+ */
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::ACUTerm;
+use crate::{
+  abstractions::{rc_cell, NatSet, RcCell},
+  core::{automata::RHSBuilder, BindingLHSAutomaton, TermBag, VariableInfo},
+  theory::{
+    acu_theory::{
+      automaton::{GroundAlien, MatchStrategy, NonGroundAlien, TopVariable},
+      ACULHSAutomaton,
+      ACURHSAutomaton,
+    },
+    find_available_terms,
+    term_compiler::compile_rhs,
+    variable::VariableTerm,
+    LHSAutomaton,
+    RcLHSAutomaton,
+    Term,
+  },
+  NONE,
+};
+
+impl ACUTerm {
+  pub fn compile_lhs(
+    &self,
+    _match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    let take_identity = self.acu_symbol().has_identity();
+
+    let mut ground_aliens: Vec<GroundAlien> = Vec::new();
+    let mut non_ground_aliens: Vec<NonGroundAlien> = Vec::new();
+    let mut top_variables: Vec<TopVariable> = Vec::new();
+    let mut unbound_variable_count = 0;
+
+    // Subterms under an ACU symbol can match many different subjects, so nothing they bind is bound uniquely. We
+    // compile them against a local copy of `bound_uniquely`.
+    let mut local_bound_uniquely = bound_uniquely.clone();
+
+    for record in &self.args {
+      let term = record.term.borrow();
+
+      if let Some(variable_term) = term.as_any().downcast_ref::<VariableTerm>() {
+        if !bound_uniquely.contains(variable_term.index as usize) {
+          unbound_variable_count += 1;
+        }
+        top_variables.push(TopVariable {
+          index: variable_term.index,
+          multiplicity: record.multiplicity,
+          sort: variable_term.sort(),
+          take_identity,
+        });
+      } else if term.ground() {
+        ground_aliens.push(GroundAlien {
+          term:         record.term.clone(),
+          multiplicity: record.multiplicity,
+        });
+      } else {
+        let (lhs_automaton, _subproblem_likely) = term.compile_lhs(false, variable_info, &mut local_bound_uniquely);
+        non_ground_aliens.push(NonGroundAlien {
+          term: record.term.clone(),
+          multiplicity: record.multiplicity,
+          lhs_automaton,
+        });
+      }
+    }
+
+    // Under an idempotent symbol even a lone variable can take the subject in several ways.
+    let match_strategy = if self.acu_symbol().is_idempotent() {
+      if non_ground_aliens.is_empty() && unbound_variable_count == 0 {
+        MatchStrategy::GroundOut
+      } else {
+        MatchStrategy::Full
+      }
+    } else if non_ground_aliens.is_empty() {
+      match unbound_variable_count {
+        0 => MatchStrategy::GroundOut,
+        1 => MatchStrategy::LoneVariable,
+        _ => MatchStrategy::Full,
+      }
+    } else {
+      MatchStrategy::Full
+    };
+
+    if match_strategy == MatchStrategy::LoneVariable {
+      // The lone variable is forced to take whatever remains of the subject.
+      for top_variable in &top_variables {
+        bound_uniquely.insert(top_variable.index as usize);
+      }
+    }
+    let subproblem_likely = match_strategy == MatchStrategy::Full;
+
+    let mut automaton: RcLHSAutomaton = rc_cell!(ACULHSAutomaton::new(
+      self.symbol(),
+      ground_aliens,
+      non_ground_aliens,
+      top_variables,
+    ));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    (automaton, subproblem_likely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  pub fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    // ACU symbols have a single strategy for both arguments.
+    let arg_eager = eager_context && self.symbol().strategy().eager_argument(0);
+
+    // We build the largest arguments first, as in the free theory.
+    let mut order: Vec<(i32, usize)> = self
+      .args
+      .iter()
+      .enumerate()
+      .map(|(i, record)| (-record.term.borrow_mut().compute_size(), i))
+      .collect();
+    order.sort_unstable();
+
+    let mut sources: Vec<i32> = vec![0; self.args.len()];
+    for (_, idx) in order {
+      sources[idx] = compile_rhs(
+        self.args[idx].term.clone(),
+        rhs_builder,
+        variable_info,
+        available_terms,
+        arg_eager,
+      );
+    }
+
+    // Need to flag last use of each source.
+    for source in &sources {
+      variable_info.use_index(*source);
+    }
+
+    let mut automaton = ACURHSAutomaton::new(self.symbol());
+    for (record, source) in self.args.iter().zip(sources) {
+      automaton.add_argument(source, record.multiplicity);
+    }
+    let index = variable_info.make_construction_index();
+    automaton.close(index);
+
+    rhs_builder.add_rhs_automaton(Box::new(automaton));
+    index
+  }
+
+  pub fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    // If everything except a single argument of multiplicity one is ground, that argument must match whatever is left
+    // of the subject, so the bindings it makes are unique. With an identity, the argument might also be forced to
+    // match the identity, which an alien need not do uniquely, so we only propagate through variables in that case.
+    // Under an idempotent symbol the argument may also take parts of the subject the ground arguments took, so nothing
+    // is unique.
+    if self.acu_symbol().is_idempotent() {
+      return;
+    }
+    let mut non_ground = self.args.iter().filter(|record| !record.term.borrow().ground());
+
+    let lone = match (non_ground.next(), non_ground.next()) {
+      (Some(record), None) if record.multiplicity == 1 => record.term.clone(),
+      _ => return,
+    };
+
+    let mut lone = lone.borrow_mut();
+    if let Some(variable_term) = lone.as_any().downcast_ref::<VariableTerm>() {
+      bound_uniquely.insert(variable_term.index as usize);
+    } else if !self.acu_symbol().has_identity() {
+      lone.analyse_constraint_propagation(bound_uniquely);
+    }
+  }
+
+  /// The theory-specific part of find_available_terms
+  pub fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    if self.ground() {
+      return;
+    }
+
+    let symbol = self.symbol();
+    let arg_eager = if at_top {
+      eager_context && symbol.strategy().eager_argument(0)
+    } else {
+      eager_context && symbol.strategy().evaluated_argument(0)
+    };
+
+    for record in &self.args {
+      find_available_terms(record.term.clone(), available_terms, arg_eager, false);
+    }
+  }
+}
@@ -0,0 +1,392 @@
+/*!
+
+`Term` implementation for the ACU theory.
+
+An `ACUTerm` holds its arguments as a list of `ACUTermRecord`s, each of which pairs an argument with its multiplicity.
+After normalization the argument list is flattened, sorted by `Term::compare`, and equal arguments are merged. Under an
+idempotent symbol every argument has multiplicity one. The compiler for the matcher is in `compiler.rs`.
+
+*/
+
+mod compiler;
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{ACUDagNode, ACUSymbol, NormalizationStatus};
+use crate::{
+  abstractions::{hash2 as term_hash, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    numeric_ordering,
+    TermBag,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{
+    make_rc_dag_node,
+    BinarySymbol,
+    DagNode,
+    NodeCache,
+    RcDagNode,
+    RcLHSAutomaton,
+    RcSymbol,
+    RcTerm,
+    Term,
+    TermMembers,
+  },
+  NONE,
+};
+
+pub type RcACUTerm = RcCell<ACUTerm>;
+
+#[derive(Clone)]
+pub struct ACUTermRecord {
+  pub(crate) term:                       RcTerm,
+  pub(crate) multiplicity:               u32,
+  pub(crate) abstraction_variable_index: i32,
+  /// The argument can collapse to a term with our top symbol.
+  pub(crate) collapse_to_our_symbol:     bool,
+  /// The argument can match our identity element.
+  pub(crate) match_our_identity:         bool,
+}
+
+impl ACUTermRecord {
+  pub fn new(term: RcTerm, multiplicity: u32) -> Self {
+    ACUTermRecord {
+      term,
+      multiplicity,
+      abstraction_variable_index: NONE,
+      collapse_to_our_symbol: false,
+      match_our_identity: false,
+    }
+  }
+}
+
+pub struct ACUTerm {
+  pub(crate) term_members:                  TermMembers,
+  pub(crate) args:                          Vec<ACUTermRecord>,
+  pub(crate) unique_collapse_subterm_index: i32,
+}
+
+// Constructors
+impl ACUTerm {
+  pub fn new(symbol: RcSymbol, args: Vec<RcTerm>) -> ACUTerm {
+    ACUTerm::with_multiplicities(symbol, args.into_iter().map(|term| (term, 1)).collect())
+  }
+
+  pub fn with_multiplicities(symbol: RcSymbol, args: Vec<(RcTerm, u32)>) -> ACUTerm {
+    assert!(
+      args.iter().map(|(_, multiplicity)| multiplicity).sum::<u32>() >= 2,
+      "an ACU term needs at least two arguments"
+    );
+    ACUTerm {
+      term_members: TermMembers::new(symbol),
+      args: args
+        .into_iter()
+        .map(|(term, multiplicity)| ACUTermRecord::new(term, multiplicity))
+        .collect(),
+      unique_collapse_subterm_index: NONE,
+    }
+  }
+
+  #[inline(always)]
+  pub fn acu_symbol(&self) -> &ACUSymbol {
+    match self.term_members.top_symbol.as_any().downcast_ref::<ACUSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+    }
+  }
+
+  /// Iterates over `(argument, multiplicity)` pairs in order.
+  pub fn iter_pairs(&self) -> impl Iterator<Item = (RcTerm, u32)> + '_ {
+    self.args.iter().map(|record| (record.term.clone(), record.multiplicity))
+  }
+
+  /// Splices the arguments of subterms with our top symbol into our own argument list. Returns `true` if anything was
+  /// flattened.
+  fn flatten(&mut self) -> bool {
+    let symbol = self.symbol();
+    let mut flattened: Vec<ACUTermRecord> = Vec::with_capacity(self.args.len());
+    let mut changed = false;
+
+    for record in self.args.drain(..) {
+      let term = record.term.borrow();
+      if term.symbol().as_ref() == symbol.as_ref() {
+        if let Some(acu_term) = term.as_any().downcast_ref::<ACUTerm>() {
+          for sub_record in &acu_term.args {
+            flattened.push(ACUTermRecord::new(
+              sub_record.term.clone(),
+              sub_record.multiplicity * record.multiplicity,
+            ));
+          }
+          changed = true;
+          continue;
+        }
+      }
+      drop(term);
+      flattened.push(record);
+    }
+
+    self.args = flattened;
+    changed
+  }
+
+  /// Removes arguments equal to our identity element, as long as at least two arguments (counting multiplicity)
+  /// remain. Returns `true` if anything was removed.
+  fn eliminate_identity(&mut self) -> bool {
+    let identity = match self.acu_symbol().get_identity() {
+      Some(identity) => identity,
+      None => return false,
+    };
+
+    let total: u32 = self.args.iter().map(|record| record.multiplicity).sum();
+    let identity_count: u32 = self
+      .args
+      .iter()
+      .filter(|record| record.term.borrow().compare(&*identity.borrow()).is_eq())
+      .map(|record| record.multiplicity)
+      .sum();
+
+    if identity_count == 0 || total - identity_count < 2 {
+      return false;
+    }
+    self
+      .args
+      .retain(|record| record.term.borrow().compare(&*identity.borrow()).is_ne());
+    true
+  }
+
+  /// Sorts the arguments and merges equal arguments. Returns `true` if anything changed.
+  fn sort_and_uniquize(&mut self) -> bool {
+    let mut changed = false;
+    if !self
+      .args
+      .windows(2)
+      .all(|pair| pair[0].term.borrow().compare(&*pair[1].term.borrow()).is_lt())
+    {
+      changed = true;
+      self
+        .args
+        .sort_by(|a, b| a.term.borrow().compare(&*b.term.borrow()));
+
+      let mut merged: Vec<ACUTermRecord> = Vec::with_capacity(self.args.len());
+      for record in self.args.drain(..) {
+        match merged.last_mut() {
+          Some(last) if last.term.borrow().compare(&*record.term.borrow()).is_eq() => {
+            last.multiplicity += record.multiplicity;
+          }
+          _ => merged.push(record),
+        }
+      }
+      self.args = merged;
+    }
+    changed
+  }
+}
+
+
+impl Term for ACUTerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  /// In sync with `normalize`.
+  fn semantic_hash(&self) -> u32 {
+    let mut hash_value: u32 = self.symbol().semantic_hash();
+
+    for record in &self.args {
+      hash_value = term_hash(term_hash(hash_value, record.term.borrow().semantic_hash()), record.multiplicity);
+    }
+
+    hash_value
+  }
+
+  /// In sync with `semantic_hash`.
+  fn normalize(&mut self, full: bool) -> (u32, bool) {
+    let mut changed = false;
+
+    for record in &self.args {
+      let (_, child_changed) = record.term.borrow_mut().normalize(full);
+      changed = changed || child_changed;
+    }
+
+    changed |= self.flatten();
+    if full {
+      changed |= self.eliminate_identity();
+    }
+    changed |= self.sort_and_uniquize();
+    if self.acu_symbol().is_idempotent() {
+      for record in self.args.iter_mut().filter(|record| record.multiplicity > 1) {
+        record.multiplicity = 1;
+        changed = true;
+      }
+    }
+
+    (self.semantic_hash(), changed)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  /// Iterates over the distinct arguments. Use `iter_pairs` to get multiplicities.
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(self.args.iter().map(|record| record.term.clone()))
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    assert_eq!(&self.symbol(), &other.symbol(), "symbols differ");
+
+    if let Some(other) = other.as_any().downcast_ref::<ACUTerm>() {
+      let r = self.args.len() as isize - other.args.len() as isize;
+      if r != 0 {
+        return numeric_ordering(r);
+      }
+
+      for (arg_self, arg_other) in self.args.iter().zip(other.args.iter()) {
+        let r = arg_self.multiplicity as isize - arg_other.multiplicity as isize;
+        if r != 0 {
+          return numeric_ordering(r);
+        }
+
+        let r = arg_self.term.borrow().compare(&*arg_other.term.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast Term to ACUTerm. This is a bug.")
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    if let Some(other) = other.as_any().downcast_ref::<ACUDagNode>() {
+      let r = self.args.len() as isize - other.len() as isize;
+      if r != 0 {
+        return numeric_ordering(r);
+      }
+
+      for (arg_self, (arg_other, other_multiplicity)) in self.args.iter().zip(other.iter_pairs()) {
+        let r = arg_self.multiplicity as isize - other_multiplicity as isize;
+        if r != 0 {
+          return numeric_ordering(r);
+        }
+
+        let r = arg_self.term.borrow().compare_dag_node(&**arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast DagNode to ACUDagNode. This is a bug.")
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, sub_dags: &mut NodeCache, set_sort_info: bool) -> RcDagNode {
+    let mut node = ACUDagNode::new(self.symbol(), self.args.len(), NormalizationStatus::Fresh);
+
+    for record in &self.args {
+      node.push_arg(record.term.borrow().dagify(sub_dags, set_sort_info), record.multiplicity);
+    }
+
+    // The term is normalized, but identity elimination may not have been done, so we renormalize the node.
+    if let Some(collapsed) = node.normalize_at_top() {
+      return collapsed;
+    }
+    make_rc_dag_node(node)
+  }
+
+  // region Compiler-related
+  #[inline(always)]
+  fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    ACUTerm::compile_lhs(self, match_at_top, variable_info, bound_uniquely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  #[inline(always)]
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    ACUTerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
+  }
+
+  #[inline(always)]
+  fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    ACUTerm::analyse_constraint_propagation(self, bound_uniquely)
+  }
+
+  #[inline(always)]
+  fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    ACUTerm::find_available_terms_aux(self, available_terms, eager_context, at_top);
+  }
+  // endregion
+}
+
+
+impl Formattable for ACUTerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut accumulator = String::new();
+    match style {
+      FormatStyle::Debug => {
+        accumulator.push_str(format!("acu<{}>", self.term_members.top_symbol.repr(style)).as_str());
+      }
+
+      _ => {
+        accumulator.push_str(self.term_members.top_symbol.repr(style).as_str());
+      }
+    }
+
+    // Arguments with multiplicity greater than one are written out once per copy.
+    accumulator.push('(');
+    accumulator.push_str(
+      self
+        .args
+        .iter()
+        .flat_map(|record| std::iter::repeat(record.term.borrow().repr(style)).take(record.multiplicity as usize))
+        .collect::<Vec<String>>()
+        .join(", ")
+        .as_str(),
+    );
+    accumulator.push(')');
+
+    accumulator
+  }
+}
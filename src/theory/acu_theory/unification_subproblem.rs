@@ -0,0 +1,259 @@
+/*!
+
+Unification modulo associativity, commutativity and, optionally, an identity, following \[Stickel 1981]. After equal
+arguments on the two sides of an equation are cancelled, what is left is a multiset equation
+
+```text
+  f(s_1^a_1, …, s_m^a_m) =? f(t_1^b_1, …, t_n^b_n)
+```
+
+whose arguments we call unknowns. The solutions of the homogeneous linear Diophantine equation
+
+```text
+  a_1·x_1 + … + a_m·x_m = b_1·y_1 + … + b_n·y_n
+```
+
+are the sums of its minimal nonzero solutions, its basis. A basis element `e` stands for a fresh variable `z_e` that
+occurs `e[u]` times in the value of the unknown `u`, so every subset of the basis gives a candidate unifier in which
+each unknown is equal to the sum of the fresh variables of the basis elements of the subset that involve it. Unknowns
+that no basis element of the subset involves must be equal to the identity, so without an identity every unknown must
+be involved.
+
+An unknown that is not a variable, an alien, cannot be split into a sum. It must be involved in at most one basis
+element of the subset, with multiplicity one, and is then unified with that element's fresh variable. The equations
+between the unknowns and their sums are solved by the `UnificationProblem`, which is how unification modulo ACU combines
+with unification in the theories of the aliens.
+
+Subsets are enumerated lazily. If the symbol has an identity and there are no aliens, the whole basis gives a most
+general unifier, so it is the only subset tried.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{DiophantineSystem, RcCell},
+  core::{
+    sort::RcSort,
+    unification::{Equation, FreshVariableSource, UnificationSubproblem},
+  },
+  rc_cell,
+  theory::{
+    acu_theory::{ACUDagNode, ACUSymbol, NormalizationStatus},
+    make_rc_dag_node,
+    DagNode,
+    RcDagNode,
+    RcSymbol,
+    Symbol,
+  },
+};
+
+pub struct ACUUnificationSubproblem {
+  symbol:       RcSymbol,
+  /// The distinct arguments left after cancellation, those of the lefthand side first.
+  unknowns:     Vec<RcDagNode>,
+  /// Which unknowns are aliens.
+  aliens:       Vec<bool>,
+  /// The minimal solutions of the Diophantine equation, each giving a multiplicity for every unknown.
+  basis:        Vec<Vec<u32>>,
+  /// The next subset of the basis to try, as a bit set of basis indices.
+  next_subset:  u64,
+  /// The subsets to try are those below this one.
+  subset_bound: u64,
+}
+
+impl ACUUnificationSubproblem {
+  /// Makes the subproblem of unifying `lhs` and `rhs` modulo the axioms of the ACU symbol `symbol`. A side whose top
+  /// symbol is not `symbol` is taken to be its only argument. Both sides must be in normal form, with the current
+  /// bindings of their variables applied.
+  pub fn new(symbol: RcSymbol, lhs: &RcDagNode, rhs: &RcDagNode) -> Self {
+    let mut lhs_arguments = arguments(&symbol, lhs);
+    let mut rhs_arguments = arguments(&symbol, rhs);
+
+    // Cancel arguments common to both sides.
+    for (lhs_argument, lhs_multiplicity) in lhs_arguments.iter_mut() {
+      let common = rhs_arguments
+        .iter_mut()
+        .find(|(rhs_argument, _)| rhs_argument.borrow().compare(&**lhs_argument.borrow()).is_eq());
+      if let Some((_, rhs_multiplicity)) = common {
+        let cancelled = (*lhs_multiplicity).min(*rhs_multiplicity);
+        *lhs_multiplicity -= cancelled;
+        *rhs_multiplicity -= cancelled;
+      }
+    }
+    lhs_arguments.retain(|(_, multiplicity)| *multiplicity > 0);
+    rhs_arguments.retain(|(_, multiplicity)| *multiplicity > 0);
+
+    let lhs_coefficients: Vec<u32> = lhs_arguments.iter().map(|(_, multiplicity)| *multiplicity).collect();
+    let rhs_coefficients: Vec<u32> = rhs_arguments.iter().map(|(_, multiplicity)| *multiplicity).collect();
+    let basis = minimal_solutions(&lhs_coefficients, &rhs_coefficients);
+    assert!(basis.len() < 64, "too many basis elements in ACU unification");
+
+    let unknowns: Vec<RcDagNode> = lhs_arguments
+      .into_iter()
+      .chain(rhs_arguments)
+      .map(|(argument, _)| argument)
+      .collect();
+    let aliens: Vec<bool> = unknowns
+      .iter()
+      .map(|unknown| !unknown.borrow().symbol().is_variable())
+      .collect();
+
+    let full_subset = (1u64 << basis.len()) - 1;
+    let (next_subset, subset_bound) = if acu_symbol(&symbol).has_identity() && !aliens.contains(&true) {
+      (full_subset, full_subset + 1)
+    } else {
+      (0, full_subset + 1)
+    };
+
+    ACUUnificationSubproblem {
+      symbol,
+      unknowns,
+      aliens,
+      basis,
+      next_subset,
+      subset_bound,
+    }
+  }
+
+  /// Whether the basis elements of `subset` give every unknown a value it can take.
+  fn is_admissible(&self, subset: u64) -> bool {
+    let has_identity = acu_symbol(&self.symbol).has_identity();
+
+    for (unknown, is_alien) in self.aliens.iter().enumerate() {
+      let mut involved = self
+        .basis
+        .iter()
+        .enumerate()
+        .filter(|(element, solution)| subset & (1 << element) != 0 && solution[unknown] > 0)
+        .map(|(_, solution)| solution[unknown]);
+
+      let admissible = match (involved.next(), involved.next()) {
+        (None, _) => has_identity,
+        (Some(multiplicity), None) => !is_alien || multiplicity == 1,
+        (Some(_), Some(_)) => !is_alien,
+      };
+      if !admissible {
+        return false;
+      }
+    }
+    true
+  }
+
+  /// The equations between the unknowns and their values under the basis elements of `subset`.
+  fn make_equations(&self, subset: u64, fresh_variables: &mut FreshVariableSource) -> Vec<Equation> {
+    let sort = argument_sort(&self.symbol);
+    let fresh: Vec<Option<RcDagNode>> = (0..self.basis.len())
+      .map(|element| (subset & (1 << element) != 0).then(|| fresh_variables.make_variable(&sort)))
+      .collect();
+
+    let mut equations = Vec::with_capacity(self.unknowns.len());
+    for (unknown, dag_node) in self.unknowns.iter().enumerate() {
+      let mut value = ACUDagNode::new(self.symbol.clone(), 0, NormalizationStatus::Fresh);
+      for (solution, variable) in self.basis.iter().zip(fresh.iter()) {
+        if let Some(variable) = variable {
+          if solution[unknown] > 0 {
+            value.push_arg(variable.clone(), solution[unknown]);
+          }
+        }
+      }
+      let value: RcDagNode = match value.normalize_at_top() {
+        Some(collapsed) => collapsed,
+        None => make_rc_dag_node(value),
+      };
+      equations.push((dag_node.clone(), value));
+    }
+    equations
+  }
+}
+
+impl UnificationSubproblem for ACUUnificationSubproblem {
+  fn next_alternative(&mut self, fresh_variables: &mut FreshVariableSource) -> Option<Vec<Equation>> {
+    while self.next_subset < self.subset_bound {
+      let subset = self.next_subset;
+      self.next_subset += 1;
+      if self.is_admissible(subset) {
+        return Some(self.make_equations(subset, fresh_variables));
+      }
+    }
+    None
+  }
+}
+
+fn acu_symbol(symbol: &RcSymbol) -> &ACUSymbol {
+  match symbol.as_any().downcast_ref::<ACUSymbol>() {
+    Some(symbol) => symbol,
+    None => unreachable!("Could not downcast to ACUSymbol. This is a bug."),
+  }
+}
+
+/// The sort of the fresh variables, which is the sort of the first argument of the symbol's first op declaration.
+fn argument_sort(symbol: &RcSymbol) -> RcSort {
+  symbol.sort_table().get_op_declarations()[0][0].clone()
+}
+
+/// The arguments of `dag_node` under `symbol` with their multiplicities. The identity has none, and a DAG node with a
+/// different top symbol is its own only argument.
+fn arguments(symbol: &RcSymbol, dag_node: &RcDagNode) -> Vec<(RcDagNode, u32)> {
+  let dag_node_ref = dag_node.borrow();
+  if *dag_node_ref.symbol() == **symbol {
+    if let Some(acu_dag_node) = dag_node_ref.as_any().downcast_ref::<ACUDagNode>() {
+      return acu_dag_node.iter_pairs().collect();
+    }
+  }
+  if acu_symbol(symbol).is_identity(&**dag_node_ref) {
+    return Vec::new();
+  }
+  vec![(dag_node.clone(), 1)]
+}
+
+/// The minimal nonzero solutions of `Σ lhs[i]·x[i] = Σ rhs[j]·y[j]` over the natural numbers, each given as `x`
+/// followed by `y`.
+fn minimal_solutions(lhs: &[u32], rhs: &[u32]) -> Vec<Vec<u32>> {
+  let mut basis: Vec<Vec<u32>> = Vec::new();
+  let (max_lhs, max_rhs) = match (lhs.iter().max(), rhs.iter().max()) {
+    (Some(max_lhs), Some(max_rhs)) => (*max_lhs, *max_rhs),
+    // Only the zero solution
+    _ => return basis,
+  };
+
+  // A minimal solution has Σx ≤ max_rhs and Σy ≤ max_lhs \[Lambert 1987], so both sides are at most max_lhs·max_rhs.
+  // Solutions are found in increasing order of value, so any solution above a smaller one is found after it.
+  for value in 1..=max_lhs * max_rhs {
+    let xs = side_solutions(lhs, value, max_rhs);
+    if xs.is_empty() {
+      continue;
+    }
+    let ys = side_solutions(rhs, value, max_lhs);
+    for x in xs.iter() {
+      for y in ys.iter() {
+        let candidate: Vec<u32> = x.iter().chain(y.iter()).copied().collect();
+        let is_minimal = !basis
+          .iter()
+          .any(|element| element.iter().zip(candidate.iter()).all(|(e, c)| e <= c));
+        if is_minimal {
+          basis.push(candidate);
+        }
+      }
+    }
+  }
+  basis
+}
+
+/// The solutions of `Σ coefficients[i]·x[i] = value` with `Σ x[i] ≤ bound`.
+fn side_solutions(coefficients: &[u32], value: u32, bound: u32) -> Vec<Vec<u32>> {
+  let mut system = DiophantineSystem::new();
+  system.insert_row(value);
+  for coefficient in coefficients {
+    system.insert_column(*coefficient, 0, bound);
+  }
+
+  let mut solutions = Vec::new();
+  while system.solve() {
+    let solution: Vec<u32> = (0..coefficients.len()).map(|column| system.solution(0, column)).collect();
+    if solution.iter().sum::<u32>() <= bound {
+      solutions.push(solution);
+    }
+  }
+  solutions
+}
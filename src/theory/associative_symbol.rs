@@ -74,7 +74,7 @@ impl Symbol for AssociativeSymbol {
     self
   }
 
-  fn rewrite(&mut self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
     unimplemented!()
   }
 }
@@ -9,7 +9,7 @@ use std::rc::Rc;
 use crate::{
   abstractions::RcCell,
   core::{sort::RcSort, substitution::Substitution},
-  theory::{ExtensionInfo, MaybeSubproblem, Outcome, RcDagNode, RcTerm, Term},
+  theory::{ExtensionInfo, MaybeSubproblem, Outcome, RcDagNode, RcExtensionInfo, RcTerm, Term},
 };
 
 pub type RcLHSAutomaton = RcCell<dyn LHSAutomaton>;
@@ -24,6 +24,22 @@ pub trait LHSAutomaton {
     // extension_info: Option<&mut dyn ExtensionInfo>,
   ) -> (bool, MaybeSubproblem);
 
+  /// Matches at the top with extension: the pattern may match just part of the subject, and which part it matched
+  /// is recorded in `extension_info`. Theories without extension match the whole subject.
+  fn match_with_extension(
+    &mut self,
+    subject: RcDagNode,
+    solution: &mut Substitution,
+    extension_info: RcExtensionInfo,
+  ) -> (bool, MaybeSubproblem) {
+    {
+      let mut extension_info = extension_info.borrow_mut();
+      extension_info.set_valid_after_match(true);
+      extension_info.set_matched_whole(true);
+    }
+    self.match_(subject, solution)
+  }
+
 
   // In Maude this is a method on DagNode.
   fn match_variable(
@@ -41,7 +57,9 @@ pub trait LHSAutomaton {
     let d = solution.get(index);
     match d {
       None => {
-        if let (Outcome::Success, maybe_subproblem) = dag_node.borrow_mut().check_sort(sort) {
+        // The sort check is bound first, so that its mutable borrow of `dag_node` ends before the copy borrows it.
+        let sort_check = dag_node.borrow_mut().check_sort(sort);
+        if let (Outcome::Success, maybe_subproblem) = sort_check {
           let dag_node_ref = if copy_to_avoid_overwriting {
             dag_node.borrow().shallow_copy()
           } else {
@@ -54,7 +72,7 @@ pub trait LHSAutomaton {
         }
       }
       Some(existing_d) => {
-        if dag_node.borrow().compare(&*existing_d.borrow()).is_eq() {
+        if dag_node.borrow().compare(&**existing_d.borrow()).is_eq() {
           (true, None)
         } else {
           (false, None)
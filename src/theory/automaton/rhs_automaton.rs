@@ -15,7 +15,7 @@ pub trait RHSAutomaton {
 
   fn remap_indices(&mut self, variable_info: &mut VariableInfo);
   fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode;
-  fn replace(&mut self, old: RcDagNode, matcher: &mut Substitution);
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution);
 
   // TODO: `StackMachineRhsCompiler` is not yet implemented.
   /*
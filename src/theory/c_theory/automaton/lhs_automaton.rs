@@ -0,0 +1,119 @@
+/*!
+
+The LHS automaton for the C theory. A pattern `f(p, q)` matches a subject `f(s, t)` if either `p` matches `s` and `q`
+matches `t`, or `p` matches `t` and `q` matches `s`. We try both argument orders at match time, each against its own
+copy of the substitution. If only one order succeeds without leaving a subproblem, its bindings are the match;
+otherwise an `AlternativesSubproblem` holds the orders that succeeded and tries them in turn.
+
+Only one order needs to be tried if the pattern's arguments are equal or the subject's arguments are equal, since both
+orders then give the same solutions.
+
+*/
+
+use crate::{
+  core::substitution::Substitution,
+  theory::{
+    c_theory::CDagNode,
+    Alternative,
+    AlternativesSubproblem,
+    DagNode,
+    LHSAutomaton,
+    MaybeSubproblem,
+    RcDagNode,
+    RcLHSAutomaton,
+    RcSymbol,
+    SubproblemSequence,
+  },
+};
+
+
+pub struct CLHSAutomaton {
+  top_symbol:    RcSymbol,
+  first:         RcLHSAutomaton,
+  second:        RcLHSAutomaton,
+  /// The pattern's arguments are equal, so the second argument order is redundant.
+  equal_pattern: bool,
+}
+
+impl CLHSAutomaton {
+  pub(crate) fn new(top_symbol: RcSymbol, first: RcLHSAutomaton, second: RcLHSAutomaton, equal_pattern: bool) -> Self {
+    CLHSAutomaton {
+      top_symbol,
+      first,
+      second,
+      equal_pattern,
+    }
+  }
+
+  /// Matches the pattern's arguments against `left` and `right` respectively, in a copy of `solution`.
+  fn match_order(&self, left: RcDagNode, right: RcDagNode, solution: &Substitution) -> Option<(Substitution, MaybeSubproblem)> {
+    let mut local = solution.clone();
+
+    let (matched, first_subproblem) = self.first.borrow_mut().match_(left, &mut local);
+    if !matched {
+      return None;
+    }
+    let (matched, second_subproblem) = self.second.borrow_mut().match_(right, &mut local);
+    if !matched {
+      return None;
+    }
+
+    let subproblem = combine_subproblems(first_subproblem, second_subproblem);
+    Some((local, subproblem))
+  }
+}
+
+
+impl LHSAutomaton for CLHSAutomaton {
+  fn match_(&mut self, subject: RcDagNode, solution: &mut Substitution) -> (bool, MaybeSubproblem) {
+    let (left, right) = {
+      let subject_ref = subject.borrow();
+      if subject_ref.symbol().as_ref() != self.top_symbol.as_ref() {
+        return (false, None);
+      }
+      match subject_ref.as_any().downcast_ref::<CDagNode>() {
+        Some(c_dag_node) => (c_dag_node.first(), c_dag_node.second()),
+        None => unreachable!("Could not downcast to CDagNode. This is a bug."),
+      }
+    };
+
+    let mut orders: Vec<(Substitution, MaybeSubproblem)> = Vec::with_capacity(2);
+    orders.extend(self.match_order(left.clone(), right.clone(), solution));
+    let equal_subject = left.borrow().compare(&**right.borrow()).is_eq();
+    if !self.equal_pattern && !equal_subject {
+      orders.extend(self.match_order(right, left, solution));
+    }
+
+    match orders.len() {
+      0 => (false, None),
+
+      1 if orders[0].1.is_none() => {
+        let (local, _) = orders.pop().unwrap();
+        *solution = local;
+        (true, None)
+      }
+
+      _ => {
+        let alternatives = orders
+          .into_iter()
+          .map(|(local, subproblem)| Alternative::new(local.subtract(solution), subproblem))
+          .collect();
+        (true, Some(Box::new(AlternativesSubproblem::new(alternatives))))
+      }
+    }
+  }
+}
+
+
+/// Combines the subproblems left by the matchers of the two arguments.
+fn combine_subproblems(first: MaybeSubproblem, second: MaybeSubproblem) -> MaybeSubproblem {
+  match (first, second) {
+    (Some(first), Some(second)) => {
+      let mut sequence = SubproblemSequence::new();
+      sequence.add(first);
+      sequence.add(second);
+      Some(Box::new(sequence))
+    }
+    (first, second) => first.or(second),
+  }
+}
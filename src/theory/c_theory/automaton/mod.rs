@@ -0,0 +1,11 @@
+/*!
+
+The LHS and RHS automata of the C theory.
+
+*/
+mod lhs_automaton;
+mod rhs_automaton;
+
+// Re-exports
+pub use lhs_automaton::CLHSAutomaton;
+pub use rhs_automaton::CRHSAutomaton;
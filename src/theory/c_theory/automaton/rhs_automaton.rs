@@ -0,0 +1,74 @@
+/*!
+
+The RHS automaton for the C theory. Each instruction builds one C DAG node from two arguments that have already been
+constructed (or bound) in the substitution, putting them in canonical order.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{rc_cell, RcCell},
+  core::{substitution::Substitution, VariableInfo},
+  theory::{c_theory::CDagNode, dag_node::MaybeDagNode, make_rc_dag_node, DagNode, RHSAutomaton, RcDagNode, RcSymbol},
+};
+
+
+pub struct CRHSAutomaton {
+  top_symbol:  RcSymbol,
+  sources:     [i32; 2],
+  destination: i32,
+}
+
+impl CRHSAutomaton {
+  pub fn new(top_symbol: RcSymbol, sources: [i32; 2], destination: i32) -> Self {
+    CRHSAutomaton {
+      top_symbol,
+      sources,
+      destination,
+    }
+  }
+
+  /// Builds the normalized node from the bindings in `matcher`.
+  fn build_arguments(&self, matcher: &mut Substitution) -> CDagNode {
+    let [first, second] = self.sources.map(|source| {
+      let value = matcher.value(source as usize);
+      assert!(value.is_some(), "unbound source {} in C RHS. This is a bug.", source);
+      value.unwrap()
+    });
+
+    let mut new_dag_node = CDagNode::new(self.top_symbol.clone(), first, second);
+    new_dag_node.normalize_at_top();
+    new_dag_node
+  }
+}
+
+
+impl RHSAutomaton for CRHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    for source in &mut self.sources {
+      *source = variable_info.remap_index(*source);
+    }
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let new_dag_node: RcDagNode = make_rc_dag_node(self.build_arguments(matcher));
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let mut new_dag_node = self.build_arguments(matcher);
+    new_dag_node.overwrite_with_clone(old);
+  }
+}
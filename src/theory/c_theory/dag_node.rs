@@ -0,0 +1,245 @@
+/*!
+
+Concrete types for the C theory implementing the DagNode trait.
+
+A `CDagNode` stores its two arguments in `DagNodeMembers::args`. Normal form for a C node is that the arguments are in
+canonical order, that is, the first argument is not greater than the second under `DagNode::compare`. Since both
+arguments of a normalized node are in normal form themselves, two normalized C nodes are equal modulo commutativity
+exactly when they are structurally equal.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{CSymbol, CTerm};
+use crate::{
+  abstractions::RcCell,
+  core::{hash_cons_set::HashConsSet, sort::SpecialSort, RedexPosition},
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, DagNodeFlags, DagNodeMembers, NodeList, RcDagNode, RcSymbol, RcTerm, Symbol},
+};
+
+
+pub type RcCDagNode = RcCell<CDagNode>;
+
+pub struct CDagNode {
+  pub(crate) members: DagNodeMembers,
+}
+
+impl CDagNode {
+  /// Makes a node over the given arguments, in whatever order they are given. Call `normalize_at_top` to put them in
+  /// canonical order.
+  pub fn new(symbol: RcSymbol, first: RcDagNode, second: RcDagNode) -> Self {
+    let mut args = NodeList::default();
+    args.push(first);
+    args.push(second);
+
+    CDagNode {
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args,
+        flags: Default::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc: None,
+        hash: 0,
+      },
+    }
+  }
+
+  #[inline(always)]
+  pub fn c_symbol(&self) -> &CSymbol {
+    match self.members.top_symbol.as_any().downcast_ref::<CSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to CSymbol. This is a bug."),
+    }
+  }
+
+  #[inline(always)]
+  pub fn first(&self) -> RcDagNode {
+    self.members.args[0].clone()
+  }
+
+  #[inline(always)]
+  pub fn second(&self) -> RcDagNode {
+    self.members.args[1].clone()
+  }
+
+  /// Puts the arguments in canonical order. Returns `true` if they had to be swapped.
+  pub fn normalize_at_top(&mut self) -> bool {
+    let (first, second) = (self.first(), self.second());
+    if first.borrow().compare(&**second.borrow()).is_gt() {
+      let mut args = NodeList::default();
+      args.push(second);
+      args.push(first);
+      self.members.args = args;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+
+impl DagNode for CDagNode {
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
+  }
+
+  fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<CDagNode>() {
+      Some(c_dag_node) => {
+        for (this_child, other_child) in self.iter_args().zip(c_dag_node.iter_args()) {
+          let r = this_child.borrow().compare(&**other_child.borrow());
+          if r != Ordering::Equal {
+            return r;
+          }
+        }
+        Ordering::Equal
+      }
+      None => unreachable!("Could not downcast a DagNode to a CDagNode. This is a bug."),
+    }
+  }
+
+  fn compute_base_sort(&mut self) -> i32 {
+    let symbol = self.symbol();
+    let mut state = 0;
+
+    for (i, arg) in self.iter_args().enumerate() {
+      let index = arg.borrow().get_sort_index();
+      assert_ne!(
+        index,
+        SpecialSort::Unknown as i32,
+        "unknown sort encountered for arg {} of {}",
+        i,
+        symbol.name()
+      );
+      state = symbol.sort_table().traverse(state as usize, index as usize);
+    }
+
+    self.set_sort_index(state);
+    state
+  }
+
+  fn termify(&self) -> RcTerm {
+    rc_cell!(CTerm::new(
+      self.symbol(),
+      self.first().borrow().termify(),
+      self.second().borrow().termify()
+    ))
+  }
+
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = CDagNode::new(self.symbol(), self.first(), self.second());
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacements(&self, redex_stack: &[RedexPosition], mut first_idx: usize, last_idx: usize) -> RcDagNode {
+    assert!(
+      first_idx <= last_idx && last_idx < redex_stack.len(),
+      "bad replacement range"
+    );
+    let mut args = [self.first(), self.second()];
+
+    while first_idx <= last_idx {
+      let arg_index = redex_stack[first_idx].arg_index;
+      assert!((0..2).contains(&arg_index), "bad replacement arg index");
+      args[arg_index as usize] = redex_stack[first_idx].dag_node.clone();
+      first_idx += 1;
+    }
+
+    // The replacements may have changed the order of the arguments.
+    let [first, second] = args;
+    let mut new_node = CDagNode::new(self.symbol(), first, second);
+    new_node.normalize_at_top();
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
+    assert!(arg_index < 2, "bad argIndex");
+    let mut new_node = if arg_index == 0 {
+      CDagNode::new(self.symbol(), replacement, self.second())
+    } else {
+      CDagNode::new(self.symbol(), self.first(), replacement)
+    };
+
+    new_node.normalize_at_top();
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    let symbol = self.symbol();
+    let copy = |index: usize, arg: RcDagNode| -> RcDagNode {
+      if symbol.strategy().eager_argument(index) {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = arg.borrow_mut().copy_eager_upto_reduced();
+        copy.unwrap_or(arg)
+      } else {
+        arg
+      }
+    };
+
+    let new_node = CDagNode::new(self.symbol(), copy(0, self.first()), copy(1, self.second()));
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    let copy = |arg: RcDagNode| -> RcDagNode {
+      let copy = arg.borrow_mut().copy_all();
+      copy.unwrap_or(arg)
+    };
+
+    let new_node = CDagNode::new(self.symbol(), copy(self.first()), copy(self.second()));
+    make_rc_dag_node(new_node)
+  }
+
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = CDagNode::new(self.symbol(), self.first(), self.second());
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+
+    *old.borrow_mut() = Box::new(new_node);
+  }
+
+  /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let (first, second) = (self.first(), self.second());
+    let (canonical_first, _) = hash_cons_set.insert(first.clone());
+    let (canonical_second, _) = hash_cons_set.insert(second.clone());
+
+    if RcCell::ptr_eq(&canonical_first, &first) && RcCell::ptr_eq(&canonical_second, &second) {
+      // Can use the original DAG node as the canonical version
+      return rc_dag_node;
+    }
+
+    // Canonical copies compare equal to the originals, so the order is unchanged.
+    let mut new_node = CDagNode::new(self.symbol(), canonical_first, canonical_second);
+    new_node.members.flags.set_copied_flags(self.members.flags);
+    new_node.members.sort_index = self.members.sort_index;
+
+    make_rc_dag_node(new_node)
+  }
+}
@@ -0,0 +1,91 @@
+/*!
+
+The C theory: binary symbols that are commutative, but neither associative nor have an identity element. Terms and DAG
+nodes with a C top symbol keep their two arguments in canonical order, so commutativity never has to be considered when
+comparing them.
+
+Matching is done by the `CLHSAutomaton`, which tries both argument orders and, if more than one way of matching
+remains, hands them to an `AlternativesSubproblem`.
+
+*/
+
+mod automaton;
+mod dag_node;
+mod symbol;
+mod term;
+
+pub use automaton::{CLHSAutomaton, CRHSAutomaton};
+pub use dag_node::{CDagNode, RcCDagNode};
+pub use symbol::{CSymbol, RcCSymbol};
+pub use term::{CTerm, RcCTerm};
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::theory::{
+    test_fixtures::{all_matches, make_c_symbol, make_constant, make_sorts, make_variable},
+    DagNode,
+    RcDagNode,
+    RcSymbol,
+    RcTerm,
+    Term,
+  };
+
+  /// Makes the DAG for `f(first, second)`, giving both arguments the sort `Elt`.
+  fn make_subject(f: &RcSymbol, first: &RcTerm, second: &RcTerm) -> RcDagNode {
+    let mut term = CTerm::new(f.clone(), first.clone(), second.clone());
+    term.normalize(true);
+
+    let subject = term.make_dag();
+    for arg in subject.borrow().iter_args() {
+      arg.borrow_mut().set_sort_index(1);
+    }
+    subject
+  }
+
+  #[test]
+  fn normalize_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_c_symbol(&elt);
+    let a = make_constant("a", &elt);
+    let b = make_constant("b", &elt);
+
+    let mut ab = CTerm::new(f.clone(), a.clone(), b.clone());
+    let mut ba = CTerm::new(f.clone(), b.clone(), a.clone());
+    let (ab_hash, _) = ab.normalize(true);
+    let (ba_hash, _) = ba.normalize(true);
+
+    assert_eq!(ab_hash, ba_hash);
+    assert!(ab.compare(&ba).is_eq());
+    assert!(ab.make_dag().borrow().compare(&**ba.make_dag().borrow()).is_eq());
+  }
+
+  #[test]
+  fn match_both_orders_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let f = make_c_symbol(&elt);
+    let a = make_constant("a", &elt);
+    let b = make_constant("b", &elt);
+
+    let x = make_variable("X", 0, &elt);
+    let y = make_variable("Y", 1, &elt);
+
+    // f(X, a) against f(b, a) has a single solution, whichever order the subject's arguments are stored in.
+    let mut pattern = CTerm::new(f.clone(), x.clone(), a.clone());
+    let solutions = all_matches(&mut pattern, make_subject(&f, &b, &a), 1);
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().compare(&**b.borrow().make_dag().borrow()).is_eq());
+
+    // f(X, Y) against f(a, b) has the two solutions X=a, Y=b and X=b, Y=a.
+    let mut pattern = CTerm::new(f.clone(), x.clone(), y.clone());
+    let solutions = all_matches(&mut pattern, make_subject(&f, &a, &b), 2);
+    assert_eq!(solutions.len(), 2);
+    assert!(solutions[0][0].borrow().compare(&**solutions[1][1].borrow()).is_eq());
+    assert!(solutions[0][1].borrow().compare(&**solutions[1][0].borrow()).is_eq());
+
+    // f(X, X) against f(a, b) has no solutions.
+    let mut pattern = CTerm::new(f.clone(), x.clone(), x.clone());
+    assert!(all_matches(&mut pattern, make_subject(&f, &a, &b), 1).is_empty());
+  }
+}
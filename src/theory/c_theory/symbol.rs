@@ -0,0 +1,84 @@
+/*!
+
+A symbol belonging to the C theory, that is, a binary symbol that is commutative but neither associative nor has an
+identity. Equality predicates like `_=_` and operators like `max` are typically such symbols.
+
+ */
+
+use std::{any::Any, rc::Rc};
+
+use super::{CDagNode, CTerm};
+use crate::{
+  abstractions::IString,
+  core::{rewrite_context::RewritingContext, Strategy},
+  theory::{BinarySymbol, RcDagNode, RcTerm, Symbol, SymbolMembers},
+};
+
+
+pub type RcCSymbol = Rc<CSymbol>;
+
+
+pub struct CSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+}
+
+impl CSymbol {
+  pub fn new(name: IString, strategy: Strategy, memo_flag: bool) -> CSymbol {
+    let mut symbol_members = SymbolMembers::new(name, 2, memo_flag);
+    symbol_members.strategy = strategy;
+
+    CSymbol { symbol_members }
+  }
+
+  pub fn make_term_with_args(self, first: RcTerm, second: RcTerm) -> CTerm {
+    CTerm::new(Rc::new(self), first, second)
+  }
+}
+
+impl Symbol for CSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    // Commutative symbols only support the standard strategy: every argument is evaluated before we try equations.
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
+    }
+
+    // Reducing the arguments may have changed their order.
+    match subject.borrow_mut().as_any_mut().downcast_mut::<CDagNode>() {
+      Some(c_dag_node) => c_dag_node.normalize_at_top(),
+      None => unreachable!("Could not downcast to CDagNode. This is a bug."),
+    };
+
+    self.symbol_members.eq_rewrite(subject, context)
+  }
+}
+
+impl BinarySymbol for CSymbol {
+  #[inline(always)]
+  fn get_identity(&self) -> Option<RcTerm> {
+    None
+  }
+
+  #[inline(always)]
+  fn get_identity_dag(&self) -> Option<RcDagNode> {
+    None
+  }
+}
@@ -0,0 +1,111 @@
+/*!
+
+The implementation of the compiler-related methods of the `Term` trait for `CTerm`.
+
+ */
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::CTerm;
+use crate::{
+  abstractions::{rc_cell, NatSet, RcCell},
+  core::{automata::RHSBuilder, BindingLHSAutomaton, TermBag, VariableInfo},
+  theory::{
+    c_theory::{CLHSAutomaton, CRHSAutomaton},
+    find_available_terms,
+    term_compiler::compile_rhs,
+    RcLHSAutomaton,
+    Term,
+  },
+  NONE,
+};
+
+impl CTerm {
+  pub fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    // Either argument may end up matching either subject argument, so nothing the arguments bind is bound uniquely.
+    // We compile them against a local copy of `bound_uniquely`.
+    let mut local_bound_uniquely = bound_uniquely.clone();
+    let (first, _) = self.args[0]
+      .borrow()
+      .compile_lhs(false, variable_info, &mut local_bound_uniquely);
+    let (second, _) = self.args[1]
+      .borrow()
+      .compile_lhs(false, variable_info, &mut local_bound_uniquely);
+
+    let equal_pattern = self.args[0].borrow().compare(&*self.args[1].borrow()).is_eq();
+    let mut automaton: RcLHSAutomaton = rc_cell!(CLHSAutomaton::new(self.symbol(), first, second, equal_pattern));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    // With two argument orders to try, a subproblem is likely unless the pattern is symmetric.
+    let subproblem_likely = !equal_pattern && !self.ground();
+    (automaton, subproblem_likely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  pub fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    let symbol = self.symbol();
+    let mut sources = [0; 2];
+
+    // We build the larger argument first, as in the free theory.
+    let order = if self.args[0].borrow_mut().compute_size() >= self.args[1].borrow_mut().compute_size() {
+      [0, 1]
+    } else {
+      [1, 0]
+    };
+    for idx in order {
+      let arg_eager = eager_context && symbol.strategy().eager_argument(idx);
+      sources[idx] = compile_rhs(self.args[idx].clone(), rhs_builder, variable_info, available_terms, arg_eager);
+    }
+
+    // Need to flag last use of each source.
+    for source in &sources {
+      variable_info.use_index(*source);
+    }
+
+    let index = variable_info.make_construction_index();
+    rhs_builder.add_rhs_automaton(Box::new(CRHSAutomaton::new(symbol, sources, index)));
+    index
+  }
+
+  pub fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    // If one argument is ground, it determines which subject argument the other one matches.
+    let ground = [self.args[0].borrow().ground(), self.args[1].borrow().ground()];
+    match ground {
+      [true, false] => self.args[1].borrow_mut().analyse_constraint_propagation(bound_uniquely),
+      [false, true] => self.args[0].borrow_mut().analyse_constraint_propagation(bound_uniquely),
+      _ => {}
+    }
+  }
+
+  /// The theory-specific part of find_available_terms
+  pub fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    if self.ground() {
+      return;
+    }
+
+    let symbol = self.symbol();
+    for (i, arg) in self.args.iter().enumerate() {
+      let arg_eager = if at_top {
+        eager_context && symbol.strategy().eager_argument(i)
+      } else {
+        eager_context && symbol.strategy().evaluated_argument(i)
+      };
+      find_available_terms(arg.clone(), available_terms, arg_eager, false);
+    }
+  }
+}
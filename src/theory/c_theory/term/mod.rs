@@ -0,0 +1,221 @@
+/*!
+
+`Term` implementation for the C theory.
+
+A `CTerm` holds its two arguments in an array. Normalization puts them in canonical order under `Term::compare`, so
+`f(b, a)` and `f(a, b)` normalize to the same term and hash to the same value. The compiler for the matcher is in
+`compiler.rs`.
+
+*/
+
+mod compiler;
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::CDagNode;
+use crate::{
+  abstractions::{hash2 as term_hash, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    TermBag,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, NodeCache, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm, Term, TermMembers},
+};
+
+pub type RcCTerm = RcCell<CTerm>;
+
+pub struct CTerm {
+  pub(crate) term_members: TermMembers,
+  pub(crate) args:         [RcTerm; 2],
+}
+
+// Constructors
+impl CTerm {
+  pub fn new(symbol: RcSymbol, first: RcTerm, second: RcTerm) -> CTerm {
+    CTerm {
+      term_members: TermMembers::new(symbol),
+      args:         [first, second],
+    }
+  }
+
+  /// Puts the arguments in canonical order. Returns `true` if they had to be swapped.
+  fn sort_arguments(&mut self) -> bool {
+    if self.args[0].borrow().compare(&*self.args[1].borrow()).is_gt() {
+      self.args.swap(0, 1);
+      true
+    } else {
+      false
+    }
+  }
+}
+
+
+impl Term for CTerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  /// In sync with `normalize`.
+  fn semantic_hash(&self) -> u32 {
+    let mut hash_value: u32 = self.symbol().semantic_hash();
+
+    for arg in &self.args {
+      hash_value = term_hash(hash_value, arg.borrow().semantic_hash());
+    }
+
+    hash_value
+  }
+
+  /// In sync with `semantic_hash`.
+  fn normalize(&mut self, full: bool) -> (u32, bool) {
+    let mut changed = false;
+
+    for arg in &self.args {
+      let (_, child_changed) = arg.borrow_mut().normalize(full);
+      changed = changed || child_changed;
+    }
+
+    // The arguments must be normalized before they are compared.
+    changed |= self.sort_arguments();
+
+    (self.semantic_hash(), changed)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(self.args.iter().cloned())
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    assert_eq!(&self.symbol(), &other.symbol(), "symbols differ");
+
+    if let Some(other) = other.as_any().downcast_ref::<CTerm>() {
+      for (arg_self, arg_other) in self.args.iter().zip(other.args.iter()) {
+        let r = arg_self.borrow().compare(&*arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast Term to CTerm. This is a bug.")
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    if let Some(other) = other.as_any().downcast_ref::<CDagNode>() {
+      for (arg_self, arg_other) in self.args.iter().zip(other.iter_args()) {
+        let r = arg_self.borrow().compare_dag_node(&**arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast DagNode to CDagNode. This is a bug.")
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, sub_dags: &mut NodeCache, set_sort_info: bool) -> RcDagNode {
+    let first = self.args[0].borrow().dagify(sub_dags, set_sort_info);
+    let second = self.args[1].borrow().dagify(sub_dags, set_sort_info);
+
+    // A shared subterm may have been rewritten since it was cached, so we reorder the node if necessary.
+    let mut node = CDagNode::new(self.symbol(), first, second);
+    node.normalize_at_top();
+    make_rc_dag_node(node)
+  }
+
+  // region Compiler-related
+  #[inline(always)]
+  fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    CTerm::compile_lhs(self, match_at_top, variable_info, bound_uniquely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  #[inline(always)]
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    CTerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
+  }
+
+  #[inline(always)]
+  fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    CTerm::analyse_constraint_propagation(self, bound_uniquely)
+  }
+
+  #[inline(always)]
+  fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    CTerm::find_available_terms_aux(self, available_terms, eager_context, at_top);
+  }
+  // endregion
+}
+
+
+impl Formattable for CTerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut accumulator = String::new();
+    match style {
+      FormatStyle::Debug => {
+        accumulator.push_str(format!("c<{}>", self.term_members.top_symbol.repr(style)).as_str());
+      }
+
+      _ => {
+        accumulator.push_str(self.term_members.top_symbol.repr(style).as_str());
+      }
+    }
+
+    accumulator.push_str(
+      format!(
+        "({}, {})",
+        self.args[0].borrow().repr(style),
+        self.args[1].borrow().repr(style)
+      )
+      .as_str(),
+    );
+
+    accumulator
+  }
+}
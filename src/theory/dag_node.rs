@@ -24,6 +24,7 @@ use super::{
   ExtensionInfo,
   MaybeSubproblem,
   Outcome,
+  RcExtensionInfo,
   RcSymbol,
   RcTerm,
   Subproblem,
@@ -31,7 +32,7 @@ use super::{
   SymbolType,
 };
 use crate::{
-  abstractions::{BigInteger, RcCell},
+  abstractions::{hash2, BigInteger, RcCell},
   core::{
     hash_cons_set::HashConsSet,
     rewrite_context::RewritingContext,
@@ -45,10 +46,18 @@ use crate::{
 // pub type BcDagNode = Box<Cell<DagNode>>;
 pub type MaybeDagNode = Option<RcDagNode>;
 pub type BcDagNode = Box<dyn DagNode>;
-pub type RcDagNode = RcCell<dyn DagNode>;
+/// The node is boxed within its cell, so that a rewrite can overwrite it in place with a node of any theory. See
+/// `RewritingContext::replace_at_top`.
+pub type RcDagNode = RcCell<BcDagNode>;
 pub type NodeList = SharedVector<RcDagNode>;
 pub type AtomicNodeList = AtomicSharedVector<RcDagNode>;
 
+/// Makes a shared node holding `dag_node`.
+#[inline(always)]
+pub fn make_rc_dag_node(dag_node: impl DagNode + 'static) -> RcDagNode {
+  RcCell::new(Box::new(dag_node))
+}
+
 /// This struct owns the DagNode. If we just want a reference, we use a tuple `(dag_node.as_ref(), multiplicity)`.
 #[derive(Clone)]
 pub struct DagPair {
@@ -150,7 +159,15 @@ pub trait DagNode {
 
   #[inline(always)]
   fn set_reduced(&mut self) {
-    self.flags().0 |= DagNodeFlag::Reduced as u32;
+    self.dag_node_members_mut().flags.0 |= DagNodeFlag::Reduced as u32;
+  }
+
+  /// Forgets the sort of an unreduced node, which is no longer valid once one of its arguments has been rewritten.
+  #[inline(always)]
+  fn repudiate_sort_info(&mut self) {
+    if !self.is_reduced() {
+      self.set_sort_index(SpecialSort::Unknown as i32);
+    }
   }
 
   #[inline(always)]
@@ -169,6 +186,10 @@ pub trait DagNode {
 
   /// Defines a partial order on `DagNode`s by comparing the symbols and the arguments recursively.
   fn compare(&self, other: &dyn DagNode) -> Ordering {
+    // Shared nodes, in particular hash consed ones, are equal without looking at their arguments.
+    if self.as_ptr() as *const () == other.as_ptr() as *const () {
+      return Ordering::Equal;
+    }
     // let symbol_order = self.cmp(other);
     let s = self.symbol();
     let symbol_order = //Ord::cmp(s, other.symbol());
@@ -182,6 +203,26 @@ pub trait DagNode {
 
   fn compare_arguments(&self, other: &dyn DagNode) -> Ordering;
 
+  /// A hash of the symbols and arguments of this node consistent with `compare`: nodes that compare equal have the
+  /// same structural hash. The value is cached in the node once it is valid, which is when the node is hash consed.
+  /// Theories whose nodes hold data beyond their arguments extend the hash with it.
+  fn structural_hash(&self) -> u32 {
+    if self.is_hash_valid() {
+      return self.dag_node_members().hash;
+    }
+    self
+      .iter_args()
+      .fold(self.symbol().semantic_hash(), |hash, arg| hash2(hash, arg.borrow().structural_hash()))
+  }
+
+  /// Caches the structural hash of this node.
+  #[inline(always)]
+  fn set_hash_value(&mut self, hash: u32) {
+    let members = self.dag_node_members_mut();
+    members.hash = hash;
+    members.flags.0 |= DagNodeFlag::HashValid as u32;
+  }
+
   #[inline(always)]
   fn leq_sort(&self, sort: &Sort) -> bool {
     assert_ne!(self.get_sort_index(), SpecialSort::Unknown as i32, "unknown sort");
@@ -281,13 +322,43 @@ pub trait DagNode {
 
   fn termify(&self) -> RcTerm;
 
-  // Only implemented for associative theories and the `S_` theory.
-  fn partial_replace(&mut self, substitution: &mut Substitution) {
+  /// Makes an empty `ExtensionInfo` for matching at the top of this node with extension, or `None` if our theory
+  /// does not have extension.
+  fn make_extension_info(&self) -> Option<RcExtensionInfo> {
+    None
+  }
+
+  /// Replaces the part of our arguments matched with extension, as recorded in `extension_info`, by `replacement`.
+  /// Only implemented for associative theories and the `S_` theory.
+  fn partial_replace(&mut self, replacement: RcDagNode, extension_info: &dyn ExtensionInfo) {
     unreachable!("partial_replace not implemented for this node type.")
   }
 
+  /// Like `partial_replace`, but the result is a new node and we are left unchanged. Rule rewriting needs this, because
+  /// a rule rewrite must not change the old state.
+  fn partial_construct(&self, replacement: RcDagNode, extension_info: &dyn ExtensionInfo) -> RcDagNode {
+    let new_node = self.shallow_copy();
+    new_node.borrow_mut().partial_replace(replacement, extension_info);
+    new_node
+  }
+
   fn shallow_copy(&self) -> RcDagNode;
 
+  /// Pushes the position of each of our arguments that might hold a rule redex onto `stack`, where `parent_index` is
+  /// our own position on the stack. Unstackable arguments are skipped, as are frozen arguments if `respect_frozen`.
+  /// An argument is eager if we are in an eager context and our symbol evaluates the argument eagerly.
+  fn stack_arguments(&self, stack: &mut Vec<RedexPosition>, parent_index: i32, respect_frozen: bool, eager_context: bool) {
+    let symbol = self.symbol();
+    let strategy = symbol.strategy();
+    for (arg_index, arg) in self.iter_args().enumerate() {
+      if (respect_frozen && strategy.get_frozen().contains(arg_index)) || arg.borrow().flags().is_unstackable() {
+        continue;
+      }
+      let eager = eager_context && strategy.eager_argument(arg_index);
+      stack.push(RedexPosition::new(arg, parent_index, arg_index as i32, eager));
+    }
+  }
+
   /// Build a copy of our dag node, replacing those arguments that were stacked with those on the stack between first
   /// and last.
   fn copy_with_replacements(&self, stack: &[RedexPosition], first_idx: usize, last_idx: usize) -> RcDagNode;
@@ -337,6 +408,8 @@ pub trait DagNode {
   }
 
 
+  /// Overwrites `old` in place with a shallow copy of this node, so that every holder of `old` sees the copy. The copy
+  /// replaces the boxed node in `old`'s cell, so `old` may belong to any theory.
   fn overwrite_with_clone(&mut self, old: RcDagNode);
 
   /// For hash consing
@@ -351,14 +424,14 @@ impl Eq for dyn DagNode {}
 impl PartialEq for dyn DagNode {
   #[inline(always)]
   fn eq(&self, other: &dyn DagNode) -> bool {
-    self.cmp(other) == Ordering::Equal
+    self.compare(other) == Ordering::Equal
   }
 }
 
 impl PartialOrd for dyn DagNode {
   #[inline(always)]
   fn partial_cmp(&self, other: &dyn DagNode) -> Option<Ordering> {
-    Some(self.cmp(other))
+    Some(self.compare(other))
   }
 }
 
@@ -446,7 +519,7 @@ impl Display for dyn DagNode {
       f,
       "[Term has {} operator symbol{} while graph has {} node{}.]",
       operators,
-      if *operators == 1 { "" } else { "s" },
+      if operators.is_one() { "" } else { "s" },
       nodes,
       if nodes == 1 { "" } else { "s" },
     )?;
@@ -470,7 +543,7 @@ impl Display for dyn DagNode {
           "#{}",
           visited
             .iter()
-            .position(|&x| x.addr() == addr_of!(*a.borrow()).addr())
+            .position(|&x| x.addr() == addr_of!(**a.borrow()).addr())
             .unwrap()
         )?;
         first = false;
@@ -503,25 +576,25 @@ fn graph_count(dag_node: &dyn DagNode, visited: &mut Vec<*const dyn DagNode>, co
     visited.iter().position(|&x| x.addr() == dag_node_ptr.addr()).unwrap(),
     "counts out of step"
   );
-  counts.push(0);
+  counts.push(BigInteger::zero());
 
-  let mut count: BigInteger = 1;
+  let mut count = BigInteger::one();
 
   for d in dag_node.iter_args().map(|v| v.clone()) {
-    let d_ptr = d.as_ptr();
+    let d_ptr = d.borrow().as_ptr();
     if visited
       .iter()
-      .find(|&&p| d_ptr.cast_const().addr() == p.addr())
+      .find(|&&p| d_ptr.addr() == p.addr())
       .is_none()
     {
-      graph_count(d.as_ref(), visited, counts);
+      graph_count(&**d.as_ref(), visited, counts);
     }
 
-    let child_count = counts[visited
+    let child_count = &counts[visited
       .iter()
-      .position(|&x| x.addr() == d_ptr.cast_const().addr())
+      .position(|&x| x.addr() == d_ptr.addr())
       .unwrap()];
-    assert_ne!(child_count, 0, "cycle in dag");
+    assert!(!child_count.is_zero(), "cycle in dag");
     count += child_count;
   }
   counts[index] = count;
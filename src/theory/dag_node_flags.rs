@@ -75,8 +75,9 @@ impl DagNodeFlags {
   // DagNodeFlag::Unstackable | DagNodeFlag::Ground;
 
   pub fn set_copied_flags(&mut self, other_flags: DagNodeFlags) {
-    self |=
-      (DagNodeFlag::Reduced | DagNodeFlag::Unrewritable | DagNodeFlag::Unstackable | DagNodeFlag::Ground) & other_flags;
+    *self = *self
+      | ((DagNodeFlag::Reduced | DagNodeFlag::Unrewritable | DagNodeFlag::Unstackable | DagNodeFlag::Ground)
+        & other_flags);
   }
 }
 
@@ -15,6 +15,7 @@ use crate::{
   theory::{
     dag_node::MaybeDagNode,
     free_theory::{automaton::FreeRHSAutomatonInstruction, FreeDagNode},
+    make_rc_dag_node,
     DagNode,
     RHSAutomaton,
     RcDagNode,
@@ -103,7 +104,7 @@ impl RHSAutomaton for FreeFast2RHSAutomaton {
       let mut new_dag_node = FreeDagNode::new(instruction.symbol.clone());
       self.fill_out_args(instruction, matcher, &mut new_dag_node);
 
-      let maybe_dag_node: MaybeDagNode = Some(rc_cell!(new_dag_node));
+      let maybe_dag_node: MaybeDagNode = Some(make_rc_dag_node(new_dag_node));
       matcher.bind(instruction.destination, maybe_dag_node.clone());
 
       if let Some(i) = instruction_iter.next() {
@@ -114,7 +115,7 @@ impl RHSAutomaton for FreeFast2RHSAutomaton {
     }
   }
 
-  fn replace(&mut self, old: RcDagNode, matcher: &mut Substitution) {
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
     let mut instruction_count = 0;
 
     for instruction in &self.fast_instructions {
@@ -124,15 +125,11 @@ impl RHSAutomaton for FreeFast2RHSAutomaton {
       instruction_count += 1;
       // ToDo: Why isn't the last one bound?
       if instruction_count == self.fast_instructions.len() {
-        if let Some(old_node) = old.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-          let _ = std::mem::replace(old_node, new_dag_node);
-        } else {
-          unreachable!("Attempted to swap non free dag node for free dag node. This is a bug.");
-        }
+        *old.borrow_mut() = Box::new(new_dag_node);
         break;
       }
 
-      matcher.bind(instruction.destination, Some(rc_cell!(new_dag_node)));
+      matcher.bind(instruction.destination, Some(make_rc_dag_node(new_dag_node)));
     }
   }
 }
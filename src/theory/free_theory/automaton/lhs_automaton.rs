@@ -253,14 +253,13 @@ impl LHSAutomaton for FreeLHSAutomaton {
       for i in &self.ground_aliens {
         let term: &mut dyn Term = unsafe { &mut *i.alien };
         if term
-          .compare_dag_node(&*self.stack[i.position as usize][i.arg_index as usize].borrow())
+          .compare_dag_node(&**self.stack[i.position as usize][i.arg_index as usize].borrow())
           .is_ne()
         {
           return (false, None);
         }
       }
 
-      assert!(self.non_ground_aliens.len() > 0, "no nrNonGroundAliens");
       if !self.non_ground_aliens.is_empty() {
         let mut subproblems = SubproblemSequence::new();
 
@@ -278,7 +277,7 @@ impl LHSAutomaton for FreeLHSAutomaton {
             return (false, None);
           }
         }
-        return (true, Some(subproblems.extract_subproblem()));
+        return (true, subproblems.extract_subproblem());
       }
       return (true, None);
     } else {
@@ -13,6 +13,7 @@ use crate::{
   theory::{
     dag_node::MaybeDagNode,
     free_theory::{automaton::FreeRHSAutomatonInstruction, FreeDagNode},
+    make_rc_dag_node,
     DagNode,
     RHSAutomaton,
     RcDagNode,
@@ -52,18 +53,14 @@ impl RHSAutomaton for FreeNullaryRHSAutomaton {
   }
 
   fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
-    let new_dag_node: RcDagNode = rc_cell!(FreeDagNode::new(self.symbol.unwrap().clone()));
+    let new_dag_node: RcDagNode = make_rc_dag_node(FreeDagNode::new(self.symbol.as_ref().unwrap().clone()));
     matcher.bind(self.destination as i32, Some(new_dag_node.clone()));
 
     Some(new_dag_node)
   }
 
-  fn replace(&mut self, old: RcDagNode, _matcher: &mut Substitution) {
-    let new_dag_node: FreeDagNode = FreeDagNode::new(self.symbol.unwrap().clone());
-    if let Some(old_node) = old.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-      let _ = std::mem::replace(old_node, new_dag_node);
-    } else {
-      unreachable!("Attempted to swap non free dag node for free dag node. This is a bug.");
-    }
+  fn replace(&self, old: RcDagNode, _matcher: &mut Substitution) {
+    let new_dag_node: FreeDagNode = FreeDagNode::new(self.symbol.as_ref().unwrap().clone());
+    *old.borrow_mut() = Box::new(new_dag_node);
   }
 }
@@ -30,6 +30,7 @@ use crate::{
       },
       FreeDagNode,
     },
+    make_rc_dag_node,
     DagNode,
     RHSAutomaton,
     RcDagNode,
@@ -89,12 +90,12 @@ impl FreeRHSAutomaton {
     self.instructions.push(new_instruction);
   }
 
-  fn fill_out_args(&self, instr: &FreeRHSAutomatonInstruction, matcher: &mut Substitution, dag_node: &mut dyn DagNode) {
-    let arg_count = dag_node.symbol().arity();
-    if arg_count != 0 {
-      for (j, mut arg) in dag_node.iter_args().enumerate() {
-        let mut new_arg = matcher.value(instr.sources[j] as usize);
-        arg.0 = new_arg.unwrap().0;
+  /// Gives `dag_node` the arguments the sources of `instr` are bound to.
+  fn fill_out_args(&self, instr: &FreeRHSAutomatonInstruction, matcher: &Substitution, dag_node: &mut FreeDagNode) {
+    for &source in instr.sources.iter() {
+      match matcher.value(source as usize) {
+        Some(arg) => dag_node.members.args.push(arg),
+        None => unreachable!("An RHS source is unbound. This is a bug."),
       }
     }
   }
@@ -126,33 +127,29 @@ impl RHSAutomaton for FreeRHSAutomaton {
       let mut naked_dag_node: FreeDagNode = FreeDagNode::new(i.symbol.clone());
       self.fill_out_args(i, matcher, &mut naked_dag_node);
 
-      new_dag_node = Some(rc_cell!(naked_dag_node));
+      new_dag_node = Some(make_rc_dag_node(naked_dag_node));
       matcher.bind(i.destination as i32, new_dag_node.clone());
     }
 
     new_dag_node
   }
 
-  fn replace(&mut self, mut old: RcDagNode, matcher: &mut Substitution) {
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
     let nr_instructions = self.instructions.len();
 
     for instruction in &self.instructions[..nr_instructions - 1] {
       let mut new_dag_node = FreeDagNode::new(instruction.symbol.clone());
       self.fill_out_args(instruction, matcher, &mut new_dag_node);
 
-      let new_dag_node: RcDagNode = rc_cell!(new_dag_node);
+      let new_dag_node: RcDagNode = make_rc_dag_node(new_dag_node);
       matcher.bind(instruction.destination as i32, Some(new_dag_node));
     }
 
     let instruction = &self.instructions[nr_instructions - 1];
 
-    if let Some(old_node) = old.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-      let new_dag_node = FreeDagNode::new(instruction.symbol.clone());
-      let _ = std::mem::replace(old_node, new_dag_node);
+    let mut new_dag_node = FreeDagNode::new(instruction.symbol.clone());
+    self.fill_out_args(instruction, matcher, &mut new_dag_node);
 
-      self.fill_out_args(instruction, matcher, old_node);
-    } else {
-      unreachable!("Attempted to swap non free dag node for free dag node. This is a bug.");
-    }
+    *old.borrow_mut() = Box::new(new_dag_node);
   }
 }
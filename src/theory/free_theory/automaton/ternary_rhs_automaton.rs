@@ -15,6 +15,7 @@ use crate::{
   theory::{
     dag_node::MaybeDagNode,
     free_theory::{automaton::FreeRHSAutomatonInstruction, FreeDagNode},
+    make_rc_dag_node,
     DagNode,
     RHSAutomaton,
     RcDagNode,
@@ -67,21 +68,17 @@ impl RHSAutomaton for FreeTernaryRHSAutomaton {
   }
 
   fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
-    let new_dag_node: RcDagNode = rc_cell!(FreeDagNode::new(self.symbol.unwrap().clone()));
+    let new_dag_node: RcDagNode = make_rc_dag_node(FreeDagNode::new(self.symbol.as_ref().unwrap().clone()));
     matcher.bind(self.destination as i32, Some(new_dag_node.clone()));
-    self.fill_out_args(matcher, &mut *new_dag_node.borrow_mut());
+    self.fill_out_args(matcher, &mut **new_dag_node.borrow_mut());
 
     Some(new_dag_node)
   }
 
-  fn replace(&mut self, old: RcDagNode, matcher: &mut Substitution) {
-    let new_dag_node: FreeDagNode = FreeDagNode::new(self.symbol.unwrap().clone());
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let mut new_dag_node: FreeDagNode = FreeDagNode::new(self.symbol.as_ref().unwrap().clone());
+    self.fill_out_args(matcher, &mut new_dag_node);
 
-    if let Some(old_node) = old.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-      let _ = std::mem::replace(old_node, new_dag_node);
-      self.fill_out_args(matcher, old_node);
-    } else {
-      unreachable!("Attempted to swap non free dag node for free dag node. This is a bug.");
-    }
+    *old.borrow_mut() = Box::new(new_dag_node);
   }
 }
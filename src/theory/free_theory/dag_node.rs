@@ -9,6 +9,7 @@ use crate::{
   rc_cell,
   theory::{
     dag_node::MaybeDagNode,
+    make_rc_dag_node,
     DagNode,
     DagNodeFlag,
     DagNodeFlags,
@@ -37,7 +38,7 @@ impl FreeDagNode {
       args:       Default::default(),
       // sort: Default::default(),
       flags:      Default::default(),
-      sort_index: 0,
+      sort_index: SpecialSort::Unknown as i32,
       copied_rc:  None,
       hash:       0,
     };
@@ -94,14 +95,14 @@ impl DagNode for FreeDagNode {
 
         // Compare all but the last (rightmost) node.
         for i in (0..arg_count - 1).rev() {
-          let r = DagNode::compare(p[i].as_ref(), q[i].as_ref());
+          let r = DagNode::compare(&**p[i].as_ref(), &**q[i].as_ref());
           if r.is_ne() {
             return r;
           }
         }
 
-        let pd2 = p[arg_count - 1].as_ref();
-        let qd2 = q[arg_count - 1].as_ref();
+        let pd2 = &**p[arg_count - 1].as_ref();
+        let qd2 = &**q[arg_count - 1].as_ref();
         // Fast bail on equal pointers.
         if std::ptr::addr_of!(pd2) == std::ptr::addr_of!(qd2) {
           return Ordering::Equal; // Points to same node
@@ -171,7 +172,7 @@ impl DagNode for FreeDagNode {
       },
     };
 
-    rc_cell!(fdg)
+    make_rc_dag_node(fdg)
   }
 
   fn copy_with_replacements(&self, redex_stack: &[RedexPosition], mut first_idx: usize, last_idx: usize) -> RcDagNode {
@@ -204,7 +205,7 @@ impl DagNode for FreeDagNode {
         new_args.push(args[i as usize].clone());
       }
     }
-    rc_cell!(new_dag_node)
+    make_rc_dag_node(new_dag_node)
   }
 
   fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
@@ -219,114 +220,75 @@ impl DagNode for FreeDagNode {
 
     new_args[arg_index] = replacement;
 
-    rc_cell!(new_dag_node)
+    make_rc_dag_node(new_dag_node)
   }
 
   fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
     let symbol = self.symbol();
-    let mut new_dag_node = FreeDagNode::new(symbol);
-    let arg_count = symbol.arity() as usize;
+    let mut new_dag_node = FreeDagNode::new(symbol.clone());
 
-    if arg_count != 0 {
-      if symbol.strategy().standard_strategy() {
-        new_dag_node.members.args.extend(
-          self
-            .members
-            .args
-            .iter()
-            .map(|v| v.borrow().copy_eager_upto_reduced().unwrap()),
-        );
+    for (i, arg) in self.members.args.iter().enumerate() {
+      let copy = if symbol.strategy().eager_argument(i) {
+        // `None` means the argument is already reduced and can be shared.
+        arg.borrow_mut().copy_eager_upto_reduced()
       } else {
-        let p = &mut self.members.args;
-        let mut q = &mut new_dag_node.dag_node_members_mut().args;
-
-        for i in 0..arg_count {
-          q[i] = if symbol.strategy().eager_argument(i) {
-            p[i].borrow().copy_eager_upto_reduced().unwrap()
-          } else {
-            p[i].clone()
-          };
-        }
-      }
+        None
+      };
+      new_dag_node.members.args.push(copy.unwrap_or(arg.clone()));
     }
-    rc_cell!(new_dag_node)
+    make_rc_dag_node(new_dag_node)
   }
 
   fn copy_all_aux(&mut self) -> RcDagNode {
-    let symbol = self.symbol();
-    let mut new_dag_node = FreeDagNode::new(symbol);
-    let arg_count = symbol.arity() as usize;
+    let mut new_dag_node = FreeDagNode::new(self.symbol());
 
-    if arg_count != 0 {
-      let p = &mut self.members.args;
-      let mut q = &mut new_dag_node.members.args;
-      for i in 0..arg_count {
-        // ToDo: Can we justify the unwrap?
-        q[i] = p[i].borrow().copy_all().unwrap();
-      }
+    for arg in self.members.args.iter() {
+      let copy = arg.borrow_mut().copy_all();
+      new_dag_node.members.args.push(copy.unwrap_or(arg.clone()));
     }
-    rc_cell!(new_dag_node)
+    make_rc_dag_node(new_dag_node)
   }
 
   fn overwrite_with_clone(&mut self, old: RcDagNode) {
-    if let Some(old_dag_node) = old.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-      let mut fdg = FreeDagNode::new(self.symbol());
-
-      fdg.set_sort_index(self.get_sort_index());
-      fdg.set_flags(
-        self.flags()
-          | DagNodeFlag::Reduced
-          | DagNodeFlag::Unrewritable
-          | DagNodeFlag::Unstackable
-          | DagNodeFlag::Ground,
-      );
-      fdg.members.args = old_dag_node.members.args.clone();
+    let mut fdg = FreeDagNode::new(self.symbol());
 
-      let _ = std::mem::replace(old_dag_node, fdg);
-    } else {
-      unreachable!("This execution path should be unreachable. This is a bug.")
-    }
+    fdg.set_sort_index(self.get_sort_index());
+    fdg.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+    fdg.members.args = self.members.args.clone();
+
+    *old.borrow_mut() = Box::new(fdg);
   }
 
   /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
-  fn make_canonical(&self, rc_dag_node: RcDagNode, hcs: &mut HashConsSet) -> RcDagNode {
-    // Downcast
-    if let Some(dag_node) = rc_dag_node.borrow_mut().as_any_mut().downcast_mut::<FreeDagNode>() {
-      let nr_args = dag_node.members.top_symbol.arity() as usize;
-      let args = &mut dag_node.members.args;
-
-      for i in 0..nr_args {
-        let d: RcDagNode = args[i].clone();
-        // let c = hcs.get_canonical(hcs.insert(d));
-        let (canonical_dag_node, _) = hcs.insert(d);
-
-        if Rc::ptr_eq(&canonical_dag_node, &args[i]) {
-          // The child node was already canonical.
-          continue;
-        }
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let args: Vec<RcDagNode> = self.iter_args().collect();
 
-        // Detected a non-canonical argument, need to make a new copy
-        let symbol = Rc::clone(&dag_node.members.top_symbol);
-        let mut new_node = FreeDagNode::new(symbol);
-        new_node.members.flags.set_copied_flags(dag_node.members.flags);
-        new_node.members.sort_index = dag_node.members.sort_index;
+    for (i, arg) in args.iter().enumerate() {
+      let (canonical_dag_node, _) = hash_cons_set.insert(arg.clone());
 
-        let new_args = &mut new_node.members.args;
-        for j in 0..i {
-          new_args.push(args[j].clone());
-        }
-        new_args.push(canonical_dag_node);
-        for j in i + 1..nr_args {
-          let (canonical, _) = hcs.insert(args[j].clone());
-          new_args.push(canonical);
-        }
+      if RcCell::ptr_eq(&canonical_dag_node, arg) {
+        // The child node was already canonical.
+        continue;
+      }
+
+      // Detected a non-canonical argument, need to make a new copy
+      let mut new_node = FreeDagNode::new(self.symbol());
+      new_node.members.flags.set_copied_flags(self.members.flags);
+      new_node.members.sort_index = self.members.sort_index;
 
-        return Rc::new(new_node) as RcDagNode;
+      let new_args = &mut new_node.members.args;
+      for arg in &args[0..i] {
+        new_args.push(arg.clone());
+      }
+      new_args.push(canonical_dag_node);
+      for arg in &args[i + 1..] {
+        let (canonical, _) = hash_cons_set.insert(arg.clone());
+        new_args.push(canonical);
       }
 
-      rc_dag_node // Can use the original DAG node as the canonical version
-    } else {
-      unreachable!("This execution path should be unreachable. This is a bug.")
+      return make_rc_dag_node(new_node);
     }
+
+    rc_dag_node // Can use the original DAG node as the canonical version
   }
 }
@@ -9,7 +9,7 @@ the end nodes of the net symbolize the outcomes for the different possible predi
 
 */
 
-use std::{collections::HashSet, rc::Rc};
+use std::{cmp::Ordering, collections::HashSet, rc::Rc};
 
 use super::{FreeRemainder, FreeRemainderList};
 use crate::{
@@ -171,15 +171,10 @@ impl FreeNet {
 
     for i in patterns_used {
       let e = equations[*i as usize].clone();
+      let lhs_term = e.borrow().lhs_term.clone();
 
-      if let Some(free_term) = e
-        .borrow_mut()
-        .lhs_term
-        .borrow_mut()
-        .as_any_mut()
-        .downcast_mut::<FreeTerm>()
-      {
-        let remainder = free_term.compile_remainder(e, slot_translation);
+      if let Some(free_term) = lhs_term.borrow_mut().as_any_mut().downcast_mut::<FreeTerm>() {
+        let remainder = free_term.compile_remainder(e.clone(), slot_translation);
         self.remainders[*i as usize] = Some(remainder.clone());
 
         // If a remainder doesn't have fast handling, neither can the discrimination net.
@@ -187,7 +182,7 @@ impl FreeNet {
       } else {
         self.remainders[*i as usize] = Some(Rc::new(FreeRemainder::with_equation(e)));
         self.fast = false; // A foreign equation always disables fast handling for the net
-      }
+      };
     }
     // Build null terminated pointer version of applicable for added speed.
     let nr_applicables = self.applicable.len();
@@ -264,18 +259,89 @@ impl FreeNet {
     }
   }
 
+  /// Whether the net has been built from the equations of its symbol.
+  #[inline(always)]
+  pub fn is_compiled(&self) -> bool {
+    !self.applicable.is_empty()
+  }
+
   /// This is the inlined gaurd for `apply_replace` that provides a fast path in the case that
   /// the term cannot be applied.
   #[inline(always)]
   pub(crate) fn apply_replace(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
     if !self.applicable.is_empty() {
-      self.apply_replace_aux(subject, context)
+      self.apply_replace_aux(subject, context, false)
+    } else {
+      false
+    }
+  }
+
+  /// Like `apply_replace`, but `owise` equations are not tried.
+  #[inline(always)]
+  pub(crate) fn apply_replace_no_owise(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    if !self.applicable.is_empty() {
+      self.apply_replace_aux(subject, context, true)
     } else {
       false
     }
   }
 
-  pub fn apply_replace_aux(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+  /// Runs the subject through the tests of the net to find the remainders that might apply, and tries them in order.
+  pub fn apply_replace_aux(&self, subject: RcDagNode, context: &mut RewritingContext, no_owise: bool) -> bool {
+    let applicable_index = match self.find_applicable(&subject) {
+      Some(applicable_index) => applicable_index,
+      None => return false,
+    };
+
+    for remainder in self.fast_applicable[applicable_index].iter().map_while(|r| r.as_ref()) {
+      if no_owise && remainder.is_owise() {
+        break;
+      }
+      if remainder.match_replace(subject.clone(), context) {
+        return true;
+      }
+    }
     false
   }
+
+  /// Walks the ternary trees of the net, returning the index of the applicable list the subject ends up at, if any.
+  fn find_applicable(&self, subject: &RcDagNode) -> Option<usize> {
+    if self.net.is_empty() {
+      // Every remainder applies without any tests.
+      return Some(0);
+    }
+
+    // The argument lists of the free subterms we have passed, indexed by slot.
+    let mut stack: Vec<NodeList> = vec![NodeList::new(); self.stack.len().max(1)];
+    stack[0] = subject.borrow().dag_node_members().args.clone();
+    let mut args = stack[0].clone();
+    let mut node_index = 0;
+
+    loop {
+      let node = &self.net[node_index];
+      if node.position != NONE {
+        args = stack[node.position as usize].clone();
+      }
+
+      let dag_node = args[node.arg_index as usize].clone();
+      let symbol_index = dag_node.borrow().symbol().index_within_parent();
+      let next = match symbol_index.cmp(&node.symbol_index) {
+        Ordering::Equal => {
+          if node.slot != NONE {
+            stack[node.slot as usize] = dag_node.borrow().dag_node_members().args.clone();
+          }
+          node.equal
+        }
+        Ordering::Less => node.not_equal.0,
+        Ordering::Greater => node.not_equal.1,
+      };
+
+      match next.cmp(&0) {
+        // Zero encodes failure, and a negative number encodes the index of an applicable list.
+        Ordering::Equal => return None,
+        Ordering::Less => return Some(!next as usize),
+        Ordering::Greater => node_index = next as usize,
+      }
+    }
+  }
 }
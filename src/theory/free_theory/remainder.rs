@@ -13,12 +13,14 @@ use super::{BoundVariable, FreeVariable, GroundAlien, NonGroundAlien};
 use crate::{
   core::{
     format::{FormatStyle, Formattable},
-    pre_equation::{PreEquation, RcPreEquation},
+    pre_equation::{equation, PreEquation, RcPreEquation},
+    rewrite_context::RewritingContext,
   },
   theory::{
     free_theory::{FreeOccurrence, FreeTerm},
     variable::VariableTerm,
     NodeList,
+    RcDagNode,
     RcLHSAutomaton,
     RcSymbol,
     Term,
@@ -203,4 +205,15 @@ impl FreeRemainder {
       non_ground_aliens,
     }
   }
+
+  #[inline(always)]
+  pub fn is_owise(&self) -> bool {
+    self.equation.borrow().is_owise()
+  }
+
+  /// Tries to match the remainder of the equation against `subject`, rewriting it if successful.
+  #[inline(always)]
+  pub fn match_replace(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    equation::apply_replace(&self.equation, subject, context)
+  }
 }
@@ -9,7 +9,11 @@ use std::{any::Any, cell::RefCell, rc::Rc};
 use super::{FreeDagNode, FreeNet, FreeTerm, RcFreeNet};
 use crate::{
   abstractions::{IString, RcCell},
-  core::{interpreter::SourceSet, rewrite_context::RewritingContext, Strategy},
+  core::{
+    interpreter::{memo_table::MemoTable, SourceSet},
+    rewrite_context::RewritingContext,
+    Strategy,
+  },
   theory::{DagNode, NodeList, RcDagNode, RcTerm, Symbol, SymbolMembers},
 };
 
@@ -38,48 +42,104 @@ impl FreeSymbol {
     FreeTerm::with_args(Rc::new(self), args)
   }
 
+  /// Tries the equations for this symbol at the top of `subject`. Once the discrimination net has been compiled it is
+  /// used to select the equations to try, otherwise they are tried in order. With `no_owise`, `owise` equations are
+  /// skipped.
+  fn apply_replace(&self, subject: RcDagNode, context: &mut RewritingContext, no_owise: bool) -> bool {
+    match (self.discrimination_net.is_compiled(), no_owise) {
+      (true, false) => self.discrimination_net.apply_replace(subject, context),
+      (true, true) => self.discrimination_net.apply_replace_no_owise(subject, context),
+      (false, false) => self.symbol_members.apply_replace(subject, context),
+      (false, true) => self.symbol_members.apply_replace_no_owise(subject, context),
+    }
+  }
+
+  /// The memoized version of `apply_replace`. See `SymbolMembers::eq_rewrite`.
+  fn memo_apply_replace(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    let mut from = SourceSet::new();
+    if !self.symbol_members.memo_rewrite(&mut from, subject.clone(), context)
+      && self.apply_replace(subject.clone(), context, false)
+    {
+      context.reduce_dag_node(subject.clone());
+    }
+    self.symbol_members.memo_enter(&from, subject);
+    false
+  }
+
+  /// Evaluates `subject` according to a user-supplied strategy. A positive entry `n` reduces the `n`th argument, and
+  /// a `0` tries the equations at the top. Only the last `0` may apply `owise` equations.
   fn complex_strategy(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
-    // if self.is_memoized() {
-    //   let mut from = SourceSet::new();
-    //   self.memo_strategy(&mut from, subject.clone(), context);
-    //   self.memo_enter(from, subject.clone());
-    //   return false;
-    // }
-
-    let nr_args = self.arity();
-    let args = &mut subject.borrow_mut().dag_node_members_mut().args;
-
-    // Execute user-supplied strategy
-    let user_strategy = self.get_strategy();
-    let strat_len = user_strategy.len();
+    if self.is_memoized() {
+      let mut from = SourceSet::new();
+      self.memo_strategy(&mut from, subject.clone(), context);
+      self.symbol_members.memo_enter(&from, subject);
+      return false;
+    }
+
+    let user_strategy = self.strategy.get_strategy();
     let mut seen_zero = false;
-    for i in 0..strat_len {
-      let mut a = user_strategy[i];
+    for (i, &a) in user_strategy.iter().enumerate() {
       if a == 0 {
         if !seen_zero {
-          for j in 0..nr_args {
-            args[j].compute_true_sort(context);
-          }
+          self.compute_arg_true_sorts(&subject, context);
           seen_zero = true;
         }
-        if (i + 1 == strat_len) && self.discrimination_net.apply_replace(subject.clone(), context)
-          || self.discrimination_net.apply_replace_no_owise(subject.clone(), context)
-        {
+        let is_last = i + 1 == user_strategy.len();
+        if self.apply_replace(subject.clone(), context, !is_last) {
           return true;
         }
       } else {
-        a -= 1; // real arguments start at 0 not 1
-        if seen_zero {
-          args[a] = args[a].copy_reducible(); // Assuming a similar method exists
-                                              // A previous call to discrimination_net.apply_replace() may have
-                                              // computed a true sort for our subject which will be
-                                              // invalidated by the reduce we are about to do.
-          subject.repudiate_sort_info(); // Assuming a similar method exists
+        self.reduce_strategy_arg(&subject, (a - 1) as usize, seen_zero, context);
+      }
+    }
+    false
+  }
+
+  /// The memoized version of `complex_strategy`. Every version of `subject` we try equations on is recorded in `from`.
+  /// Since a successful rewrite is followed by reduction to normal form, we never report a rewrite.
+  fn memo_strategy(&self, from: &mut SourceSet, subject: RcDagNode, context: &mut RewritingContext) {
+    let user_strategy = self.strategy.get_strategy();
+    let mut seen_zero = false;
+    for (i, &a) in user_strategy.iter().enumerate() {
+      if a == 0 {
+        if !seen_zero {
+          self.compute_arg_true_sorts(&subject, context);
+          seen_zero = true;
+        }
+        if self.symbol_members.memo_rewrite(from, subject.clone(), context) {
+          return;
         }
-        args[a].reduce(context);
+        let is_last = i + 1 == user_strategy.len();
+        if self.apply_replace(subject.clone(), context, !is_last) {
+          context.reduce_dag_node(subject);
+          return;
+        }
+      } else {
+        self.reduce_strategy_arg(&subject, (a - 1) as usize, seen_zero, context);
       }
     }
-    return false;
+  }
+
+  /// Before equations are first tried, the unevaluated arguments still need sorts to be matched against.
+  fn compute_arg_true_sorts(&self, subject: &RcDagNode, context: &mut RewritingContext) {
+    let args = subject.borrow().dag_node_members().args.clone();
+    for arg in args.iter() {
+      context.compute_true_sort(arg.clone());
+    }
+  }
+
+  /// Reduces the argument with the given (zero based) index as a step of a user strategy.
+  ///
+  /// ToDo: Maude reduces a copy of the argument once equations have been tried, in case the argument is shared with
+  ///       a DAG that has been entered into a memo table. We reduce in place.
+  fn reduce_strategy_arg(&self, subject: &RcDagNode, arg_index: usize, seen_zero: bool, context: &mut RewritingContext) {
+    let arg = subject.borrow().dag_node_members().args[arg_index].clone();
+    if seen_zero {
+      // A previous attempt to apply equations may have computed a true sort for our subject which will be invalidated
+      // by the reduce we are about to do.
+      subject.borrow_mut().repudiate_sort_info();
+    }
+    context.reduce_dag_node(arg);
   }
 }
 
@@ -99,7 +159,7 @@ impl Symbol for FreeSymbol {
     self
   }
 
-  fn rewrite(&mut self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
     // println!("attempting {}", self);
     assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
     if self.strategy.is_standard {
@@ -108,7 +168,10 @@ impl Symbol for FreeSymbol {
       for arg in args.iter_mut().take(arg_count).rev() {
         context.reduce_dag_node(arg.clone())
       }
-      return self.discrimination_net.apply_replace(subject.clone(), context);
+      if self.is_memoized() {
+        return self.memo_apply_replace(subject, context);
+      }
+      return self.apply_replace(subject, context, false);
     }
     return self.complex_strategy(subject, context);
   }
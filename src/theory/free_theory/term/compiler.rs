@@ -110,7 +110,7 @@ impl FreeTerm {
     if step < alien_count {
       // Now we search over possible ordering of remaining NGAs.
 
-      let mut new_bounds: Vec<NatSet> = Vec::with_capacity(alien_count);
+      let mut new_bounds: Vec<NatSet> = vec![NatSet::default(); alien_count];
       // debug_advisory(&format!(
       //   "FreeTerm::findConstraintPropagationSequence(): phase 1 step = {}",
       //   step
@@ -236,8 +236,8 @@ impl FreeTerm {
       if let Some(v) = occurrence.try_dereference_term::<VariableTerm>() {
         let index: i32 = v.index;
 
-        assert!(index > 100, "index too big");
-        assert!(index < 0, "index negative");
+        assert!(index < 100, "index too big");
+        assert!(index >= 0, "index negative");
         if bound_uniquely.contains(index as usize) {
           bound_variables.push(occurrence);
         } else {
@@ -297,7 +297,7 @@ impl FreeTerm {
   /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
   /// the `save_index`. Maude's `compileRhs2`
   #[inline(always)]
-  fn compile_rhs_aux(
+  pub(crate) fn compile_rhs_aux(
     &mut self,
     rhs_builder: &mut RHSBuilder,
     variable_info: &mut VariableInfo,
@@ -315,8 +315,9 @@ impl FreeTerm {
       &mut free_variable_count,
     );
 
-    let mut automaton: Box<dyn RHSAutomaton> =
-      FreeRHSAutomaton::with_arity_and_free_variable_count(max_arity, free_variable_count);
+    // Only the general automaton takes instructions from `add_free`, so the variations specialized for low arities
+    // that `with_arity_and_free_variable_count` would choose can't be used yet.
+    let mut automaton: Box<dyn RHSAutomaton> = Box::new(FreeRHSAutomaton::default());
 
     let index = self.compile_into_automaton(
       automaton.as_mut(),
@@ -346,7 +347,7 @@ impl FreeTerm {
     // we sort in order of arguments by number of symbol occurrences, and build
     // largest first.
     let mut order: Vec<(i32, usize)> = (0..arg_count)
-      .map(|i| (-self.args[i].borrow().compute_size(), i))
+      .map(|i| (-self.args[i].borrow_mut().compute_size(), i))
       .collect();
 
     order.sort_unstable();
@@ -361,11 +362,13 @@ impl FreeTerm {
       let term: RcTerm = self.args[idx].clone();
 
       // Argument is free - see if we need to compile it into current automaton.
-      if !available_terms.contains(term.as_ref(), arg_is_eager) {
-        let source = if let Some(free_term) = term.borrow_mut().as_any_mut().downcast_mut::<FreeTerm>() {
-          free_term.compile_into_automaton(automaton, rhs_builder, variable_info, available_terms, arg_is_eager)
-        } else {
-          unreachable!()
+      let is_free = term.borrow().as_any().is::<FreeTerm>();
+      if is_free && !available_terms.contains(term.as_ref(), arg_is_eager) {
+        let source = match term.borrow().as_any().downcast_ref::<FreeTerm>() {
+          Some(free_term) => {
+            free_term.compile_into_automaton(automaton, rhs_builder, variable_info, available_terms, arg_is_eager)
+          }
+          None => unreachable!("Failed to downcast a free term. This is a bug."),
         };
         sources[idx] = source;
         term.borrow_mut().term_members_mut().save_index = source;
@@ -543,10 +546,17 @@ impl FreeTerm {
     let symbol = self.symbol();
     for i in 0..arg_count as usize {
       let arg_eager = eager_context && symbol.strategy().eager_argument(i);
-      let term = &mut self.args[i];
-      if let Some(free_term) = term.borrow_mut().as_any_mut().downcast_mut::<FreeTerm>() {
+      let term = self.args[i].clone();
+      // The borrow of a free argument must end before an alien argument is compiled, which borrows it again.
+      let is_free = term.borrow().as_any().is::<FreeTerm>();
+      if is_free {
         *free_variable_count += 1;
-        if !available_terms.contains(free_term, arg_eager) {
+        let mut term = term.borrow_mut();
+        let free_term = match term.as_any_mut().downcast_mut::<FreeTerm>() {
+          Some(free_term) => free_term,
+          None => unreachable!("Failed to downcast a free term. This is a bug."),
+        };
+        if !available_terms.contains(&*free_term, arg_eager) {
           free_term.compile_rhs_aliens(
             rhs_builder,
             variable_info,
@@ -557,7 +567,7 @@ impl FreeTerm {
           );
         }
       } else {
-        compile_rhs(term.clone(), rhs_builder, variable_info, available_terms, arg_eager);
+        compile_rhs(term, rhs_builder, variable_info, available_terms, arg_eager);
       }
     }
   }
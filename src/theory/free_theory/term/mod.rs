@@ -28,6 +28,7 @@ use crate::{
   rc_cell,
   theory::{
     free_theory::{FreeRemainder, RcFreeRemainder},
+    make_rc_dag_node,
     DagNode,
     NodeCache,
     RcDagNode,
@@ -158,7 +159,7 @@ impl Term for FreeTerm {
     // assert_eq!(self.symbol(), other.symbol(), "symbols differ");
     if let Some(other) = other.as_any().downcast_ref::<FreeDagNode>() {
       for (arg_self, arg_other) in self.args.iter().zip(other.iter_args()) {
-        let r = arg_self.borrow().compare_dag_node(arg_other.as_ref());
+        let r = arg_self.borrow().compare_dag_node(&**arg_other.as_ref());
         if r.is_ne() {
           return r;
         }
@@ -177,7 +178,7 @@ impl Term for FreeTerm {
       for (term_arg, dag_arg) in self.args.iter().zip(da.iter_args()) {
         let r = term_arg
           .borrow()
-          .partial_compare(partial_substitution, dag_arg.as_ref());
+          .partial_compare(partial_substitution, &**dag_arg.as_ref());
         if r != OrderingValue::Equal {
           return r;
         }
@@ -200,9 +201,7 @@ impl Term for FreeTerm {
     for arg in &self.args {
       node.members.args.push(arg.borrow_mut().dagify(sub_dags, set_sort_info));
     }
-    // Needed to specify generic trait object.
-    let node: RcCell<dyn DagNode> = rc_cell!(node);
-    node
+    make_rc_dag_node(node)
   }
 
   // region Compiler-related
@@ -222,11 +221,11 @@ impl Term for FreeTerm {
   fn compile_rhs_aux(
     &mut self,
     rhs_builder: &mut RHSBuilder,
-    variable_info: &VariableInfo,
+    variable_info: &mut VariableInfo,
     available_terms: &mut TermBag,
     eager_context: bool,
   ) -> i32 {
-    FreeTerm::compile_rhs_aux(&mut self, rhs_builder, variable_info, available_terms, eager_context)
+    FreeTerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
   }
 
   #[inline(always)]
@@ -26,10 +26,16 @@ mod term;
 // mod associative_symbol;
 
 // Theories
+pub mod a_theory;
+pub mod acu_theory;
+pub mod c_theory;
 pub mod free_theory;
+pub mod s_theory;
+pub mod u_theory;
 pub mod term_compiler;
 pub mod variable;
-// pub mod acu_theory;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
 
 use std::rc::Rc;
 
@@ -37,18 +43,30 @@ pub(crate) use automaton::{
   lhs_automaton::{BxLHSAutomaton, LHSAutomaton, RcLHSAutomaton},
   rhs_automaton::{BxRHSAutomaton, RHSAutomaton, RcRHSAutomaton},
 };
-pub(crate) use dag_node::{AtomicNodeList, DagNode, DagNodeMembers, DagPair, NodeList, RcDagNode};
+pub(crate) use dag_node::{
+  make_rc_dag_node,
+  AtomicNodeList,
+  DagNode,
+  DagNodeMembers,
+  DagPair,
+  MaybeDagNode,
+  NodeList,
+  RcDagNode,
+};
 pub(crate) use dag_node_flags::{DagNodeFlag, DagNodeFlags};
 pub(crate) use subproblem::{
+  Alternative,
+  AlternativesSubproblem,
   ExtensionInfo,
   MaybeSubproblem,
+  RcExtensionInfo,
   RcSubproblem,
   Subproblem,
   SubproblemSequence,
   VariableAbstractionSubproblem,
 };
 pub(crate) use symbol::{BinarySymbol, RcSymbol, Symbol, SymbolMembers, SymbolSet};
-pub(crate) use symbol_type::{BasicSymbolTypes, SymbolType};
+pub(crate) use symbol_type::{BasicSymbolTypes, SymbolAttribute, SymbolType};
 pub(crate) use term::{
   find_available_terms,
   index_variables,
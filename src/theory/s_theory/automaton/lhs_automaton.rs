@@ -0,0 +1,84 @@
+/*!
+
+The LHS automaton for the S theory. A pattern `s^k(p)` matches a subject `s^n(u)` only if `n ≥ k`, in which case `p`
+must match `s^(n-k)(u)`, or `u` itself if `n = k`. A subject without our symbol counts as `n = 0`. Since the pattern is
+normalized, `p` does not have our symbol, so if it is not a variable it can only match when `n = k`. In every case
+matching takes constant time in the exponents.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{rc_cell, BigInteger, RcCell},
+  core::{sort::SpecialSort, substitution::Substitution},
+  theory::{
+    make_rc_dag_node,
+    s_theory::SDagNode,
+    DagNode,
+    LHSAutomaton,
+    MaybeSubproblem,
+    RcDagNode,
+    RcLHSAutomaton,
+    RcSymbol,
+  },
+};
+
+
+pub struct SLHSAutomaton {
+  top_symbol:      RcSymbol,
+  number:          BigInteger,
+  /// Whether the argument is a variable, and so can take what is left of a subject with a larger exponent.
+  arg_is_variable: bool,
+  arg_automaton:   RcLHSAutomaton,
+}
+
+impl SLHSAutomaton {
+  pub(crate) fn new(
+    top_symbol: RcSymbol,
+    number: BigInteger,
+    arg_is_variable: bool,
+    arg_automaton: RcLHSAutomaton,
+  ) -> Self {
+    SLHSAutomaton {
+      top_symbol,
+      number,
+      arg_is_variable,
+      arg_automaton,
+    }
+  }
+}
+
+
+impl LHSAutomaton for SLHSAutomaton {
+  fn match_(&mut self, subject: RcDagNode, solution: &mut Substitution) -> (bool, MaybeSubproblem) {
+    let (number, arg) = {
+      let subject_ref = subject.borrow();
+      if subject_ref.symbol().as_ref() != self.top_symbol.as_ref() {
+        return (false, None);
+      }
+      match subject_ref.as_any().downcast_ref::<SDagNode>() {
+        Some(s_dag_node) => (s_dag_node.number().clone(), s_dag_node.arg()),
+        None => unreachable!("Could not downcast to SDagNode. This is a bug."),
+      }
+    };
+
+    if number < self.number {
+      return (false, None);
+    }
+    if number == self.number {
+      return self.arg_automaton.borrow_mut().match_(arg, solution);
+    }
+    if !self.arg_is_variable {
+      return (false, None);
+    }
+
+    // The variable takes what is left of the subject.
+    let mut remainder = SDagNode::new(self.top_symbol.clone(), &number - &self.number, arg.clone());
+    if arg.borrow().get_sort_index() != SpecialSort::Unknown as i32 {
+      remainder.compute_base_sort();
+    }
+    let remainder: RcDagNode = make_rc_dag_node(remainder);
+    self.arg_automaton.borrow_mut().match_(remainder, solution)
+  }
+}
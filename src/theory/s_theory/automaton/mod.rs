@@ -0,0 +1,12 @@
+/*!
+
+The LHS and RHS automata of the S theory.
+
+*/
+mod lhs_automaton;
+mod rhs_automaton;
+
+
+// Re-exports
+pub use lhs_automaton::SLHSAutomaton;
+pub use rhs_automaton::SRHSAutomaton;
@@ -0,0 +1,69 @@
+/*!
+
+The RHS automaton for the S theory. Each instruction builds one S DAG node from an argument that has already been
+constructed (or bound) in the substitution. If the argument has our symbol, its exponent is added to ours.
+
+*/
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+  abstractions::{rc_cell, BigInteger, RcCell},
+  core::{substitution::Substitution, VariableInfo},
+  theory::{dag_node::MaybeDagNode, make_rc_dag_node, s_theory::SDagNode, DagNode, RHSAutomaton, RcDagNode, RcSymbol},
+};
+
+
+pub struct SRHSAutomaton {
+  top_symbol:  RcSymbol,
+  number:      BigInteger,
+  source:      i32,
+  destination: i32,
+}
+
+impl SRHSAutomaton {
+  pub fn new(top_symbol: RcSymbol, number: BigInteger, source: i32, destination: i32) -> Self {
+    SRHSAutomaton {
+      top_symbol,
+      number,
+      source,
+      destination,
+    }
+  }
+
+  /// Builds the normalized node from the binding in `matcher`.
+  fn build(&self, matcher: &mut Substitution) -> SDagNode {
+    let value = matcher.value(self.source as usize);
+    assert!(value.is_some(), "unbound source {} in S RHS. This is a bug.", self.source);
+
+    SDagNode::make_normalized(self.top_symbol.clone(), self.number.clone(), value.unwrap())
+  }
+}
+
+
+impl RHSAutomaton for SRHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    self.source = variable_info.remap_index(self.source);
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let new_dag_node: RcDagNode = make_rc_dag_node(self.build(matcher));
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let mut new_dag_node = self.build(matcher);
+    new_dag_node.overwrite_with_clone(old);
+  }
+}
@@ -0,0 +1,232 @@
+/*!
+
+Concrete types for the S theory implementing the DagNode trait.
+
+An `SDagNode` stands for `number` applications of its symbol to its argument, which is stored in
+`DagNodeMembers::args`. Normal form for an S node is that the argument does not have the node's symbol, so that
+`s(s(s(X)))` is a single node `s^3(X)`.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{SSymbol, STerm};
+use crate::{
+  abstractions::{hash3, BigInteger, RcCell},
+  core::{hash_cons_set::HashConsSet, sort::SpecialSort, RedexPosition},
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, DagNodeFlags, DagNodeMembers, NodeList, RcDagNode, RcSymbol, RcTerm, Symbol},
+};
+
+
+pub type RcSDagNode = RcCell<SDagNode>;
+
+pub struct SDagNode {
+  pub(crate) members: DagNodeMembers,
+  pub(crate) number:  BigInteger,
+}
+
+impl SDagNode {
+  pub fn new(symbol: RcSymbol, number: BigInteger, arg: RcDagNode) -> Self {
+    assert!(number.is_positive(), "an S node needs a positive exponent");
+    let mut args = NodeList::default();
+    args.push(arg);
+
+    SDagNode {
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args,
+        flags: Default::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc: None,
+        hash: 0,
+      },
+      number,
+    }
+  }
+
+  /// Makes the node `s^number(arg)` in normal form.
+  pub fn make_normalized(symbol: RcSymbol, number: BigInteger, arg: RcDagNode) -> SDagNode {
+    let mut node = SDagNode::new(symbol, number, arg);
+    node.normalize_at_top();
+    node
+  }
+
+  #[inline(always)]
+  pub fn s_symbol(&self) -> &SSymbol {
+    match self.members.top_symbol.as_any().downcast_ref::<SSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to SSymbol. This is a bug."),
+    }
+  }
+
+  #[inline(always)]
+  pub fn arg(&self) -> RcDagNode {
+    self.members.args[0].clone()
+  }
+
+  #[inline(always)]
+  pub fn number(&self) -> &BigInteger {
+    &self.number
+  }
+
+  /// If the argument has our symbol, absorbs it into our exponent. Since the argument is in normal form, one step is
+  /// enough.
+  pub fn normalize_at_top(&mut self) {
+    let absorbed = {
+      let arg = self.arg();
+      let arg_ref = arg.borrow();
+      if arg_ref.symbol().as_ref() != self.symbol().as_ref() {
+        return;
+      }
+      match arg_ref.as_any().downcast_ref::<SDagNode>() {
+        Some(s_dag_node) => (s_dag_node.number.clone(), s_dag_node.arg()),
+        None => unreachable!("Could not downcast to SDagNode. This is a bug."),
+      }
+    };
+
+    let (number, inner) = absorbed;
+    self.number += &number;
+    self.members.args = NodeList::default();
+    self.members.args.push(inner);
+  }
+}
+
+
+impl DagNode for SDagNode {
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
+  }
+
+  /// Nodes with the larger exponent are larger; equal exponents are ordered by their arguments.
+  fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => {
+        self
+          .number
+          .cmp(&s_dag_node.number)
+          .then_with(|| self.arg().borrow().compare(&**s_dag_node.arg().borrow()))
+      }
+      None => unreachable!("Could not downcast a DagNode to an SDagNode. This is a bug."),
+    }
+  }
+
+  fn structural_hash(&self) -> u32 {
+    if self.is_hash_valid() {
+      return self.members.hash;
+    }
+    hash3(
+      self.symbol().semantic_hash(),
+      self.number.hash_value(),
+      self.arg().borrow().structural_hash(),
+    )
+  }
+
+  fn compute_base_sort(&mut self) -> i32 {
+    let arg_index = self.arg().borrow().get_sort_index();
+    assert_ne!(
+      arg_index,
+      SpecialSort::Unknown as i32,
+      "unknown sort encountered for arg of {}",
+      self.symbol().name()
+    );
+
+    let index = self.s_symbol().compute_iterated_sort_index(arg_index, &self.number);
+    self.set_sort_index(index);
+    index
+  }
+
+  fn termify(&self) -> RcTerm {
+    rc_cell!(STerm::new(self.symbol(), self.number.clone(), self.arg().borrow().termify()))
+  }
+
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = SDagNode::new(self.symbol(), self.number.clone(), self.arg());
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacements(&self, redex_stack: &[RedexPosition], first_idx: usize, last_idx: usize) -> RcDagNode {
+    assert!(
+      first_idx == last_idx && last_idx < redex_stack.len(),
+      "bad replacement range"
+    );
+    assert_eq!(redex_stack[first_idx].arg_index, 0, "bad replacement arg index");
+
+    self.copy_with_replacement(redex_stack[first_idx].dag_node.clone(), 0)
+  }
+
+  fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
+    assert_eq!(arg_index, 0, "bad argIndex");
+    // The replacement may have our symbol.
+    make_rc_dag_node(SDagNode::make_normalized(self.symbol(), self.number.clone(), replacement))
+  }
+
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    let arg = self.arg();
+    let arg = if self.symbol().strategy().eager_argument(0) {
+      // `None` means the argument is already reduced and can be shared.
+      let copy = arg.borrow_mut().copy_eager_upto_reduced();
+      copy.unwrap_or(arg)
+    } else {
+      arg
+    };
+
+    make_rc_dag_node(SDagNode::new(self.symbol(), self.number.clone(), arg))
+  }
+
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    let arg = self.arg();
+    let copy = arg.borrow_mut().copy_all();
+
+    make_rc_dag_node(SDagNode::new(self.symbol(), self.number.clone(), copy.unwrap_or(arg)))
+  }
+
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = SDagNode::new(self.symbol(), self.number.clone(), self.arg());
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+
+    *old.borrow_mut() = Box::new(new_node);
+  }
+
+  /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let arg = self.arg();
+    let (canonical_arg, _) = hash_cons_set.insert(arg.clone());
+
+    if RcCell::ptr_eq(&canonical_arg, &arg) {
+      // Can use the original DAG node as the canonical version
+      return rc_dag_node;
+    }
+
+    let mut new_node = SDagNode::new(self.symbol(), self.number.clone(), canonical_arg);
+    new_node.members.flags.set_copied_flags(self.members.flags);
+    new_node.members.sort_index = self.members.sort_index;
+
+    make_rc_dag_node(new_node)
+  }
+}
@@ -0,0 +1,111 @@
+/*!
+
+The S theory: unary symbols declared with the `Iter` attribute. Terms and DAG nodes with an S top symbol store a tower
+of applications `s(s(…s(t)…))` as the single node `s^n(t)` with an exponent `n`, so Peano style numbers don't turn into
+DAGs with millions of nodes.
+
+Matching is done by the `SLHSAutomaton`, which compares exponents and matches the argument once, so it takes constant
+time in the exponents.
+
+*/
+
+mod automaton;
+mod dag_node;
+mod symbol;
+mod term;
+
+pub use automaton::{SLHSAutomaton, SRHSAutomaton};
+pub use dag_node::{RcSDagNode, SDagNode};
+pub use symbol::{RcSSymbol, SSymbol};
+pub use term::{RcSTerm, STerm};
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::{BigInteger, NatSet, RcCell},
+    core::{substitution::Substitution, VariableInfo},
+    rc_cell,
+    theory::{
+      test_fixtures::{make_constant, make_s_symbol, make_sorts, make_variable},
+      DagNode,
+      RHSAutomaton,
+      RcDagNode,
+      RcSymbol,
+      RcTerm,
+      Term,
+    },
+  };
+
+  /// Makes `s(s(…s(arg)…))` the long way, with `count` applications.
+  fn make_tower(s: &RcSymbol, count: usize, arg: &RcTerm) -> RcTerm {
+    let mut term = arg.clone();
+    for _ in 0..count {
+      term = rc_cell!(STerm::new(s.clone(), BigInteger::one(), term));
+    }
+    term
+  }
+
+  #[test]
+  fn normalize_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let s = make_s_symbol(&nat);
+    let zero = make_constant("0", &nat);
+
+    // s(s(s(0))) normalizes to the single term s^3(0), and dagifies to a single node.
+    let tower = make_tower(&s, 3, &zero);
+    tower.borrow_mut().normalize(true);
+    let compact: RcTerm = rc_cell!(STerm::new(s.clone(), BigInteger::from(3), zero.clone()));
+    assert!(tower.borrow().compare(&*compact.borrow()).is_eq());
+
+    let dag = tower.borrow().make_dag();
+    match dag.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => {
+        assert_eq!(*s_dag_node.number(), BigInteger::from(3));
+        assert!(s_dag_node.arg().borrow().symbol().as_ref() == zero.borrow().symbol().as_ref());
+      }
+      None => panic!("s^3(0) did not dagify to an SDagNode"),
+    };
+  }
+
+  #[test]
+  fn match_and_construct_test() {
+    let (_kind, nat) = make_sorts("Nat");
+    let s = make_s_symbol(&nat);
+    let zero = make_constant("0", &nat);
+    let x = make_variable("X", 0, &nat);
+
+    let subject = STerm::new(s.clone(), BigInteger::from(2), zero.clone()).make_dag();
+    let zero_dag: RcDagNode = subject.borrow().iter_args().next().unwrap();
+    zero_dag.borrow_mut().set_sort_index(1);
+
+    // s^2(X) against s^2(0) binds X to 0, while s^3(X) doesn't match.
+    let mut pattern = STerm::new(s.clone(), BigInteger::from(2), x.clone());
+    pattern.normalize(true);
+    let (automaton, _) = pattern.compile_lhs(true, &VariableInfo::default(), &mut NatSet::default());
+    let mut solution = Substitution::with_capacity(1);
+    let (matched, subproblem) = automaton.borrow_mut().match_(subject.clone(), &mut solution);
+    assert!(matched && subproblem.is_none());
+    assert!(solution.value(0).unwrap().borrow().compare(&**zero_dag.borrow()).is_eq());
+
+    let pattern = STerm::new(s.clone(), BigInteger::from(3), x.clone());
+    let (automaton, _) = pattern.compile_lhs(true, &VariableInfo::default(), &mut NatSet::default());
+    let (matched, _) = automaton
+      .borrow_mut()
+      .match_(subject.clone(), &mut Substitution::with_capacity(1));
+    assert!(!matched);
+
+    // Building s^3(X) with X bound to s^2(0) absorbs the argument's exponent.
+    let automaton = SRHSAutomaton::new(s.clone(), BigInteger::from(3), 0, 1);
+    let mut matcher = Substitution::with_capacity(2);
+    matcher.bind(0, Some(subject.clone()));
+    let built = automaton.construct(&mut matcher).unwrap();
+    match built.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => assert_eq!(*s_dag_node.number(), BigInteger::from(5)),
+      None => panic!("s^3(s^2(0)) was not built as an SDagNode"),
+    };
+  }
+}
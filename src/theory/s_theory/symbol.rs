@@ -0,0 +1,102 @@
+/*!
+
+A symbol belonging to the S theory, that is, a unary symbol declared with the `Iter` attribute. A stack of `n`
+applications of the symbol is stored as a single node with the exponent `n`.
+
+ */
+
+use std::{any::Any, rc::Rc};
+
+use super::{SDagNode, STerm};
+use crate::{
+  abstractions::{BigInteger, IString},
+  core::{rewrite_context::RewritingContext, sort::SpecialSort, Strategy},
+  theory::{DagNode, RcDagNode, RcTerm, Symbol, SymbolMembers},
+};
+
+
+pub type RcSSymbol = Rc<SSymbol>;
+
+
+pub struct SSymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+}
+
+impl SSymbol {
+  pub fn new(name: IString, strategy: Strategy, memo_flag: bool) -> SSymbol {
+    let mut symbol_members = SymbolMembers::new(name, 1, memo_flag);
+    symbol_members.strategy = strategy;
+
+    SSymbol { symbol_members }
+  }
+
+  pub fn make_term_with_args(self, number: BigInteger, arg: RcTerm) -> STerm {
+    STerm::new(Rc::new(self), number, arg)
+  }
+
+  /// Computes the sort index of `number` applications of the symbol to an argument with sort index `arg_index`.
+  /// Walking the sort diagram once per application would take time linear in `number`, but the sequence of sort
+  /// indices must repeat after at most as many steps as there are sorts, so we stop at the first repeat and use the
+  /// period to find the answer.
+  pub(crate) fn compute_iterated_sort_index(&self, arg_index: i32, number: &BigInteger) -> i32 {
+    let sort_table = self.sort_table();
+    let mut seen: Vec<i32> = vec![arg_index];
+    let mut index = arg_index;
+    let mut steps = BigInteger::zero();
+
+    while steps < *number {
+      if index == SpecialSort::ErrorSort as i32 {
+        return index;
+      }
+      index = sort_table.traverse(0, index as usize);
+      steps += BigInteger::one();
+
+      if let Some(start) = seen.iter().position(|&seen_index| seen_index == index) {
+        // The indices from `start` on repeat with this period.
+        let period = BigInteger::from(seen.len() - start);
+        let offset = &(number - &steps) % &period;
+        return seen[start + offset.to_usize().unwrap()];
+      }
+      seen.push(index);
+    }
+
+    index
+  }
+}
+
+impl Symbol for SSymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    // S symbols only support the standard strategy: the argument is evaluated before we try equations.
+    let arg = match subject.borrow().as_any().downcast_ref::<SDagNode>() {
+      Some(s_dag_node) => s_dag_node.arg(),
+      None => unreachable!("Could not downcast to SDagNode. This is a bug."),
+    };
+    context.reduce_dag_node(arg);
+
+    // The argument may have rewritten to a node with our symbol, which we absorb into our exponent.
+    match subject.borrow_mut().as_any_mut().downcast_mut::<SDagNode>() {
+      Some(s_dag_node) => s_dag_node.normalize_at_top(),
+      None => unreachable!("Could not downcast to SDagNode. This is a bug."),
+    };
+
+    self.symbol_members.eq_rewrite(subject, context)
+  }
+}
@@ -0,0 +1,89 @@
+/*!
+
+The implementation of the compiler-related methods of the `Term` trait for `STerm`.
+
+ */
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::STerm;
+use crate::{
+  abstractions::{rc_cell, NatSet, RcCell},
+  core::{automata::RHSBuilder, BindingLHSAutomaton, TermBag, VariableInfo},
+  theory::{
+    find_available_terms,
+    s_theory::{SLHSAutomaton, SRHSAutomaton},
+    term_compiler::compile_rhs,
+    RcLHSAutomaton,
+    Term,
+  },
+  NONE,
+};
+
+impl STerm {
+  pub fn compile_lhs(
+    &self,
+    _match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    // The argument is matched against a single, uniquely determined subject, so what it binds is bound uniquely.
+    let arg = self.arg.borrow();
+    let (arg_automaton, subproblem_likely) = arg.compile_lhs(false, variable_info, bound_uniquely);
+
+    let mut automaton: RcLHSAutomaton = rc_cell!(SLHSAutomaton::new(
+      self.symbol(),
+      self.number.clone(),
+      arg.is_variable(),
+      arg_automaton,
+    ));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    (automaton, subproblem_likely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  pub fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    let symbol = self.symbol();
+    let arg_eager = eager_context && symbol.strategy().eager_argument(0);
+    let source = compile_rhs(self.arg.clone(), rhs_builder, variable_info, available_terms, arg_eager);
+
+    // Need to flag last use of the source.
+    variable_info.use_index(source);
+
+    let index = variable_info.make_construction_index();
+    rhs_builder.add_rhs_automaton(Box::new(SRHSAutomaton::new(symbol, self.number.clone(), source, index)));
+    index
+  }
+
+  pub fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    // The argument is matched against whatever is left of the subject once our exponent is taken off, so whatever it
+    // binds uniquely, we bind uniquely.
+    self.arg.borrow_mut().analyse_constraint_propagation(bound_uniquely);
+  }
+
+  /// The theory-specific part of find_available_terms
+  pub fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    if self.ground() {
+      return;
+    }
+
+    let symbol = self.symbol();
+    let arg_eager = if at_top {
+      eager_context && symbol.strategy().eager_argument(0)
+    } else {
+      eager_context && symbol.strategy().evaluated_argument(0)
+    };
+    find_available_terms(self.arg.clone(), available_terms, arg_eager, false);
+  }
+}
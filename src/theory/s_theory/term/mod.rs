@@ -0,0 +1,211 @@
+/*!
+
+`Term` implementation for the S theory.
+
+An `STerm` stands for `number` applications of its symbol to its argument. Normalization absorbs an argument with the
+same symbol into the exponent, so `s(s(s(X)))` becomes `s^3(X)`. The compiler for the matcher is in `compiler.rs`.
+
+*/
+
+mod compiler;
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{SDagNode, SSymbol};
+use crate::{
+  abstractions::{hash2 as term_hash, BigInteger, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    TermBag,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, NodeCache, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm, Term, TermMembers},
+};
+
+pub type RcSTerm = RcCell<STerm>;
+
+pub struct STerm {
+  pub(crate) term_members: TermMembers,
+  pub(crate) arg:          RcTerm,
+  pub(crate) number:       BigInteger,
+}
+
+// Constructors
+impl STerm {
+  pub fn new(symbol: RcSymbol, number: BigInteger, arg: RcTerm) -> STerm {
+    assert!(number.is_positive(), "an S term needs a positive exponent");
+    STerm {
+      term_members: TermMembers::new(symbol),
+      arg,
+      number,
+    }
+  }
+
+  #[inline(always)]
+  pub fn s_symbol(&self) -> &SSymbol {
+    match self.term_members.top_symbol.as_any().downcast_ref::<SSymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to SSymbol. This is a bug."),
+    }
+  }
+
+  #[inline(always)]
+  pub fn number(&self) -> &BigInteger {
+    &self.number
+  }
+}
+
+
+impl Term for STerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  /// In sync with `normalize`.
+  fn semantic_hash(&self) -> u32 {
+    term_hash(
+      term_hash(self.symbol().semantic_hash(), self.arg.borrow().semantic_hash()),
+      self.number.hash_value(),
+    )
+  }
+
+  /// In sync with `semantic_hash`.
+  fn normalize(&mut self, full: bool) -> (u32, bool) {
+    let (_, mut changed) = self.arg.borrow_mut().normalize(full);
+
+    // Absorb an argument with our symbol. It is already normalized, so one step is enough.
+    let absorbed = match self.arg.borrow().as_any().downcast_ref::<STerm>() {
+      Some(s_term) if s_term.symbol().as_ref() == self.symbol().as_ref() => Some((s_term.number.clone(), s_term.arg.clone())),
+      _ => None,
+    };
+    if let Some((number, arg)) = absorbed {
+      self.number += &number;
+      self.arg = arg;
+      changed = true;
+    }
+
+    (self.semantic_hash(), changed)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(std::iter::once(self.arg.clone()))
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    assert_eq!(&self.symbol(), &other.symbol(), "symbols differ");
+
+    if let Some(other) = other.as_any().downcast_ref::<STerm>() {
+      self
+        .number
+        .cmp(&other.number)
+        .then_with(|| self.arg.borrow().compare(&*other.arg.borrow()))
+    } else {
+      unreachable!("Could not downcast Term to STerm. This is a bug.")
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    if let Some(other) = other.as_any().downcast_ref::<SDagNode>() {
+      self
+        .number
+        .cmp(other.number())
+        .then_with(|| self.arg.borrow().compare_dag_node(&**other.arg().borrow()))
+    } else {
+      unreachable!("Could not downcast DagNode to SDagNode. This is a bug.")
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, sub_dags: &mut NodeCache, set_sort_info: bool) -> RcDagNode {
+    let arg = self.arg.borrow().dagify(sub_dags, set_sort_info);
+    make_rc_dag_node(SDagNode::make_normalized(self.symbol(), self.number.clone(), arg))
+  }
+
+  // region Compiler-related
+  #[inline(always)]
+  fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    STerm::compile_lhs(self, match_at_top, variable_info, bound_uniquely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  #[inline(always)]
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    STerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
+  }
+
+  #[inline(always)]
+  fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    STerm::analyse_constraint_propagation(self, bound_uniquely)
+  }
+
+  #[inline(always)]
+  fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    STerm::find_available_terms_aux(self, available_terms, eager_context, at_top);
+  }
+  // endregion
+}
+
+
+impl Formattable for STerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut accumulator = String::new();
+    match style {
+      FormatStyle::Debug => {
+        accumulator.push_str(format!("s<{}>", self.term_members.top_symbol.repr(style)).as_str());
+      }
+
+      _ => {
+        accumulator.push_str(self.term_members.top_symbol.repr(style).as_str());
+      }
+    }
+
+    if !self.number.is_one() {
+      accumulator.push_str(format!("^{}", self.number).as_str());
+    }
+    accumulator.push_str(format!("({})", self.arg.borrow().repr(style)).as_str());
+
+    accumulator
+  }
+}
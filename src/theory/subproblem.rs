@@ -27,19 +27,32 @@ object with its state updated. Thus, solutions can be extracted from the subprob
 
  */
 
-use std::rc::Rc;
+use std::{any::Any, rc::Rc};
 
-use super::{automaton::LHSAutomaton, RcDagNode};
-use crate::core::{rewrite_context::RewritingContext, substitution::Substitution, LocalBindings};
+use super::{RcDagNode, RcLHSAutomaton};
+use crate::{
+  abstractions::RcCell,
+  core::{rewrite_context::RewritingContext, substitution::Substitution, LocalBindings},
+};
 
 //	These traits must be derived from for equational theories that
 //	need to generate matching or unification subproblems or
 //	pass back extension information.
 
-// ToDo: This appears to be used as an output parameter for methods that create subproblems. So far
-//       I have omitted these because they aren't used for the free theory.
+/// Records which part of the subject a pattern matched when matching at the top with extension, that is, when the
+/// pattern is allowed to match only part of the subject's arguments and leave the rest as context. Only the
+/// associative theories produce extension information; see `DagNode::make_extension_info`.
 pub trait ExtensionInfo {
-  // Todo: Implement `ExtensionInfo`.
+  fn as_any(&self) -> &dyn Any;
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+
+  /// Whether the extension information is still valid after the match has returned, or only becomes valid once the
+  /// returned subproblem is solved.
+  fn valid_after_match(&self) -> bool;
+
+  /// Whether the pattern matched the whole subject, in which case there is no extension.
+  fn matched_whole(&self) -> bool;
+
   /// sets the valid_after_match field
   fn set_valid_after_match(&mut self, value: bool);
 
@@ -50,6 +63,8 @@ pub trait ExtensionInfo {
   fn set_unmatched(&mut self, value: RcDagNode);
 }
 
+pub type RcExtensionInfo = RcCell<dyn ExtensionInfo>;
+
 pub type RcSubproblem = Rc<dyn Subproblem>;
 // pub type MaybeSubproblem = Option<RcSubproblem>;
 pub type MaybeSubproblem = Option<Box<dyn Subproblem>>;
@@ -59,28 +74,38 @@ pub trait Subproblem {
   fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool;
 }
 
+/// Matches a pattern against the value of an abstraction variable. A theory that cannot match a subterm of a pattern
+/// in place, typically because the subterm might collapse, binds an abstraction variable to the part of the subject
+/// the subterm must match and defers matching the subterm itself to this subproblem.
 pub struct VariableAbstractionSubproblem {
-  pub abstracted_pattern:   Box<dyn LHSAutomaton>,
+  pub abstracted_pattern:   RcLHSAutomaton,
   pub abstraction_variable: i32,
   pub variable_count:       u32,
   pub difference:           Option<LocalBindings>,
-  pub subproblem:           Option<Box<dyn Subproblem>>,
-  pub local:                Substitution, // Todo: How does this differ from `difference`?
-  pub solved:               bool,
+  pub subproblem:           MaybeSubproblem,
+  /// Scratch space for matching the abstracted pattern, so that we can compute the bindings it adds.
+  pub local:                Substitution,
 }
 
 impl VariableAbstractionSubproblem {
-  pub fn new(abstracted_pattern: Box<dyn LHSAutomaton>, abstraction_variable: i32, variable_count: u32) -> Self {
+  pub fn new(abstracted_pattern: RcLHSAutomaton, abstraction_variable: i32, variable_count: u32) -> Self {
     VariableAbstractionSubproblem {
       abstracted_pattern,
       abstraction_variable,
       variable_count,
-      difference: Some(LocalBindings::default()),
+      difference: None,
       subproblem: None,
-      local: Default::default(),
-      solved: false,
+      local: Substitution::with_capacity(variable_count as usize),
     }
   }
+
+  fn retract(&mut self, context: &mut RewritingContext) {
+    if let Some(difference) = self.difference.as_mut() {
+      difference.retract(&mut context.substitution);
+    }
+    self.difference = None;
+    self.subproblem = None;
+  }
 }
 
 impl Subproblem for VariableAbstractionSubproblem {
@@ -88,43 +113,122 @@ impl Subproblem for VariableAbstractionSubproblem {
     if find_first {
       self.local.copy_from_substitution(&context.substitution);
 
-      let v = context.substitution.get(self.abstraction_variable);
-      assert!(v.is_some(), "Unbound abstraction variable");
-      let v = v.unwrap();
+      let value = context.substitution.get(self.abstraction_variable);
+      assert!(value.is_some(), "Unbound abstraction variable");
+      let value = value.unwrap();
 
-      // Todo: What about the potential subproblem? Is it pushed to self.subproblem? If so, why return it?
-      if let (false, _) = self.abstracted_pattern.match_(v.clone(), &mut self.local /* None */) {
+      let (matched, subproblem) = self.abstracted_pattern.borrow_mut().match_(value, &mut self.local);
+      if !matched {
         return false;
       }
 
       self.difference = self.local.subtract(&context.substitution);
       if let Some(difference) = self.difference.as_mut() {
-        difference.assert(&mut context.substitution);
+        if !difference.assert(&mut context.substitution) {
+          self.difference = None;
+          return false;
+        }
       }
 
-      if let Some(subproblem) = &mut self.subproblem {
-        if subproblem.solve(true, context) {
-          return true;
+      self.subproblem = subproblem;
+      match self.subproblem.as_mut() {
+        None => return true,
+        Some(subproblem) => {
+          if subproblem.solve(true, context) {
+            return true;
+          }
         }
-      } else {
+      }
+    } else if let Some(subproblem) = self.subproblem.as_mut() {
+      // Without a subproblem, the match had exactly one solution, which we have already returned.
+      if subproblem.solve(false, context) {
         return true;
       }
+    }
+
+    self.retract(context);
+    false
+  }
+}
+
+/// One way a pattern matched, for theories that try a few ways of matching at match time, such as the argument orders
+/// of the C theory or the collapse cases of the U theory: the bindings the match made, relative to the substitution at
+/// match time, and whatever subproblem the argument matchers left over.
+pub(crate) struct Alternative {
+  difference: Option<LocalBindings>,
+  subproblem: MaybeSubproblem,
+}
+
+impl Alternative {
+  pub fn new(difference: Option<LocalBindings>, subproblem: MaybeSubproblem) -> Self {
+    Alternative { difference, subproblem }
+  }
+
+  fn retract(&mut self, context: &mut RewritingContext) {
+    if let Some(difference) = self.difference.as_mut() {
+      difference.retract(&mut context.substitution);
+    }
+  }
+}
+
+/// Enumerates the solutions of a match that succeeded in several ways by taking the alternatives in turn and, within
+/// an alternative, enumerating the solutions of its subproblem.
+pub struct AlternativesSubproblem {
+  alternatives: Vec<Alternative>,
+  /// The alternative the current solution came from.
+  current:      usize,
+}
+
+impl AlternativesSubproblem {
+  pub(crate) fn new(alternatives: Vec<Alternative>) -> Self {
+    AlternativesSubproblem {
+      alternatives,
+      current: 0,
+    }
+  }
+}
+
+impl Subproblem for AlternativesSubproblem {
+  fn solve(&mut self, find_first: bool, context: &mut RewritingContext) -> bool {
+    if find_first {
+      self.current = 0;
     } else {
-      if let Some(subproblem) = &mut self.subproblem {
-        if subproblem.solve(true, context) {
+      if self.current == self.alternatives.len() {
+        return false;
+      }
+      // Look for another solution of the current alternative's subproblem before moving on.
+      let alternative = &mut self.alternatives[self.current];
+      if let Some(subproblem) = alternative.subproblem.as_mut() {
+        if subproblem.solve(false, context) {
           return true;
         }
-      } else {
-        return true;
       }
+      alternative.retract(context);
+      self.current += 1;
     }
 
-    if let Some(difference) = self.difference.as_mut() {
-      difference.retract(&mut context.substitution);
-      self.difference = None;
+    while self.current < self.alternatives.len() {
+      let alternative = &mut self.alternatives[self.current];
+
+      let asserted = match alternative.difference.as_mut() {
+        Some(difference) => difference.assert(&mut context.substitution),
+        None => true,
+      };
+      if asserted {
+        match alternative.subproblem.as_mut() {
+          None => return true,
+          Some(subproblem) => {
+            if subproblem.solve(true, context) {
+              return true;
+            }
+          }
+        }
+        alternative.retract(context);
+      }
+
+      self.current += 1;
     }
 
-    self.subproblem = None;
     false
   }
 }
@@ -143,11 +247,12 @@ impl SubproblemSequence {
     self.sequence.push(subproblem);
   }
 
-  pub fn extract_subproblem(mut self) -> Box<dyn Subproblem> {
-    if self.sequence.len() == 1 {
-      self.sequence.pop().unwrap()
-    } else {
-      Box::new(self)
+  /// The accumulated subproblem, if any. As with Maude's accumulator, an empty sequence has no subproblem.
+  pub fn extract_subproblem(mut self) -> MaybeSubproblem {
+    match self.sequence.len() {
+      0 => None,
+      1 => self.sequence.pop(),
+      _ => Some(Box::new(self)),
     }
   }
 
@@ -28,25 +28,24 @@ parent, not the symbol, so that's what we do. Here's a summary.
 
 use std::{
   any::Any,
+  cell::{Cell, Ref, RefCell},
   cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd},
   fmt::{Debug, Display, Formatter},
   rc::Rc,
 };
 
-use tiny_logger::{log, Channel};
-
 use crate::{
   abstractions::{IString, Set},
   core::{
     format::{FormatStyle, Formattable},
-    interpreter::module::item::ModuleItem,
+    interpreter::{memo_table::MemoTable, module::item::ModuleItem, SourceSet},
     module::WeakModule,
-    pre_equation::{sort_constraint_table::SortConstraintTable, PreEquationKind, RcPreEquation},
+    pre_equation::{equation, rule, sort_constraint_table::SortConstraintTable, PreEquation, RcPreEquation},
     rewrite_context::RewritingContext,
-    sort::SortTable,
+    sort::{SignatureWarning, SortTable},
     Strategy,
   },
-  theory::{MaybeSubproblem, RcDagNode, RcTerm},
+  theory::{RcDagNode, RcTerm},
   NONE,
   UNDEFINED,
 };
@@ -76,8 +75,9 @@ pub struct SymbolMembers {
   pub memo_flag:         bool,
 
   /// `SortConstraintTable` members.
-  /// It is Maude's Symbol superclass, but we use composition instead.
-  pub sort_constraint_table: SortConstraintTable,
+  /// It is Maude's Symbol superclass, but we use composition instead. Symbols are shared by the time their module is
+  /// closed and the table is filled, hence the `RefCell`.
+  pub sort_constraint_table: RefCell<SortConstraintTable>,
 
   /// `SortTable` is Maude's Symbol superclass, but we use composition instead.
   pub sort_table: SortTable,
@@ -89,8 +89,12 @@ pub struct SymbolMembers {
   // `Strategy`
   pub(crate) strategy: Strategy,
 
-  // `EquationTable`
-  equations: Vec<RcPreEquation>,
+  // `EquationTable`, filled like the `SortConstraintTable` once the symbol is shared, hence the `RefCell`.
+  equations: RefCell<Vec<RcPreEquation>>,
+
+  // `RuleTable`
+  rules:     RefCell<Vec<RcPreEquation>>,
+  next_rule: Cell<usize>, // The rule to try first, so that every rule gets a turn
 }
 
 impl SymbolMembers {
@@ -103,11 +107,13 @@ impl SymbolMembers {
       arity,
       memo_flag,
       sort_constraint_table: Default::default(),
-      sort_table: Default::default(),
+      sort_table: SortTable::new(arity as usize),
       index_within_parent_module: NONE,
       parent_module: Default::default(),
       strategy: Strategy::default(),
-      equations: vec![],
+      equations: Default::default(),
+      rules: Default::default(),
+      next_rule: Cell::new(0),
     };
     // The only time the hash is computed.
     new_symbol.hash_value = new_symbol.compute_hash();
@@ -124,138 +130,175 @@ impl SymbolMembers {
     //       order. However, it still produces a total order on symbols in which symbols are ordered first
     //       by arity and then arbitrarily (by hash). Ordering by insertion order is just as arbitrary, so
     //       it should be ok.
-    IString::get_hash(&self.name) | (self.arity << 32) // Maude: self.arity << 24
+    IString::get_hash(&self.name) | (self.arity << 24)
   }
 
   // region EquationTable methods
 
-  fn apply_replace(&mut self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
-    for eq in &self.equations {
-      // Destructure the equation
-      if let PreEquationKind::Equation {
-        rhs_term,
-        ref mut rhs_builder,
-        fast_variable_count,
-      } = &eq.borrow().kind
-      {
-        if *fast_variable_count >= 0 {
-          // Fast case
-          context.substitution.clear_first_n(*fast_variable_count as usize);
-          if let Some(lhs_automaton) = &eq.borrow().lhs_automaton {
-            if let (true, sp) = lhs_automaton
-              .borrow_mut()
-              .match_(subject.clone(), &mut context.substitution)
-            {
-              if sp.is_some() || context.trace_status() {
-                self.apply_replace_slow_case(subject.clone(), eq.clone(), sp, context);
-              }
-              if
-              /* extension_info.is_none() || extension_info.matched_whole() */
-              true {
-                rhs_builder.replace(subject.clone(), &mut context.substitution); // Implement get_rhs_builder and
-                                                                                 // replace methods
-              } else {
-                // ToDo: Implement `partial_replace` on `RcDagNode`, or else determine what replaces it.
-                subject.borrow_mut().partial_replace(
-                  // ToDo: Implement get_rhs_builder and construct methods
-                  rhs_builder.construct(&mut context.substitution),
-                  // extension_info,
-                );
-              }
-              context.eq_count += 1;
-              context.finished();
-              // Memory::ok_to_collect_garbage();
-              return true;
-            }
-          } else {
-            unreachable!("LHS automaton expected. This is a bug.")
-          }
-        } else {
-          // General case
-          let nr_variables = eq.borrow().variable_info.protected_variable_count();
-          context.clear(nr_variables);
-          if let Some(lhs_automaton) = eq.borrow().lhs_automaton.clone() {
-            if let (true, sp) = lhs_automaton.match_(
-              subject.clone(),
-              context,
-              // extension_info,
-            ) {
-              self.apply_replace_slow_case(subject.clone(), eq, sp, context /* extension_info */);
-            }
-          }
-          context.finished();
-          // MemoryCell::ok_to_collect_garbage(); // Implement ok_to_collect_garbage
-        }
-      } else {
-        unreachable!("Destructured a nonequation as an equation. This is a bug.");
-      };
+  /// Whether `statement`, an equation or a rule, might rewrite a DAG node headed by this symbol. A statement whose lhs
+  /// is a variable would rewrite everything, so no symbol takes it.
+  #[inline(always)]
+  pub(crate) fn accept_statement(&self, statement: &PreEquation) -> bool {
+    !statement.lhs_term.borrow().is_variable() && self.might_match_pattern(&statement.lhs_term)
+  }
+
+  /// Adds an equation to the table if it might apply to this symbol. Equations are tried in the order they are
+  /// offered, except that `owise` equations are tried only after all the others.
+  pub fn offer_equation(&self, equation: RcPreEquation) {
+    if !self.accept_statement(&equation.borrow()) {
+      return;
+    }
+    let mut equations = self.equations.borrow_mut();
+    if equation.borrow().is_owise() {
+      equations.push(equation);
+    } else {
+      let position = equations.partition_point(|e| !e.borrow().is_owise());
+      equations.insert(position, equation);
     }
-    false
   }
 
-  fn apply_replace_slow_case(
-    &mut self,
-    subject: RcDagNode,
-    eq: RcPreEquation,
-    sp: MaybeSubproblem,
-    context: &mut RewritingContext,
-    /* extension_info: &mut ExtensionInfo, */
-  ) -> bool {
-    #[cfg(debug_assertions)]
-    log(
-      Channel::Debug,
-      5,
-      format!(
-        "EquationTable::applyReplace() slowCase:\nsubject = {}\neq = {}",
-        subject.borrow(),
-        eq.borrow().repr(FormatStyle::Simple)
-      )
-      .as_str(),
-    );
-
-    if sp.is_none() || sp.unwrap().solve(true, context) {
-      if !eq.has_condition() || eq.check_condition(subject, context, sp) {
-        let trace = RewritingContext::get_trace_status();
-        if trace {
-          context.trace_pre_eq_rewrite(subject.clone(), eq, RewritingContext::NORMAL);
-          if context.trace_abort() {
-            context.finished();
-            return false;
-          }
-        }
-        // Destructure the equation
-        if let PreEquationKind::Equation {
-          rhs_term,
-          ref mut rhs_builder,
-          fast_variable_count,
-        } = &eq.borrow().kind
-        {
-          // ToDo: Implement extension_info
-          if
-          /* extension_info.is_none() || extension_info.unwrap().matched_whole() */
-          true {
-            rhs_builder.replace(subject.clone(), &mut context.substitution);
-          } else {
-            // ToDo: Implement `partial_replace` on `RcDagNode`, or else determine what replaces it.
-            //       Apparently only used by AU, ACU, S theories.
-            subject.borrow_mut().partial_replace(
-              rhs_builder.construct(&mut context.substitution),
-              // extension_info.unwrap(),
-            );
-          }
-          context.increment_eq_count();
-          if trace {
-            context.trace_post_eq_rewrite(subject.clone());
-          }
-          context.finished();
-          // MemoryCell::ok_to_collect_garbage(); // Implement ok_to_collect_garbage if necessary
-          return true;
-        }
-      }
+  #[inline(always)]
+  pub fn equations(&self) -> Ref<Vec<RcPreEquation>> {
+    self.equations.borrow()
+  }
+
+  /// Compiles the equations offered to this symbol. Called once the module is complete.
+  pub fn compile_equations(&self) {
+    for equation in self.equations.borrow().iter() {
+      equation.borrow_mut().compile(true);
     }
+  }
+
+  /// Rewrites `subject` at the top with the first equation that applies, returning whether one did.
+  pub(crate) fn apply_replace(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    self
+      .equations
+      .borrow()
+      .iter()
+      .any(|eq| equation::apply_replace(eq, subject.clone(), context))
+  }
+
+  /// Like `apply_replace`, but `owise` equations are not tried. This is for user strategies that try equations before
+  /// all arguments have been evaluated.
+  pub(crate) fn apply_replace_no_owise(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    self
+      .equations
+      .borrow()
+      .iter()
+      .take_while(|eq| !eq.borrow().is_owise())
+      .any(|eq| equation::apply_replace(eq, subject.clone(), context))
+  }
+
+  /// Rewrites `subject` at the top with this symbol's equations. A memoized symbol instead looks the subject up in the
+  /// module's memo map, and on a miss reduces the subject to normal form here, so that the normal form can be recorded
+  /// for the subject. In that case the subject is already in normal form, so we return `false`.
+  pub(crate) fn eq_rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    if !self.memo_flag {
+      return self.apply_replace(subject, context);
+    }
+
+    let mut from = SourceSet::new();
+    if !self.memo_rewrite(&mut from, subject.clone(), context) && self.apply_replace(subject.clone(), context) {
+      context.reduce_dag_node(subject.clone());
+    }
+    self.memo_enter(&from, subject);
     false
   }
+
   // endregion EquationTable methods
+
+  // region RuleTable methods
+
+  /// Adds a rule to the table if it might apply to this symbol.
+  #[inline(always)]
+  pub fn offer_rule(&self, rule: RcPreEquation) {
+    if self.accept_statement(&rule.borrow()) {
+      self.rules.borrow_mut().push(rule);
+    }
+  }
+
+  #[inline(always)]
+  pub fn rules(&self) -> Ref<Vec<RcPreEquation>> {
+    self.rules.borrow()
+  }
+
+  #[inline(always)]
+  pub fn is_rule_free(&self) -> bool {
+    self.rules.borrow().is_empty()
+  }
+
+  /// Compiles the rules offered to this symbol. Called once the module is complete.
+  pub fn compile_rules(&self) {
+    for rule in self.rules.borrow().iter() {
+      rule.borrow_mut().compile(true);
+    }
+  }
+
+  /// Rewrites `subject` at the top with the first rule that applies, returning the result. Rules are tried round robin,
+  /// starting just after the rule that applied last time, so that a rule that always applies can't starve the others.
+  pub(crate) fn apply_rules(&self, subject: RcDagNode, context: &mut RewritingContext) -> Option<RcDagNode> {
+    let rules = self.rules.borrow();
+    let rule_count = rules.len();
+    let first = self.next_rule.get();
+    for i in 0..rule_count {
+      let n = (first + i) % rule_count;
+      if let Some(result) = rule::apply(&rules[n], subject.clone(), context) {
+        self.next_rule.set(n + 1);
+        return Some(result);
+      }
+    }
+    None
+  }
+
+  // endregion RuleTable methods
+
+  // region SortTable methods
+
+  /// Builds the sort diagram from the op declarations, returning the problems found with them.
+  #[inline(always)]
+  pub fn compile_op_declarations(&mut self) -> Vec<SignatureWarning> {
+    let name = self.name.clone();
+    self.sort_table.compile_op_declarations(&name)
+  }
+
+  // endregion SortTable methods
+
+  // region SortConstraintTable methods
+
+  /// Whether `pattern` might match a DAG node headed by this symbol: it is a variable, or its top symbol is this
+  /// symbol.
+  // ToDo: Variables whose sort is never below our range sort and patterns that collapse to our symbol are not yet
+  //       distinguished.
+  fn might_match_pattern(&self, pattern: &RcTerm) -> bool {
+    let pattern = pattern.borrow();
+    pattern.is_variable() || pattern.symbol().semantic_hash() == self.hash_value
+  }
+
+  #[inline(always)]
+  pub(crate) fn accept_sort_constraint(&self, sort_constraint: &PreEquation) -> bool {
+    self.might_match_pattern(&sort_constraint.lhs_term)
+  }
+
+  /// Adds a sort constraint (membership axiom) to the table if it might apply to this symbol.
+  pub fn offer_sort_constraint(&self, sort_constraint: RcPreEquation) {
+    if self.accept_sort_constraint(&sort_constraint.borrow()) {
+      self.sort_constraint_table.borrow_mut().offer_sort_constraint(sort_constraint);
+    }
+  }
+
+  /// Orders the sort constraints smallest target sort first. Called once the module is complete.
+  pub fn order_sort_constraints(&self) {
+    let mut sort_constraint_table = self.sort_constraint_table.take();
+    sort_constraint_table.order_sort_constraints(|sort_constraint| self.accept_sort_constraint(sort_constraint));
+    self.sort_constraint_table.replace(sort_constraint_table);
+  }
+
+  #[inline(always)]
+  pub fn compile_sort_constraints(&self) {
+    self.sort_constraint_table.borrow().compile_sort_constraints();
+  }
+
+  // endregion SortConstraintTable methods
 }
 
 pub trait Symbol {
@@ -283,13 +326,8 @@ pub trait Symbol {
   }
 
   #[inline(always)]
-  fn sort_constraint_table(&self) -> &SortConstraintTable {
-    &self.symbol_members().sort_constraint_table
-  }
-
-  #[inline(always)]
-  fn sort_constraint_table_mut(&mut self) -> &mut SortConstraintTable {
-    &mut self.symbol_members_mut().sort_constraint_table
+  fn sort_constraint_table(&self) -> Ref<SortConstraintTable> {
+    self.symbol_members().sort_constraint_table.borrow()
   }
 
   #[inline(always)]
@@ -341,7 +379,13 @@ pub trait Symbol {
     self.symbol_members().memo_flag
   }
 
-  fn rewrite(&mut self, subject: RcDagNode, context: &mut RewritingContext) -> bool;
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool;
+
+  /// Rewrites `subject` at the top with one of our rules, returning the result. The subject itself is left unchanged.
+  #[inline(always)]
+  fn rule_rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> Option<RcDagNode> {
+    self.symbol_members().apply_rules(subject, context)
+  }
 }
 
 //  region Order and Equality impls
@@ -138,6 +138,7 @@ impl SymbolAttribute {
   );
 }
 
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
 pub struct SymbolType(u32);
 
 impl SymbolType {
@@ -156,6 +157,15 @@ impl SymbolType {
   pub fn set(&mut self, flags: SymbolType) {
     self.0 = self.0 | flags.0;
   }
+
+  /// The basic type is stored in the bits above the attribute flags.
+  pub fn set_basic_type(&mut self, basic_type: BasicSymbolTypes) {
+    self.0 = (self.0 & FLAG_MASK) | ((basic_type as u32) << TYPE_SHIFT);
+  }
+
+  pub fn has_basic_type(&self, basic_type: BasicSymbolTypes) -> bool {
+    self.0 >> TYPE_SHIFT == basic_type as u32
+  }
 }
 
 impl std::ops::BitOr<SymbolAttribute> for SymbolType {
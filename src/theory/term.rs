@@ -7,8 +7,12 @@ is a corresponding `DagNode` type. However, because of structural sharing, the n
 1-to-1 correspondence.
 
 Types Implementing `Term`:
+    `ATerm`
     `ACUTerm`
+    `CTerm`
     `FreeTerm`
+    `STerm`
+    `UTerm`
     `VariableTerm`
 
 */
@@ -41,6 +45,7 @@ use crate::{
   core::{
     automata::RHSBuilder,
     format::{FormatStyle, Formattable},
+    hash_cons_set::HashConsSet,
     sort::{RcConnectedComponent, SpecialSort},
     substitution::Substitution,
     OrderingValue,
@@ -129,7 +134,7 @@ impl TermMembers {
       attributes:          0,
       sort_index:          SpecialSort::Unknown as i32,
       connected_component: Default::default(),
-      save_index:          0,
+      save_index:          NONE,
       // hash_value         : 0,
       cached_size:         UNDEFINED,
     }
@@ -337,6 +342,13 @@ pub trait Term: Formattable {
     self.dagify(&mut node_cache, false)
   }
 
+  /// Like `make_dag`, but every node of the result is canonicalized in `hash_cons_set`, so that the DAG shares
+  /// structurally equal subterms with every other DAG in the table. Sort information on the nodes is kept. The result
+  /// is shared, so it must not be reduced or otherwise rewritten in place.
+  fn make_dag_hash_consed(&self, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    hash_cons_set.insert(self.make_dag()).0
+  }
+
   /// Create a directed acyclic graph from this term. This trait-level implemented function takes care of structural
   /// sharing. Each implementing type will supply its own implementation of `dagify_aux(…)`, which recursively
   /// calls `dagify(…)` on its children and then converts itself to a type implementing DagNode, returning `RcDagNode`.
@@ -385,7 +397,7 @@ pub trait Term: Formattable {
   fn compile_rhs_aux(
     &mut self,
     builder: &mut RHSBuilder,
-    variable_info: &VariableInfo,
+    variable_info: &mut VariableInfo,
     available_terms: &mut TermBag,
     eager_context: bool,
   ) -> i32;
@@ -500,10 +512,9 @@ impl Display for dyn Term {
 
 
 // ToDo: Revisit whether `semantic_hash` is appropriate for the `Hash` trait.
-// Use the `Term::compute_hash(…)` hash for `HashSet`s and friends.
 impl Hash for dyn Term {
   fn hash<H: Hasher>(&self, state: &mut H) {
-    state.write_u32(self.compute_hash())
+    state.write_u32(self.semantic_hash())
   }
 }
 
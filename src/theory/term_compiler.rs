@@ -29,7 +29,10 @@ pub fn compile_rhs(
   available_terms: &mut TermBag,
   eager_context: bool,
 ) -> i32 {
-  if let Some(found_term) = available_terms.find(&*term.borrow(), eager_context) {
+  // The lookup is bound first, so that its borrow of `term` ends before the found term, which may be `term` itself, is
+  // borrowed mutably.
+  let found = available_terms.find(&*term.borrow(), eager_context);
+  if let Some((found_term, _)) = found {
     let mut found_term = found_term.borrow_mut();
 
     if found_term.term_members_mut().save_index == NONE {
@@ -50,7 +53,7 @@ pub fn compile_rhs(
       let index = variable_info.make_construction_index();
       rhs_builder.add_rhs_automaton(Box::new(CopyRHSAutomaton::new(var_index, index)));
       vt.term_members_mut().save_index = index;
-      available_terms.insert_built_term(term, true);
+      available_terms.insert_built_term(term.clone(), true);
       return index;
     }
     return var_index;
@@ -0,0 +1,390 @@
+/*!
+
+Fixtures shared by the tests of the theories and of the engine: a signature with a single user sort, constants and
+variables of that sort, a binary (or, for the S theory, unary) symbol of each theory declared on it, and the
+enumeration of every solution of a match. Terms are built directly, without a module, except by the tests that rewrite:
+those add their symbols and statements to a module and close its theory, as the interpreter does.
+
+*/
+
+use std::{
+  cell::RefCell,
+  rc::{Rc, Weak},
+};
+
+use crate::{
+  abstractions::{BigInteger, IString, NatSet, RcCell},
+  core::{
+    interpreter::module::item::ModuleItem,
+    module::Module,
+    pre_equation::{equation, pattern, rule},
+    rewrite_context::RewritingContext,
+    rewrite_sequence_search::{RewriteSequenceSearch, SearchType},
+    sort::{ConnectedComponent, RcSort, Sort},
+    substitution::Substitution,
+    Strategy,
+    VariableInfo,
+  },
+  rc_cell,
+  theory::{
+    a_theory::ASymbol,
+    acu_theory::{ACUDagNode, ACUSymbol, NormalizationStatus},
+    c_theory::CSymbol,
+    free_theory::{FreeDagNode, FreeSymbol, FreeTerm},
+    make_rc_dag_node,
+    s_theory::{SDagNode, SSymbol, STerm},
+    u_theory::USymbol,
+    variable::{VariableSymbol, VariableTerm},
+    BinarySymbol,
+    DagNode,
+    MaybeSubproblem,
+    RcDagNode,
+    RcSymbol,
+    RcTerm,
+    Symbol,
+    SymbolType,
+    Term,
+  },
+};
+
+/// Builds a kind `[name]` and a single user sort `name` in one connected component. Components only hold weak
+/// references to their sorts, so both sorts are returned to keep them alive.
+pub(crate) fn make_sorts(name: &str) -> (RcSort, RcSort) {
+  let component = rc_cell!(ConnectedComponent::default());
+  let kind = rc_cell!(Sort {
+    name: IString::from(format!("[{}]", name).as_str()),
+    sort_index: 0,
+    sort_component: component.clone(),
+    ..Default::default()
+  });
+  let sort = rc_cell!(Sort {
+    name: IString::from(name),
+    sort_index: 1,
+    sort_component: component.clone(),
+    ..Default::default()
+  });
+  kind.borrow_mut().leq_sorts.insert(0);
+  kind.borrow_mut().leq_sorts.insert(1);
+  sort.borrow_mut().leq_sorts.insert(1);
+
+  component.borrow_mut().append_sort(kind.downgrade());
+  component.borrow_mut().register_sort();
+  component.borrow_mut().append_sort(sort.downgrade());
+  component.borrow_mut().register_sort();
+
+  (kind, sort)
+}
+
+/// Declares `symbol` with every argument and its range of sort `sort`, and compiles its sort diagram so that sorts
+/// can be computed from the signature.
+fn declare(symbol: &mut dyn Symbol, sort: &RcSort) {
+  let arity = symbol.arity() as usize;
+  symbol
+    .symbol_members_mut()
+    .sort_table
+    .add_op_declaration(vec![sort.clone(); arity + 1], true);
+  assert!(symbol.symbol_members_mut().compile_op_declarations().is_empty());
+}
+
+pub(crate) fn make_free_symbol(name: &str, arity: u32, sort: &RcSort) -> RcSymbol {
+  let mut symbol = FreeSymbol::new(IString::from(name), arity, false, Strategy::default());
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+/// Makes a free symbol with the given op declarations, each listing the argument sorts and then the range, and compiles
+/// its sort diagram.
+pub(crate) fn make_free_symbol_with_declarations(name: &str, declarations: &[&[&RcSort]]) -> RcSymbol {
+  let arity = declarations[0].len() as u32 - 1;
+  let mut symbol = FreeSymbol::new(IString::from(name), arity, false, Strategy::default());
+  for declaration in declarations {
+    symbol
+      .symbol_members_mut()
+      .sort_table
+      .add_op_declaration(declaration.iter().map(|sort| (*sort).clone()).collect(), true);
+  }
+  assert!(symbol.symbol_members_mut().compile_op_declarations().is_empty());
+  Rc::new(symbol)
+}
+
+/// Makes a free symbol declared like those of `make_free_symbol`. The symbol is not yet shared, so equations and rules
+/// can still be offered to it.
+pub(crate) fn make_compiled_symbol(name: &str, arity: u32, sort: &RcSort) -> FreeSymbol {
+  let mut symbol = FreeSymbol::new(IString::from(name), arity, false, Strategy::default());
+  declare(&mut symbol, sort);
+  symbol
+}
+
+/// Makes the free term `symbol(args)`.
+pub(crate) fn make_free_term(symbol: &RcSymbol, args: Vec<RcTerm>) -> RcTerm {
+  rc_cell!(FreeTerm::with_args(symbol.clone(), args))
+}
+
+/// Makes the free DAG node `symbol(args)` as is, without reducing it.
+pub(crate) fn make_free_dag(symbol: &RcSymbol, args: &[&RcDagNode]) -> RcDagNode {
+  let mut dag_node = FreeDagNode::new(symbol.clone());
+  for arg in args {
+    dag_node.members.args.push((*arg).clone());
+  }
+  make_rc_dag_node(dag_node)
+}
+
+/// Makes the ACU DAG node `symbol(args)`, normalized at the top, so that it collapses to its argument if all but one
+/// argument is the identity.
+pub(crate) fn make_acu_dag(symbol: &RcSymbol, args: &[&RcDagNode]) -> RcDagNode {
+  let mut dag_node = ACUDagNode::new(symbol.clone(), args.len(), NormalizationStatus::Fresh);
+  for arg in args {
+    dag_node.push_arg((*arg).clone(), 1);
+  }
+  match dag_node.normalize_at_top() {
+    Some(collapsed) => collapsed,
+    None => make_rc_dag_node(dag_node),
+  }
+}
+
+pub(crate) fn is_equal(lhs: &RcDagNode, rhs: &RcDagNode) -> bool {
+  lhs.borrow().compare(&**rhs.borrow()).is_eq()
+}
+
+pub(crate) fn make_constant_symbol(name: &str, sort: &RcSort) -> RcSymbol {
+  make_free_symbol(name, 0, sort)
+}
+
+pub(crate) fn make_constant(name: &str, sort: &RcSort) -> RcTerm {
+  rc_cell!(FreeTerm::new(make_constant_symbol(name, sort)))
+}
+
+pub(crate) fn make_variable(name: &str, index: i32, sort: &RcSort) -> RcTerm {
+  let mut symbol = VariableSymbol::new(IString::from(name));
+  symbol.symbol_members_mut().sort_table.add_op_declaration(vec![sort.clone()], true);
+
+  let mut term = VariableTerm::new(IString::from(name), Rc::new(symbol));
+  term.index = index;
+  term.occurs_below_mut().insert(index as usize);
+  rc_cell!(term)
+}
+
+pub(crate) fn make_variable_dag(name: &str, index: i32, sort: &RcSort) -> RcDagNode {
+  make_variable(name, index, sort).borrow().make_dag()
+}
+
+pub(crate) fn make_a_symbol(sort: &RcSort) -> RcSymbol {
+  let mut symbol = ASymbol::new(IString::from("f"), Strategy::default(), false);
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+pub(crate) fn make_acu_symbol(sort: &RcSort) -> RcSymbol {
+  make_acu_symbol_with_type(sort, SymbolType::default())
+}
+
+pub(crate) fn make_acu_symbol_with_type(sort: &RcSort, symbol_type: SymbolType) -> RcSymbol {
+  let mut symbol = ACUSymbol::new(IString::from("f"), symbol_type, Strategy::default(), false, None);
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+/// Makes `f` with identity `identity`.
+pub(crate) fn make_acu_symbol_with_identity(sort: &RcSort, identity: &RcTerm) -> RcSymbol {
+  let mut symbol = ACUSymbol::new(
+    IString::from("f"),
+    SymbolType::default(),
+    Strategy::default(),
+    false,
+    Some(identity.clone()),
+  );
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+pub(crate) fn make_c_symbol(sort: &RcSort) -> RcSymbol {
+  let mut symbol = CSymbol::new(IString::from("f"), Strategy::default(), false);
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+/// Makes `f` with left identity `identity`, and gives the identity's DAG the sort `sort`.
+pub(crate) fn make_u_symbol(sort: &RcSort, identity: &RcTerm) -> RcSymbol {
+  let mut symbol = USymbol::new(
+    IString::from("f"),
+    Strategy::default(),
+    false,
+    identity.clone(),
+    true,
+    false,
+  );
+  declare(&mut symbol, sort);
+  let sort_index = sort.borrow().sort_index;
+  symbol.get_identity_dag().unwrap().borrow_mut().set_sort_index(sort_index);
+  Rc::new(symbol)
+}
+
+pub(crate) fn make_s_symbol(sort: &RcSort) -> RcSymbol {
+  let mut symbol = SSymbol::new(IString::from("s"), Strategy::default(), false);
+  declare(&mut symbol, sort);
+  Rc::new(symbol)
+}
+
+/// Adds `symbol` to `module`, declared like the symbols above, and shares it. The statements of the module are
+/// offered to it when the theory of the module is closed.
+pub(crate) fn add_symbol(module: &RcCell<Module>, mut symbol: impl Symbol + 'static, sort: &RcSort) -> RcSymbol {
+  declare(&mut symbol, sort);
+  let index = module.borrow().symbols.len() as i32;
+  symbol.symbol_members_mut().parent_module = module.downgrade();
+  symbol.symbol_members_mut().index_within_parent_module = index;
+
+  let symbol: RcSymbol = Rc::new(symbol);
+  module.borrow_mut().symbols.push(symbol.clone());
+  symbol
+}
+
+pub(crate) fn add_free_symbol(module: &RcCell<Module>, name: &str, arity: u32, sort: &RcSort) -> RcSymbol {
+  add_symbol(module, FreeSymbol::new(IString::from(name), arity, false, Strategy::default()), sort)
+}
+
+pub(crate) fn add_s_symbol(module: &RcCell<Module>, sort: &RcSort) -> RcSymbol {
+  add_symbol(module, SSymbol::new(IString::from("s"), Strategy::default(), false), sort)
+}
+
+/// Adds the constant `name` to `module` and makes the term for it.
+pub(crate) fn add_constant(module: &RcCell<Module>, name: &str, sort: &RcSort) -> RcTerm {
+  rc_cell!(FreeTerm::new(add_free_symbol(module, name, 0, sort)))
+}
+
+/// Adds the equation `lhs = rhs` to `module`.
+pub(crate) fn add_equation(module: &RcCell<Module>, lhs: RcTerm, rhs: RcTerm) {
+  let mut eq = equation::new(None, lhs, rhs, false, vec![]);
+  let index = module.borrow().equations.len() as i32;
+  eq.set_module_information(module.downgrade(), index);
+  module.borrow_mut().equations.push(rc_cell!(eq));
+}
+
+/// Adds the rule `lhs => rhs` to `module`.
+pub(crate) fn add_rule(module: &RcCell<Module>, lhs: RcTerm, rhs: RcTerm) {
+  let mut rl = rule::new(None, lhs, rhs, vec![]);
+  let index = module.borrow().rules.len() as i32;
+  rl.set_module_information(module.downgrade(), index);
+  module.borrow_mut().rules.push(rc_cell!(rl));
+}
+
+/// Makes the S term `s^exponent(arg)`.
+pub(crate) fn make_s_term(s: &RcSymbol, exponent: u32, arg: &RcTerm) -> RcTerm {
+  rc_cell!(STerm::new(s.clone(), BigInteger::from(exponent), arg.clone()))
+}
+
+/// Makes a context for rewriting `subject` with the statements of `module`, whose theory must be closed.
+pub(crate) fn make_context(module: &RcCell<Module>, subject: RcDagNode) -> RewritingContext {
+  let mut context = RewritingContext::new(Some(subject), Weak::new());
+  context.substitution = Substitution::with_capacity(module.borrow().minimum_substitution_size as usize);
+  context
+}
+
+/// The exponents of the arguments of `dag_node`, which are all S nodes.
+pub(crate) fn arg_exponents(dag_node: &RcDagNode) -> Vec<BigInteger> {
+  let exponents = dag_node
+    .borrow()
+    .iter_args()
+    .map(|arg| {
+      match arg.borrow().as_any().downcast_ref::<SDagNode>() {
+        Some(s_dag_node) => s_dag_node.number().clone(),
+        None => panic!("expected an SDagNode"),
+      }
+    })
+    .collect();
+  exponents
+}
+
+/// Gives `module` the symbols `f`, `s` and `0` of sort `sort` and the rule `s^3(X) => s^2(X)`, closes its theory, and
+/// makes the term `f(s^4(0), s^4(0))`, in which each argument can be rewritten twice.
+pub(crate) fn make_two_step_subject(module: &RcCell<Module>, sort: &RcSort) -> FreeTerm {
+  let f = add_free_symbol(module, "f", 2, sort);
+  let s = add_s_symbol(module, sort);
+  let zero = add_constant(module, "0", sort);
+  let x = make_variable("X", 0, sort);
+  add_rule(module, make_s_term(&s, 3, &x), make_s_term(&s, 2, &x));
+  Module::close_theory(module);
+
+  FreeTerm::with_args(f, vec![make_s_term(&s, 4, &zero), make_s_term(&s, 4, &zero)])
+}
+
+/// Fills in the occurs sets of `term` and its descendants from those of its variables, keeping the indices the
+/// variables were made with. Compound terms are only ground once they know which variables occur below them.
+pub(crate) fn collect_occurs_sets(term: &mut dyn Term) {
+  if term.is_variable() {
+    return;
+  }
+  let mut occurs_below = NatSet::new();
+  for arg in term.iter_args() {
+    collect_occurs_sets(&mut *arg.borrow_mut());
+    occurs_below.union_in_place(arg.borrow().occurs_below());
+  }
+  term.occurs_below_mut().union_in_place(&occurs_below);
+}
+
+/// Makes a search from `subject` with the statements of `module` for states matching `pattern`, which has at most two
+/// variables.
+pub(crate) fn make_search(
+  module: &RcCell<Module>,
+  subject: &dyn Term,
+  pattern: &RcTerm,
+  search_type: SearchType,
+  max_depth: Option<usize>,
+) -> RewriteSequenceSearch {
+  let mut context = RewritingContext::new(Some(subject.make_dag()), Weak::new());
+  let substitution_size = module.borrow().minimum_substitution_size.max(2);
+  context.substitution = Substitution::with_capacity(substitution_size as usize);
+  // The pattern's variables are indexed by `check`, so that each match clears the bindings of the last one.
+  let mut pattern = pattern::new(pattern.clone(), vec![]);
+  pattern.check();
+  let (lhs_automaton, _) = pattern
+    .lhs_term
+    .borrow()
+    .compile_lhs(true, &pattern.variable_info, &mut NatSet::default());
+  pattern.lhs_automaton = Some(lhs_automaton);
+  RewriteSequenceSearch::new(context, rc_cell!(pattern), search_type, max_depth)
+}
+
+/// Compiles `pattern` and returns every solution of matching it against `subject`.
+pub(crate) fn all_matches(pattern: &mut dyn Term, subject: RcDagNode, variable_count: usize) -> Vec<Vec<RcDagNode>> {
+  pattern.normalize(true);
+  collect_occurs_sets(pattern);
+  let (automaton, _subproblem_likely) = pattern.compile_lhs(true, &VariableInfo::default(), &mut NatSet::default());
+
+  let mut solution = Substitution::with_capacity(variable_count);
+  let (matched, subproblem) = automaton.borrow_mut().match_(subject.clone(), &mut solution);
+  if !matched {
+    return vec![];
+  }
+  solutions(subject, solution, subproblem, variable_count)
+}
+
+/// Returns every solution of a match against `subject` that returned `solution` and `subproblem`.
+pub(crate) fn solutions(
+  subject: RcDagNode,
+  solution: Substitution,
+  subproblem: MaybeSubproblem,
+  variable_count: usize,
+) -> Vec<Vec<RcDagNode>> {
+  let extract = |substitution: &Substitution| -> Vec<RcDagNode> {
+    (0..variable_count)
+      .map(|i| substitution.value(i).expect("unbound variable"))
+      .collect()
+  };
+
+  match subproblem {
+    None => vec![extract(&solution)],
+
+    Some(mut subproblem) => {
+      let mut context = RewritingContext::new(Some(subject), Weak::new());
+      context.substitution = solution;
+
+      let mut solutions = vec![];
+      let mut find_first = true;
+      while subproblem.solve(find_first, &mut context) {
+        solutions.push(extract(&context.substitution));
+        find_first = false;
+      }
+      solutions
+    }
+  }
+}
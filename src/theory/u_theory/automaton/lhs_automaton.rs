@@ -0,0 +1,159 @@
+/*!
+
+The LHS automaton for the U theory. A pattern `f(p, q)` can match a subject in up to three ways:
+
+  1. If the subject is `f(s, t)`, by matching `p` against `s` and `q` against `t`.
+  2. If we have a left identity `e`, by matching `p` against `e` and `q` against the whole subject.
+  3. If we have a right identity `e`, by matching `p` against the whole subject and `q` against `e`.
+
+The last two are the collapse cases, and are only tried for arguments that can take the identity at all. Each case is
+tried against its own copy of the substitution. If exactly one succeeds without leaving a subproblem, its bindings are
+the match; otherwise an `AlternativesSubproblem` holds the cases that succeeded and tries them in turn.
+
+In the first case, an argument that could itself collapse is not matched in place. Its abstraction variable is bound
+to the subject argument, and the argument is matched against it by a `VariableAbstractionSubproblem`.
+
+*/
+
+use super::UArgument;
+use crate::{
+  core::substitution::Substitution,
+  theory::{
+    u_theory::{UDagNode, USymbol},
+    Alternative,
+    AlternativesSubproblem,
+    BinarySymbol,
+    DagNode,
+    LHSAutomaton,
+    MaybeSubproblem,
+    RcDagNode,
+    RcSymbol,
+    Subproblem,
+    SubproblemSequence,
+    VariableAbstractionSubproblem,
+  },
+  NONE,
+};
+
+
+pub struct ULHSAutomaton {
+  top_symbol:   RcSymbol,
+  args:         [UArgument; 2],
+  /// Whether each argument can take the identity, so that the pattern can collapse to the other argument.
+  can_collapse: [bool; 2],
+}
+
+impl ULHSAutomaton {
+  pub(crate) fn new(top_symbol: RcSymbol, args: [UArgument; 2], can_collapse: [bool; 2]) -> Self {
+    ULHSAutomaton {
+      top_symbol,
+      args,
+      can_collapse,
+    }
+  }
+
+  #[inline(always)]
+  fn u_symbol(&self) -> &USymbol {
+    match self.top_symbol.as_any().downcast_ref::<USymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to USymbol. This is a bug."),
+    }
+  }
+
+  /// Matches the arguments against `values` in a copy of `solution`. Abstraction variables are only used when matching
+  /// against the arguments of the subject.
+  fn match_case(
+    &self,
+    values: [RcDagNode; 2],
+    use_abstraction: bool,
+    solution: &Substitution,
+  ) -> Option<(Substitution, MaybeSubproblem)> {
+    let mut local = solution.clone();
+    let mut subproblems: Vec<Box<dyn Subproblem>> = Vec::new();
+
+    for (arg, value) in self.args.iter().zip(values) {
+      if use_abstraction && arg.abstraction_variable != NONE {
+        local.bind(arg.abstraction_variable, Some(value));
+        subproblems.push(Box::new(VariableAbstractionSubproblem::new(
+          arg.automaton.clone(),
+          arg.abstraction_variable,
+          local.fragile_binding_count() as u32,
+        )));
+      } else {
+        let (matched, subproblem) = arg.automaton.borrow_mut().match_(value, &mut local);
+        if !matched {
+          return None;
+        }
+        subproblems.extend(subproblem);
+      }
+    }
+
+    let subproblem: MaybeSubproblem = match subproblems.len() {
+      0 => None,
+      1 => subproblems.pop(),
+      _ => {
+        let mut sequence = SubproblemSequence::new();
+        for subproblem in subproblems {
+          sequence.add(subproblem);
+        }
+        Some(Box::new(sequence))
+      }
+    };
+    Some((local, subproblem))
+  }
+}
+
+
+impl LHSAutomaton for ULHSAutomaton {
+  fn match_(&mut self, subject: RcDagNode, solution: &mut Substitution) -> (bool, MaybeSubproblem) {
+    let subject_args = {
+      let subject_ref = subject.borrow();
+      if subject_ref.symbol().as_ref() == self.top_symbol.as_ref() {
+        match subject_ref.as_any().downcast_ref::<UDagNode>() {
+          Some(u_dag_node) => Some([u_dag_node.arg(0), u_dag_node.arg(1)]),
+          None => unreachable!("Could not downcast to UDagNode. This is a bug."),
+        }
+      } else {
+        None
+      }
+    };
+
+    let mut cases: Vec<(Substitution, MaybeSubproblem)> = Vec::with_capacity(3);
+    if let Some(subject_args) = subject_args {
+      cases.extend(self.match_case(subject_args, true, solution));
+    }
+
+    let identity = match self.u_symbol().get_identity_dag() {
+      Some(identity) => identity,
+      None => unreachable!("U symbol without an identity. This is a bug."),
+    };
+    // If the subject is the identity itself, both collapse cases give the same solutions.
+    let subject_is_identity = self.u_symbol().is_identity(&**subject.borrow());
+    for index in 0..2 {
+      if !self.can_collapse[index] || (index == 1 && subject_is_identity && self.can_collapse[0]) {
+        continue;
+      }
+      let mut values = [subject.clone(), subject.clone()];
+      values[index] = identity.clone();
+      cases.extend(self.match_case(values, false, solution));
+    }
+
+    match cases.len() {
+      0 => (false, None),
+
+      1 if cases[0].1.is_none() => {
+        let (local, _) = cases.pop().unwrap();
+        *solution = local;
+        (true, None)
+      }
+
+      _ => {
+        let alternatives = cases
+          .into_iter()
+          .map(|(local, subproblem)| Alternative::new(local.subtract(solution), subproblem))
+          .collect();
+        (true, Some(Box::new(AlternativesSubproblem::new(alternatives))))
+      }
+    }
+  }
+}
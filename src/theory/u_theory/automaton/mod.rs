@@ -0,0 +1,23 @@
+/*!
+
+The LHS and RHS automata of the U theory.
+
+*/
+mod lhs_automaton;
+mod rhs_automaton;
+
+use crate::theory::RcLHSAutomaton;
+
+
+// Re-exports
+pub use lhs_automaton::ULHSAutomaton;
+pub use rhs_automaton::URHSAutomaton;
+
+
+/// An argument of a U pattern, as seen by the matcher.
+#[derive(Clone)]
+pub(crate) struct UArgument {
+  pub(crate) automaton:            RcLHSAutomaton,
+  /// If not `NONE`, the argument is matched through a `VariableAbstractionSubproblem` for this variable.
+  pub(crate) abstraction_variable: i32,
+}
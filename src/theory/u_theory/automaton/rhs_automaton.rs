@@ -0,0 +1,71 @@
+/*!
+
+The RHS automaton for the U theory. Each instruction builds one U DAG node from two arguments that have already been
+constructed (or bound) in the substitution. If one of the arguments is an identity that can be eliminated, the result
+is the other argument rather than a new node.
+
+*/
+
+use crate::{
+  core::{substitution::Substitution, VariableInfo},
+  theory::{dag_node::MaybeDagNode, u_theory::UDagNode, RHSAutomaton, RcDagNode, RcSymbol},
+};
+
+
+pub struct URHSAutomaton {
+  top_symbol:  RcSymbol,
+  sources:     [i32; 2],
+  destination: i32,
+}
+
+impl URHSAutomaton {
+  pub fn new(top_symbol: RcSymbol, sources: [i32; 2], destination: i32) -> Self {
+    URHSAutomaton {
+      top_symbol,
+      sources,
+      destination,
+    }
+  }
+
+  /// Builds the normalized node, or the argument it collapses to, from the bindings in `matcher`.
+  fn build(&self, matcher: &mut Substitution) -> RcDagNode {
+    let [first, second] = self.sources.map(|source| {
+      let value = matcher.value(source as usize);
+      assert!(value.is_some(), "unbound source {} in U RHS. This is a bug.", source);
+      value.unwrap()
+    });
+
+    UDagNode::make_normalized(self.top_symbol.clone(), first, second)
+  }
+}
+
+
+impl RHSAutomaton for URHSAutomaton {
+  fn as_any(&self) -> &dyn std::any::Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    self
+  }
+
+  fn remap_indices(&mut self, variable_info: &mut VariableInfo) {
+    for source in &mut self.sources {
+      *source = variable_info.remap_index(*source);
+    }
+    self.destination = variable_info.remap_index(self.destination);
+  }
+
+  fn construct(&self, matcher: &mut Substitution) -> MaybeDagNode {
+    let new_dag_node = self.build(matcher);
+
+    matcher.bind(self.destination, Some(new_dag_node.clone()));
+    Some(new_dag_node)
+  }
+
+  fn replace(&self, old: RcDagNode, matcher: &mut Substitution) {
+    let new_dag_node = self.build(matcher);
+    // Like the ACU theory, a collapse to an alien argument relies on `overwrite_with_clone` of the argument's theory.
+    new_dag_node.borrow_mut().overwrite_with_clone(old);
+  }
+}
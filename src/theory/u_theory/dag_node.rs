@@ -0,0 +1,241 @@
+/*!
+
+Concrete types for the U theory implementing the DagNode trait.
+
+A `UDagNode` stores its two arguments in `DagNodeMembers::args`. Normal form for a U node is that no argument that could
+be eliminated as an identity is equal to the identity. A node that is not in normal form collapses to its other
+argument, so `normalize_at_top` returns that argument rather than modifying the node.
+
+*/
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{USymbol, UTerm};
+use crate::{
+  abstractions::RcCell,
+  core::{hash_cons_set::HashConsSet, sort::SpecialSort, RedexPosition},
+  rc_cell,
+  theory::{make_rc_dag_node, DagNode, DagNodeFlags, DagNodeMembers, NodeList, RcDagNode, RcSymbol, RcTerm, Symbol},
+};
+
+
+pub type RcUDagNode = RcCell<UDagNode>;
+
+pub struct UDagNode {
+  pub(crate) members: DagNodeMembers,
+}
+
+impl UDagNode {
+  pub fn new(symbol: RcSymbol, first: RcDagNode, second: RcDagNode) -> Self {
+    let mut args = NodeList::default();
+    args.push(first);
+    args.push(second);
+
+    UDagNode {
+      members: DagNodeMembers {
+        top_symbol: symbol,
+        args,
+        flags: Default::default(),
+        sort_index: SpecialSort::Unknown as i32,
+        copied_rc: None,
+        hash: 0,
+      },
+    }
+  }
+
+  /// Makes the node `f(first, second)`, or the argument it collapses to.
+  pub fn make_normalized(symbol: RcSymbol, first: RcDagNode, second: RcDagNode) -> RcDagNode {
+    let node = UDagNode::new(symbol, first, second);
+    match node.normalize_at_top() {
+      Some(collapsed) => collapsed,
+      None => make_rc_dag_node(node),
+    }
+  }
+
+  #[inline(always)]
+  pub fn u_symbol(&self) -> &USymbol {
+    match self.members.top_symbol.as_any().downcast_ref::<USymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to USymbol. This is a bug."),
+    }
+  }
+
+  #[inline(always)]
+  pub fn arg(&self, index: usize) -> RcDagNode {
+    self.members.args[index].clone()
+  }
+
+  /// Eliminates an identity argument. Returns the argument the node collapses to, or `None` if the node is already in
+  /// normal form.
+  pub fn normalize_at_top(&self) -> Option<RcDagNode> {
+    let symbol = self.u_symbol();
+
+    for index in 0..2 {
+      if symbol.identity_eliminable(index) && symbol.is_identity(&**self.arg(index).borrow()) {
+        return Some(self.arg(1 - index));
+      }
+    }
+    None
+  }
+}
+
+
+impl DagNode for UDagNode {
+  #[inline(always)]
+  fn dag_node_members(&self) -> &DagNodeMembers {
+    &self.members
+  }
+
+  #[inline(always)]
+  fn dag_node_members_mut(&mut self) -> &mut DagNodeMembers {
+    &mut self.members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  #[inline(always)]
+  fn as_ptr(&self) -> *const dyn DagNode {
+    self
+  }
+
+  fn compare_arguments(&self, other: &dyn DagNode) -> Ordering {
+    match other.as_any().downcast_ref::<UDagNode>() {
+      Some(u_dag_node) => {
+        for (this_child, other_child) in self.iter_args().zip(u_dag_node.iter_args()) {
+          let r = this_child.borrow().compare(&**other_child.borrow());
+          if r != Ordering::Equal {
+            return r;
+          }
+        }
+        Ordering::Equal
+      }
+      None => unreachable!("Could not downcast a DagNode to a UDagNode. This is a bug."),
+    }
+  }
+
+  fn compute_base_sort(&mut self) -> i32 {
+    let symbol = self.symbol();
+    let mut state = 0;
+
+    for (i, arg) in self.iter_args().enumerate() {
+      let index = arg.borrow().get_sort_index();
+      assert_ne!(
+        index,
+        SpecialSort::Unknown as i32,
+        "unknown sort encountered for arg {} of {}",
+        i,
+        symbol.name()
+      );
+      state = symbol.sort_table().traverse(state as usize, index as usize);
+    }
+
+    self.set_sort_index(state);
+    state
+  }
+
+  fn termify(&self) -> RcTerm {
+    rc_cell!(UTerm::new(
+      self.symbol(),
+      self.arg(0).borrow().termify(),
+      self.arg(1).borrow().termify()
+    ))
+  }
+
+  fn shallow_copy(&self) -> RcDagNode {
+    let mut new_node = UDagNode::new(self.symbol(), self.arg(0), self.arg(1));
+    new_node.members.flags = self.flags() & DagNodeFlags::RewritingFlags;
+    new_node.members.sort_index = self.get_sort_index();
+
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_with_replacements(&self, redex_stack: &[RedexPosition], mut first_idx: usize, last_idx: usize) -> RcDagNode {
+    assert!(
+      first_idx <= last_idx && last_idx < redex_stack.len(),
+      "bad replacement range"
+    );
+    let mut args = [self.arg(0), self.arg(1)];
+
+    while first_idx <= last_idx {
+      let arg_index = redex_stack[first_idx].arg_index;
+      assert!((0..2).contains(&arg_index), "bad replacement arg index");
+      args[arg_index as usize] = redex_stack[first_idx].dag_node.clone();
+      first_idx += 1;
+    }
+
+    // A replacement may be our identity.
+    let [first, second] = args;
+    UDagNode::make_normalized(self.symbol(), first, second)
+  }
+
+  fn copy_with_replacement(&self, replacement: RcDagNode, arg_index: usize) -> RcDagNode {
+    assert!(arg_index < 2, "bad argIndex");
+    let (first, second) = if arg_index == 0 {
+      (replacement, self.arg(1))
+    } else {
+      (self.arg(0), replacement)
+    };
+
+    UDagNode::make_normalized(self.symbol(), first, second)
+  }
+
+  fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
+    let symbol = self.symbol();
+    let copy = |index: usize, arg: RcDagNode| -> RcDagNode {
+      if symbol.strategy().eager_argument(index) {
+        // `None` means the argument is already reduced and can be shared.
+        let copy = arg.borrow_mut().copy_eager_upto_reduced();
+        copy.unwrap_or(arg)
+      } else {
+        arg
+      }
+    };
+
+    let new_node = UDagNode::new(self.symbol(), copy(0, self.arg(0)), copy(1, self.arg(1)));
+    make_rc_dag_node(new_node)
+  }
+
+  fn copy_all_aux(&mut self) -> RcDagNode {
+    let copy = |arg: RcDagNode| -> RcDagNode {
+      let copy = arg.borrow_mut().copy_all();
+      copy.unwrap_or(arg)
+    };
+
+    let new_node = UDagNode::new(self.symbol(), copy(self.arg(0)), copy(self.arg(1)));
+    make_rc_dag_node(new_node)
+  }
+
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut new_node = UDagNode::new(self.symbol(), self.arg(0), self.arg(1));
+    new_node.set_sort_index(self.get_sort_index());
+    new_node.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
+
+    *old.borrow_mut() = Box::new(new_node);
+  }
+
+  /// For hash consing, recursively checks child nodes to determine if a canonical copy needs to be made.
+  fn make_canonical(&self, rc_dag_node: RcDagNode, hash_cons_set: &mut HashConsSet) -> RcDagNode {
+    let (first, second) = (self.arg(0), self.arg(1));
+    let (canonical_first, _) = hash_cons_set.insert(first.clone());
+    let (canonical_second, _) = hash_cons_set.insert(second.clone());
+
+    if RcCell::ptr_eq(&canonical_first, &first) && RcCell::ptr_eq(&canonical_second, &second) {
+      // Can use the original DAG node as the canonical version
+      return rc_dag_node;
+    }
+
+    let mut new_node = UDagNode::new(self.symbol(), canonical_first, canonical_second);
+    new_node.members.flags.set_copied_flags(self.members.flags);
+    new_node.members.sort_index = self.members.sort_index;
+
+    make_rc_dag_node(new_node)
+  }
+}
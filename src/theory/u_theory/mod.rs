@@ -0,0 +1,94 @@
+/*!
+
+The U theory: binary symbols with a left identity, a right identity, or both, but which are neither associative nor
+commutative. Normal form for terms and DAG nodes is that no argument that could be eliminated is equal to the identity,
+so `f(a, e)` with `e` a right identity is just `a`.
+
+Matching is done by the `ULHSAutomaton`. Besides matching the arguments of the subject, a pattern whose arguments can
+take the identity may collapse to its other argument, in which case it matches subjects with any top symbol. When more
+than one of these ways of matching succeeds, they are handed to an `AlternativesSubproblem`.
+
+*/
+
+mod automaton;
+mod dag_node;
+mod symbol;
+mod term;
+
+pub use automaton::{ULHSAutomaton, URHSAutomaton};
+pub use dag_node::{RcUDagNode, UDagNode};
+pub use symbol::{RcUSymbol, USymbol};
+pub use term::{RcUTerm, UTerm};
+
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    abstractions::RcCell,
+    rc_cell,
+    theory::{
+      test_fixtures::{all_matches, make_constant, make_sorts, make_u_symbol, make_variable},
+      DagNode,
+      RcDagNode,
+      RcTerm,
+      Term,
+    },
+  };
+
+  /// Makes the DAG for `term`, giving it and its arguments the sort `Elt`.
+  fn make_subject(term: &RcTerm) -> RcDagNode {
+    let subject = term.borrow().make_dag();
+    subject.borrow_mut().set_sort_index(1);
+    for arg in subject.borrow().iter_args() {
+      arg.borrow_mut().set_sort_index(1);
+    }
+    subject
+  }
+
+  #[test]
+  fn collapse_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let e = make_constant("e", &elt);
+    let f = make_u_symbol(&elt, &e);
+    let a = make_constant("a", &elt);
+
+    // f(e, a) collapses to a, but f(a, e) doesn't, as e is only a left identity.
+    let collapsing: RcTerm = rc_cell!(UTerm::new(f.clone(), e.clone(), a.clone()));
+    let dag = collapsing.borrow().make_dag();
+    assert!(dag.borrow().compare(&**a.borrow().make_dag().borrow()).is_eq());
+
+    let stable = UTerm::new(f.clone(), a.clone(), e.clone());
+    assert!(stable.collapse().is_none());
+    assert_eq!(stable.make_dag().borrow().iter_args().count(), 2);
+  }
+
+  #[test]
+  fn match_collapse_test() {
+    let (_kind, elt) = make_sorts("Elt");
+    let e = make_constant("e", &elt);
+    let f = make_u_symbol(&elt, &e);
+    let a = make_constant("a", &elt);
+    let b = make_constant("b", &elt);
+    let x = make_variable("X", 0, &elt);
+
+    // f(X, a) against f(b, a) only matches the subject's arguments.
+    let subject: RcTerm = rc_cell!(UTerm::new(f.clone(), b.clone(), a.clone()));
+    let mut pattern = UTerm::new(f.clone(), x.clone(), a.clone());
+    let solutions = all_matches(&mut pattern, make_subject(&subject), 1);
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().compare(&**b.borrow().make_dag().borrow()).is_eq());
+
+    // f(X, a) against a collapses, with X bound to the identity.
+    let mut pattern = UTerm::new(f.clone(), x.clone(), a.clone());
+    let solutions = all_matches(&mut pattern, make_subject(&a), 1);
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0][0].borrow().compare(&**e.borrow().make_dag().borrow()).is_eq());
+
+    // f(X, a) against b doesn't match at all.
+    let mut pattern = UTerm::new(f.clone(), x.clone(), a.clone());
+    assert!(all_matches(&mut pattern, make_subject(&b), 1).is_empty());
+  }
+}
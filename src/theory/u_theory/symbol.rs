@@ -0,0 +1,134 @@
+/*!
+
+A symbol belonging to the U theory, that is, a binary symbol that has a left identity, a right identity, or both, but
+is neither associative nor commutative.
+
+ */
+
+use std::{any::Any, rc::Rc};
+
+use super::{UDagNode, UTerm};
+use crate::{
+  abstractions::IString,
+  core::{rewrite_context::RewritingContext, CachedDag, Strategy},
+  theory::{BinarySymbol, DagNode, RcDagNode, RcTerm, Symbol, SymbolMembers},
+};
+
+
+pub type RcUSymbol = Rc<USymbol>;
+
+
+pub struct USymbol {
+  // `SymbolMembers`
+  symbol_members: SymbolMembers,
+
+  identity: CachedDag,
+  left_id:  bool,
+  right_id: bool,
+}
+
+impl USymbol {
+  pub fn new(name: IString, strategy: Strategy, memo_flag: bool, identity: RcTerm, left_id: bool, right_id: bool) -> USymbol {
+    assert!(left_id || right_id, "a U symbol needs a left or right identity");
+    let mut symbol_members = SymbolMembers::new(name, 2, memo_flag);
+    symbol_members.strategy = strategy;
+
+    // The identity is dagified once, up front, so that `get_identity_dag` can be a simple accessor.
+    let identity_dag = identity.borrow().make_dag();
+
+    USymbol {
+      symbol_members,
+      identity: CachedDag {
+        term:     Some(identity),
+        dag_node: Some(identity_dag),
+      },
+      left_id,
+      right_id,
+    }
+  }
+
+  pub fn make_term_with_args(self, first: RcTerm, second: RcTerm) -> UTerm {
+    UTerm::new(Rc::new(self), first, second)
+  }
+
+  #[inline(always)]
+  pub fn has_left_identity(&self) -> bool {
+    self.left_id
+  }
+
+  #[inline(always)]
+  pub fn has_right_identity(&self) -> bool {
+    self.right_id
+  }
+
+  /// Can the argument at `index` be eliminated when it is equal to the identity? The first argument can be eliminated
+  /// if we have a left identity, and the second if we have a right identity.
+  #[inline(always)]
+  pub fn identity_eliminable(&self, index: usize) -> bool {
+    if index == 0 {
+      self.left_id
+    } else {
+      self.right_id
+    }
+  }
+
+  /// Is `dag_node` equal to our identity element?
+  pub fn is_identity(&self, dag_node: &dyn DagNode) -> bool {
+    match &self.identity.dag_node {
+      Some(identity) => identity.borrow().compare(dag_node).is_eq(),
+      None => false,
+    }
+  }
+}
+
+impl Symbol for USymbol {
+  #[inline(always)]
+  fn symbol_members(&self) -> &SymbolMembers {
+    &self.symbol_members
+  }
+
+  #[inline(always)]
+  fn symbol_members_mut(&mut self) -> &mut SymbolMembers {
+    &mut self.symbol_members
+  }
+
+  #[inline(always)]
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    assert!(subject.borrow().symbol().as_ref().eq(self), "bad symbol");
+
+    // U symbols only support the standard strategy: every argument is evaluated before we try equations.
+    let args: Vec<RcDagNode> = subject.borrow().iter_args().collect();
+    for arg in args {
+      context.reduce_dag_node(arg);
+    }
+
+    // An argument may have rewritten to our identity, in which case we collapse.
+    let collapse = match subject.borrow().as_any().downcast_ref::<UDagNode>() {
+      Some(u_dag_node) => u_dag_node.normalize_at_top(),
+      None => unreachable!("Could not downcast to UDagNode. This is a bug."),
+    };
+    if let Some(remaining) = collapse {
+      // The subject collapsed to one of its arguments, which is already reduced.
+      remaining.borrow_mut().overwrite_with_clone(subject.clone());
+      return false;
+    }
+
+    self.symbol_members.eq_rewrite(subject, context)
+  }
+}
+
+impl BinarySymbol for USymbol {
+  #[inline(always)]
+  fn get_identity(&self) -> Option<RcTerm> {
+    self.identity.term.clone()
+  }
+
+  #[inline(always)]
+  fn get_identity_dag(&self) -> Option<RcDagNode> {
+    self.identity.dag_node.clone()
+  }
+}
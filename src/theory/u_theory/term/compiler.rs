@@ -0,0 +1,134 @@
+/*!
+
+The implementation of the compiler-related methods of the `Term` trait for `UTerm`.
+
+ */
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::UTerm;
+use crate::{
+  abstractions::{rc_cell, NatSet, RcCell},
+  core::{automata::RHSBuilder, BindingLHSAutomaton, TermBag, VariableInfo},
+  theory::{
+    find_available_terms,
+    term_compiler::compile_rhs,
+    u_theory::{automaton::UArgument, ULHSAutomaton, URHSAutomaton},
+    RcLHSAutomaton,
+    Term,
+  },
+  NONE,
+};
+
+impl UTerm {
+  pub fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    // A pattern that collapses at the top is matched by what it collapses to.
+    if let Some(remaining) = self.collapse() {
+      let (mut automaton, subproblem_likely) = remaining
+        .borrow()
+        .compile_lhs(match_at_top, variable_info, bound_uniquely);
+      if self.term_members.save_index != NONE {
+        automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+      }
+      return (automaton, subproblem_likely);
+    }
+
+    let can_collapse = [self.can_match_identity(0), self.can_match_identity(1)];
+
+    // If neither argument can take the identity, the arguments are matched against the subject's arguments and
+    // nothing else, so what they bind is bound uniquely. Otherwise we compile them against a local copy.
+    let mut local_bound_uniquely = bound_uniquely.clone();
+    let bound = if can_collapse.contains(&true) {
+      &mut local_bound_uniquely
+    } else {
+      &mut *bound_uniquely
+    };
+
+    let mut subproblem_likely = can_collapse.contains(&true);
+    let mut compile_argument = |index: usize| -> UArgument {
+      let (automaton, arg_subproblem_likely) = self.args[index].borrow().compile_lhs(false, variable_info, bound);
+      subproblem_likely |= arg_subproblem_likely;
+      UArgument {
+        automaton,
+        abstraction_variable: self.abstraction_variable_indices[index],
+      }
+    };
+    let args = [compile_argument(0), compile_argument(1)];
+    subproblem_likely |= args.iter().any(|arg| arg.abstraction_variable != NONE);
+
+    let mut automaton: RcLHSAutomaton = rc_cell!(ULHSAutomaton::new(self.symbol(), args, can_collapse));
+
+    if self.term_members.save_index != NONE {
+      automaton = rc_cell!(BindingLHSAutomaton::new(self.term_members.save_index, automaton));
+    }
+
+    (automaton, subproblem_likely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  pub fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    let symbol = self.symbol();
+    let mut sources = [0; 2];
+
+    // We build the larger argument first, as in the free theory.
+    let order = if self.args[0].borrow_mut().compute_size() >= self.args[1].borrow_mut().compute_size() {
+      [0, 1]
+    } else {
+      [1, 0]
+    };
+    for idx in order {
+      let arg_eager = eager_context && symbol.strategy().eager_argument(idx);
+      sources[idx] = compile_rhs(self.args[idx].clone(), rhs_builder, variable_info, available_terms, arg_eager);
+    }
+
+    // Need to flag last use of each source.
+    for source in &sources {
+      variable_info.use_index(*source);
+    }
+
+    // The automaton collapses the new node if an argument turns out to be the identity.
+    let index = variable_info.make_construction_index();
+    rhs_builder.add_rhs_automaton(Box::new(URHSAutomaton::new(symbol, sources, index)));
+    index
+  }
+
+  pub fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    // If neither argument can take the identity, each argument is matched against the corresponding subject
+    // argument, and whatever the arguments bind uniquely, we bind uniquely.
+    if self.can_match_identity(0) || self.can_match_identity(1) {
+      return;
+    }
+    for arg in &self.args {
+      arg.borrow_mut().analyse_constraint_propagation(bound_uniquely);
+    }
+  }
+
+  /// The theory-specific part of find_available_terms
+  pub fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    if self.ground() {
+      return;
+    }
+
+    let symbol = self.symbol();
+    for (i, arg) in self.args.iter().enumerate() {
+      let arg_eager = if at_top {
+        eager_context && symbol.strategy().eager_argument(i)
+      } else {
+        eager_context && symbol.strategy().evaluated_argument(i)
+      };
+      find_available_terms(arg.clone(), available_terms, arg_eager, false);
+    }
+  }
+}
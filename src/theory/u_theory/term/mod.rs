@@ -0,0 +1,330 @@
+/*!
+
+`Term` implementation for the U theory.
+
+A `UTerm` holds its two arguments in an array. Full normalization eliminates identities below the top: an argument that
+is a U term with an identity argument on an eliminable side is replaced by its other argument. A term can't replace
+itself, so a term that collapses at the top is left as it is, and the collapse happens when it is dagified or compiled.
+The compiler for the matcher is in `compiler.rs`.
+
+*/
+
+mod compiler;
+
+use std::{any::Any, cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{UDagNode, USymbol};
+use crate::{
+  abstractions::{hash2 as term_hash, NatSet, RcCell},
+  core::{
+    automata::RHSBuilder,
+    format::{FormatStyle, Formattable},
+    TermBag,
+    VariableInfo,
+  },
+  theory::{
+    BinarySymbol,
+    DagNode,
+    NodeCache,
+    RcDagNode,
+    RcLHSAutomaton,
+    RcSymbol,
+    RcTerm,
+    Symbol,
+    SymbolSet,
+    Term,
+    TermAttribute,
+    TermMembers,
+  },
+  NONE,
+};
+
+pub type RcUTerm = RcCell<UTerm>;
+
+pub struct UTerm {
+  pub(crate) term_members:                 TermMembers,
+  pub(crate) args:                         [RcTerm; 2],
+  /// The abstraction variable of each argument that is matched through a `VariableAbstractionSubproblem`, or `NONE`.
+  pub(crate) abstraction_variable_indices: [i32; 2],
+}
+
+// Constructors
+impl UTerm {
+  pub fn new(symbol: RcSymbol, first: RcTerm, second: RcTerm) -> UTerm {
+    UTerm {
+      term_members:                 TermMembers::new(symbol),
+      args:                         [first, second],
+      abstraction_variable_indices: [NONE, NONE],
+    }
+  }
+
+  #[inline(always)]
+  pub fn u_symbol(&self) -> &USymbol {
+    match self.term_members.top_symbol.as_any().downcast_ref::<USymbol>() {
+      Some(symbol) => symbol,
+      None => unreachable!("Could not downcast to USymbol. This is a bug."),
+    }
+  }
+
+  /// Returns the argument this term collapses to if it has an identity argument on an eliminable side.
+  pub fn collapse(&self) -> Option<RcTerm> {
+    let symbol = self.u_symbol();
+    let identity = symbol.get_identity()?;
+
+    (0..2)
+      .find(|&index| {
+        symbol.identity_eliminable(index) && self.args[index].borrow().compare(&*identity.borrow()).is_eq()
+      })
+      .map(|index| self.args[1 - index].clone())
+  }
+
+  /// Could the argument at `index` match our identity? This is a conservative approximation: variables are assumed
+  /// to be able to take the identity regardless of their sort.
+  pub(crate) fn can_match_identity(&self, index: usize) -> bool {
+    let symbol = self.u_symbol();
+    if !symbol.identity_eliminable(index) {
+      return false;
+    }
+
+    let arg = self.args[index].borrow();
+    if arg.is_variable() || !arg.collapse_symbols().is_empty() {
+      return true;
+    }
+    match symbol.get_identity() {
+      Some(identity) => arg.compare(&*identity.borrow()).is_eq(),
+      None => false,
+    }
+  }
+
+  /// Replaces arguments that are U terms collapsing to one of their own arguments. Returns `true` if anything changed.
+  fn eliminate_identity(&mut self) -> bool {
+    let mut changed = false;
+
+    for arg in self.args.iter_mut() {
+      loop {
+        let collapsed = match arg.borrow().as_any().downcast_ref::<UTerm>() {
+          Some(u_term) => u_term.collapse(),
+          None => None,
+        };
+        match collapsed {
+          Some(collapsed) => {
+            *arg = collapsed;
+            changed = true;
+          }
+          None => break,
+        }
+      }
+    }
+
+    changed
+  }
+}
+
+
+impl Term for UTerm {
+  // region Representation and Reduction Methods
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn as_ptr(&self) -> *const dyn Term {
+    self
+  }
+
+  /// In sync with `normalize`.
+  fn semantic_hash(&self) -> u32 {
+    let mut hash_value: u32 = self.symbol().semantic_hash();
+
+    for arg in &self.args {
+      hash_value = term_hash(hash_value, arg.borrow().semantic_hash());
+    }
+
+    hash_value
+  }
+
+  /// In sync with `semantic_hash`.
+  fn normalize(&mut self, full: bool) -> (u32, bool) {
+    let mut changed = false;
+
+    for arg in &self.args {
+      let (_, child_changed) = arg.borrow_mut().normalize(full);
+      changed = changed || child_changed;
+    }
+
+    if full {
+      changed |= self.eliminate_identity();
+    }
+
+    (self.semantic_hash(), changed)
+  }
+
+  // endregion
+
+  // region Accessors
+  #[inline(always)]
+  fn term_members(&self) -> &TermMembers {
+    &self.term_members
+  }
+
+  #[inline(always)]
+  fn term_members_mut(&mut self) -> &mut TermMembers {
+    &mut self.term_members
+  }
+
+  #[inline(always)]
+  fn iter_args(&self) -> Box<dyn Iterator<Item = RcTerm> + '_> {
+    Box::new(self.args.iter().cloned())
+  }
+
+  // endregion
+
+  // region Comparison Methods
+
+  fn compare_term_arguments(&self, other: &dyn Term) -> Ordering {
+    assert_eq!(&self.symbol(), &other.symbol(), "symbols differ");
+
+    if let Some(other) = other.as_any().downcast_ref::<UTerm>() {
+      for (arg_self, arg_other) in self.args.iter().zip(other.args.iter()) {
+        let r = arg_self.borrow().compare(&*arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast Term to UTerm. This is a bug.")
+    }
+  }
+
+  fn compare_dag_arguments(&self, other: &dyn DagNode) -> Ordering {
+    if let Some(other) = other.as_any().downcast_ref::<UDagNode>() {
+      for (arg_self, arg_other) in self.args.iter().zip(other.iter_args()) {
+        let r = arg_self.borrow().compare_dag_node(&**arg_other.borrow());
+        if r.is_ne() {
+          return r;
+        }
+      }
+      Ordering::Equal
+    } else {
+      unreachable!("Could not downcast DagNode to UDagNode. This is a bug.")
+    }
+  }
+
+  // endregion
+
+  fn dagify_aux(&self, sub_dags: &mut NodeCache, set_sort_info: bool) -> RcDagNode {
+    let first = self.args[0].borrow().dagify(sub_dags, set_sort_info);
+    let second = self.args[1].borrow().dagify(sub_dags, set_sort_info);
+
+    // The term may collapse at the top, which normalization can't do for us.
+    UDagNode::make_normalized(self.symbol(), first, second)
+  }
+
+  // region Compiler-related
+  #[inline(always)]
+  fn compile_lhs(
+    &self,
+    match_at_top: bool,
+    variable_info: &VariableInfo,
+    bound_uniquely: &mut NatSet,
+  ) -> (RcLHSAutomaton, bool) {
+    UTerm::compile_lhs(self, match_at_top, variable_info, bound_uniquely)
+  }
+
+  /// The theory-dependent part of `compile_rhs` called by `term_compiler::compile_rhs(…)`. Returns
+  /// the `save_index`.
+  #[inline(always)]
+  fn compile_rhs_aux(
+    &mut self,
+    rhs_builder: &mut RHSBuilder,
+    variable_info: &mut VariableInfo,
+    available_terms: &mut TermBag,
+    eager_context: bool,
+  ) -> i32 {
+    UTerm::compile_rhs_aux(self, rhs_builder, variable_info, available_terms, eager_context)
+  }
+
+  #[inline(always)]
+  fn analyse_constraint_propagation(&mut self, bound_uniquely: &mut NatSet) {
+    UTerm::analyse_constraint_propagation(self, bound_uniquely)
+  }
+
+  /// `f(p, q)` can collapse to `q` if `p` can match a left identity, in which case it can also collapse to anything
+  /// `q` can collapse to, and symmetrically for a right identity.
+  fn analyse_collapses(&mut self) {
+    for arg in &self.args {
+      arg.borrow_mut().analyse_collapses();
+    }
+
+    let mut collapse_set = SymbolSet::default();
+    for index in 0..2 {
+      if self.can_match_identity(index) {
+        let other = self.args[1 - index].borrow();
+        collapse_set.insert(other.symbol().as_ref() as *const dyn Symbol);
+        collapse_set.extend(other.collapse_symbols().iter().cloned());
+      }
+    }
+
+    let stable = collapse_set.is_empty();
+    self.term_members.collapse_set = collapse_set;
+    if stable {
+      self.set_attribute(TermAttribute::Stable);
+    }
+  }
+
+  /// An argument that can collapse may match the corresponding subject argument in several ways, so it is matched
+  /// through an abstraction variable once the rest of the pattern has been matched.
+  fn insert_abstraction_variables(&mut self, variable_info: &mut VariableInfo) {
+    let mut honors_ground_out_match = true;
+
+    for index in 0..2 {
+      let mut arg = self.args[index].borrow_mut();
+      arg.insert_abstraction_variables(variable_info);
+      honors_ground_out_match &= arg.honors_ground_out_match();
+
+      if !arg.collapse_symbols().is_empty() {
+        self.abstraction_variable_indices[index] = variable_info.make_protected_variable();
+        honors_ground_out_match = false;
+      }
+    }
+
+    self.set_honors_ground_out_match(honors_ground_out_match);
+  }
+
+  #[inline(always)]
+  fn find_available_terms_aux(&self, available_terms: &mut TermBag, eager_context: bool, at_top: bool) {
+    UTerm::find_available_terms_aux(self, available_terms, eager_context, at_top);
+  }
+  // endregion
+}
+
+
+impl Formattable for UTerm {
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut accumulator = String::new();
+    match style {
+      FormatStyle::Debug => {
+        accumulator.push_str(format!("u<{}>", self.term_members.top_symbol.repr(style)).as_str());
+      }
+
+      _ => {
+        accumulator.push_str(self.term_members.top_symbol.repr(style).as_str());
+      }
+    }
+
+    accumulator.push_str(
+      format!(
+        "({}, {})",
+        self.args[0].borrow().repr(style),
+        self.args[1].borrow().repr(style)
+      )
+      .as_str(),
+    );
+
+    accumulator
+  }
+}
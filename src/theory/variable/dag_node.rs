@@ -11,6 +11,7 @@ use crate::{
   rc_cell,
   theory::{
     dag_node_flags,
+    make_rc_dag_node,
     variable::{VariableSymbol, VariableTerm},
     DagNode,
     DagNodeFlag,
@@ -94,7 +95,7 @@ impl DagNode for VariableDagNode {
     let mut fdg = VariableDagNode::new(self.symbol(), self.name.clone(), self.index);
     fdg.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
 
-    rc_cell!(fdg)
+    make_rc_dag_node(fdg)
   }
 
   fn copy_with_replacements(&self, _stack: &[RedexPosition], _first_idx: usize, _last_idx: usize) -> RcDagNode {
@@ -106,28 +107,19 @@ impl DagNode for VariableDagNode {
   }
 
   fn copy_eager_upto_reduced_aux(&mut self) -> RcDagNode {
-    rc_cell!(VariableDagNode::new(self.symbol(), self.name, self.index))
+    make_rc_dag_node(VariableDagNode::new(self.symbol(), self.name.clone(), self.index))
   }
 
   fn copy_all_aux(&mut self) -> RcDagNode {
-    rc_cell!(VariableDagNode::new(self.symbol(), self.name, self.index))
+    make_rc_dag_node(VariableDagNode::new(self.symbol(), self.name.clone(), self.index))
   }
 
-  fn overwrite_with_clone(&mut self, mut old: RcDagNode) {
-    if let Some(old_dag_node) = old.borrow_mut().as_any_mut().downcast_mut::<VariableDagNode>() {
-      let mut fdg = VariableDagNode::new(self.symbol(), self.name.clone(), self.index);
-      fdg.set_flags(
-        self.flags()
-          | DagNodeFlag::Reduced
-          | DagNodeFlag::Unrewritable
-          | DagNodeFlag::Unstackable
-          | DagNodeFlag::Ground,
-      );
+  fn overwrite_with_clone(&mut self, old: RcDagNode) {
+    let mut fdg = VariableDagNode::new(self.symbol(), self.name.clone(), self.index);
+    fdg.set_sort_index(self.get_sort_index());
+    fdg.set_flags(self.flags() & DagNodeFlags::RewritingFlags);
 
-      let _ = std::mem::replace(old_dag_node, fdg);
-    } else {
-      unreachable!("This execution path should be unreachable. This is a bug.")
-    }
+    *old.borrow_mut() = Box::new(fdg);
   }
 
   fn make_canonical(&self, node: RcDagNode, _: &mut HashConsSet) -> RcDagNode {
@@ -56,7 +56,7 @@ impl Symbol for VariableSymbol {
     true
   }
 
-  fn rewrite(&mut self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
-    self.apply_replace(subject, context)
+  fn rewrite(&self, subject: RcDagNode, context: &mut RewritingContext) -> bool {
+    self.symbol_members.apply_replace(subject, context)
   }
 }
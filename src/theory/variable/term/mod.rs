@@ -23,7 +23,17 @@ use crate::{
     TermBag,
     VariableInfo,
   },
-  theory::{DagNode, NodeCache, RcDagNode, RcLHSAutomaton, RcSymbol, RcTerm, Term, TermMembers},
+  theory::{
+    make_rc_dag_node,
+    DagNode,
+    NodeCache,
+    RcDagNode,
+    RcLHSAutomaton,
+    RcSymbol,
+    RcTerm,
+    Term,
+    TermMembers,
+  },
 };
 
 pub type RcVariableTerm = Rc<VariableTerm>;
@@ -129,7 +139,7 @@ impl Term for VariableTerm {
   // endregion
   #[inline(always)]
   fn dagify_aux(&self, _sub_dags: &mut NodeCache, _set_sort_info: bool) -> RcDagNode {
-    rc_cell!(VariableDagNode::new(self.symbol(), self.name.clone(), self.index))
+    make_rc_dag_node(VariableDagNode::new(self.symbol(), self.name.clone(), self.index))
   }
 
   // region Compiler-Related
@@ -139,8 +149,8 @@ impl Term for VariableTerm {
     _variable_info: &VariableInfo,
     bound_uniquely: &mut NatSet,
   ) -> (RcLHSAutomaton, bool) {
-    assert!(self.index > 100, "index too big");
-    assert!(self.index < 0, "index negative");
+    assert!(self.index < 100, "index too big");
+    assert!(self.index >= 0, "index negative");
     bound_uniquely.insert(self.index as usize);
 
     let mut automaton: RcLHSAutomaton =
@@ -159,7 +169,7 @@ impl Term for VariableTerm {
   fn compile_rhs_aux(
     &mut self,
     builder: &mut RHSBuilder,
-    variable_info: &VariableInfo,
+    variable_info: &mut VariableInfo,
     available_terms: &mut TermBag,
     eager_context: bool,
   ) -> i32 {